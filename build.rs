@@ -2,6 +2,7 @@ fn main() {
     linker_be_nice();
     println!("cargo:rustc-link-arg=-Tlinkall.x");
     generate_bitmap_fonts();
+    generate_paged_fonts();
 }
 
 fn hint(msg: &str) {
@@ -64,8 +65,14 @@ fn linker_be_nice() {
 //   extended unicode (sorted codepoint table, binary-searched at runtime)
 //
 // extended set covers latin-1 supplement, common punctuation (smart
-// quotes, dashes, ellipsis, bullet), and a handful of currency/math
-// symbols, enough for the vast majority of european-language epubs.
+// quotes, dashes, ellipsis, bullet), greek, cyrillic, and a handful of
+// currency/math symbols, enough for the vast majority of
+// european-language epubs. each font only emits the codepoints its
+// TTF actually has, so runtime lookups can tell coverage apart.
+//
+// fallback chain: TTFs in assets/fonts/fallback/ (name order) are
+// rasterised for the extended codepoints the regular font lacks and
+// tried in order when a glyph is missing (see FontSet::lookup).
 
 use std::fs;
 use std::io::Write;
@@ -262,6 +269,20 @@ fn extended_codepoints() -> Vec<u32> {
     ];
     cps.extend_from_slice(latin_ext_b);
 
+    // Greek: monotonic letters with tonos (U+0386-03CE, skipping the
+    // unassigned U+0387/038B/038D/03A2 gaps)
+    cps.push(0x0386);
+    cps.extend(0x0388..=0x038Au32);
+    cps.push(0x038C);
+    cps.extend(0x038E..=0x03A1u32);
+    cps.extend(0x03A3..=0x03CEu32);
+
+    // Cyrillic: basic block (Russian, Ukrainian, Belarusian, Serbian,
+    // Macedonian, Bulgarian) plus Ukrainian ghe with upturn
+    cps.extend(0x0400..=0x045Fu32);
+    cps.push(0x0490);
+    cps.push(0x0491);
+
     cps.sort();
     cps.dedup();
     cps
@@ -270,19 +291,7 @@ fn extended_codepoints() -> Vec<u32> {
 // find first .ttf in dir whose name contains all keywords (case-insensitive);
 // excludes BoldItalic unless explicitly requested
 fn find_ttf(dir: &Path, keywords: &[&str]) -> Option<PathBuf> {
-    let Ok(entries) = fs::read_dir(dir) else {
-        return None;
-    };
-    let mut candidates: Vec<PathBuf> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| {
-            p.extension()
-                .map(|e| e.eq_ignore_ascii_case("ttf"))
-                .unwrap_or(false)
-        })
-        .collect();
-    candidates.sort();
+    let candidates = list_ttfs(dir);
 
     for path in &candidates {
         let stem = path
@@ -311,6 +320,24 @@ fn find_ttf(dir: &Path, keywords: &[&str]) -> Option<PathBuf> {
     None
 }
 
+// all .ttf files in dir, sorted by name
+fn list_ttfs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .map(|e| e.eq_ignore_ascii_case("ttf"))
+                .unwrap_or(false)
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
 fn generate_bitmap_fonts() {
     let out_dir = std::env::var("OUT_DIR").unwrap();
     let dest = Path::new(&out_dir).join("font_data.rs");
@@ -463,8 +490,82 @@ fn generate_bitmap_fonts() {
             emit_stub(&mut out, &format!("ITALIC_BODY_{suffix}"));
         }
     }
+
+    // fallback chain: only the extended codepoints regular lacks
+    let fallback_dir = font_dir.join("fallback");
+    println!("cargo:rerun-if-changed={}", fallback_dir.display());
+    let fallbacks = list_ttfs(&fallback_dir);
+    let regular_font = regular.as_ref().map(|p| {
+        let data = fs::read(p).unwrap();
+        fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default())
+            .expect("failed to parse regular TTF")
+    });
+    let lacking: Vec<u32> = ext_codepoints
+        .iter()
+        .copied()
+        .filter(|&cp| regular_font.as_ref().is_none_or(|f| !font_covers(f, cp)))
+        .collect();
+
+    for (i, path) in fallbacks.iter().enumerate() {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let data = fs::read(path).unwrap();
+        let font = fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default())
+            .expect("failed to parse fallback TTF");
+        let covered = lacking.iter().filter(|&&cp| font_covers(&font, cp)).count();
+        eprintln!(
+            "cargo:warning=font: fallback {} covers {} of {} missing codepoints",
+            path.file_name().unwrap().to_string_lossy(),
+            covered,
+            lacking.len(),
+        );
+        for (px, suffix) in &BODY_PX {
+            emit_font(
+                &mut out,
+                &font,
+                &format!("FALLBACK{i}_BODY_{suffix}"),
+                *px,
+                &lacking,
+            );
+        }
+        for (px, suffix) in &HEADING_PX {
+            emit_font(
+                &mut out,
+                &font,
+                &format!("FALLBACK{i}_HEADING_{suffix}"),
+                *px,
+                &lacking,
+            );
+        }
+    }
+
+    emit_fallback_table(&mut out, "BODY", fallbacks.len());
+    emit_fallback_table(&mut out, "HEADING", fallbacks.len());
 }
 
+// true if the TTF has a real glyph (not .notdef) for cp
+fn font_covers(font: &fontdue::Font, cp: u32) -> bool {
+    char::from_u32(cp).is_some_and(|ch| font.lookup_glyph_index(ch) != 0)
+}
+
+// FALLBACK_<kind>: per size tier, the fallback fonts in chain order
+fn emit_fallback_table(out: &mut fs::File, kind: &str, count: usize) {
+    writeln!(
+        out,
+        "pub static FALLBACK_{kind}: [&[&BitmapFont]; {}] = [",
+        BODY_PX.len()
+    )
+    .unwrap();
+    for (_px, suffix) in &BODY_PX {
+        let fonts: Vec<String> = (0..count)
+            .map(|i| format!("&FALLBACK{i}_{kind}_{suffix}"))
+            .collect();
+        writeln!(out, "    &[{}],", fonts.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+}
+
+#[derive(Clone)]
 struct RasterGlyph {
     advance: u8,
     offset_x: i8,
@@ -524,6 +625,15 @@ fn emit_font(
     let line_height = lm.new_line_size.ceil() as u16;
     let ascent = lm.ascent.ceil() as u16;
 
+    // only codepoints this TTF really has; fontdue would hand back
+    // .notdef for the rest and hide the gap from the fallback chain
+    let ext_codepoints: Vec<u32> = ext_codepoints
+        .iter()
+        .copied()
+        .filter(|&cp| font_covers(font, cp))
+        .collect();
+    let ext_codepoints = ext_codepoints.as_slice();
+
     // ascii glyphs (direct-indexed 0x20-0x7E)
 
     let mut ascii_glyphs: Vec<RasterGlyph> = Vec::with_capacity(GLYPH_COUNT);
@@ -679,3 +789,128 @@ fn emit_bitmap_bytes(out: &mut fs::File, glyphs: &[RasterGlyph]) {
         writeln!(out).unwrap();
     }
 }
+
+// paged glyph tables (src/fonts/paged.rs): CJK and other large scripts
+// too big for flash. opt-in: set PULP_CJK_FONT to a TTF/OTF path and
+// copy the generated CJK<n>.PGF files to _PULP/FONTS/ on the SD card
+
+const PGF_BLOCK_SHIFT: u32 = 6;
+const PGF_BLOCK_COUNT: usize = 1024; // whole BMP
+const PGF_HEADER_LEN: usize = 32;
+const PGF_CELL_HDR: usize = 8;
+const PGF_NO_BLOCK: u32 = u32::MAX;
+
+const PGF_RANGES: &[(u32, u32)] = &[
+    (0x3000, 0x30FF), // CJK punctuation, hiragana, katakana
+    (0x3100, 0x31FF), // bopomofo, katakana phonetic extensions
+    (0x4E00, 0x9FFF), // CJK unified ideographs
+    (0xAC00, 0xD7A3), // hangul syllables
+    (0xF900, 0xFAFF), // CJK compatibility ideographs
+    (0xFF00, 0xFFEF), // halfwidth and fullwidth forms
+];
+
+fn generate_paged_fonts() {
+    println!("cargo:rerun-if-env-changed=PULP_CJK_FONT");
+    let Ok(path) = std::env::var("PULP_CJK_FONT") else {
+        return;
+    };
+    println!("cargo:rerun-if-changed={path}");
+
+    let data = fs::read(&path).expect("PULP_CJK_FONT: cannot read font");
+    let font = fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default())
+        .expect("PULP_CJK_FONT: failed to parse font");
+
+    let out_dir = Path::new(&std::env::var("OUT_DIR").unwrap()).join("FONTS");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    for (i, (px, _)) in BODY_PX.iter().enumerate() {
+        let dest = out_dir.join(format!("CJK{i}.PGF"));
+        let count = emit_paged_font(&dest, &font, *px);
+        eprintln!(
+            "cargo:warning=paged font: {} glyphs at {:.0} px -> {}",
+            count,
+            px,
+            dest.display()
+        );
+    }
+}
+
+fn emit_paged_font(dest: &Path, font: &fontdue::Font, px: f32) -> u32 {
+    let lm = font
+        .horizontal_line_metrics(px)
+        .expect("font has no horizontal metrics");
+
+    // rasterise every covered codepoint, grouped by 64-codepoint block
+    let mut blocks: Vec<Option<Vec<Option<RasterGlyph>>>> = vec![None; PGF_BLOCK_COUNT];
+    let mut bitmap_max = 1usize;
+    let mut advance = 0u8;
+    let mut glyph_count = 0u32;
+    for &(lo, hi) in PGF_RANGES {
+        for cp in lo..=hi {
+            let Some(ch) = char::from_u32(cp) else {
+                continue;
+            };
+            if font.lookup_glyph_index(ch) == 0 {
+                continue;
+            }
+            let g = rasterize_char(font, ch, px);
+            bitmap_max = bitmap_max.max(g.bits.len());
+            advance = advance.max(g.advance);
+            glyph_count += 1;
+            let block = blocks[(cp >> PGF_BLOCK_SHIFT) as usize]
+                .get_or_insert_with(|| vec![None; 1 << PGF_BLOCK_SHIFT]);
+            block[(cp & ((1 << PGF_BLOCK_SHIFT) - 1)) as usize] = Some(g);
+        }
+    }
+
+    let cell_size = PGF_CELL_HDR + bitmap_max;
+    let cells_start = (PGF_HEADER_LEN + PGF_BLOCK_COUNT * 4) as u32;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"PGF1");
+    out.extend_from_slice(&(lm.new_line_size.ceil() as u16).to_le_bytes());
+    out.extend_from_slice(&(lm.ascent.ceil() as u16).to_le_bytes());
+    out.push(advance);
+    out.push(0);
+    out.extend_from_slice(&(cell_size as u16).to_le_bytes());
+    out.extend_from_slice(&(PGF_BLOCK_COUNT as u16).to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&glyph_count.to_le_bytes());
+    out.extend_from_slice(&cells_start.to_le_bytes());
+    out.resize(PGF_HEADER_LEN, 0);
+
+    // directory: first cell index of each present block
+    let mut next_cell = 0u32;
+    for block in &blocks {
+        if block.is_some() {
+            out.extend_from_slice(&next_cell.to_le_bytes());
+            next_cell += 1 << PGF_BLOCK_SHIFT;
+        } else {
+            out.extend_from_slice(&PGF_NO_BLOCK.to_le_bytes());
+        }
+    }
+
+    // cells: full 64 per present block; absent codepoints stay zeroed
+    for block in blocks.iter().flatten() {
+        for g in block {
+            let start = out.len();
+            if let Some(g) = g {
+                out.extend_from_slice(&[
+                    g.advance,
+                    g.offset_x as u8,
+                    g.offset_y as u8,
+                    g.width,
+                    g.height,
+                    0,
+                    0,
+                    0,
+                ]);
+                out.extend_from_slice(&g.bits);
+            }
+            out.resize(start + cell_size, 0);
+        }
+    }
+
+    fs::write(dest, out).unwrap();
+    glyph_count
+}
@@ -43,7 +43,7 @@ impl ActionEvent {
     }
 }
 
-// portrait one-handed layout with optional button swap
+// one-handed layouts with optional button swap
 //
 // default layout (right-handed):
 //   bottom row: Back  Confirm(=Select)  Left(=PrevJump)  Right(=NextJump)
@@ -55,15 +55,25 @@ impl ActionEvent {
 //   so the spatial position of Back/OK moves to the right side of the
 //   device where the left hand naturally rests.
 //   volume buttons are NOT swapped (up=prev, down=next always).
+//
+// landscape layout (device turned so the button row is on the right):
+//   right edge, top to bottom: Right(=Prev)  Left(=Next)  Confirm(=Select)  Back
+//   top edge:                  VolUp(=PrevJump)  VolDown(=NextJump)
+//   the upper pair sits under the thumb and turns pages; the volume
+//   rocker is now out of reach, so it takes the jumps. swapping still
+//   exchanges Back<->Left and Confirm<->Right, moving page turns to
+//   the lower pair.
 #[derive(Default)]
 pub struct ButtonMapper {
     swap_buttons: bool,
+    landscape: bool,
 }
 
 impl ButtonMapper {
     pub const fn new() -> Self {
         Self {
             swap_buttons: false,
+            landscape: false,
         }
     }
 
@@ -75,16 +85,36 @@ impl ButtonMapper {
         self.swap_buttons
     }
 
+    pub fn set_landscape(&mut self, landscape: bool) {
+        self.landscape = landscape;
+    }
+
+    pub fn is_landscape(&self) -> bool {
+        self.landscape
+    }
+
     pub fn map_button(&self, button: Button) -> Action {
-        if self.swap_buttons {
-            // swapped: Back<->Left, Confirm<->Right
+        // swapped: Back<->Left, Confirm<->Right
+        let button = if self.swap_buttons {
             match button {
-                Button::VolDown => Action::Next,
-                Button::VolUp => Action::Prev,
-                Button::Right => Action::Select,     // was NextJump
-                Button::Left => Action::Back,        // was PrevJump
-                Button::Confirm => Action::NextJump, // was Select
-                Button::Back => Action::PrevJump,    // was Back
+                Button::Right => Button::Confirm,
+                Button::Left => Button::Back,
+                Button::Confirm => Button::Right,
+                Button::Back => Button::Left,
+                b => b,
+            }
+        } else {
+            button
+        };
+
+        if self.landscape {
+            match button {
+                Button::VolDown => Action::NextJump,
+                Button::VolUp => Action::PrevJump,
+                Button::Right => Action::Prev,
+                Button::Left => Action::Next,
+                Button::Confirm => Action::Select,
+                Button::Back => Action::Back,
                 Button::Power => Action::Menu,
             }
         } else {
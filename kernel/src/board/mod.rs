@@ -8,7 +8,7 @@ pub mod layout;
 pub mod raw_gpio;
 
 pub use crate::drivers::sdcard::{SdStorage, SyncSdCard};
-pub use crate::drivers::ssd1677::{DisplayDriver, HEIGHT, SPI_FREQ_MHZ, WIDTH};
+pub use crate::drivers::ssd1677::{DisplayDriver, HEIGHT, Rotation, SPI_FREQ_MHZ, WIDTH};
 pub use crate::drivers::strip::StripBuffer;
 pub use button::{Button, ROW1_THRESHOLDS, ROW2_THRESHOLDS, decode_ladder};
 
@@ -16,6 +16,40 @@ pub use button::{Button, ROW1_THRESHOLDS, ROW2_THRESHOLDS, decode_ladder};
 pub const SCREEN_W: u16 = HEIGHT; // 480
 pub const SCREEN_H: u16 = WIDTH; // 800
 
+// how the device is held; portrait is the native UI layout, landscape
+// uses the panel unrotated so the front buttons sit on the right edge
+// and the volume rocker on the top edge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+impl Orientation {
+    pub const fn rotation(self) -> Rotation {
+        match self {
+            Self::Portrait => Rotation::Deg270,
+            Self::Landscape => Rotation::Deg0,
+        }
+    }
+
+    // logical screen size in this orientation
+    pub const fn screen_w(self) -> u16 {
+        match self {
+            Self::Portrait => SCREEN_W,
+            Self::Landscape => WIDTH,
+        }
+    }
+
+    pub const fn screen_h(self) -> u16 {
+        match self {
+            Self::Portrait => SCREEN_H,
+            Self::Landscape => HEIGHT,
+        }
+    }
+}
+
 use core::cell::RefCell;
 
 use critical_section::Mutex;
@@ -0,0 +1,214 @@
+// per-window row-band hashes of what the panel BW RAM holds
+//
+// apps over-mark (a whole page region for one changed line); as a
+// partial window is rendered strip by strip the driver hashes each band
+// of BAND_ROWS panel rows and compares against the hashes from the last
+// write of the same window, then sends a strip only from its first to
+// its last changed band
+//
+// hashes are only comparable for the same panel columns, so entries
+// are keyed by (px, pw); a write to any window forgets the overlapping
+// bands of every entry it touches, a full frame clears all. the new
+// hashes of a write are staged and only committed once its refresh
+// has completed, so an aborted refresh leaves those bands unknown
+
+use super::ssd1677::HEIGHT;
+
+pub const BAND_ROWS: u16 = 8;
+pub const BANDS: usize = (HEIGHT / BAND_ROWS) as usize;
+
+const ENTRIES: usize = 6;
+
+// never produced by band_hash, so it doubles as "unknown"
+const UNKNOWN: u32 = 0;
+
+const FNV_OFFSET: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    px: u16,
+    pw: u16,
+    used: bool,
+    bands: [u32; BANDS],
+    // bands as they will be once the refresh in flight completes
+    pending: [u32; BANDS],
+}
+
+impl Entry {
+    const EMPTY: Self = Self {
+        px: 0,
+        pw: 0,
+        used: false,
+        bands: [UNKNOWN; BANDS],
+        pending: [UNKNOWN; BANDS],
+    };
+}
+
+pub struct FrameHashes {
+    entries: [Entry; ENTRIES],
+    next: u8,
+}
+
+impl Default for FrameHashes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameHashes {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Entry::EMPTY; ENTRIES],
+            next: 0,
+        }
+    }
+
+    // panel RAM rewritten wholesale
+    pub fn clear(&mut self) {
+        for e in &mut self.entries {
+            e.used = false;
+        }
+    }
+
+    // bands py..py+ph of window (px, pw) were written with content this
+    // table never saw; forget them for every window sharing columns
+    pub fn invalidate(&mut self, px: u16, pw: u16, py: u16, ph: u16) {
+        let (b0, b1) = band_range(py, ph);
+        self.forget(px, pw, b0, b1);
+    }
+
+    // start a write of window (px, pw); returns the entry its strips
+    // are staged into
+    pub fn begin(&mut self, px: u16, pw: u16) -> usize {
+        let idx = match self
+            .entries
+            .iter()
+            .position(|e| e.used && e.px == px && e.pw == pw)
+        {
+            Some(i) => i,
+            None => {
+                let i = self.next as usize;
+                self.next = ((i + 1) % ENTRIES) as u8;
+                self.entries[i] = Entry {
+                    px,
+                    pw,
+                    used: true,
+                    ..Entry::EMPTY
+                };
+                i
+            }
+        };
+        let e = &mut self.entries[idx];
+        e.pending = e.bands;
+        idx
+    }
+
+    // compare the band hashes of strip rows y..y+rows, part of window
+    // rows py..py+ph, against the stored ones and return the changed
+    // row span (y, h) within the strip, or None when it is unchanged.
+    // the changed bands are forgotten and their new hashes staged for
+    // commit; only bands that lie wholly inside the window rows are
+    // compared, and the strip must hold every band it touches whole
+    pub fn stage(
+        &mut self,
+        idx: usize,
+        py: u16,
+        ph: u16,
+        y: u16,
+        rows: u16,
+        hasher: &BandHasher,
+    ) -> Option<(u16, u16)> {
+        // an edge band the rows only partly cover hashed just those
+        // rows: never comparable, so it is always written and kept
+        // unknown
+        let hash = |b: usize| {
+            let top = b as u16 * BAND_ROWS;
+            if top >= py && top + BAND_ROWS <= py + ph {
+                hasher.band(b)
+            } else {
+                UNKNOWN
+            }
+        };
+
+        let (b0, b1) = band_range(y, rows);
+        let stored = &self.entries[idx].bands;
+        let mut changed = (b0..b1).filter(|&b| hash(b) == UNKNOWN || stored[b] != hash(b));
+        let first = changed.next()?;
+        let last = changed.last().unwrap_or(first);
+
+        let (px, pw) = (self.entries[idx].px, self.entries[idx].pw);
+        self.forget(px, pw, first, last + 1);
+        let e = &mut self.entries[idx];
+        for b in first..=last {
+            e.pending[b] = hash(b);
+        }
+
+        let y0 = (first as u16 * BAND_ROWS).max(y);
+        let y1 = ((last as u16 + 1) * BAND_ROWS).min(y + rows);
+        Some((y0, y1 - y0))
+    }
+
+    // the refresh of window (px, pw) completed: what was staged for it
+    // is now on the panel
+    pub fn commit(&mut self, px: u16, pw: u16) {
+        if let Some(e) = self
+            .entries
+            .iter_mut()
+            .find(|e| e.used && e.px == px && e.pw == pw)
+        {
+            e.bands = e.pending;
+        }
+    }
+
+    // bands b0..b1 of every window over columns px..px+pw, staged ones
+    // included, no longer match the panel
+    fn forget(&mut self, px: u16, pw: u16, b0: usize, b1: usize) {
+        for e in self.entries.iter_mut().filter(|e| e.used) {
+            if px < e.px + e.pw && e.px < px + pw {
+                e.bands[b0..b1].fill(UNKNOWN);
+                e.pending[b0..b1].fill(UNKNOWN);
+            }
+        }
+    }
+}
+
+// running FNV-1a per band; rows may arrive in any strip chunking
+pub struct BandHasher {
+    state: [u32; BANDS],
+}
+
+impl BandHasher {
+    pub const fn new() -> Self {
+        Self {
+            state: [FNV_OFFSET; BANDS],
+        }
+    }
+
+    pub fn row(&mut self, y: u16, data: &[u8]) {
+        let h = &mut self.state[(y / BAND_ROWS) as usize];
+        for &b in data {
+            *h ^= b as u32;
+            *h = h.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // hash of band b so far; whole once all its rows are in
+    #[inline]
+    pub fn band(&self, b: usize) -> u32 {
+        self.state[b] | 1
+    }
+}
+
+impl Default for BandHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn band_range(py: u16, ph: u16) -> (usize, usize) {
+    let b0 = (py / BAND_ROWS) as usize;
+    let b1 = (py + ph).div_ceil(BAND_ROWS) as usize;
+    (b0, b1.min(BANDS))
+}
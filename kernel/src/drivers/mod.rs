@@ -1,6 +1,7 @@
 // hardware drivers: chip-level and protocol-level, board-independent
 
 pub mod battery;
+pub mod frame_hash;
 pub mod input;
 pub mod sdcard;
 pub mod ssd1677;
@@ -6,6 +6,14 @@
 //   start_du     -- kick DU waveform; caller polls input while BUSY
 //   phase3_sync  -- sync RED+BW; skipped on rapid nav (red_stale)
 //
+// start_fast replaces start_du with a register-loaded A2-style LUT
+// (black/white only, single short drive phase) for menus and scrolling;
+// it leaves more ghosting, which the scheduler accounts for
+//
+// phase1_bw hashes each strip in row bands as it renders it
+// (frame_hash); only rows whose bands changed since the window was
+// last written go to the panel, and an unchanged window is skipped
+//
 // when phase3 is skipped, phase1_bw_inv_red writes RED=!BW so DU
 // drives every pixel to the correct BW target without a full GC
 
@@ -14,6 +22,7 @@ use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal::spi::SpiDevice;
 use esp_hal::delay::Delay;
 
+use super::frame_hash::{BAND_ROWS, BandHasher, FrameHashes};
 use super::strip::{STRIP_COUNT, StripBuffer};
 
 pub const WIDTH: u16 = 800;
@@ -46,6 +55,7 @@ mod cmd {
     pub const DISPLAY_UPDATE_CONTROL_2: u8 = 0x22;
     pub const WRITE_RAM_BW: u8 = 0x24;
     pub const WRITE_RAM_RED: u8 = 0x26;
+    pub const WRITE_LUT: u8 = 0x32;
     pub const BORDER_WAVEFORM: u8 = 0x3C;
     pub const SET_RAM_X_RANGE: u8 = 0x44;
     pub const SET_RAM_Y_RANGE: u8 = 0x45;
@@ -53,6 +63,29 @@ mod cmd {
     pub const SET_RAM_Y_COUNTER: u8 = 0x4F;
 }
 
+// A2-style waveform, SSD1677 register layout: 5 x 10 bytes of voltage
+// selects (LUT0-3 by RED/BW bit pair, LUT4 = VCOM), 10 groups of
+// TP[A..D] + repeat, then frame rate per group pair; 2-bit VS codes
+// are 00 VSS, 01 VSH1 (black), 10 VSL (white)
+//
+// only group 0 phase A drives, and only pixels whose bit changed
+// (LUT1 black->white, LUT2 white->black); ~8 frames, roughly 150 ms.
+// gate/source voltages stay as the last OTP load set them, so this
+// must only run after the panel has done a normal refresh
+const LUT_LEN: usize = 105;
+const LUT_FAST: [u8; LUT_LEN] = {
+    let mut lut = [0u8; LUT_LEN];
+    lut[10] = 0b10 << 6; // LUT1 group 0 phase A: VSL
+    lut[20] = 0b01 << 6; // LUT2 group 0 phase A: VSH1
+    lut[50] = 8; // group 0 TP[A]
+    let mut i = 100;
+    while i < LUT_LEN {
+        lut[i] = 0x22;
+        i += 1;
+    }
+    lut
+};
+
 #[derive(Clone, Copy, Debug)]
 pub struct RenderState {
     pub px: u16,
@@ -61,6 +94,28 @@ pub struct RenderState {
     pub ph: u16,
     pub left_mask: u8,
     pub right_mask: u8,
+    // black pixels written to BW RAM by phase 1; feeds ghosting debt
+    pub ink: u32,
+}
+
+impl RenderState {
+    // smallest panel window covering both; edge masks are dropped, so
+    // only use the result to kick a waveform, never to write RAM
+    pub fn span(&self, other: &RenderState) -> RenderState {
+        let px = self.px.min(other.px);
+        let py = self.py.min(other.py);
+        let x2 = (self.px + self.pw).max(other.px + other.pw);
+        let y2 = (self.py + self.ph).max(other.py + other.ph);
+        RenderState {
+            px,
+            py,
+            pw: x2 - px,
+            ph: y2 - py,
+            left_mask: 0,
+            right_mask: 0,
+            ink: self.ink + other.ink,
+        }
+    }
 }
 
 pub struct DisplayDriver<SPI, DC, RST, BUSY> {
@@ -72,6 +127,13 @@ pub struct DisplayDriver<SPI, DC, RST, BUSY> {
     power_is_on: bool,
     init_done: bool,
     initial_refresh: bool,
+    hashes: FrameHashes,
+}
+
+// black pixels are 0 bits in BW RAM
+#[inline]
+fn count_ink(data: &[u8]) -> u32 {
+    data.iter().map(|b| b.count_zeros()).sum()
 }
 
 impl<SPI, DC, RST, BUSY, E> DisplayDriver<SPI, DC, RST, BUSY>
@@ -91,6 +153,7 @@ where
             power_is_on: false,
             init_done: false,
             initial_refresh: true,
+            hashes: FrameHashes::new(),
         }
     }
 
@@ -108,6 +171,18 @@ where
         self.init_display(delay);
     }
 
+    #[inline]
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    // panel RAM still holds the frame in the old orientation, so the
+    // next refresh after a change must be a full one; partial windows
+    // would be mapped against the new rotation
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn write_region_strips<F>(
         &mut self,
@@ -120,12 +195,14 @@ where
         draw: &F,
         left_mask: u8,
         right_mask: u8,
-    ) where
+    ) -> u32
+    where
         F: Fn(&mut StripBuffer),
     {
         let max_rows = StripBuffer::max_rows_for_width(pw);
         let row_bytes = (pw / 8) as usize;
         let needs_mask = left_mask != 0 || right_mask != 0;
+        let mut ink = 0u32;
 
         self.set_partial_ram_area(px, py, pw, ph);
         self.send_command(ram_cmd);
@@ -142,9 +219,76 @@ where
                     row[row.len() - 1] |= right_mask;
                 }
             }
+            ink += count_ink(strip.data());
             self.send_data(strip.data());
             y += rows;
         }
+        ink
+    }
+
+    // render the window strip by strip, hashing each in row bands, and
+    // write BW RAM only from the first to the last band of a strip that
+    // differs from the last write of the same window; returns the
+    // window narrowed to the rows written, None when nothing changed
+    fn write_changed_strips<F>(
+        &mut self,
+        strip: &mut StripBuffer,
+        rs: RenderState,
+        draw: &F,
+    ) -> Option<RenderState>
+    where
+        F: Fn(&mut StripBuffer),
+    {
+        // strips start on band boundaries so every band is hashed whole
+        let step = StripBuffer::max_rows_for_width(rs.pw) / BAND_ROWS * BAND_ROWS;
+        let row_bytes = (rs.pw / 8) as usize;
+        if row_bytes == 0 || step == 0 {
+            return None;
+        }
+        let needs_mask = rs.left_mask != 0 || rs.right_mask != 0;
+
+        let idx = self.hashes.begin(rs.px, rs.pw);
+        let mut hasher = BandHasher::new();
+        let mut written: Option<(u16, u16)> = None;
+        let mut ink = 0u32;
+        let end = rs.py + rs.ph;
+        let mut y = rs.py;
+        while y < end {
+            let next = (y / BAND_ROWS * BAND_ROWS + step).min(end);
+            let rows = next - y;
+            strip.begin_window(self.rotation, rs.px, y, rs.pw, rows);
+            draw(strip);
+
+            if needs_mask {
+                for row in strip.data_mut().chunks_mut(row_bytes) {
+                    row[0] |= rs.left_mask;
+                    row[row.len() - 1] |= rs.right_mask;
+                }
+            }
+            for (i, row) in strip.data().chunks_exact(row_bytes).enumerate() {
+                hasher.row(y + i as u16, row);
+            }
+
+            if let Some((y0, h)) = self.hashes.stage(idx, rs.py, rs.ph, y, rows, &hasher) {
+                let from = (y0 - y) as usize * row_bytes;
+                let data = &strip.data()[from..from + h as usize * row_bytes];
+                ink += count_ink(data);
+                self.set_partial_ram_area(rs.px, y0, rs.pw, h);
+                self.send_command(cmd::WRITE_RAM_BW);
+                self.send_data(data);
+                let top = written.map_or(y0, |(top, _)| top);
+                written = Some((top, y0 + h));
+            }
+            y = next;
+        }
+
+        let (top, bottom) = written?;
+        Some(RenderState {
+            py: top,
+            ph: bottom - top,
+            ink,
+            ..rs
+        })
     }
 
     // write BW RAM with content, RED RAM with inverted content
@@ -159,12 +303,14 @@ where
         draw: &F,
         left_mask: u8,
         right_mask: u8,
-    ) where
+    ) -> u32
+    where
         F: Fn(&mut StripBuffer),
     {
         let max_rows = StripBuffer::max_rows_for_width(pw);
         let row_bytes = (pw / 8) as usize;
         let needs_mask = left_mask != 0 || right_mask != 0;
+        let mut ink = 0u32;
 
         let mut y = py;
         while y < py + ph {
@@ -178,6 +324,7 @@ where
                     row[row.len() - 1] |= right_mask;
                 }
             }
+            ink += count_ink(strip.data());
 
             self.set_partial_ram_area(px, y, pw, rows);
             self.send_command(cmd::WRITE_RAM_BW);
@@ -189,6 +336,7 @@ where
 
             y += rows;
         }
+        ink
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -288,6 +436,7 @@ where
             ph,
             left_mask,
             right_mask,
+            ink: 0,
         })
     }
 
@@ -408,18 +557,7 @@ where
         }
 
         let rs = self.align_partial_region(x, y, w, h)?;
-        self.write_region_strips(
-            strip,
-            rs.px,
-            rs.py,
-            rs.pw,
-            rs.ph,
-            cmd::WRITE_RAM_BW,
-            draw,
-            rs.left_mask,
-            rs.right_mask,
-        );
-        Some(rs)
+        self.write_changed_strips(strip, rs, draw)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -443,8 +581,11 @@ where
             self.init_display(delay);
         }
 
-        let rs = self.align_partial_region(x, y, w, h)?;
-        self.write_region_strips_bw_inv_red(
+        // RED RAM is out of sync, so the whole window must be driven
+        // whether or not its content changed
+        let mut rs = self.align_partial_region(x, y, w, h)?;
+        self.hashes.invalidate(rs.px, rs.pw, rs.py, rs.ph);
+        rs.ink = self.write_region_strips_bw_inv_red(
             strip,
             rs.px,
             rs.py,
@@ -470,15 +611,37 @@ where
         self.power_is_on = true;
     }
 
+    // same contract as partial_start_du, but with LUT_FAST loaded from
+    // registers; 0xCC runs display mode 2 without reloading the OTP LUT,
+    // and the next DU/GC (0xFC/0xF7) reloads it
+    pub fn partial_start_fast(&mut self, rs: &RenderState) {
+        self.send_command(cmd::WRITE_LUT);
+        self.send_data(&LUT_FAST);
+
+        self.set_partial_ram_area(rs.px, rs.py, rs.pw, rs.ph);
+
+        self.send_command(cmd::DISPLAY_UPDATE_CONTROL_1);
+        self.send_data(&[0x00, 0x00]);
+
+        self.send_command(cmd::DISPLAY_UPDATE_CONTROL_2);
+        self.send_data(&[0xCC]);
+
+        self.send_command(cmd::MASTER_ACTIVATION);
+        self.power_is_on = true;
+    }
+
     #[inline]
     pub fn is_busy(&mut self) -> bool {
         self.busy.is_high().unwrap_or(false)
     }
 
+    // runs only once the waveform has finished, so the hashes staged
+    // for the window in phase 1 now describe the panel
     pub fn partial_phase3_sync<F>(&mut self, strip: &mut StripBuffer, rs: &RenderState, draw: &F)
     where
         F: Fn(&mut StripBuffer),
     {
+        self.hashes.commit(rs.px, rs.pw);
         self.write_region_strips_dual(
             strip,
             rs.px,
@@ -504,6 +667,7 @@ where
         }
 
         delay.delay_millis(1);
+        self.hashes.clear();
 
         for &ram_cmd in &[cmd::WRITE_RAM_RED, cmd::WRITE_RAM_BW] {
             self.set_partial_ram_area(0, 0, WIDTH, HEIGHT);
@@ -599,6 +763,7 @@ where
             Some(rs) => rs,
             None => return,
         };
+        self.hashes.invalidate(rs.px, rs.pw, rs.py, rs.ph);
 
         self.write_region_strips(
             strip,
@@ -107,7 +107,18 @@ fn ext_eq(name: &[u8], target: &[u8]) -> bool {
 }
 
 fn has_supported_ext(name: &[u8]) -> bool {
-    ext_eq(name, b"TXT") || ext_eq(name, b"EPUB") || ext_eq(name, b"EPU") || ext_eq(name, b"MD")
+    ext_eq(name, b"TXT")
+        || ext_eq(name, b"EPUB")
+        || ext_eq(name, b"EPU")
+        || ext_eq(name, b"MD")
+        || ext_eq(name, b"FB2")
+        || ext_eq(name, b"ZIP")
+        || ext_eq(name, b"XTC")
+        || ext_eq(name, b"CBZ")
+        || ext_eq(name, b"HTM")
+        || ext_eq(name, b"HTML")
+        || ext_eq(name, b"XHT")
+        || ext_eq(name, b"XHTML")
 }
 
 // build "NAME.EXT" bytes from a ShortFileName
@@ -482,6 +493,14 @@ pub fn read_file_chunk_in_dir(
     })
 }
 
+pub fn file_size_in_dir(sd: &SdStorage, dir: &str, name: &str) -> crate::error::Result<u32> {
+    poll_once(async {
+        let mut guard = borrow(sd)?;
+        let inner = &mut *guard;
+        in_dir!(inner, dir, |dir_h| op_file_size!(inner, dir_h, name))
+    })
+}
+
 pub fn read_file_start_in_dir(
     sd: &SdStorage,
     dir: &str,
@@ -592,6 +611,54 @@ pub fn read_chunk_in_pulp_subdir(
     })
 }
 
+// batched seek+read: open once, read `span` bytes at each offset into
+// consecutive slots of buf; returns the number of slots filled
+// used by paged glyph tables where one page touches many scattered cells
+pub fn read_spans_in_pulp_subdir(
+    sd: &SdStorage,
+    dir: &str,
+    name: &str,
+    offsets: &[u32],
+    span: usize,
+    buf: &mut [u8],
+) -> crate::error::Result<usize> {
+    poll_once(async {
+        let mut guard = borrow(sd)?;
+        let inner = &mut *guard;
+        in_subdir!(inner, PULP_DIR, dir, |sub_h| {
+            match inner
+                .mgr
+                .open_file_in_dir(sub_h, name, Mode::ReadOnly)
+                .await
+            {
+                Err(_) => Err(Error::new(ErrorKind::OpenFile, "read_spans")),
+                Ok(file) => {
+                    let mut result = Ok(0usize);
+                    for (i, &off) in offsets.iter().enumerate() {
+                        let Some(slot) = buf.get_mut(i * span..(i + 1) * span) else {
+                            break;
+                        };
+                        if inner.mgr.file_seek_from_start(file, off).is_err() {
+                            result = Err(Error::new(ErrorKind::SeekFailed, "read_spans"));
+                            break;
+                        }
+                        match inner.mgr.read(file, slot).await {
+                            Ok(n) if n == span => result = Ok(i + 1),
+                            Ok(_) => break,
+                            Err(_) => {
+                                result = Err(Error::new(ErrorKind::ReadFailed, "read_spans"));
+                                break;
+                            }
+                        }
+                    }
+                    let _ = inner.mgr.close_file(file).await;
+                    result
+                }
+            }
+        })
+    })
+}
+
 pub fn file_size_in_pulp_subdir(
     sd: &SdStorage,
     dir: &str,
@@ -614,6 +681,50 @@ pub fn delete_in_pulp_subdir(sd: &SdStorage, dir: &str, name: &str) -> crate::er
     })
 }
 
+// plain files in _PULP/<dir>/ (no extension filter, no titles);
+// returns how many entries were written to buf
+pub fn list_pulp_subdir(
+    sd: &SdStorage,
+    dir: &str,
+    buf: &mut [DirEntry],
+) -> crate::error::Result<usize> {
+    poll_once(async {
+        let mut guard = borrow(sd)?;
+        let inner = &mut *guard;
+        let mut count = 0usize;
+        in_subdir!(inner, PULP_DIR, dir, |sub_h| inner
+            .mgr
+            .iterate_dir(sub_h, |entry| {
+                if entry.attributes.is_volume() || entry.attributes.is_directory() {
+                    return ControlFlow::Continue(());
+                }
+
+                let mut name_buf = [0u8; 13];
+                let name_len = sfn_to_bytes(&entry.name, &mut name_buf);
+                if name_len == 0 || name_buf[0] == b'.' || name_buf[0] == b'_' {
+                    return ControlFlow::Continue(());
+                }
+                if count == buf.len() {
+                    return ControlFlow::Break(());
+                }
+
+                buf[count] = DirEntry {
+                    name: name_buf,
+                    name_len,
+                    is_dir: false,
+                    size: entry.size,
+                    title: [0u8; TITLE_CAP],
+                    title_len: 0,
+                };
+                count += 1;
+                ControlFlow::Continue(())
+            })
+            .await
+            .map_err(|_| Error::new(ErrorKind::ReadFailed, "list_pulp_subdir")))?;
+        Ok(count)
+    })
+}
+
 // _PULP/ direct file operations (cache files live directly in _PULP/)
 
 pub fn read_chunk_in_pulp(
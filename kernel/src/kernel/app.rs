@@ -16,13 +16,13 @@
 use embassy_time::Instant;
 use esp_hal::delay::Delay;
 
-use crate::board::Epd;
 use crate::board::action::ActionEvent;
+use crate::board::{Epd, Orientation};
 use crate::drivers::input::Event;
 use crate::drivers::sdcard::SdStorage;
 #[allow(unused_imports)]
 use crate::drivers::strip::StripBuffer;
-use crate::ui::Region;
+use crate::ui::{Region, RegionSet};
 
 use super::KernelHandle;
 use super::bookmarks::BookmarkCache;
@@ -96,10 +96,48 @@ pub enum Transition<Id> {
     Home,
 }
 
+// waveform hint for a partial redraw; Fast (A2-style custom LUT,
+// black/white only) is for selection moves and scrolling, Normal (DU)
+// for content that should stay clean, and Clean asks for a full GC
+// right away (chapter changes, image pages) instead of waiting for
+// the ghosting debt to run up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    Normal,
+    Fast,
+    Clean,
+}
+
+impl Quality {
+    // a merged redraw is only fast when every part asked for it, and
+    // clean when any part did
+    #[inline]
+    pub const fn merge(self, other: Quality) -> Quality {
+        match (self, other) {
+            (Quality::Clean, _) | (_, Quality::Clean) => Quality::Clean,
+            (Quality::Fast, Quality::Fast) => Quality::Fast,
+            _ => Quality::Normal,
+        }
+    }
+}
+
+// a partial redraw carries every dirty rectangle of the frame rather
+// than one bounding box, so a header and a far-away list row don't
+// refresh the screen between them
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Redraw {
     None,
-    Partial(Region),
+    Partial(RegionSet, Quality),
+    Full,
+}
+
+// what enter_sleep leaves on the panel; Badge keeps the current frame
+// and refreshes only the given window (current orientation) on top of
+// it, Full is a portrait frame drawn by AppLayer::draw_sleep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepScreen {
+    Text,
+    Badge(Region),
     Full,
 }
 
@@ -169,19 +207,42 @@ impl AppContext {
     }
 
     pub fn request_partial_redraw(&mut self, region: Region) {
+        self.request_partial_redraw_as(region, Quality::Normal);
+    }
+
+    pub fn request_partial_redraw_as(&mut self, region: Region, quality: Quality) {
         match self.redraw {
             Redraw::Full => {}
-            Redraw::Partial(existing) => {
-                self.redraw = Redraw::Partial(existing.union(region));
+            Redraw::Partial(mut set, q) => {
+                set.add(region);
+                self.redraw = Redraw::Partial(set, q.merge(quality));
             }
-            Redraw::None => self.redraw = Redraw::Partial(region),
+            Redraw::None => self.redraw = Redraw::Partial(RegionSet::single(region), quality),
         }
     }
 
     // mark dirty and render on next tick; the default for all callers
     #[inline]
     pub fn mark_dirty(&mut self, region: Region) {
-        self.request_partial_redraw(region);
+        self.mark_dirty_as(region, Quality::Normal);
+    }
+
+    // mark dirty for the fast waveform; for cursor and selection moves
+    // where latency matters more than a little extra ghosting
+    #[inline]
+    pub fn mark_dirty_fast(&mut self, region: Region) {
+        self.mark_dirty_as(region, Quality::Fast);
+    }
+
+    // mark dirty and clear ghosting with a full GC on this render
+    #[inline]
+    pub fn mark_dirty_clean(&mut self, region: Region) {
+        self.mark_dirty_as(region, Quality::Clean);
+    }
+
+    #[inline]
+    pub fn mark_dirty_as(&mut self, region: Region, quality: Quality) {
+        self.request_partial_redraw_as(region, quality);
         self.immediate = true;
         self.coalesce_until = None;
     }
@@ -209,7 +270,7 @@ impl AppContext {
         match self.redraw {
             Redraw::None => false,
             Redraw::Full => true,
-            Redraw::Partial(_) => {
+            Redraw::Partial(..) => {
                 self.immediate || self.coalesce_until.is_none_or(|t| Instant::now() >= t)
             }
         }
@@ -462,6 +523,13 @@ pub trait AppLayer {
     fn request_full_redraw(&mut self);
     fn ctx_mut(&mut self) -> &mut AppContext;
 
+    // orientation the next frame should be drawn in; the scheduler
+    // rotates the display and promotes the frame to a full refresh
+    // whenever this changes
+    fn orientation(&self) -> Orientation {
+        Orientation::Portrait
+    }
+
     // system configuration
     fn system_settings(&self) -> &SystemSettings;
     fn settings_loaded(&self) -> bool;
@@ -504,6 +572,15 @@ pub trait AppLayer {
     ) {
     }
 
+    // sleep screen; prepare_sleep runs while the SD card is still up
+    // and does all the I/O (cover or image decode), draw_sleep then
+    // renders the frame or badge without touching storage
+    fn prepare_sleep(&mut self, _k: &mut KernelHandle<'_>) -> SleepScreen {
+        SleepScreen::Text
+    }
+
+    fn draw_sleep(&self, _strip: &mut StripBuffer) {}
+
     // true when deferred input during EPD refresh should be
     // suppressed (e.g. quick menu overlay is open)
     fn suppress_deferred_input(&self) -> bool {
@@ -3,7 +3,7 @@
 // record layout (little-endian, 48 bytes per slot):
 //   [0..4)   name_hash  u32    [8..10)  chapter    u16
 //   [4..8)   byte_offset u32   [10..12) flags      u16 (bit 0 = valid)
-//   [12..14) generation u16    [14] name_len u8  [15] pad
+//   [12..14) generation u16    [14] name_len u8  [15] progress u8 (%)
 //   [16..48) filename [u8;32]
 
 use crate::drivers::sdcard::SdStorage;
@@ -50,6 +50,7 @@ pub struct BookmarkSlot {
     pub name_hash: u32,
     pub byte_offset: u32,
     pub chapter: u16,
+    pub progress: u8,
     pub valid: bool,
     pub generation: u16,
     pub name_len: u8,
@@ -61,6 +62,7 @@ impl BookmarkSlot {
         name_hash: 0,
         byte_offset: 0,
         chapter: 0,
+        progress: 0,
         valid: false,
         generation: 0,
         name_len: 0,
@@ -83,6 +85,7 @@ impl BookmarkSlot {
             name_hash: read_u32_le(rec, 0),
             byte_offset: read_u32_le(rec, 4),
             chapter: read_u16_le(rec, 8),
+            progress: rec[15].min(100),
             valid: read_u16_le(rec, 10) & 1 != 0,
             generation: read_u16_le(rec, 12),
             name_len,
@@ -98,6 +101,7 @@ impl BookmarkSlot {
         write_u16_le(&mut rec, 10, if self.valid { 1 } else { 0 });
         write_u16_le(&mut rec, 12, self.generation);
         rec[14] = self.name_len;
+        rec[15] = self.progress;
         rec[16..16 + self.name_len as usize]
             .copy_from_slice(&self.filename[..self.name_len as usize]);
         rec
@@ -266,7 +270,7 @@ impl BookmarkCache {
         count
     }
 
-    pub fn save(&mut self, filename: &[u8], byte_offset: u32, chapter: u16) {
+    pub fn save(&mut self, filename: &[u8], byte_offset: u32, chapter: u16, progress: u8) {
         if !self.loaded {
             log::warn!("bookmarks: save called before load, ignoring");
             return;
@@ -324,6 +328,7 @@ impl BookmarkCache {
             name_hash: key,
             byte_offset,
             chapter,
+            progress: progress.min(100),
             valid: true,
             generation,
             name_len: name_len as u8,
@@ -77,6 +77,28 @@ pub const READING_THEMES: [ReadingTheme; NUM_READING_THEMES as usize] = [
     },
 ];
 
+// text columns per page; 0 = auto (split when lines get too long
+// to read comfortably, e.g. in landscape or at the smallest sizes)
+pub const MAX_READING_COLUMNS: u8 = 3;
+pub const AUTO_READING_COLUMNS: u8 = 0;
+
+// what the panel shows while the device sleeps; Cover and Image fall
+// back to Card when there is no book cover or no usable image
+pub const SLEEP_SCREEN_COVER: u8 = 0;
+pub const SLEEP_SCREEN_PAGE: u8 = 1;
+pub const SLEEP_SCREEN_IMAGE: u8 = 2;
+pub const SLEEP_SCREEN_CARD: u8 = 3;
+pub const NUM_SLEEP_SCREENS: u8 = 4;
+
+pub fn sleep_screen_name(idx: u8) -> &'static str {
+    match idx {
+        SLEEP_SCREEN_COVER => "Cover",
+        SLEEP_SCREEN_PAGE => "Page",
+        SLEEP_SCREEN_IMAGE => "Image",
+        _ => "Card",
+    }
+}
+
 // look up the active reading theme by index; falls back to Default
 pub fn reading_theme(idx: u8) -> &'static ReadingTheme {
     let i = (idx as usize).min(READING_THEMES.len() - 1);
@@ -87,7 +109,8 @@ pub fn reading_theme(idx: u8) -> &'static ReadingTheme {
 pub struct SystemSettings {
     // power settings
     pub sleep_timeout: u16,    // minutes idle before sleep; 0 = never
-    pub ghost_clear_every: u8, // partial refreshes before forced full GC
+    pub ghost_clear_every: u8, // full panels of partial refresh before forced full GC
+    pub sleep_screen: u8,      // SLEEP_SCREEN_*
 
     // font settings
     pub book_font_size_idx: u8, // 0 = XSmall, 1 = Small, 2 = Medium, 3 = Large, 4 = XLarge
@@ -95,6 +118,10 @@ pub struct SystemSettings {
 
     // reading settings
     pub reading_theme: u8, // index into READING_THEMES
+    pub landscape: bool,   // read with the device turned sideways (reader only)
+    pub columns: u8,       // text columns per page; AUTO_READING_COLUMNS = auto
+    pub comic_split: bool, // show double-page comic spreads one half at a time
+    pub comic_rtl: bool,   // right half of a spread first (manga)
 
     // control settings
     pub swap_buttons: bool, // swap Back/Select with Left/Right physical buttons
@@ -111,9 +138,14 @@ impl SystemSettings {
         Self {
             sleep_timeout: DEFAULT_SLEEP_TIMEOUT,
             ghost_clear_every: DEFAULT_GHOST_CLEAR,
+            sleep_screen: SLEEP_SCREEN_COVER,
             book_font_size_idx: DEFAULT_FONT_SIZE_IDX,
             ui_font_size_idx: DEFAULT_FONT_SIZE_IDX,
             reading_theme: DEFAULT_READING_THEME,
+            landscape: false,
+            columns: AUTO_READING_COLUMNS,
+            comic_split: false,
+            comic_rtl: false,
             swap_buttons: false,
         }
     }
@@ -130,6 +162,8 @@ impl SystemSettings {
         self.book_font_size_idx = self.book_font_size_idx.min(max_font);
         self.ui_font_size_idx = self.ui_font_size_idx.min(max_font);
         self.reading_theme = self.reading_theme.min(NUM_READING_THEMES - 1);
+        self.columns = self.columns.min(MAX_READING_COLUMNS);
+        self.sleep_screen = self.sleep_screen.min(NUM_SLEEP_SCREENS - 1);
     }
 
     // reasonable default - override via sanitize_with_max_font
@@ -229,11 +263,30 @@ fn apply_setting(key: &[u8], val: &[u8], s: &mut SystemSettings, w: &mut WifiCon
                 s.ui_font_size_idx = v as u8;
             }
         }
+        b"sleep_screen" => {
+            if let Some(v) = parse_u16(val) {
+                s.sleep_screen = v as u8;
+            }
+        }
         b"reading_theme" => {
             if let Some(v) = parse_u16(val) {
                 s.reading_theme = v as u8;
             }
         }
+        b"landscape" => {
+            s.landscape = val == b"1" || val == b"true";
+        }
+        b"columns" => {
+            if let Some(v) = parse_u16(val) {
+                s.columns = v as u8;
+            }
+        }
+        b"comic_split" => {
+            s.comic_split = val == b"1" || val == b"true";
+        }
+        b"comic_rtl" => {
+            s.comic_rtl = val == b"1" || val == b"true";
+        }
         b"swap_buttons" => {
             s.swap_buttons = val == b"1" || val == b"true";
         }
@@ -312,6 +365,8 @@ pub fn write_settings_txt(s: &SystemSettings, w: &WifiConfig, buf: &mut [u8]) ->
     wr.put(b"# power settings\n");
     wr.kv_num(b"sleep_timeout", s.sleep_timeout);
     wr.kv_num(b"ghost_clear", s.ghost_clear_every as u16);
+    wr.put(b"# sleep screen: 0=Cover, 1=Page, 2=Image, 3=Card\n");
+    wr.kv_num(b"sleep_screen", s.sleep_screen as u16);
 
     wr.put(b"\n# font settings\n");
     wr.kv_num(b"book_font", s.book_font_size_idx as u16);
@@ -319,6 +374,10 @@ pub fn write_settings_txt(s: &SystemSettings, w: &WifiConfig, buf: &mut [u8]) ->
 
     wr.put(b"\n# reading settings (0=Compact, 1=Default, 2=Relaxed, 3=Spacious)\n");
     wr.kv_num(b"reading_theme", s.reading_theme as u16);
+    wr.kv_num(b"landscape", if s.landscape { 1 } else { 0 });
+    wr.kv_num(b"columns", s.columns as u16);
+    wr.kv_num(b"comic_split", if s.comic_split { 1 } else { 0 });
+    wr.kv_num(b"comic_rtl", if s.comic_rtl { 1 } else { 0 });
 
     wr.put(b"\n# control settings\n");
     wr.kv_num(b"swap_buttons", if s.swap_buttons { 1 } else { 0 });
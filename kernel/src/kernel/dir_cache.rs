@@ -102,6 +102,26 @@ impl DirCache {
         DirPage { total, count }
     }
 
+    // like page, over only the entries keep accepts; total counts
+    // the matches so callers can page through them the same way
+    pub fn page_filtered(
+        &self,
+        offset: usize,
+        buf: &mut [DirEntry],
+        keep: impl Fn(&DirEntry) -> bool,
+    ) -> DirPage {
+        let mut total = 0;
+        let mut count = 0;
+        for e in self.entries[..self.count].iter().filter(|e| keep(e)) {
+            if total >= offset && count < buf.len() {
+                buf[count].clone_from(e);
+                count += 1;
+            }
+            total += 1;
+        }
+        DirPage { total, count }
+    }
+
     pub fn invalidate(&mut self) {
         self.valid = false;
     }
@@ -109,14 +129,17 @@ impl DirCache {
     pub fn next_untitled_epub(&self, from: usize) -> Option<(usize, [u8; 13], u8)> {
         for i in from..self.count {
             let e = &self.entries[i];
-            if e.has_real_title() || e.is_dir {
-                continue;
+            if !e.has_real_title() && is_epub_entry(e) {
+                return Some((i, e.name, e.name_len));
             }
-            let name = e.name_str().as_bytes();
-            if name.len() >= 5
-                && name[name.len() - 5..name.len() - 4] == [b'.']
-                && name[name.len() - 4..].eq_ignore_ascii_case(b"EPUB")
-            {
+        }
+        None
+    }
+
+    pub fn next_epub(&self, from: usize) -> Option<(usize, [u8; 13], u8)> {
+        for i in from..self.count {
+            let e = &self.entries[i];
+            if is_epub_entry(e) {
                 return Some((i, e.name, e.name_len));
             }
         }
@@ -150,6 +173,14 @@ impl DirCache {
     }
 }
 
+fn is_epub_entry(e: &DirEntry) -> bool {
+    let name = e.name_str().as_bytes();
+    !e.is_dir
+        && name.len() >= 5
+        && name[name.len() - 5..name.len() - 4] == [b'.']
+        && name[name.len() - 4..].eq_ignore_ascii_case(b"EPUB")
+}
+
 // insertion sort; count <= 128
 fn sort_entries(entries: &mut [DirEntry], count: usize) {
     for i in 1..count {
@@ -80,6 +80,30 @@ impl<'k> KernelHandle<'k> {
         storage::read_file_start(&self.kernel.sd, name, buf)
     }
 
+    // files beside the books: dir is a folder in the card root, or
+    // empty for the root itself
+    pub fn file_size_in(&mut self, dir: &str, name: &str) -> Result<u32> {
+        if dir.is_empty() {
+            storage::file_size(&self.kernel.sd, name)
+        } else {
+            storage::file_size_in_dir(&self.kernel.sd, dir, name)
+        }
+    }
+
+    pub fn read_chunk_in(
+        &mut self,
+        dir: &str,
+        name: &str,
+        offset: u32,
+        buf: &mut [u8],
+    ) -> Result<usize> {
+        if dir.is_empty() {
+            storage::read_file_chunk(&self.kernel.sd, name, offset, buf)
+        } else {
+            storage::read_file_chunk_in_dir(&self.kernel.sd, dir, name, offset, buf)
+        }
+    }
+
     #[inline]
     pub fn save_title(&mut self, filename: &str, title: &str) -> Result<()> {
         storage::save_title(&self.kernel.sd, filename, title)
@@ -111,6 +135,18 @@ impl<'k> KernelHandle<'k> {
         storage::read_chunk_in_pulp_subdir(&self.kernel.sd, dir, name, offset, buf)
     }
 
+    #[inline]
+    pub fn read_app_subdir_spans(
+        &mut self,
+        dir: &str,
+        name: &str,
+        offsets: &[u32],
+        span: usize,
+        buf: &mut [u8],
+    ) -> Result<usize> {
+        storage::read_spans_in_pulp_subdir(&self.kernel.sd, dir, name, offsets, span, buf)
+    }
+
     #[inline]
     pub fn write_app_subdir(&mut self, dir: &str, name: &str, data: &[u8]) -> Result<()> {
         storage::write_in_pulp_subdir(&self.kernel.sd, dir, name, data)
@@ -131,6 +167,11 @@ impl<'k> KernelHandle<'k> {
         storage::delete_in_pulp_subdir(&self.kernel.sd, dir, name)
     }
 
+    #[inline]
+    pub fn list_app_subdir(&mut self, dir: &str, buf: &mut [DirEntry]) -> Result<usize> {
+        storage::list_pulp_subdir(&self.kernel.sd, dir, buf)
+    }
+
     // _PULP/ direct file ops (v3 unified cache files)
 
     #[inline]
@@ -175,6 +216,17 @@ impl<'k> KernelHandle<'k> {
         Ok(k.dir_cache.page(offset, buf))
     }
 
+    pub fn dir_page_filtered(
+        &mut self,
+        offset: usize,
+        buf: &mut [DirEntry],
+        keep: impl Fn(&DirEntry) -> bool,
+    ) -> Result<DirPage> {
+        let k = &mut *self.kernel;
+        k.dir_cache.ensure_loaded(&k.sd)?;
+        Ok(k.dir_cache.page_filtered(offset, buf, keep))
+    }
+
     pub fn invalidate_dir_cache(&mut self) {
         self.kernel.dir_cache.invalidate();
     }
@@ -26,8 +26,8 @@ pub use crate::error::{Error, ErrorKind, Result, ResultExt};
 pub use crate::drivers::storage::StorageError;
 
 pub use app::{
-    App, AppContext, AppIdType, AppLayer, Launcher, NavEvent, PendingSetting, QuickAction,
-    QuickActionKind, RECENT_FILE, Redraw, Transition,
+    App, AppContext, AppIdType, AppLayer, Launcher, NavEvent, PendingSetting, Quality, QuickAction,
+    QuickActionKind, RECENT_FILE, Redraw, SleepScreen, Transition,
 };
 pub use bookmarks::BookmarkCache;
 pub use console::BootConsole;
@@ -53,7 +53,8 @@ pub struct Kernel {
     pub(crate) delay: Delay,
     pub(crate) sd_ok: bool,
     pub(crate) cached_battery_mv: u16,
-    pub(crate) partial_refreshes: u32,
+    // ghosting debt since the last full GC; see scheduler::window_debt
+    pub(crate) ghost_debt: u32,
 
     // true when RED RAM is out of sync with BW after a skipped
     // phase3_sync (rapid navigation); next partial uses inv_red
@@ -81,7 +82,7 @@ impl Kernel {
             delay,
             sd_ok,
             cached_battery_mv: battery_mv,
-            partial_refreshes: 0,
+            ghost_debt: 0,
             red_stale: false,
         }
     }
@@ -97,13 +98,8 @@ impl Kernel {
     }
 
     #[inline]
-    pub fn reset_partial_count(&mut self) {
-        self.partial_refreshes = 0;
+    pub fn reset_ghost_debt(&mut self) {
+        self.ghost_debt = 0;
         self.red_stale = false;
     }
-
-    #[inline]
-    pub fn bump_partial_count(&mut self) {
-        self.partial_refreshes += 1;
-    }
 }
@@ -53,7 +53,7 @@ pub struct RtcSession {
     _files_pad: [u8; 3],
 
     // home state (8 bytes)
-    pub home_state: u8, // 0=Menu, 1=ShowBookmarks
+    pub home_state: u8, // 0=Menu, 1=ShowBookmarks, 2=ShowCollections
     pub home_selected: u8,
     pub home_bm_selected: u8,
     pub home_bm_scroll: u8,
@@ -18,17 +18,39 @@ use embassy_futures::select::{Either, select};
 use embassy_time::{Duration, Ticker, with_timeout};
 use log::info;
 
-use super::app::{AppLayer, Redraw, Transition};
+use super::app::{AppLayer, Quality, Redraw, SleepScreen, Transition};
 use crate::board::button::Button;
+use crate::board::{HEIGHT, Orientation, WIDTH};
 use crate::drivers::battery;
 use crate::drivers::input::Event;
+use crate::drivers::ssd1677::RenderState;
 use crate::drivers::strip::StripBuffer;
 use crate::kernel::tasks;
 
-use crate::ui::{free_stack_bytes, stack_high_water_mark};
+use crate::ui::{MAX_DIRTY, Region, RegionSet, free_stack_bytes, stack_high_water_mark};
 
 use super::timing;
 
+// ghosting debt is counted in DEBT_PER_PANEL units per full-panel
+// DU of blank content. a window costs its share of the panel area,
+// up to twice that for solid ink: the strips only show the new frame,
+// and the ink written now is what ghosts once it turns white again
+const DEBT_PER_PANEL: u32 = 100;
+const PANEL_PX: u32 = WIDTH as u32 * HEIGHT as u32;
+
+// even a tiny window runs a waveform over the whole panel
+const MIN_REFRESH_DEBT: u32 = 1;
+
+// multiplier for a fast (A2) partial; the two-level waveform leaves
+// visibly more residue than DU
+const FAST_GHOST_COST: u32 = 3;
+
+#[inline]
+fn window_debt(rs: &RenderState) -> u32 {
+    let area = rs.pw as u32 * rs.ph as u32;
+    (area + rs.ink.min(area)) * DEBT_PER_PANEL / PANEL_PX
+}
+
 #[inline]
 fn is_power_event(ev: Event) -> bool {
     matches!(
@@ -90,6 +112,7 @@ impl super::Kernel {
             app_mgr.enter_initial(&mut self.handle());
         }
 
+        self.epd.set_rotation(app_mgr.orientation().rotation());
         {
             let draw = |s: &mut StripBuffer| app_mgr.draw(s);
             self.epd
@@ -196,6 +219,7 @@ impl super::Kernel {
     // delegate to app layer for modes that bypass normal dispatch
     // (e.g. wifi upload); kernel passes hardware resources through
     async fn handle_special_mode<A: AppLayer>(&mut self, app_mgr: &mut A) {
+        self.epd.set_rotation(app_mgr.orientation().rotation());
         app_mgr
             .run_special_mode(&mut self.epd, self.strip, &mut self.delay, &self.sd)
             .await;
@@ -271,74 +295,48 @@ impl super::Kernel {
         self.poll_housekeeping_inner(app_mgr);
     }
 
-    // partial refreshes use DU waveform (~400 ms); after ghost_clear_every
-    // partials, a full GC refresh (~1.6 s) clears ghosting
+    // partial refreshes use DU waveform (~400 ms), or the fast A2 LUT
+    // when the redraw asks for it; each adds ghosting debt by area and
+    // ink, and once the debt reaches ghost_clear_every panels' worth (or
+    // the redraw asks for Clean) a full GC refresh (~1.6 s) clears it
     //
     // returns true if power-long-press arrived during the waveform and
     // the caller should enter sleep
     async fn render<A: AppLayer>(&mut self, app_mgr: &mut A, redraw: Redraw) -> bool {
         let mut sleep_requested = false;
 
+        // panel RAM holds the old orientation; only a full frame can
+        // switch it over
+        let rotation = app_mgr.orientation().rotation();
+        let redraw = if rotation != self.epd.rotation() {
+            info!(
+                "display: rotation {:?} -> {:?}",
+                self.epd.rotation(),
+                rotation
+            );
+            self.epd.set_rotation(rotation);
+            Redraw::Full
+        } else {
+            redraw
+        };
+
         'render: {
-            if let Redraw::Partial(r) = redraw {
-                let ghost_clear_every = app_mgr.ghost_clear_every();
-
-                if self.partial_refreshes < ghost_clear_every {
-                    let r = r.align8();
-
-                    let rs = {
-                        let draw = |s: &mut StripBuffer| app_mgr.draw(s);
-                        if self.red_stale {
-                            self.epd.partial_phase1_bw_inv_red(
-                                self.strip,
-                                r.x,
-                                r.y,
-                                r.w,
-                                r.h,
-                                &mut self.delay,
-                                &draw,
-                            )
-                        } else {
-                            self.epd.partial_phase1_bw(
-                                self.strip,
-                                r.x,
-                                r.y,
-                                r.w,
-                                r.h,
-                                &mut self.delay,
-                                &draw,
-                            )
-                        }
+            if let Redraw::Partial(set, quality) = redraw {
+                let debt_limit = app_mgr.ghost_clear_every() * DEBT_PER_PANEL;
+
+                if quality == Quality::Clean {
+                    info!("display: clean refresh requested, promoting to full");
+                } else if self.ghost_debt < debt_limit {
+                    // one waveform over the span of every window when
+                    // that costs no more than refreshing them one after
+                    // another (RegionSet::refresh_merged)
+                    let written = if set.refresh_merged() {
+                        self.partial_merged(app_mgr, &set, quality).await
+                    } else {
+                        self.partial_each(app_mgr, &set, quality).await
                     };
-
-                    if let Some(rs) = rs {
-                        self.epd.partial_start_du(&rs);
-                        let (deferred, sleep) = self.busy_wait_with_background(app_mgr).await;
+                    if let Some(sleep) = written {
                         sleep_requested = sleep;
-
-                        // skip phase 3 when content changed mid-DU or
-                        // a deferred transition is queued (the screen
-                        // will be redrawn immediately after); the next
-                        // partial will use inv_red to compensate for
-                        // the desynchronised RED RAM
-                        if app_mgr.has_redraw() || deferred.is_some() {
-                            app_mgr.ctx_mut().mark_dirty(r);
-                            self.red_stale = true;
-                            self.partial_refreshes += 1;
-                        } else {
-                            self.red_stale = false;
-                            {
-                                let draw = |s: &mut StripBuffer| app_mgr.draw(s);
-                                self.epd.partial_phase3_sync(self.strip, &rs, &draw);
-                            }
-                            self.partial_refreshes += 1;
-                            self.epd.power_off_async().await;
-                        }
-
-                        if let Some(transition) = deferred {
-                            app_mgr.apply_transition(transition, &mut self.handle());
-                        }
-
                         break 'render;
                     }
 
@@ -347,11 +345,14 @@ impl super::Kernel {
                     }
                     info!("display: partial failed (initial refresh), promoting to full");
                 } else {
-                    info!("display: promoted partial to full (ghosting clear)");
+                    info!(
+                        "display: promoted partial to full (ghosting debt {}/{})",
+                        self.ghost_debt, debt_limit
+                    );
                 }
             }
 
-            if matches!(redraw, Redraw::Full | Redraw::Partial(_)) {
+            if matches!(redraw, Redraw::Full | Redraw::Partial(..)) {
                 self.epd.power_off_async().await;
 
                 self.log_stats();
@@ -368,7 +369,7 @@ impl super::Kernel {
                 sleep_requested = sleep;
 
                 self.epd.finish_full_update();
-                self.partial_refreshes = 0;
+                self.ghost_debt = 0;
                 self.red_stale = false;
 
                 if let Some(transition) = deferred {
@@ -380,6 +381,147 @@ impl super::Kernel {
         sleep_requested
     }
 
+    // phase 1 of one window: BW RAM, and RED RAM inverted while RED is
+    // out of sync; None when nothing in the window changed
+    fn partial_phase1<A: AppLayer>(&mut self, app_mgr: &A, r: Region) -> Option<RenderState> {
+        let r = r.align8();
+        let draw = |s: &mut StripBuffer| app_mgr.draw(s);
+        if self.red_stale {
+            self.epd.partial_phase1_bw_inv_red(
+                self.strip,
+                r.x,
+                r.y,
+                r.w,
+                r.h,
+                &mut self.delay,
+                &draw,
+            )
+        } else {
+            self.epd
+                .partial_phase1_bw(self.strip, r.x, r.y, r.w, r.h, &mut self.delay, &draw)
+        }
+    }
+
+    // kick the waveform over rs; returns the ghosting debt it adds
+    fn partial_start(&mut self, rs: &RenderState, area_debt: u32, quality: Quality) -> u32 {
+        match quality {
+            Quality::Fast => {
+                self.epd.partial_start_fast(rs);
+                area_debt * FAST_GHOST_COST
+            }
+            _ => {
+                self.epd.partial_start_du(rs);
+                area_debt
+            }
+        }
+    }
+
+    // write each window's BW RAM, kick one waveform over their span,
+    // then sync each window in phase 3. None when no window was
+    // written, else whether sleep was requested
+    async fn partial_merged<A: AppLayer>(
+        &mut self,
+        app_mgr: &mut A,
+        set: &RegionSet,
+        quality: Quality,
+    ) -> Option<bool> {
+        let mut states: [Option<RenderState>; MAX_DIRTY] = [None; MAX_DIRTY];
+        let mut span: Option<RenderState> = None;
+        for (slot, &r) in states.iter_mut().zip(set.as_slice()) {
+            let rs = self.partial_phase1(app_mgr, r);
+            if let Some(rs) = rs {
+                span = Some(span.map_or(rs, |s| s.span(&rs)));
+            }
+            *slot = rs;
+        }
+        let span = span?;
+
+        let area_debt = states
+            .iter()
+            .flatten()
+            .map(window_debt)
+            .sum::<u32>()
+            .max(MIN_REFRESH_DEBT);
+        let debt = self.partial_start(&span, area_debt, quality);
+        let (deferred, sleep) = self.busy_wait_with_background(app_mgr).await;
+        self.ghost_debt += debt;
+
+        // skip phase 3 when content changed mid-DU or a deferred
+        // transition is queued (the screen will be redrawn immediately
+        // after); the next partial will use inv_red to compensate for
+        // the desynchronised RED RAM
+        if app_mgr.has_redraw() || deferred.is_some() {
+            for &r in set.as_slice() {
+                app_mgr.ctx_mut().mark_dirty_as(r, quality);
+            }
+            self.red_stale = true;
+        } else {
+            self.red_stale = false;
+            {
+                let draw = |s: &mut StripBuffer| app_mgr.draw(s);
+                for rs in states.iter().flatten() {
+                    self.epd.partial_phase3_sync(self.strip, rs, &draw);
+                }
+            }
+            self.epd.power_off_async().await;
+        }
+
+        if let Some(transition) = deferred {
+            app_mgr.apply_transition(transition, &mut self.handle());
+        }
+        Some(sleep)
+    }
+
+    // a full three-phase partial per window, one after another; a
+    // redraw, transition or sleep arriving mid-waveform stops the run
+    // and leaves the windows not yet synced dirty. returns as
+    // partial_merged does
+    async fn partial_each<A: AppLayer>(
+        &mut self,
+        app_mgr: &mut A,
+        set: &RegionSet,
+        quality: Quality,
+    ) -> Option<bool> {
+        let windows = set.as_slice();
+        let mut written = false;
+        for (i, &r) in windows.iter().enumerate() {
+            let Some(rs) = self.partial_phase1(app_mgr, r) else {
+                continue;
+            };
+            written = true;
+
+            let debt = self.partial_start(&rs, window_debt(&rs).max(MIN_REFRESH_DEBT), quality);
+            let (deferred, sleep) = self.busy_wait_with_background(app_mgr).await;
+            self.ghost_debt += debt;
+
+            let changed = app_mgr.has_redraw() || deferred.is_some();
+            if changed {
+                self.red_stale = true;
+            } else {
+                self.red_stale = false;
+                let draw = |s: &mut StripBuffer| app_mgr.draw(s);
+                self.epd.partial_phase3_sync(self.strip, &rs, &draw);
+            }
+
+            if changed || sleep {
+                let rest = if changed { i } else { i + 1 };
+                for &r in &windows[rest..] {
+                    app_mgr.ctx_mut().mark_dirty_as(r, quality);
+                }
+                if let Some(transition) = deferred {
+                    app_mgr.apply_transition(transition, &mut self.handle());
+                }
+                return Some(sleep);
+            }
+        }
+
+        if !written {
+            return None;
+        }
+        self.epd.power_off_async().await;
+        Some(false)
+    }
+
     // collect input and run background work while EPD is busy refreshing
     //
     // during the DU/GC waveform the EPD charge pump drives pixels;
@@ -478,7 +620,7 @@ impl super::Kernel {
         rtc_session::save(&session);
         info!("session: saved to RTC memory");
 
-        self.enter_sleep(reason).await;
+        self.enter_sleep(app_mgr, reason).await;
     }
 
     // flush bookmarks, render sleep screen, enter MCU deep sleep;
@@ -486,7 +628,7 @@ impl super::Kernel {
     //
     // uses a custom sleep config that keeps RTC FAST memory powered
     // so session state survives the sleep cycle (~1-2µA extra)
-    async fn enter_sleep(&mut self, reason: &str) {
+    async fn enter_sleep<A: AppLayer>(&mut self, app_mgr: &mut A, reason: &str) {
         use embedded_graphics::mono_font::MonoTextStyle;
         use embedded_graphics::mono_font::ascii::FONT_9X18;
         use embedded_graphics::pixelcolor::BinaryColor;
@@ -502,14 +644,35 @@ impl super::Kernel {
             self.bm_cache.flush(&self.sd);
         }
 
+        // all sleep-screen I/O happens here, before the card goes down
+        let screen = app_mgr.prepare_sleep(&mut self.handle());
+
         self.sd_card_sleep();
 
-        self.epd
-            .full_refresh_async(self.strip, &mut self.delay, &|s: &mut StripBuffer| {
-                let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::On);
-                let _ = Text::new("(sleep)", Point::new(210, 400), style).draw(s);
-            })
-            .await;
+        let draw = |s: &mut StripBuffer| app_mgr.draw_sleep(s);
+        match screen {
+            SleepScreen::Badge(r) => {
+                // panel still holds the last frame in the current rotation
+                self.epd
+                    .partial_refresh_async(self.strip, &mut self.delay, r.x, r.y, r.w, r.h, &draw)
+                    .await;
+            }
+            SleepScreen::Full => {
+                self.epd.set_rotation(Orientation::Portrait.rotation());
+                self.epd
+                    .full_refresh_async(self.strip, &mut self.delay, &draw)
+                    .await;
+            }
+            SleepScreen::Text => {
+                self.epd.set_rotation(Orientation::Portrait.rotation());
+                self.epd
+                    .full_refresh_async(self.strip, &mut self.delay, &|s: &mut StripBuffer| {
+                        let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::On);
+                        let _ = Text::new("(sleep)", Point::new(210, 400), style).draw(s);
+                    })
+                    .await;
+            }
+        }
         info!("display: sleep screen rendered");
 
         self.epd.enter_deep_sleep();
@@ -15,7 +15,8 @@ pub use layout::{
 pub use stack_fmt::{StackFmt, stack_fmt};
 pub use statusbar::{BAR_HEIGHT, free_stack_bytes, paint_stack, stack_high_water_mark};
 pub use widget::{
-    Alignment, Region, draw_loading_indicator, draw_progress_bar, wrap_next, wrap_prev,
+    Alignment, MAX_DIRTY, Region, RegionSet, WINDOW_COST_PX, draw_loading_indicator,
+    draw_progress_bar, wrap_next, wrap_prev,
 };
 
 pub use crate::board::{SCREEN_H, SCREEN_W};
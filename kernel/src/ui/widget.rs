@@ -62,6 +62,151 @@ impl Region {
             && self.y < other.y + other.h
             && self.y + self.h > other.y
     }
+
+    #[inline]
+    pub fn area(self) -> u32 {
+        self.w as u32 * self.h as u32
+    }
+
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+}
+
+// dirty rectangles for one frame; more than this many get folded into
+// whichever neighbour grows least
+pub const MAX_DIRTY: usize = 4;
+
+// fixed cost of one extra refresh window (RAM window setup, strip
+// restart, second pass in phase3, its own waveform when refreshed on
+// its own) expressed as pixels of area
+pub const WINDOW_COST_PX: u32 = 4096;
+
+// a small set of disjoint-ish dirty rectangles; two rectangles are
+// merged only when their bounding box is cheaper to refresh than
+// keeping them as separate windows
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RegionSet {
+    rects: [Region; MAX_DIRTY],
+    len: u8,
+}
+
+impl RegionSet {
+    pub const fn new() -> Self {
+        Self {
+            rects: [Region::new(0, 0, 0, 0); MAX_DIRTY],
+            len: 0,
+        }
+    }
+
+    pub fn single(region: Region) -> Self {
+        let mut set = Self::new();
+        set.add(region);
+        set
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[Region] {
+        &self.rects[..self.len as usize]
+    }
+
+    // bounding box of every rectangle
+    pub fn bounds(&self) -> Region {
+        let mut it = self.as_slice().iter();
+        let Some(&first) = it.next() else {
+            return Region::new(0, 0, 0, 0);
+        };
+        it.fold(first, |acc, &r| acc.union(r))
+    }
+
+    pub fn area(&self) -> u32 {
+        self.as_slice().iter().map(|r| r.area()).sum()
+    }
+
+    pub fn add(&mut self, region: Region) {
+        if region.is_empty() {
+            return;
+        }
+
+        // absorb every rectangle that is cheaper to refresh together;
+        // a grown rectangle may now pay off against one it skipped
+        let mut r = region;
+        loop {
+            let mut merged = false;
+            let mut i = 0;
+            while i < self.len as usize {
+                if cheaper_merged(self.rects[i], r) {
+                    r = r.union(self.rects[i]);
+                    self.remove(i);
+                    merged = true;
+                } else {
+                    i += 1;
+                }
+            }
+            if !merged {
+                break;
+            }
+        }
+
+        if (self.len as usize) < MAX_DIRTY {
+            self.rects[self.len as usize] = r;
+            self.len += 1;
+            return;
+        }
+
+        // full: fold into the rectangle whose bounding box grows least
+        let mut best = 0;
+        let mut best_growth = u32::MAX;
+        for (i, &existing) in self.as_slice().iter().enumerate() {
+            let growth = existing.union(r).area() - existing.area();
+            if growth < best_growth {
+                best = i;
+                best_growth = growth;
+            }
+        }
+        let grown = self.rects[best].union(r);
+        self.remove(best);
+        self.add(grown);
+    }
+
+    pub fn union(mut self, other: &RegionSet) -> Self {
+        for &r in other.as_slice() {
+            self.add(r);
+        }
+        self
+    }
+
+    // whether one waveform over the bounding box is no dearer than a
+    // refresh per rectangle; the same cost cheaper_merged weighs for
+    // two of them
+    pub fn refresh_merged(&self) -> bool {
+        merge_pays(self.bounds().area(), self.area(), self.len as u32)
+    }
+
+    fn remove(&mut self, i: usize) {
+        let last = self.len as usize - 1;
+        self.rects[i] = self.rects[last];
+        self.len -= 1;
+    }
+}
+
+#[inline]
+fn cheaper_merged(a: Region, b: Region) -> bool {
+    merge_pays(a.union(b).area(), a.area() + b.area(), 2)
+}
+
+// a box of `merged` px against `windows` separate ones covering
+// `separate` px: merging pays unless the separate windows save more
+// area than their extra overhead
+#[inline]
+fn merge_pays(merged: u32, separate: u32, windows: u32) -> bool {
+    merged <= separate + WINDOW_COST_PX * windows.saturating_sub(1)
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -1,5 +1,7 @@
 // utility modules: small, reusable components without hardware dependencies
 
+mod nfc;
 mod utf8;
 
-pub use utf8::{Utf8Iter, decode_utf8_char};
+pub use nfc::{FILLER as NFC_FILLER, compose_in_place as nfc_compose_in_place};
+pub use utf8::{Utf8Iter, decode_utf8_char, floor_char_boundary};
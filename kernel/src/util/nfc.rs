@@ -0,0 +1,399 @@
+// canonical composition (NFC) for decomposed latin, greek and cyrillic
+//
+// some EPUB tools and TXT exports store accented letters as base letter
+// plus combining mark (NFD); the fonts only carry precomposed glyphs.
+// compose_in_place rewrites such pairs to the precomposed codepoint
+// and pads the freed bytes with FILLER, so buffer length and every
+// byte offset after the pair stay valid (page tables index the source)
+//
+// covers compositions landing in U+00C0-024F, U+0370-03FF, U+0400-04FF.
+// a mark that doesn't compose ends the run; later marks stay as-is
+
+use super::utf8::decode_utf8_char;
+
+// padding byte for freed space; layout and draw already skip CR
+pub const FILLER: u8 = b'\r';
+
+// (base, combining mark, composed), sorted by (base, mark)
+static COMPOSE: [(u16, u16, u16); 327] = [
+    (0x0041, 0x0300, 0x00C0), // À
+    (0x0041, 0x0301, 0x00C1), // Á
+    (0x0041, 0x0302, 0x00C2), // Â
+    (0x0041, 0x0303, 0x00C3), // Ã
+    (0x0041, 0x0304, 0x0100), // Ā
+    (0x0041, 0x0306, 0x0102), // Ă
+    (0x0041, 0x0307, 0x0226), // Ȧ
+    (0x0041, 0x0308, 0x00C4), // Ä
+    (0x0041, 0x030A, 0x00C5), // Å
+    (0x0041, 0x030C, 0x01CD), // Ǎ
+    (0x0041, 0x030F, 0x0200), // Ȁ
+    (0x0041, 0x0311, 0x0202), // Ȃ
+    (0x0041, 0x0328, 0x0104), // Ą
+    (0x0043, 0x0301, 0x0106), // Ć
+    (0x0043, 0x0302, 0x0108), // Ĉ
+    (0x0043, 0x0307, 0x010A), // Ċ
+    (0x0043, 0x030C, 0x010C), // Č
+    (0x0043, 0x0327, 0x00C7), // Ç
+    (0x0044, 0x030C, 0x010E), // Ď
+    (0x0045, 0x0300, 0x00C8), // È
+    (0x0045, 0x0301, 0x00C9), // É
+    (0x0045, 0x0302, 0x00CA), // Ê
+    (0x0045, 0x0304, 0x0112), // Ē
+    (0x0045, 0x0306, 0x0114), // Ĕ
+    (0x0045, 0x0307, 0x0116), // Ė
+    (0x0045, 0x0308, 0x00CB), // Ë
+    (0x0045, 0x030C, 0x011A), // Ě
+    (0x0045, 0x030F, 0x0204), // Ȅ
+    (0x0045, 0x0311, 0x0206), // Ȇ
+    (0x0045, 0x0327, 0x0228), // Ȩ
+    (0x0045, 0x0328, 0x0118), // Ę
+    (0x0047, 0x0301, 0x01F4), // Ǵ
+    (0x0047, 0x0302, 0x011C), // Ĝ
+    (0x0047, 0x0306, 0x011E), // Ğ
+    (0x0047, 0x0307, 0x0120), // Ġ
+    (0x0047, 0x030C, 0x01E6), // Ǧ
+    (0x0047, 0x0327, 0x0122), // Ģ
+    (0x0048, 0x0302, 0x0124), // Ĥ
+    (0x0048, 0x030C, 0x021E), // Ȟ
+    (0x0049, 0x0300, 0x00CC), // Ì
+    (0x0049, 0x0301, 0x00CD), // Í
+    (0x0049, 0x0302, 0x00CE), // Î
+    (0x0049, 0x0303, 0x0128), // Ĩ
+    (0x0049, 0x0304, 0x012A), // Ī
+    (0x0049, 0x0306, 0x012C), // Ĭ
+    (0x0049, 0x0307, 0x0130), // İ
+    (0x0049, 0x0308, 0x00CF), // Ï
+    (0x0049, 0x030C, 0x01CF), // Ǐ
+    (0x0049, 0x030F, 0x0208), // Ȉ
+    (0x0049, 0x0311, 0x020A), // Ȋ
+    (0x0049, 0x0328, 0x012E), // Į
+    (0x004A, 0x0302, 0x0134), // Ĵ
+    (0x004B, 0x030C, 0x01E8), // Ǩ
+    (0x004B, 0x0327, 0x0136), // Ķ
+    (0x004C, 0x0301, 0x0139), // Ĺ
+    (0x004C, 0x030C, 0x013D), // Ľ
+    (0x004C, 0x0327, 0x013B), // Ļ
+    (0x004E, 0x0300, 0x01F8), // Ǹ
+    (0x004E, 0x0301, 0x0143), // Ń
+    (0x004E, 0x0303, 0x00D1), // Ñ
+    (0x004E, 0x030C, 0x0147), // Ň
+    (0x004E, 0x0327, 0x0145), // Ņ
+    (0x004F, 0x0300, 0x00D2), // Ò
+    (0x004F, 0x0301, 0x00D3), // Ó
+    (0x004F, 0x0302, 0x00D4), // Ô
+    (0x004F, 0x0303, 0x00D5), // Õ
+    (0x004F, 0x0304, 0x014C), // Ō
+    (0x004F, 0x0306, 0x014E), // Ŏ
+    (0x004F, 0x0307, 0x022E), // Ȯ
+    (0x004F, 0x0308, 0x00D6), // Ö
+    (0x004F, 0x030B, 0x0150), // Ő
+    (0x004F, 0x030C, 0x01D1), // Ǒ
+    (0x004F, 0x030F, 0x020C), // Ȍ
+    (0x004F, 0x0311, 0x020E), // Ȏ
+    (0x004F, 0x031B, 0x01A0), // Ơ
+    (0x004F, 0x0328, 0x01EA), // Ǫ
+    (0x0052, 0x0301, 0x0154), // Ŕ
+    (0x0052, 0x030C, 0x0158), // Ř
+    (0x0052, 0x030F, 0x0210), // Ȑ
+    (0x0052, 0x0311, 0x0212), // Ȓ
+    (0x0052, 0x0327, 0x0156), // Ŗ
+    (0x0053, 0x0301, 0x015A), // Ś
+    (0x0053, 0x0302, 0x015C), // Ŝ
+    (0x0053, 0x030C, 0x0160), // Š
+    (0x0053, 0x0326, 0x0218), // Ș
+    (0x0053, 0x0327, 0x015E), // Ş
+    (0x0054, 0x030C, 0x0164), // Ť
+    (0x0054, 0x0326, 0x021A), // Ț
+    (0x0054, 0x0327, 0x0162), // Ţ
+    (0x0055, 0x0300, 0x00D9), // Ù
+    (0x0055, 0x0301, 0x00DA), // Ú
+    (0x0055, 0x0302, 0x00DB), // Û
+    (0x0055, 0x0303, 0x0168), // Ũ
+    (0x0055, 0x0304, 0x016A), // Ū
+    (0x0055, 0x0306, 0x016C), // Ŭ
+    (0x0055, 0x0308, 0x00DC), // Ü
+    (0x0055, 0x030A, 0x016E), // Ů
+    (0x0055, 0x030B, 0x0170), // Ű
+    (0x0055, 0x030C, 0x01D3), // Ǔ
+    (0x0055, 0x030F, 0x0214), // Ȕ
+    (0x0055, 0x0311, 0x0216), // Ȗ
+    (0x0055, 0x031B, 0x01AF), // Ư
+    (0x0055, 0x0328, 0x0172), // Ų
+    (0x0057, 0x0302, 0x0174), // Ŵ
+    (0x0059, 0x0301, 0x00DD), // Ý
+    (0x0059, 0x0302, 0x0176), // Ŷ
+    (0x0059, 0x0304, 0x0232), // Ȳ
+    (0x0059, 0x0308, 0x0178), // Ÿ
+    (0x005A, 0x0301, 0x0179), // Ź
+    (0x005A, 0x0307, 0x017B), // Ż
+    (0x005A, 0x030C, 0x017D), // Ž
+    (0x0061, 0x0300, 0x00E0), // à
+    (0x0061, 0x0301, 0x00E1), // á
+    (0x0061, 0x0302, 0x00E2), // â
+    (0x0061, 0x0303, 0x00E3), // ã
+    (0x0061, 0x0304, 0x0101), // ā
+    (0x0061, 0x0306, 0x0103), // ă
+    (0x0061, 0x0307, 0x0227), // ȧ
+    (0x0061, 0x0308, 0x00E4), // ä
+    (0x0061, 0x030A, 0x00E5), // å
+    (0x0061, 0x030C, 0x01CE), // ǎ
+    (0x0061, 0x030F, 0x0201), // ȁ
+    (0x0061, 0x0311, 0x0203), // ȃ
+    (0x0061, 0x0328, 0x0105), // ą
+    (0x0063, 0x0301, 0x0107), // ć
+    (0x0063, 0x0302, 0x0109), // ĉ
+    (0x0063, 0x0307, 0x010B), // ċ
+    (0x0063, 0x030C, 0x010D), // č
+    (0x0063, 0x0327, 0x00E7), // ç
+    (0x0064, 0x030C, 0x010F), // ď
+    (0x0065, 0x0300, 0x00E8), // è
+    (0x0065, 0x0301, 0x00E9), // é
+    (0x0065, 0x0302, 0x00EA), // ê
+    (0x0065, 0x0304, 0x0113), // ē
+    (0x0065, 0x0306, 0x0115), // ĕ
+    (0x0065, 0x0307, 0x0117), // ė
+    (0x0065, 0x0308, 0x00EB), // ë
+    (0x0065, 0x030C, 0x011B), // ě
+    (0x0065, 0x030F, 0x0205), // ȅ
+    (0x0065, 0x0311, 0x0207), // ȇ
+    (0x0065, 0x0327, 0x0229), // ȩ
+    (0x0065, 0x0328, 0x0119), // ę
+    (0x0067, 0x0301, 0x01F5), // ǵ
+    (0x0067, 0x0302, 0x011D), // ĝ
+    (0x0067, 0x0306, 0x011F), // ğ
+    (0x0067, 0x0307, 0x0121), // ġ
+    (0x0067, 0x030C, 0x01E7), // ǧ
+    (0x0067, 0x0327, 0x0123), // ģ
+    (0x0068, 0x0302, 0x0125), // ĥ
+    (0x0068, 0x030C, 0x021F), // ȟ
+    (0x0069, 0x0300, 0x00EC), // ì
+    (0x0069, 0x0301, 0x00ED), // í
+    (0x0069, 0x0302, 0x00EE), // î
+    (0x0069, 0x0303, 0x0129), // ĩ
+    (0x0069, 0x0304, 0x012B), // ī
+    (0x0069, 0x0306, 0x012D), // ĭ
+    (0x0069, 0x0308, 0x00EF), // ï
+    (0x0069, 0x030C, 0x01D0), // ǐ
+    (0x0069, 0x030F, 0x0209), // ȉ
+    (0x0069, 0x0311, 0x020B), // ȋ
+    (0x0069, 0x0328, 0x012F), // į
+    (0x006A, 0x0302, 0x0135), // ĵ
+    (0x006A, 0x030C, 0x01F0), // ǰ
+    (0x006B, 0x030C, 0x01E9), // ǩ
+    (0x006B, 0x0327, 0x0137), // ķ
+    (0x006C, 0x0301, 0x013A), // ĺ
+    (0x006C, 0x030C, 0x013E), // ľ
+    (0x006C, 0x0327, 0x013C), // ļ
+    (0x006E, 0x0300, 0x01F9), // ǹ
+    (0x006E, 0x0301, 0x0144), // ń
+    (0x006E, 0x0303, 0x00F1), // ñ
+    (0x006E, 0x030C, 0x0148), // ň
+    (0x006E, 0x0327, 0x0146), // ņ
+    (0x006F, 0x0300, 0x00F2), // ò
+    (0x006F, 0x0301, 0x00F3), // ó
+    (0x006F, 0x0302, 0x00F4), // ô
+    (0x006F, 0x0303, 0x00F5), // õ
+    (0x006F, 0x0304, 0x014D), // ō
+    (0x006F, 0x0306, 0x014F), // ŏ
+    (0x006F, 0x0307, 0x022F), // ȯ
+    (0x006F, 0x0308, 0x00F6), // ö
+    (0x006F, 0x030B, 0x0151), // ő
+    (0x006F, 0x030C, 0x01D2), // ǒ
+    (0x006F, 0x030F, 0x020D), // ȍ
+    (0x006F, 0x0311, 0x020F), // ȏ
+    (0x006F, 0x031B, 0x01A1), // ơ
+    (0x006F, 0x0328, 0x01EB), // ǫ
+    (0x0072, 0x0301, 0x0155), // ŕ
+    (0x0072, 0x030C, 0x0159), // ř
+    (0x0072, 0x030F, 0x0211), // ȑ
+    (0x0072, 0x0311, 0x0213), // ȓ
+    (0x0072, 0x0327, 0x0157), // ŗ
+    (0x0073, 0x0301, 0x015B), // ś
+    (0x0073, 0x0302, 0x015D), // ŝ
+    (0x0073, 0x030C, 0x0161), // š
+    (0x0073, 0x0326, 0x0219), // ș
+    (0x0073, 0x0327, 0x015F), // ş
+    (0x0074, 0x030C, 0x0165), // ť
+    (0x0074, 0x0326, 0x021B), // ț
+    (0x0074, 0x0327, 0x0163), // ţ
+    (0x0075, 0x0300, 0x00F9), // ù
+    (0x0075, 0x0301, 0x00FA), // ú
+    (0x0075, 0x0302, 0x00FB), // û
+    (0x0075, 0x0303, 0x0169), // ũ
+    (0x0075, 0x0304, 0x016B), // ū
+    (0x0075, 0x0306, 0x016D), // ŭ
+    (0x0075, 0x0308, 0x00FC), // ü
+    (0x0075, 0x030A, 0x016F), // ů
+    (0x0075, 0x030B, 0x0171), // ű
+    (0x0075, 0x030C, 0x01D4), // ǔ
+    (0x0075, 0x030F, 0x0215), // ȕ
+    (0x0075, 0x0311, 0x0217), // ȗ
+    (0x0075, 0x031B, 0x01B0), // ư
+    (0x0075, 0x0328, 0x0173), // ų
+    (0x0077, 0x0302, 0x0175), // ŵ
+    (0x0079, 0x0301, 0x00FD), // ý
+    (0x0079, 0x0302, 0x0177), // ŷ
+    (0x0079, 0x0304, 0x0233), // ȳ
+    (0x0079, 0x0308, 0x00FF), // ÿ
+    (0x007A, 0x0301, 0x017A), // ź
+    (0x007A, 0x0307, 0x017C), // ż
+    (0x007A, 0x030C, 0x017E), // ž
+    (0x00A8, 0x0301, 0x0385), // ΅
+    (0x00C4, 0x0304, 0x01DE), // Ǟ
+    (0x00C5, 0x0301, 0x01FA), // Ǻ
+    (0x00C6, 0x0301, 0x01FC), // Ǽ
+    (0x00C6, 0x0304, 0x01E2), // Ǣ
+    (0x00D5, 0x0304, 0x022C), // Ȭ
+    (0x00D6, 0x0304, 0x022A), // Ȫ
+    (0x00D8, 0x0301, 0x01FE), // Ǿ
+    (0x00DC, 0x0300, 0x01DB), // Ǜ
+    (0x00DC, 0x0301, 0x01D7), // Ǘ
+    (0x00DC, 0x0304, 0x01D5), // Ǖ
+    (0x00DC, 0x030C, 0x01D9), // Ǚ
+    (0x00E4, 0x0304, 0x01DF), // ǟ
+    (0x00E5, 0x0301, 0x01FB), // ǻ
+    (0x00E6, 0x0301, 0x01FD), // ǽ
+    (0x00E6, 0x0304, 0x01E3), // ǣ
+    (0x00F5, 0x0304, 0x022D), // ȭ
+    (0x00F6, 0x0304, 0x022B), // ȫ
+    (0x00F8, 0x0301, 0x01FF), // ǿ
+    (0x00FC, 0x0300, 0x01DC), // ǜ
+    (0x00FC, 0x0301, 0x01D8), // ǘ
+    (0x00FC, 0x0304, 0x01D6), // ǖ
+    (0x00FC, 0x030C, 0x01DA), // ǚ
+    (0x01B7, 0x030C, 0x01EE), // Ǯ
+    (0x01EA, 0x0304, 0x01EC), // Ǭ
+    (0x01EB, 0x0304, 0x01ED), // ǭ
+    (0x0226, 0x0304, 0x01E0), // Ǡ
+    (0x0227, 0x0304, 0x01E1), // ǡ
+    (0x022E, 0x0304, 0x0230), // Ȱ
+    (0x022F, 0x0304, 0x0231), // ȱ
+    (0x0292, 0x030C, 0x01EF), // ǯ
+    (0x0391, 0x0301, 0x0386), // Ά
+    (0x0395, 0x0301, 0x0388), // Έ
+    (0x0397, 0x0301, 0x0389), // Ή
+    (0x0399, 0x0301, 0x038A), // Ί
+    (0x0399, 0x0308, 0x03AA), // Ϊ
+    (0x039F, 0x0301, 0x038C), // Ό
+    (0x03A5, 0x0301, 0x038E), // Ύ
+    (0x03A5, 0x0308, 0x03AB), // Ϋ
+    (0x03A9, 0x0301, 0x038F), // Ώ
+    (0x03B1, 0x0301, 0x03AC), // ά
+    (0x03B5, 0x0301, 0x03AD), // έ
+    (0x03B7, 0x0301, 0x03AE), // ή
+    (0x03B9, 0x0301, 0x03AF), // ί
+    (0x03B9, 0x0308, 0x03CA), // ϊ
+    (0x03BF, 0x0301, 0x03CC), // ό
+    (0x03C5, 0x0301, 0x03CD), // ύ
+    (0x03C5, 0x0308, 0x03CB), // ϋ
+    (0x03C9, 0x0301, 0x03CE), // ώ
+    (0x03CA, 0x0301, 0x0390), // ΐ
+    (0x03CB, 0x0301, 0x03B0), // ΰ
+    (0x03D2, 0x0301, 0x03D3), // ϓ
+    (0x03D2, 0x0308, 0x03D4), // ϔ
+    (0x0406, 0x0308, 0x0407), // Ї
+    (0x0410, 0x0306, 0x04D0), // Ӑ
+    (0x0410, 0x0308, 0x04D2), // Ӓ
+    (0x0413, 0x0301, 0x0403), // Ѓ
+    (0x0415, 0x0300, 0x0400), // Ѐ
+    (0x0415, 0x0306, 0x04D6), // Ӗ
+    (0x0415, 0x0308, 0x0401), // Ё
+    (0x0416, 0x0306, 0x04C1), // Ӂ
+    (0x0416, 0x0308, 0x04DC), // Ӝ
+    (0x0417, 0x0308, 0x04DE), // Ӟ
+    (0x0418, 0x0300, 0x040D), // Ѝ
+    (0x0418, 0x0304, 0x04E2), // Ӣ
+    (0x0418, 0x0306, 0x0419), // Й
+    (0x0418, 0x0308, 0x04E4), // Ӥ
+    (0x041A, 0x0301, 0x040C), // Ќ
+    (0x041E, 0x0308, 0x04E6), // Ӧ
+    (0x0423, 0x0304, 0x04EE), // Ӯ
+    (0x0423, 0x0306, 0x040E), // Ў
+    (0x0423, 0x0308, 0x04F0), // Ӱ
+    (0x0423, 0x030B, 0x04F2), // Ӳ
+    (0x0427, 0x0308, 0x04F4), // Ӵ
+    (0x042B, 0x0308, 0x04F8), // Ӹ
+    (0x042D, 0x0308, 0x04EC), // Ӭ
+    (0x0430, 0x0306, 0x04D1), // ӑ
+    (0x0430, 0x0308, 0x04D3), // ӓ
+    (0x0433, 0x0301, 0x0453), // ѓ
+    (0x0435, 0x0300, 0x0450), // ѐ
+    (0x0435, 0x0306, 0x04D7), // ӗ
+    (0x0435, 0x0308, 0x0451), // ё
+    (0x0436, 0x0306, 0x04C2), // ӂ
+    (0x0436, 0x0308, 0x04DD), // ӝ
+    (0x0437, 0x0308, 0x04DF), // ӟ
+    (0x0438, 0x0300, 0x045D), // ѝ
+    (0x0438, 0x0304, 0x04E3), // ӣ
+    (0x0438, 0x0306, 0x0439), // й
+    (0x0438, 0x0308, 0x04E5), // ӥ
+    (0x043A, 0x0301, 0x045C), // ќ
+    (0x043E, 0x0308, 0x04E7), // ӧ
+    (0x0443, 0x0304, 0x04EF), // ӯ
+    (0x0443, 0x0306, 0x045E), // ў
+    (0x0443, 0x0308, 0x04F1), // ӱ
+    (0x0443, 0x030B, 0x04F3), // ӳ
+    (0x0447, 0x0308, 0x04F5), // ӵ
+    (0x044B, 0x0308, 0x04F9), // ӹ
+    (0x044D, 0x0308, 0x04ED), // ӭ
+    (0x0456, 0x0308, 0x0457), // ї
+    (0x0474, 0x030F, 0x0476), // Ѷ
+    (0x0475, 0x030F, 0x0477), // ѷ
+    (0x04D8, 0x0308, 0x04DA), // Ӛ
+    (0x04D9, 0x0308, 0x04DB), // ӛ
+    (0x04E8, 0x0308, 0x04EA), // Ӫ
+    (0x04E9, 0x0308, 0x04EB), // ӫ
+];
+
+#[inline]
+fn compose(base: char, mark: char) -> Option<char> {
+    let key = (base as u32, mark as u32);
+    COMPOSE
+        .binary_search_by(|&(b, m, _)| (b as u32, m as u32).cmp(&key))
+        .ok()
+        .and_then(|i| char::from_u32(COMPOSE[i].2 as u32))
+}
+
+// U+0300-036F, lead byte 0xCC or 0xCD
+#[inline]
+fn is_combining_lead(b: u8) -> bool {
+    b == 0xCC || b == 0xCD
+}
+
+// compose base+mark pairs in place; returns the number of pairs merged
+pub fn compose_in_place(buf: &mut [u8]) -> usize {
+    // quick reject: nothing to do without a combining mark
+    if !buf.iter().any(|&b| is_combining_lead(b)) {
+        return 0;
+    }
+
+    let mut merged = 0;
+    // start and current char of the last base that can take a mark
+    let mut base: Option<(usize, char)> = None;
+    let mut i = 0;
+    while i < buf.len() {
+        let (ch, len) = decode_utf8_char(buf, i);
+        let is_mark = ('\u{0300}'..='\u{036F}').contains(&ch);
+
+        if !is_mark {
+            base = Some((i, ch));
+            i += len;
+            continue;
+        }
+
+        match base.and_then(|(start, b)| compose(b, ch).map(|c| (start, c))) {
+            Some((start, composed)) => {
+                let end = i + len;
+                let mut tmp = [0u8; 4];
+                let enc = composed.encode_utf8(&mut tmp).len();
+                buf[start..start + enc].copy_from_slice(&tmp[..enc]);
+                buf[start + enc..end].fill(FILLER);
+                base = Some((start, composed));
+                merged += 1;
+            }
+            None => base = None,
+        }
+        i += len;
+    }
+    merged
+}
@@ -50,6 +50,16 @@ pub fn decode_utf8_char(buf: &[u8], pos: usize) -> (char, usize) {
     (ch, expected)
 }
 
+// largest char boundary of s at or below max, for cutting text to a
+// fixed-size buffer without splitting a character
+pub fn floor_char_boundary(s: &str, max: usize) -> usize {
+    let mut i = max.min(s.len());
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
 // iterator over UTF-8 characters in a byte slice
 // invalid sequences yield U+FFFD
 pub struct Utf8Iter<'a> {
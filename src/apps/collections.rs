@@ -0,0 +1,272 @@
+// user collections (shelves) in _PULP/COLLECT.TXT
+//
+// one collection per line, name then its books as 8.3 file names:
+//   Fantasy=DUNE.EPUB,HOBBIT.EPUB
+// the file is small enough to parse whole; files, home and upload
+// mode each load their own copy, edit it and write it back
+//
+// names lose '=', ',' and control characters; books that are no
+// longer on the card stay listed until removed, they just never
+// match a directory entry
+
+use core::fmt::Write as _;
+
+use crate::drivers::sdcard::SdStorage;
+use crate::drivers::storage::{self, PULP_DIR};
+use crate::kernel::KernelHandle;
+use crate::ui::StackFmt;
+
+pub const COLLECTIONS_FILE: &str = "COLLECT.TXT";
+pub const MAX_COLLECTIONS: usize = 8;
+pub const MAX_MEMBERS: usize = 32;
+pub const NAME_CAP: usize = 24;
+
+// worst case: every collection full, plus the header line
+pub const FILE_CAP: usize = MAX_COLLECTIONS * (NAME_CAP + 2 + MAX_MEMBERS * 13) + 64;
+
+#[derive(Clone, Copy)]
+struct Member {
+    name: [u8; 12],
+    len: u8,
+}
+
+impl Member {
+    const EMPTY: Self = Self {
+        name: [0u8; 12],
+        len: 0,
+    };
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.name[..self.len as usize]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Collection {
+    name: [u8; NAME_CAP],
+    name_len: u8,
+    members: [Member; MAX_MEMBERS],
+    count: u8,
+}
+
+impl Collection {
+    const EMPTY: Self = Self {
+        name: [0u8; NAME_CAP],
+        name_len: 0,
+        members: [Member::EMPTY; MAX_MEMBERS],
+        count: 0,
+    };
+
+    fn position(&self, file: &[u8]) -> Option<usize> {
+        self.members[..self.count as usize]
+            .iter()
+            .position(|m| m.as_bytes().eq_ignore_ascii_case(file))
+    }
+}
+
+pub struct Collections {
+    items: [Collection; MAX_COLLECTIONS],
+    count: usize,
+}
+
+impl Default for Collections {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collections {
+    pub const fn new() -> Self {
+        Self {
+            items: [Collection::EMPTY; MAX_COLLECTIONS],
+            count: 0,
+        }
+    }
+
+    // a missing file is an empty set of collections
+    pub fn load(&mut self, k: &mut KernelHandle<'_>) {
+        let mut buf = [0u8; FILE_CAP];
+        match k.read_app_data_start(COLLECTIONS_FILE, &mut buf) {
+            Ok((_, n)) => self.parse(&buf[..n]),
+            Err(_) => self.count = 0,
+        }
+    }
+
+    pub fn save(&self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let mut buf = [0u8; FILE_CAP];
+        let n = self.write(&mut buf);
+        k.write_app_data(COLLECTIONS_FILE, &buf[..n])
+    }
+
+    // upload mode runs outside the kernel and talks to the card directly
+    pub fn load_sd(&mut self, sd: &SdStorage) {
+        let mut buf = [0u8; FILE_CAP];
+        match storage::read_file_start_in_dir(sd, PULP_DIR, COLLECTIONS_FILE, &mut buf) {
+            Ok((_, n)) => self.parse(&buf[..n]),
+            Err(_) => self.count = 0,
+        }
+    }
+
+    pub fn save_sd(&self, sd: &SdStorage) -> crate::error::Result<()> {
+        let mut buf = [0u8; FILE_CAP];
+        let n = self.write(&mut buf);
+        storage::write_file_in_dir(sd, PULP_DIR, COLLECTIONS_FILE, &buf[..n])
+    }
+
+    pub fn parse(&mut self, data: &[u8]) {
+        self.count = 0;
+        for line in data.split(|&b| b == b'\n') {
+            let line = line.trim_ascii();
+            if line.is_empty() || line[0] == b'#' {
+                continue;
+            }
+            let (name, files) = match line.iter().position(|&b| b == b'=') {
+                Some(eq) => (&line[..eq], &line[eq + 1..]),
+                None => (line, &b""[..]),
+            };
+            let Some(idx) = self.create(name) else {
+                continue;
+            };
+            for file in files.split(|&b| b == b',') {
+                self.add(idx, file.trim_ascii());
+            }
+        }
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> usize {
+        let mut pos = 0;
+        let mut put = |data: &[u8]| {
+            let n = data.len().min(buf.len() - pos);
+            buf[pos..pos + n].copy_from_slice(&data[..n]);
+            pos += n;
+        };
+        put(b"# pulp-os collections: name=FILE.EPUB,FILE.TXT\n");
+        for c in &self.items[..self.count] {
+            put(&c.name[..c.name_len as usize]);
+            put(b"=");
+            for (i, m) in c.members[..c.count as usize].iter().enumerate() {
+                if i > 0 {
+                    put(b",");
+                }
+                put(m.as_bytes());
+            }
+            put(b"\n");
+        }
+        pos
+    }
+
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn name(&self, idx: usize) -> &str {
+        let c = &self.items[idx];
+        core::str::from_utf8(&c.name[..c.name_len as usize]).unwrap_or("")
+    }
+
+    #[inline]
+    pub fn member_count(&self, idx: usize) -> usize {
+        self.items[idx].count as usize
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        (0..self.count).find(|&i| self.name(i).eq_ignore_ascii_case(name))
+    }
+
+    pub fn contains(&self, idx: usize, file: &[u8]) -> bool {
+        self.items[idx].position(file).is_some()
+    }
+
+    // index of the collection with this name, created if needed;
+    // None when the name is empty after cleaning or all slots are used
+    pub fn create(&mut self, name: &[u8]) -> Option<usize> {
+        let mut clean = [0u8; NAME_CAP];
+        let mut len = 0;
+        let s = core::str::from_utf8(name).unwrap_or("");
+        for ch in s.trim().chars() {
+            if ch.is_control() || ch == '=' || ch == ',' {
+                continue;
+            }
+            let n = ch.len_utf8();
+            if len + n > NAME_CAP {
+                break;
+            }
+            ch.encode_utf8(&mut clean[len..]);
+            len += n;
+        }
+        let clean = core::str::from_utf8(&clean[..len]).unwrap_or("").trim_end();
+        if clean.is_empty() {
+            return None;
+        }
+        if let Some(i) = self.find(clean) {
+            return Some(i);
+        }
+        if self.count >= MAX_COLLECTIONS {
+            return None;
+        }
+
+        let c = &mut self.items[self.count];
+        *c = Collection::EMPTY;
+        c.name[..clean.len()].copy_from_slice(clean.as_bytes());
+        c.name_len = clean.len() as u8;
+        self.count += 1;
+        Some(self.count - 1)
+    }
+
+    // first "Collection N" name not taken yet
+    pub fn create_numbered(&mut self) -> Option<usize> {
+        if self.count >= MAX_COLLECTIONS {
+            return None;
+        }
+        let mut buf = StackFmt::<NAME_CAP>::new();
+        for n in 1..=MAX_COLLECTIONS + 1 {
+            buf.clear();
+            let _ = write!(buf, "Collection {}", n);
+            if self.find(buf.as_str()).is_none() {
+                break;
+            }
+        }
+        self.create(buf.as_str().as_bytes())
+    }
+
+    // false when already present, full, or not an 8.3 name
+    pub fn add(&mut self, idx: usize, file: &[u8]) -> bool {
+        let c = &mut self.items[idx];
+        if file.is_empty() || file.len() > 12 || c.position(file).is_some() {
+            return false;
+        }
+        if c.count as usize >= MAX_MEMBERS {
+            return false;
+        }
+        let m = &mut c.members[c.count as usize];
+        m.name[..file.len()].copy_from_slice(file);
+        m.len = file.len() as u8;
+        c.count += 1;
+        true
+    }
+
+    pub fn remove(&mut self, idx: usize, file: &[u8]) -> bool {
+        let c = &mut self.items[idx];
+        let Some(pos) = c.position(file) else {
+            return false;
+        };
+        let count = c.count as usize;
+        c.members.copy_within(pos + 1..count, pos);
+        c.count -= 1;
+        true
+    }
+
+    // drop a deleted book from every collection; true if any changed
+    pub fn remove_everywhere(&mut self, file: &[u8]) -> bool {
+        let mut changed = false;
+        for i in 0..self.count {
+            changed |= self.remove(i, file);
+        }
+        changed
+    }
+}
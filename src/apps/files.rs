@@ -1,20 +1,33 @@
 // paginated file browser for SD card root directory
-// background title scanner resolves EPUB titles from OPF metadata
+// background title scanner resolves EPUB titles from OPF metadata;
+// once it is done the cover scanner fills the thumbnail cache
+//
+// the quick menu switches between the plain list and the library
+// view, a grid of cover cells with title, author and reading progress;
+// thumbnails for the visible page load one per tick in background
+//
+// entered with a collection name as message, the browser lists only
+// that collection's books; the collection picker toggles membership
+// of the selected book
 
 use alloc::vec::Vec;
 use core::fmt::Write as _;
 
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::*;
-use embedded_graphics::primitives::PrimitiveStyle;
+use embedded_graphics::primitives::{
+    PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StrokeAlignment,
+};
 
+use crate::apps::collections::{Collections, MAX_COLLECTIONS};
+use crate::apps::thumbs::{self, BookThumb, THUMB_H, THUMB_W};
 use crate::apps::{App, AppContext, AppId, Transition};
 use crate::board::action::{Action, ActionEvent};
 use crate::board::{SCREEN_H, SCREEN_W};
 use crate::drivers::storage::DirEntry;
 use crate::drivers::strip::StripBuffer;
 use crate::error::{Error, ErrorKind};
-use crate::fonts;
+use crate::fonts::{self, bitmap::BitmapFont};
 use crate::kernel::KernelHandle;
 use crate::kernel::QuickAction;
 use crate::ui::{
@@ -29,7 +42,12 @@ const MAX_PAGE_SIZE: usize = 14;
 
 const QA_DELETE_FILE: u8 = 1;
 const QA_DELETE_CACHE: u8 = 2;
-const QA_MAX: usize = 2;
+const QA_VIEW: u8 = 3;
+const QA_COLLECT: u8 = 4;
+const QA_UNCOLLECT: u8 = 5;
+const QA_MAX: usize = 5;
+
+const VIEW_NAMES: &[&str] = &["List", "Library"];
 
 const LIST_X: u16 = LARGE_MARGIN;
 const LIST_W: u16 = FULL_CONTENT_W;
@@ -48,12 +66,26 @@ const ROW_STRIDE: u16 = ROW_H + ROW_GAP;
 
 const HEADER_LIST_GAP: u16 = SECTION_GAP;
 
+// library grid: GRID_COLS cells across, as many rows as fit a cover
+// plus two caption lines and the progress bar
+const GRID_COLS: usize = 3;
+const CELL_W: u16 = LIST_W / GRID_COLS as u16;
+const CELL_PAD: u16 = 6;
+const CELL_BORDER: u32 = 3;
+const BAR_H: u16 = 6;
+
 fn compute_page_size(list_y: u16) -> usize {
     let available = SCREEN_H.saturating_sub(list_y);
     let rows = (available / ROW_STRIDE) as usize;
     rows.min(MAX_PAGE_SIZE)
 }
 
+fn compute_grid_rows(list_y: u16, line_h: u16) -> usize {
+    let cell_h = THUMB_H + 2 * line_h + BAR_H + 4 * CELL_PAD;
+    let available = SCREEN_H.saturating_sub(list_y + LARGE_MARGIN);
+    ((available / cell_h) as usize).clamp(1, MAX_PAGE_SIZE / GRID_COLS)
+}
+
 impl Default for FilesApp {
     fn default() -> Self {
         Self::new()
@@ -73,10 +105,29 @@ pub struct FilesApp {
     ui_fonts: fonts::UiFonts,
     list_y: u16,
 
+    library: bool,
+    grid_rows: usize,
+    // per visible cell, filled by background after each page load;
+    // thumbs_for is the scroll offset they were loaded for
+    thumbs: [Option<BookThumb>; MAX_PAGE_SIZE],
+    progress: [Option<u8>; MAX_PAGE_SIZE],
+    thumbs_for: Option<usize>,
+    thumb_idx: usize,
+
+    collections: Collections,
+    // collection index when browsing one collection
+    filter: Option<usize>,
+    picking: bool,
+    pick_selected: usize,
+    collections_dirty: bool,
+
     title_scan_idx: usize,
     title_scanning: bool,
     title_reload: bool,
 
+    cover_scan_idx: usize,
+    cover_scanning: bool,
+
     qa_buf: [QuickAction; QA_MAX],
     qa_count: usize,
     pending_delete_file: bool,
@@ -99,9 +150,22 @@ impl FilesApp {
             error: None,
             ui_fonts: uf,
             list_y,
+            library: false,
+            grid_rows: compute_grid_rows(list_y, uf.body.line_height),
+            thumbs: [const { None }; MAX_PAGE_SIZE],
+            progress: [None; MAX_PAGE_SIZE],
+            thumbs_for: None,
+            thumb_idx: 0,
+            collections: Collections::new(),
+            filter: None,
+            picking: false,
+            pick_selected: 0,
+            collections_dirty: false,
             title_scan_idx: 0,
             title_scanning: false,
             title_reload: false,
+            cover_scan_idx: 0,
+            cover_scanning: false,
             qa_buf: [QuickAction::trigger(0, "", ""); QA_MAX],
             qa_count: 0,
             pending_delete_file: false,
@@ -112,7 +176,16 @@ impl FilesApp {
     pub fn set_ui_font_size(&mut self, idx: u8) {
         self.ui_fonts = fonts::UiFonts::for_size(idx);
         self.list_y = TITLE_Y + self.ui_fonts.heading.line_height + HEADER_LIST_GAP;
-        self.page_size = compute_page_size(self.list_y);
+        self.grid_rows = compute_grid_rows(self.list_y, self.ui_fonts.body.line_height);
+        self.apply_page_size();
+    }
+
+    fn apply_page_size(&mut self) {
+        self.page_size = if self.library {
+            GRID_COLS * self.grid_rows
+        } else {
+            compute_page_size(self.list_y)
+        };
     }
 
     // Session state accessors for RTC persistence
@@ -171,7 +244,18 @@ impl FilesApp {
         self.count = 0;
     }
 
+    fn cell_h(&self) -> u16 {
+        SCREEN_H.saturating_sub(self.list_y + LARGE_MARGIN) / self.grid_rows as u16
+    }
+
+    // list row, or grid cell in the library view
     fn row_region(&self, index: usize) -> Region {
+        if self.library {
+            let col = (index % GRID_COLS) as u16;
+            let row = (index / GRID_COLS) as u16;
+            let h = self.cell_h();
+            return Region::new(LIST_X + col * CELL_W, self.list_y + row * h, CELL_W, h);
+        }
         Region::new(
             LIST_X,
             self.list_y + index as u16 * (ROW_H + ROW_GAP),
@@ -181,6 +265,14 @@ impl FilesApp {
     }
 
     fn list_region(&self) -> Region {
+        if self.library {
+            return Region::new(
+                LIST_X,
+                self.list_y,
+                LIST_W,
+                self.cell_h() * self.grid_rows as u16,
+            );
+        }
         Region::new(
             LIST_X,
             self.list_y,
@@ -189,18 +281,131 @@ impl FilesApp {
         )
     }
 
+    // one-line messages (loading, errors) span the full width
+    fn message_region(&self) -> Region {
+        Region::new(LIST_X, self.list_y, LIST_W, ROW_H)
+    }
+
+    fn clear_thumbs(&mut self) {
+        self.thumbs = [const { None }; MAX_PAGE_SIZE];
+        self.progress = [None; MAX_PAGE_SIZE];
+        self.thumbs_for = None;
+        self.thumb_idx = 0;
+    }
+
+    // picker rows: every collection, then "New collection" while
+    // there is room for one
+    fn pick_rows(&self) -> usize {
+        let n = self.collections.count();
+        if n < MAX_COLLECTIONS { n + 1 } else { n }
+    }
+
+    fn pick_region(&self, row: usize) -> Region {
+        Region::new(LIST_X, self.list_y + row as u16 * ROW_STRIDE, LIST_W, ROW_H)
+    }
+
+    fn on_event_picker(&mut self, event: ActionEvent, ctx: &mut AppContext) {
+        let rows = self.pick_rows();
+        match event {
+            ActionEvent::Press(Action::Back) | ActionEvent::LongPress(Action::Back) => {
+                self.picking = false;
+                // the book may have left the collection being shown
+                if self.filter.is_some() {
+                    self.needs_load = true;
+                }
+                ctx.mark_dirty(Region::new(
+                    0,
+                    CONTENT_TOP,
+                    SCREEN_W,
+                    SCREEN_H - CONTENT_TOP,
+                ));
+            }
+            ActionEvent::Press(Action::Prev) | ActionEvent::Repeat(Action::Prev) => {
+                ctx.mark_dirty_fast(self.pick_region(self.pick_selected));
+                self.pick_selected = (self.pick_selected + rows - 1) % rows;
+                ctx.mark_dirty_fast(self.pick_region(self.pick_selected));
+            }
+            ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next) => {
+                ctx.mark_dirty_fast(self.pick_region(self.pick_selected));
+                self.pick_selected = (self.pick_selected + 1) % rows;
+                ctx.mark_dirty_fast(self.pick_region(self.pick_selected));
+            }
+            ActionEvent::Press(Action::Select) => {
+                let Some(entry) = self.selected_entry() else {
+                    return;
+                };
+                let mut nb = [0u8; 13];
+                let nl = entry.name_len as usize;
+                nb[..nl].copy_from_slice(&entry.name[..nl]);
+                let file = &nb[..nl];
+
+                let idx = if self.pick_selected < self.collections.count() {
+                    Some(self.pick_selected)
+                } else {
+                    self.collections.create_numbered()
+                };
+                let Some(idx) = idx else {
+                    return;
+                };
+                if !self.collections.remove(idx, file) {
+                    self.collections.add(idx, file);
+                }
+                self.collections_dirty = true;
+                let last = self.pick_region(self.pick_rows() - 1);
+                ctx.mark_dirty(self.pick_region(0).union(last));
+            }
+            _ => {}
+        }
+    }
+
+    // cover and progress for the next cell still missing them;
+    // false once the page is complete
+    fn load_one_thumb(&mut self, ctx: &mut AppContext, k: &mut KernelHandle<'_>) -> bool {
+        let i = self.thumb_idx;
+        if i >= self.count {
+            return false;
+        }
+        self.thumb_idx += 1;
+
+        let entry = &self.entries[i];
+        if entry.is_dir {
+            return true;
+        }
+        self.progress[i] = k
+            .bookmark_cache()
+            .find(entry.name_str().as_bytes())
+            .map(|s| s.progress);
+        self.thumbs[i] = if is_epub_name(entry.name_str()) {
+            thumbs::load_thumb(k, entry.name_str())
+        } else {
+            None
+        };
+        ctx.mark_dirty_coalesced(self.row_region(i));
+        true
+    }
+
     fn move_up(&mut self, ctx: &mut AppContext) {
         if self.selected > 0 {
-            ctx.mark_dirty(self.row_region(self.selected));
+            ctx.mark_dirty_fast(self.row_region(self.selected));
             self.selected -= 1;
-            ctx.mark_dirty(self.row_region(self.selected));
-            ctx.mark_dirty(STATUS_REGION);
+            ctx.mark_dirty_fast(self.row_region(self.selected));
+            ctx.mark_dirty_fast(STATUS_REGION);
             self.rebuild_quick_actions();
         } else if self.scroll > 0 {
-            self.scroll = self.scroll.saturating_sub(1);
+            // the grid turns whole pages so cells keep their place
+            if self.library {
+                self.scroll = self.scroll.saturating_sub(self.page_size);
+                self.selected = self.page_size - 1;
+            } else {
+                self.scroll = self.scroll.saturating_sub(1);
+            }
             self.needs_load = true;
         } else if self.total > 0 {
-            self.scroll = self.total.saturating_sub(self.page_size);
+            self.scroll = if self.library {
+                (self.total - 1) / self.page_size * self.page_size
+            } else {
+                self.total.saturating_sub(self.page_size)
+            };
             self.selected = self.total.saturating_sub(self.scroll) - 1;
             self.needs_load = true;
         }
@@ -208,13 +413,18 @@ impl FilesApp {
 
     fn move_down(&mut self, ctx: &mut AppContext) {
         if self.selected + 1 < self.count {
-            ctx.mark_dirty(self.row_region(self.selected));
+            ctx.mark_dirty_fast(self.row_region(self.selected));
             self.selected += 1;
-            ctx.mark_dirty(self.row_region(self.selected));
-            ctx.mark_dirty(STATUS_REGION);
+            ctx.mark_dirty_fast(self.row_region(self.selected));
+            ctx.mark_dirty_fast(STATUS_REGION);
             self.rebuild_quick_actions();
         } else if self.scroll + self.count < self.total {
-            self.scroll += 1;
+            if self.library {
+                self.scroll += self.page_size;
+                self.selected = 0;
+            } else {
+                self.scroll += 1;
+            }
             self.needs_load = true;
         } else if self.total > 0 {
             self.scroll = 0;
@@ -235,19 +445,23 @@ impl FilesApp {
 
     fn rebuild_quick_actions(&mut self) {
         let mut n = 0usize;
+        self.qa_buf[n] = QuickAction::cycle(QA_VIEW, "View", self.library as u8, VIEW_NAMES);
+        n += 1;
+
         let (is_file, is_epub) = if self.selected < self.count {
             let e = &self.entries[self.selected];
-            let nm = &e.name[..e.name_len as usize];
-            let epub = !e.is_dir
-                && nm.len() >= 5
-                && nm[nm.len() - 5] == b'.'
-                && nm[nm.len() - 4..].eq_ignore_ascii_case(b"EPUB");
-            (!e.is_dir, epub)
+            (!e.is_dir, !e.is_dir && is_epub_name(e.name_str()))
         } else {
             (false, false)
         };
 
         if is_file {
+            self.qa_buf[n] = QuickAction::trigger(QA_COLLECT, "Collections", "Edit");
+            n += 1;
+            if self.filter.is_some() {
+                self.qa_buf[n] = QuickAction::trigger(QA_UNCOLLECT, "From Collection", "Remove");
+                n += 1;
+            }
             self.qa_buf[n] = QuickAction::trigger(QA_DELETE_FILE, "Delete File", "Delete");
             n += 1;
             if is_epub {
@@ -271,7 +485,10 @@ impl FilesApp {
 }
 
 impl App<AppId> for FilesApp {
-    fn on_enter(&mut self, ctx: &mut AppContext, _k: &mut KernelHandle<'_>) {
+    fn on_enter(&mut self, ctx: &mut AppContext, k: &mut KernelHandle<'_>) {
+        self.collections.load(k);
+        self.filter = self.collections.find(ctx.message_str());
+        self.picking = false;
         self.scroll = 0;
         self.selected = 0;
         self.needs_load = true;
@@ -279,6 +496,8 @@ impl App<AppId> for FilesApp {
         self.error = None;
         self.title_scan_idx = 0;
         self.title_scanning = true;
+        self.cover_scan_idx = 0;
+        self.cover_scanning = true;
         ctx.mark_dirty(Region::new(
             0,
             CONTENT_TOP,
@@ -290,9 +509,14 @@ impl App<AppId> for FilesApp {
     fn on_exit(&mut self) {
         self.count = 0;
         self.title_scanning = false;
+        self.cover_scanning = false;
+        self.clear_thumbs();
     }
 
-    fn on_suspend(&mut self) {}
+    // the reader needs the heap more than the grid does
+    fn on_suspend(&mut self) {
+        self.clear_thumbs();
+    }
 
     fn on_resume(&mut self, ctx: &mut AppContext, _k: &mut KernelHandle<'_>) {
         ctx.mark_dirty(Region::new(
@@ -304,6 +528,13 @@ impl App<AppId> for FilesApp {
     }
 
     async fn background(&mut self, ctx: &mut AppContext, k: &mut KernelHandle<'_>) {
+        if self.collections_dirty {
+            self.collections_dirty = false;
+            if let Err(e) = self.collections.save(k) {
+                log::warn!("files: collections save failed: {}", e);
+            }
+        }
+
         if self.pending_delete_file {
             self.pending_delete_file = false;
             if let Some(entry) = self.selected_entry() {
@@ -314,8 +545,13 @@ impl App<AppId> for FilesApp {
                     let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
                     log::info!("files: deleting {}", name);
 
-                    // also remove bookmark
+                    // also remove bookmark, cover thumbnail and
+                    // collection entries
                     k.bookmark_cache_mut().remove(&nb[..nl]);
+                    thumbs::delete_thumb(k, name);
+                    if self.collections.remove_everywhere(&nb[..nl]) {
+                        self.collections_dirty = true;
+                    }
 
                     match k.delete_file(name) {
                         Ok(()) => {
@@ -325,6 +561,7 @@ impl App<AppId> for FilesApp {
                             self.stale_cache = true;
                             self.title_scan_idx = 0;
                             self.title_scanning = true;
+                            self.thumbs_for = None;
                         }
                         Err(e) => {
                             log::warn!("files: delete failed: {}", e);
@@ -365,9 +602,22 @@ impl App<AppId> for FilesApp {
             }
 
             let mut buf = [DirEntry::EMPTY; MAX_PAGE_SIZE];
-            match k.dir_page(self.scroll, &mut buf[..self.page_size]) {
+            let page = match self.filter {
+                Some(c) => {
+                    let colls = &self.collections;
+                    k.dir_page_filtered(self.scroll, &mut buf[..self.page_size], |e| {
+                        !e.is_dir && colls.contains(c, &e.name[..e.name_len as usize])
+                    })
+                }
+                None => k.dir_page(self.scroll, &mut buf[..self.page_size]),
+            };
+            match page {
                 Ok(page) => {
                     self.load_page(&buf[..page.count], page.total);
+                    if self.library && self.thumbs_for != Some(self.scroll) {
+                        self.clear_thumbs();
+                        self.thumbs_for = Some(self.scroll);
+                    }
                 }
                 Err(e) => {
                     log::info!("SD load failed: {}", e);
@@ -386,6 +636,10 @@ impl App<AppId> for FilesApp {
             return;
         }
 
+        if self.library && self.load_one_thumb(ctx, k) {
+            return;
+        }
+
         if self.title_scanning {
             if let Some(dirty) = scan_one_epub_title(k, self.title_scan_idx) {
                 self.title_scan_idx = dirty.next_idx;
@@ -397,10 +651,35 @@ impl App<AppId> for FilesApp {
                 self.title_scanning = false;
                 log::info!("titles: scan complete");
             }
+            return;
+        }
+
+        if self.cover_scanning {
+            match scan_one_epub_cover(k, self.cover_scan_idx) {
+                Some(next) => {
+                    self.cover_scan_idx = next;
+                    // a fresh thumbnail on the visible page: reload from there
+                    let idx = next - 1;
+                    let visible = self.filter.is_none()
+                        && idx >= self.scroll
+                        && idx < self.scroll + self.count;
+                    if self.library && visible {
+                        self.thumb_idx = self.thumb_idx.min(idx - self.scroll);
+                    }
+                }
+                None => {
+                    self.cover_scanning = false;
+                    log::info!("covers: scan complete");
+                }
+            }
         }
     }
 
     fn on_event(&mut self, event: ActionEvent, ctx: &mut AppContext) -> Transition {
+        if self.picking {
+            self.on_event_picker(event, ctx);
+            return Transition::None;
+        }
         match event {
             ActionEvent::Press(Action::Back) => Transition::Pop,
             ActionEvent::LongPress(Action::Back) => Transition::Home,
@@ -453,11 +732,25 @@ impl App<AppId> for FilesApp {
     fn draw(&self, strip: &mut StripBuffer) {
         let header_region =
             Region::new(LIST_X, TITLE_Y, HEADER_W, self.ui_fonts.heading.line_height);
-        BitmapLabel::new(header_region, "Files", self.ui_fonts.heading)
+        let header = if self.picking {
+            "Collections"
+        } else if let Some(c) = self.filter {
+            self.collections.name(c)
+        } else if self.library {
+            "Library"
+        } else {
+            "Files"
+        };
+        BitmapLabel::new(header_region, header, self.ui_fonts.heading)
             .alignment(Alignment::CenterLeft)
             .draw(strip)
             .unwrap();
 
+        if self.picking {
+            self.draw_picker(strip);
+            return;
+        }
+
         if self.total > 0 {
             let mut status = BitmapDynLabel::<24>::new(STATUS_REGION, self.ui_fonts.body)
                 .alignment(Alignment::CenterRight);
@@ -469,7 +762,7 @@ impl App<AppId> for FilesApp {
         }
 
         if let Some(e) = self.error {
-            let mut label = BitmapDynLabel::<32>::new(self.row_region(0), self.ui_fonts.body)
+            let mut label = BitmapDynLabel::<32>::new(self.message_region(), self.ui_fonts.body)
                 .alignment(Alignment::CenterLeft);
             let _ = core::fmt::Write::write_fmt(&mut label, format_args!("{}", e));
             label.draw(strip).unwrap();
@@ -477,7 +770,7 @@ impl App<AppId> for FilesApp {
         }
 
         if self.count == 0 && self.needs_load {
-            BitmapLabel::new(self.row_region(0), "Loading...", self.ui_fonts.body)
+            BitmapLabel::new(self.message_region(), "Loading...", self.ui_fonts.body)
                 .alignment(Alignment::CenterLeft)
                 .draw(strip)
                 .unwrap();
@@ -485,7 +778,7 @@ impl App<AppId> for FilesApp {
         }
 
         if self.count == 0 && !self.needs_load {
-            BitmapLabel::new(self.row_region(0), "No files found", self.ui_fonts.body)
+            BitmapLabel::new(self.message_region(), "No files found", self.ui_fonts.body)
                 .alignment(Alignment::CenterLeft)
                 .draw(strip)
                 .unwrap();
@@ -495,7 +788,9 @@ impl App<AppId> for FilesApp {
         for i in 0..self.page_size {
             let region = self.row_region(i);
 
-            if i < self.count {
+            if self.library {
+                self.draw_cell(strip, i, region);
+            } else if i < self.count {
                 let entry = &self.entries[i];
                 let name = entry.display_name();
 
@@ -518,8 +813,55 @@ impl App<AppId> for FilesApp {
         &self.qa_buf[..self.qa_count]
     }
 
-    fn on_quick_trigger(&mut self, id: u8, _ctx: &mut AppContext) {
+    fn on_quick_cycle_update(&mut self, id: u8, value: u8, ctx: &mut AppContext) {
+        if id != QA_VIEW || (value == 1) == self.library {
+            return;
+        }
+        let abs = self.scroll + self.selected;
+        self.library = value == 1;
+        self.apply_page_size();
+        // keep the selected book on screen; grid pages start on a multiple
+        self.scroll = if self.library {
+            abs - abs % self.page_size
+        } else {
+            self.scroll.max((abs + 1).saturating_sub(self.page_size))
+        };
+        self.selected = abs - self.scroll;
+        self.clear_thumbs();
+        self.needs_load = true;
+        ctx.mark_dirty(Region::new(
+            0,
+            CONTENT_TOP,
+            SCREEN_W,
+            SCREEN_H - CONTENT_TOP,
+        ));
+    }
+
+    fn on_quick_trigger(&mut self, id: u8, ctx: &mut AppContext) {
         match id {
+            QA_COLLECT => {
+                if self.selected_entry().is_some() {
+                    self.picking = true;
+                    self.pick_selected = 0;
+                    ctx.mark_dirty(Region::new(
+                        0,
+                        CONTENT_TOP,
+                        SCREEN_W,
+                        SCREEN_H - CONTENT_TOP,
+                    ));
+                }
+            }
+            QA_UNCOLLECT => {
+                if let (Some(c), Some(entry)) = (self.filter, self.selected_entry()) {
+                    let nl = entry.name_len as usize;
+                    let mut nb = [0u8; 13];
+                    nb[..nl].copy_from_slice(&entry.name[..nl]);
+                    if self.collections.remove(c, &nb[..nl]) {
+                        self.collections_dirty = true;
+                        self.needs_load = true;
+                    }
+                }
+            }
             QA_DELETE_FILE => {
                 self.pending_delete_file = true;
             }
@@ -531,11 +873,237 @@ impl App<AppId> for FilesApp {
     }
 }
 
+impl FilesApp {
+    // one row per collection, checked when it holds the selected book
+    fn draw_picker(&self, strip: &mut StripBuffer) {
+        let file = match self.selected_entry() {
+            Some(e) => &e.name[..e.name_len as usize],
+            None => return,
+        };
+        for row in 0..self.pick_rows() {
+            let mut label = BitmapDynLabel::<40>::new(self.pick_region(row), self.ui_fonts.body)
+                .alignment(Alignment::CenterLeft)
+                .inverted(row == self.pick_selected);
+            if row < self.collections.count() {
+                let mark = if self.collections.contains(row, file) {
+                    "[x]"
+                } else {
+                    "[  ]"
+                };
+                let _ = write!(label, "{} {}", mark, self.collections.name(row));
+            } else {
+                let _ = write!(label, "+ New collection");
+            }
+            label.draw(strip).unwrap();
+        }
+    }
+
+    // cover (or a framed placeholder naming the file type), title,
+    // author and progress bar; the selected cell gets a thick border
+    fn draw_cell(&self, strip: &mut StripBuffer, i: usize, region: Region) {
+        region
+            .to_rect()
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+            .draw(strip)
+            .unwrap();
+        if i >= self.count {
+            return;
+        }
+
+        let entry = &self.entries[i];
+        let font = self.ui_fonts.body;
+        let cover_x = region.x + (CELL_W - THUMB_W) / 2;
+        let cover_y = region.y + CELL_PAD;
+
+        match self.thumbs[i].as_ref().and_then(|t| t.image.as_ref()) {
+            Some(img) => {
+                strip.blit_1bpp(
+                    &img.data,
+                    0,
+                    img.width as usize,
+                    img.height as usize,
+                    img.stride,
+                    (cover_x + (THUMB_W - img.width.min(THUMB_W)) / 2) as i32,
+                    (cover_y + (THUMB_H - img.height.min(THUMB_H)) / 2) as i32,
+                    true,
+                );
+            }
+            None => {
+                let cover = Region::new(cover_x, cover_y, THUMB_W, THUMB_H);
+                cover
+                    .to_rect()
+                    .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                    .draw(strip)
+                    .unwrap();
+                let kind = if entry.is_dir {
+                    "DIR"
+                } else {
+                    entry.name_str().rsplit_once('.').map_or("", |(_, ext)| ext)
+                };
+                font.draw_aligned(strip, cover, kind, Alignment::Center, BinaryColor::On);
+            }
+        }
+
+        let text_w = CELL_W - 2 * CELL_PAD;
+        let mut y = cover_y + THUMB_H + CELL_PAD;
+        let line = Region::new(region.x + CELL_PAD, y, text_w, font.line_height);
+        let title = fit_width(font, entry.display_name(), text_w);
+        font.draw_aligned(strip, line, title, Alignment::Center, BinaryColor::On);
+        y += font.line_height;
+
+        if let Some(thumb) = &self.thumbs[i] {
+            let line = Region::new(region.x + CELL_PAD, y, text_w, font.line_height);
+            let author = fit_width(font, thumb.author(), text_w);
+            font.draw_aligned(strip, line, author, Alignment::Center, BinaryColor::On);
+        }
+        y += font.line_height + CELL_PAD;
+
+        if let Some(pct) = self.progress[i] {
+            let bar_x = region.x + CELL_PAD;
+            let _ = Rectangle::new(
+                Point::new(bar_x as i32, y as i32),
+                Size::new(text_w as u32, BAR_H as u32),
+            )
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(strip);
+            let fill_w = (text_w as u32 - 2) * pct.min(100) as u32 / 100;
+            if fill_w > 0 {
+                let _ = Rectangle::new(
+                    Point::new(bar_x as i32 + 1, y as i32 + 1),
+                    Size::new(fill_w, BAR_H as u32 - 2),
+                )
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(strip);
+            }
+        }
+
+        if i == self.selected {
+            region
+                .to_rect()
+                .into_styled(
+                    PrimitiveStyleBuilder::new()
+                        .stroke_color(BinaryColor::On)
+                        .stroke_width(CELL_BORDER)
+                        .stroke_alignment(StrokeAlignment::Inside)
+                        .build(),
+                )
+                .draw(strip)
+                .unwrap();
+        }
+    }
+}
+
+fn is_epub_name(name: &str) -> bool {
+    let nm = name.as_bytes();
+    nm.len() >= 5 && nm[nm.len() - 5] == b'.' && nm[nm.len() - 4..].eq_ignore_ascii_case(b"EPUB")
+}
+
+// longest prefix of text that fits width; labels don't clip
+fn fit_width<'a>(font: &BitmapFont, text: &'a str, width: u16) -> &'a str {
+    if font.measure_str(text) <= width {
+        return text;
+    }
+    text.char_indices()
+        .rev()
+        .map(|(i, _)| &text[..i])
+        .find(|s| font.measure_str(s) <= width)
+        .unwrap_or("")
+}
+
 struct TitleScanResult {
     next_idx: usize,
     resolved: bool,
 }
 
+// an EPUB's ZIP index and OPF document, as both background scanners
+// need them; the OPF directory is the prefix of opf_path
+struct EpubOpf {
+    zip: ZipIndex,
+    opf: Vec<u8>,
+    path_buf: [u8; epub::OPF_PATH_CAP],
+    path_len: usize,
+}
+
+impl EpubOpf {
+    fn dir(&self) -> &str {
+        let path = core::str::from_utf8(&self.path_buf[..self.path_len]).unwrap_or("");
+        path.rsplit_once('/').map(|(d, _)| d).unwrap_or("")
+    }
+}
+
+fn read_epub_opf(k: &mut KernelHandle<'_>, name: &str) -> crate::error::Result<EpubOpf> {
+    let file_size = k.file_size(name)?;
+    if file_size < 22 {
+        return Err(Error::new(ErrorKind::InvalidData, "epub_opf: too small"));
+    }
+
+    let tail_size = (file_size as usize).min(512);
+    let tail_offset = file_size - tail_size as u32;
+    let mut buf = [0u8; 512];
+    let n = k.read_chunk(name, tail_offset, &mut buf[..tail_size])?;
+
+    // ZipIndex::parse_eocd returns Result<_, &'static str>;
+    // the From<&'static str> impl on Error converts automatically via ?
+    let (cd_offset, cd_size) = ZipIndex::parse_eocd(&buf[..n], file_size)?;
+
+    let mut cd_buf = Vec::new();
+    cd_buf
+        .try_reserve_exact(cd_size as usize)
+        .map_err(|_| Error::new(ErrorKind::OutOfMemory, "epub_opf: CD alloc"))?;
+    cd_buf.resize(cd_size as usize, 0);
+
+    let mut total = 0usize;
+    while total < cd_buf.len() {
+        let rd = k.read_chunk(name, cd_offset + total as u32, &mut cd_buf[total..])?;
+        if rd == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "epub_opf: CD truncated"));
+        }
+        total += rd;
+    }
+
+    let mut zip = ZipIndex::new();
+    zip.parse_central_directory(&cd_buf)?;
+    drop(cd_buf);
+
+    let mut path_buf = [0u8; epub::OPF_PATH_CAP];
+    let path_len = if let Some(ci) = zip.find("META-INF/container.xml") {
+        let container =
+            smol_epub::zip::extract_entry(zip.entry(ci), zip.entry(ci).local_offset, |off, b| {
+                k.read_chunk(name, off, b)
+                    .map_err(|e: Error| -> &'static str { e.into() })
+            })?;
+        let len = epub::parse_container(&container, &mut path_buf)?;
+        drop(container);
+        len
+    } else {
+        epub::find_opf_in_zip(&zip, &mut path_buf)?
+    };
+
+    let opf_path = core::str::from_utf8(&path_buf[..path_len])
+        .map_err(|_| Error::new(ErrorKind::BadEncoding, "epub_opf: OPF path"))?;
+
+    let opf_idx = zip
+        .find(opf_path)
+        .or_else(|| zip.find_icase(opf_path))
+        .ok_or(Error::new(ErrorKind::NotFound, "epub_opf: OPF entry"))?;
+
+    let opf = smol_epub::zip::extract_entry(
+        zip.entry(opf_idx),
+        zip.entry(opf_idx).local_offset,
+        |off, b| {
+            k.read_chunk(name, off, b)
+                .map_err(|e: Error| -> &'static str { e.into() })
+        },
+    )?;
+
+    Ok(EpubOpf {
+        zip,
+        opf,
+        path_buf,
+        path_len,
+    })
+}
+
 fn scan_one_epub_title(k: &mut KernelHandle<'_>, from: usize) -> Option<TitleScanResult> {
     let (idx, name_buf, name_len) = k.dir_cache_mut().next_untitled_epub(from)?;
     let name = core::str::from_utf8(&name_buf[..name_len as usize]).unwrap_or("");
@@ -544,81 +1112,12 @@ fn scan_one_epub_title(k: &mut KernelHandle<'_>, from: usize) -> Option<TitleSca
     log::info!("titles: scanning {} (idx {})", name, idx);
 
     let result = (|| -> crate::error::Result<()> {
-        let file_size = k.file_size(name)?;
-        if file_size < 22 {
-            return Err(Error::new(ErrorKind::InvalidData, "title_scan: too small"));
-        }
-
-        let tail_size = (file_size as usize).min(512);
-        let tail_offset = file_size - tail_size as u32;
-        let mut buf = [0u8; 512];
-        let n = k.read_chunk(name, tail_offset, &mut buf[..tail_size])?;
-
-        // ZipIndex::parse_eocd returns Result<_, &'static str>;
-        // the From<&'static str> impl on Error converts automatically via ?
-        let (cd_offset, cd_size) = ZipIndex::parse_eocd(&buf[..n], file_size)?;
-
-        let mut cd_buf = Vec::new();
-        cd_buf
-            .try_reserve_exact(cd_size as usize)
-            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "title_scan: CD alloc"))?;
-        cd_buf.resize(cd_size as usize, 0);
-
-        let mut total = 0usize;
-        while total < cd_buf.len() {
-            let rd = k.read_chunk(name, cd_offset + total as u32, &mut cd_buf[total..])?;
-            if rd == 0 {
-                return Err(Error::new(
-                    ErrorKind::InvalidData,
-                    "title_scan: CD truncated",
-                ));
-            }
-            total += rd;
-        }
-
-        let mut zip = ZipIndex::new();
-        zip.parse_central_directory(&cd_buf)?;
-        drop(cd_buf);
-
-        let mut opf_path_buf = [0u8; epub::OPF_PATH_CAP];
-        let opf_path_len = if let Some(ci) = zip.find("META-INF/container.xml") {
-            let container = smol_epub::zip::extract_entry(
-                zip.entry(ci),
-                zip.entry(ci).local_offset,
-                |off, b| {
-                    k.read_chunk(name, off, b)
-                        .map_err(|e: Error| -> &'static str { e.into() })
-                },
-            )?;
-            let len = epub::parse_container(&container, &mut opf_path_buf)?;
-            drop(container);
-            len
-        } else {
-            epub::find_opf_in_zip(&zip, &mut opf_path_buf)?
-        };
-
-        let opf_path = core::str::from_utf8(&opf_path_buf[..opf_path_len])
-            .map_err(|_| Error::new(ErrorKind::BadEncoding, "title_scan: OPF path"))?;
-
-        let opf_idx = zip
-            .find(opf_path)
-            .or_else(|| zip.find_icase(opf_path))
-            .ok_or(Error::new(ErrorKind::NotFound, "title_scan: OPF entry"))?;
+        let book = read_epub_opf(k, name)?;
 
-        let opf_data = smol_epub::zip::extract_entry(
-            zip.entry(opf_idx),
-            zip.entry(opf_idx).local_offset,
-            |off, b| {
-                k.read_chunk(name, off, b)
-                    .map_err(|e: Error| -> &'static str { e.into() })
-            },
-        )?;
-
-        let opf_dir = opf_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
         let mut meta = EpubMeta::new();
         let mut spine = EpubSpine::new();
-        epub::parse_opf(&opf_data, opf_dir, &zip, &mut meta, &mut spine)?;
-        drop(opf_data);
+        epub::parse_opf(&book.opf, book.dir(), &book.zip, &mut meta, &mut spine)?;
+        drop(book);
 
         let title = meta.title_str();
         if title.is_empty() {
@@ -644,3 +1143,45 @@ fn scan_one_epub_title(k: &mut KernelHandle<'_>, from: usize) -> Option<TitleSca
         resolved: result.is_ok(),
     })
 }
+
+// thumbnail the next EPUB at or after `from` that has no thumbnail
+// (or no-cover marker) yet; returns the index to continue from, None
+// once every book has been visited
+fn scan_one_epub_cover(k: &mut KernelHandle<'_>, from: usize) -> Option<usize> {
+    let mut from = from;
+    let (idx, name_buf, name_len) = loop {
+        let (idx, name_buf, name_len) = k.dir_cache_mut().next_epub(from)?;
+        let name = core::str::from_utf8(&name_buf[..name_len as usize]).unwrap_or("");
+        if !thumbs::has_thumb(k, name) {
+            break (idx, name_buf, name_len);
+        }
+        from = idx + 1;
+    };
+    let name = core::str::from_utf8(&name_buf[..name_len as usize]).unwrap_or("");
+
+    log::info!("covers: scanning {} (idx {})", name, idx);
+
+    let result = read_epub_opf(k, name)
+        .and_then(|book| thumbs::make_thumb(k, name, &book.zip, &book.opf, book.dir()));
+    match result {
+        Ok(true) => log::info!("covers: {} done", name),
+        Ok(false) => log::info!("covers: {} has no cover", name),
+        Err(e) => {
+            log::warn!("covers: {} failed: {}", name, e);
+            // a broken book is not retried; a failed read or
+            // allocation is, on the next scan
+            if matches!(
+                e.kind(),
+                ErrorKind::ParseFailed
+                    | ErrorKind::InvalidData
+                    | ErrorKind::BadEncoding
+                    | ErrorKind::NotFound
+                    | ErrorKind::Other
+            ) {
+                thumbs::mark_no_thumb(k, name);
+            }
+        }
+    }
+
+    Some(idx + 1)
+}
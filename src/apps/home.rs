@@ -1,7 +1,8 @@
-// launcher screen: menu, bookmarks browser
+// launcher screen: menu, bookmarks browser, collections list
 
 use core::fmt::Write as _;
 
+use crate::apps::collections::Collections;
 use crate::apps::{App, AppContext, AppId, RECENT_FILE, Transition};
 use crate::board::action::{Action, ActionEvent};
 use crate::board::{SCREEN_H, SCREEN_W};
@@ -20,7 +21,7 @@ const ITEM_GAP: u16 = 14;
 const ITEM_STRIDE: u16 = ITEM_H + ITEM_GAP;
 const ITEM_X: u16 = (SCREEN_W - ITEM_W) / 2;
 const TITLE_ITEM_GAP: u16 = 24;
-const MAX_ITEMS: usize = 5;
+const MAX_ITEMS: usize = 6;
 
 // bookmark list layout (matches Files app)
 const BM_ROW_H: u16 = 52;
@@ -41,6 +42,7 @@ fn compute_item_regions(heading_line_h: u16) -> [Region; MAX_ITEMS] {
         Region::new(ITEM_X, item_y + ITEM_STRIDE * 2, ITEM_W, ITEM_H),
         Region::new(ITEM_X, item_y + ITEM_STRIDE * 3, ITEM_W, ITEM_H),
         Region::new(ITEM_X, item_y + ITEM_STRIDE * 4, ITEM_W, ITEM_H),
+        Region::new(ITEM_X, item_y + ITEM_STRIDE * 5, ITEM_W, ITEM_H),
     ]
 }
 
@@ -48,12 +50,14 @@ fn compute_item_regions(heading_line_h: u16) -> [Region; MAX_ITEMS] {
 enum HomeState {
     Menu,
     ShowBookmarks,
+    ShowCollections,
 }
 
 enum MenuAction {
     Continue,
     Push(AppId),
     OpenBookmarks,
+    OpenCollections,
 }
 
 pub struct HomeApp {
@@ -72,6 +76,10 @@ pub struct HomeApp {
     bm_selected: usize,
     bm_scroll: usize,
     needs_load_bookmarks: bool,
+
+    collections: Collections,
+    co_selected: usize,
+    needs_load_collections: bool,
 }
 
 impl Default for HomeApp {
@@ -88,7 +96,7 @@ impl HomeApp {
             selected: 0,
             ui_fonts: uf,
             item_regions: compute_item_regions(uf.heading.line_height),
-            item_count: 4, // updated after load; may include Continue
+            item_count: 5, // updated after load; may include Continue
             recent_book: [0u8; 32],
             recent_book_len: 0,
             needs_load_recent: false,
@@ -97,6 +105,9 @@ impl HomeApp {
             bm_selected: 0,
             bm_scroll: 0,
             needs_load_bookmarks: false,
+            collections: Collections::new(),
+            co_selected: 0,
+            needs_load_collections: false,
         }
     }
 
@@ -111,6 +122,7 @@ impl HomeApp {
         match self.state {
             HomeState::Menu => 0,
             HomeState::ShowBookmarks => 1,
+            HomeState::ShowCollections => 2,
         }
     }
 
@@ -139,6 +151,7 @@ impl HomeApp {
     ) {
         self.state = match state_id {
             1 => HomeState::ShowBookmarks,
+            2 => HomeState::ShowCollections,
             _ => HomeState::Menu,
         };
         self.selected = selected;
@@ -147,6 +160,10 @@ impl HomeApp {
         if self.state == HomeState::ShowBookmarks {
             self.needs_load_bookmarks = true;
         }
+        if self.state == HomeState::ShowCollections {
+            self.co_selected = 0;
+            self.needs_load_collections = true;
+        }
         log::info!(
             "home: restore_state state={:?} selected={}",
             self.state,
@@ -171,7 +188,7 @@ impl HomeApp {
     }
 
     fn rebuild_item_count(&mut self) {
-        self.item_count = if self.recent_book_len > 0 { 5 } else { 4 };
+        self.item_count = if self.recent_book_len > 0 { 6 } else { 5 };
         if self.selected >= self.item_count {
             self.selected = 0;
         }
@@ -186,15 +203,17 @@ impl HomeApp {
             match idx {
                 0 => "Continue",
                 1 => "Files",
-                2 => "Bookmarks",
-                3 => "Settings",
+                2 => "Collections",
+                3 => "Bookmarks",
+                4 => "Settings",
                 _ => "Upload",
             }
         } else {
             match idx {
                 0 => "Files",
-                1 => "Bookmarks",
-                2 => "Settings",
+                1 => "Collections",
+                2 => "Bookmarks",
+                3 => "Settings",
                 _ => "Upload",
             }
         }
@@ -205,15 +224,17 @@ impl HomeApp {
             match idx {
                 0 => MenuAction::Continue,
                 1 => MenuAction::Push(AppId::Files),
-                2 => MenuAction::OpenBookmarks,
-                3 => MenuAction::Push(AppId::Settings),
+                2 => MenuAction::OpenCollections,
+                3 => MenuAction::OpenBookmarks,
+                4 => MenuAction::Push(AppId::Settings),
                 _ => MenuAction::Push(AppId::Upload),
             }
         } else {
             match idx {
                 0 => MenuAction::Push(AppId::Files),
-                1 => MenuAction::OpenBookmarks,
-                2 => MenuAction::Push(AppId::Settings),
+                1 => MenuAction::OpenCollections,
+                2 => MenuAction::OpenBookmarks,
+                3 => MenuAction::Push(AppId::Settings),
                 _ => MenuAction::Push(AppId::Upload),
             }
         }
@@ -329,12 +350,22 @@ impl App<AppId> for HomeApp {
                 ctx.mark_dirty(self.bm_list_region());
             }
         }
+
+        if self.needs_load_collections {
+            self.collections.load(k);
+            self.needs_load_collections = false;
+            if self.state == HomeState::ShowCollections {
+                ctx.mark_dirty(self.bm_list_region());
+                ctx.mark_dirty(self.bm_status_region());
+            }
+        }
     }
 
     fn on_event(&mut self, event: ActionEvent, ctx: &mut AppContext) -> Transition {
         match self.state {
             HomeState::Menu => self.on_event_menu(event, ctx),
             HomeState::ShowBookmarks => self.on_event_bookmarks(event, ctx),
+            HomeState::ShowCollections => self.on_event_collections(event, ctx),
         }
     }
 
@@ -342,6 +373,7 @@ impl App<AppId> for HomeApp {
         match self.state {
             HomeState::Menu => self.draw_menu(strip),
             HomeState::ShowBookmarks => self.draw_bookmarks(strip),
+            HomeState::ShowCollections => self.draw_collections(strip),
         }
     }
 }
@@ -364,7 +396,11 @@ impl HomeApp {
                     }
                     Transition::Push(AppId::Reader)
                 }
-                MenuAction::Push(app) => Transition::Push(app),
+                MenuAction::Push(app) => {
+                    // Files reads a collection name from the message
+                    ctx.clear_message();
+                    Transition::Push(app)
+                }
                 MenuAction::OpenBookmarks => {
                     self.bm_selected = 0;
                     self.bm_scroll = 0;
@@ -373,6 +409,13 @@ impl HomeApp {
                     ctx.request_full_redraw();
                     Transition::None
                 }
+                MenuAction::OpenCollections => {
+                    self.co_selected = 0;
+                    self.needs_load_collections = true;
+                    self.state = HomeState::ShowCollections;
+                    ctx.request_full_redraw();
+                    Transition::None
+                }
             },
             _ => Transition::None,
         }
@@ -474,6 +517,52 @@ impl HomeApp {
     }
 }
 
+impl HomeApp {
+    // collections fit on one screen, so moves just redraw the list
+    fn on_event_collections(&mut self, event: ActionEvent, ctx: &mut AppContext) -> Transition {
+        let count = self.collections.count();
+        match event {
+            ActionEvent::Press(Action::Back) | ActionEvent::LongPress(Action::Back) => {
+                self.state = HomeState::Menu;
+                ctx.request_full_redraw();
+                Transition::None
+            }
+
+            ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next) => {
+                if count > 0 {
+                    ctx.mark_dirty(self.bm_row_region(self.co_selected));
+                    self.co_selected = (self.co_selected + 1) % count;
+                    ctx.mark_dirty(self.bm_row_region(self.co_selected));
+                    ctx.mark_dirty(self.bm_status_region());
+                }
+                Transition::None
+            }
+
+            ActionEvent::Press(Action::Prev) | ActionEvent::Repeat(Action::Prev) => {
+                if count > 0 {
+                    ctx.mark_dirty(self.bm_row_region(self.co_selected));
+                    self.co_selected = (self.co_selected + count - 1) % count;
+                    ctx.mark_dirty(self.bm_row_region(self.co_selected));
+                    ctx.mark_dirty(self.bm_status_region());
+                }
+                Transition::None
+            }
+
+            ActionEvent::Press(Action::Select) => {
+                if self.co_selected < count {
+                    ctx.set_message(self.collections.name(self.co_selected).as_bytes());
+                    self.state = HomeState::Menu;
+                    Transition::Push(AppId::Files)
+                } else {
+                    Transition::None
+                }
+            }
+
+            _ => Transition::None,
+        }
+    }
+}
+
 impl HomeApp {
     fn draw_menu(&self, strip: &mut StripBuffer) {
         let title_region = Region::new(
@@ -544,6 +633,58 @@ impl HomeApp {
     }
 }
 
+impl HomeApp {
+    fn draw_collections(&self, strip: &mut StripBuffer) {
+        let header_region = Region::new(
+            LARGE_MARGIN,
+            BM_TITLE_Y,
+            HEADER_W,
+            self.ui_fonts.heading.line_height,
+        );
+        BitmapLabel::new(header_region, "Collections", self.ui_fonts.heading)
+            .alignment(Alignment::CenterLeft)
+            .draw(strip)
+            .unwrap();
+
+        let count = self.collections.count();
+        if count == 0 {
+            if !self.needs_load_collections {
+                BitmapLabel::new(self.bm_row_region(0), "No collections", self.ui_fonts.body)
+                    .alignment(Alignment::CenterLeft)
+                    .draw(strip)
+                    .unwrap();
+                BitmapLabel::new(
+                    self.bm_row_region(1),
+                    "Add books from Files",
+                    self.ui_fonts.body,
+                )
+                .alignment(Alignment::CenterLeft)
+                .draw(strip)
+                .unwrap();
+            }
+            return;
+        }
+
+        let mut status = BitmapDynLabel::<20>::new(self.bm_status_region(), self.ui_fonts.body)
+            .alignment(Alignment::CenterRight);
+        let _ = write!(status, "{}/{}", self.co_selected + 1, count);
+        status.draw(strip).unwrap();
+
+        for i in 0..count.min(self.bm_visible_lines()) {
+            let mut label = BitmapDynLabel::<40>::new(self.bm_row_region(i), self.ui_fonts.body)
+                .alignment(Alignment::CenterLeft)
+                .inverted(i == self.co_selected);
+            let _ = write!(
+                label,
+                "{} ({})",
+                self.collections.name(i),
+                self.collections.member_count(i)
+            );
+            label.draw(strip).unwrap();
+        }
+    }
+}
+
 // humanize an all-uppercase SFN bookmark filename into the title field
 fn humanize_bm_entry(entry: &mut BmListEntry) {
     let nlen = entry.name_len as usize;
@@ -8,21 +8,22 @@ use crate::apps::files::FilesApp;
 use crate::apps::home::HomeApp;
 use crate::apps::reader::ReaderApp;
 use crate::apps::settings::SettingsApp;
+use crate::apps::sleep::SleepView;
 use crate::apps::{App, AppContext, AppId, Launcher, PendingSetting, Redraw, Transition};
 use esp_hal::delay::Delay;
 
 use crate::apps::widgets::quick_menu::{MAX_APP_ACTIONS, QuickMenuResult};
 use crate::apps::widgets::{ButtonFeedback, QuickMenu};
 use crate::board::action::{Action, ActionEvent, ButtonMapper};
-use crate::board::{Epd, SCREEN_H, SCREEN_W};
+use crate::board::{Epd, Orientation};
 use crate::drivers::input::Event;
 use crate::drivers::sdcard::SdStorage;
 use crate::drivers::strip::StripBuffer;
 use crate::fonts;
-use crate::kernel::KernelHandle;
 use crate::kernel::app::AppLayer;
 use crate::kernel::bookmarks::BookmarkCache;
 use crate::kernel::config::{SystemSettings, WifiConfig};
+use crate::kernel::{KernelHandle, SleepScreen};
 use crate::ui::Region;
 
 // monomorphized dispatch from AppId to concrete app type
@@ -96,6 +97,8 @@ pub struct AppManager {
     pub bumps: &'static mut ButtonFeedback,
 
     pub mapper: ButtonMapper,
+
+    sleep: SleepView,
 }
 
 impl AppManager {
@@ -119,6 +122,7 @@ impl AppManager {
             quick_menu,
             bumps,
             mapper,
+            sleep: SleepView::new(),
         }
     }
 
@@ -163,18 +167,34 @@ impl AppManager {
         self.sync_button_config();
     }
 
+    // landscape only applies while reading, and not to books with
+    // pre-rendered pages; every other app keeps the portrait layout
+    pub fn orientation(&self) -> Orientation {
+        if self.launcher.active() == AppId::Reader
+            && self.settings.system_settings().landscape
+            && !self.reader.fixed_layout()
+        {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+
     // sync button mapper and label widget from settings
     pub fn sync_button_config(&mut self) {
         let swap = self.settings.system_settings().swap_buttons;
+        let orientation = self.orientation();
+        let landscape = orientation == Orientation::Landscape;
         self.mapper.set_swap(swap);
-        if self.bumps.set_swap(swap) {
+        self.mapper.set_landscape(landscape);
+        self.quick_menu.set_orientation(orientation);
+        let swapped = self.bumps.set_swap(swap);
+        if self.bumps.set_landscape(landscape) {
+            // orientation change; the scheduler repaints the whole screen
+            self.launcher.ctx.request_full_redraw();
+        } else if swapped {
             // labels changed, need to redraw the button bar
-            self.launcher.ctx.mark_dirty(crate::ui::Region::new(
-                0,
-                crate::board::SCREEN_H - crate::ui::BUTTON_BAR_H,
-                crate::board::SCREEN_W,
-                crate::ui::BUTTON_BAR_H,
-            ));
+            self.launcher.ctx.mark_dirty(self.bumps.bar_region());
         }
     }
 
@@ -299,6 +319,7 @@ impl AppManager {
 
         // propagate fonts before entering apps
         self.propagate_fonts();
+        self.sync_button_config();
 
         // enter apps in stack order (bottom to top)
         // Home is always at bottom
@@ -449,6 +470,7 @@ impl AppManager {
             }
 
             self.propagate_fonts();
+            self.sync_button_config();
             self.launcher.ctx.clear_loading();
 
             if nav.to != AppId::Upload {
@@ -464,9 +486,10 @@ impl AppManager {
             }
 
             if nav.resume {
+                let o = self.orientation();
                 self.launcher
                     .ctx
-                    .mark_dirty(Region::new(0, 0, SCREEN_W, SCREEN_H));
+                    .mark_dirty(Region::new(0, 0, o.screen_w(), o.screen_h()));
             } else {
                 self.launcher.ctx.request_full_redraw();
             }
@@ -522,11 +545,17 @@ impl AppManager {
         let ui_idx = ss.ui_font_size_idx;
         let book_idx = ss.book_font_size_idx;
         let theme_idx = ss.reading_theme;
+        let landscape = ss.landscape;
+        let columns = ss.columns;
+        let (comic_split, comic_rtl) = (ss.comic_split, ss.comic_rtl);
 
         self.home.set_ui_font_size(ui_idx);
         self.files.set_ui_font_size(ui_idx);
         self.settings.set_ui_font_size(ui_idx);
         self.reader.set_book_font_size(book_idx);
+        self.reader.set_landscape(landscape);
+        self.reader.set_columns(columns);
+        self.reader.set_comic_layout(comic_split, comic_rtl);
         self.reader.set_reading_theme(theme_idx);
 
         let chrome = fonts::chrome_font();
@@ -560,6 +589,23 @@ impl AppManager {
         }
     }
 
+    pub fn prepare_sleep(&mut self, k: &mut KernelHandle<'_>) -> SleepScreen {
+        let ss = self.settings.system_settings();
+        let (setting, ui_idx) = (ss.sleep_screen, ss.ui_font_size_idx);
+        let orientation = self.orientation();
+        let reader = if self.launcher.contains(AppId::Reader) && self.reader.has_open_book() {
+            Some(&*self.reader)
+        } else {
+            None
+        };
+        self.sleep.prepare(setting, reader, orientation, ui_idx, k)
+    }
+
+    #[inline]
+    pub fn draw_sleep(&self, strip: &mut StripBuffer) {
+        self.sleep.draw(strip);
+    }
+
     #[inline]
     pub fn system_settings(&self) -> &crate::kernel::config::SystemSettings {
         self.settings.system_settings()
@@ -608,6 +654,10 @@ impl AppLayer for AppManager {
         AppManager::draw(self, strip);
     }
 
+    fn orientation(&self) -> Orientation {
+        AppManager::orientation(self)
+    }
+
     #[inline]
     fn has_redraw(&self) -> bool {
         self.launcher.ctx.has_redraw()
@@ -698,6 +748,14 @@ impl AppLayer for AppManager {
         .await;
     }
 
+    fn prepare_sleep(&mut self, k: &mut KernelHandle<'_>) -> SleepScreen {
+        AppManager::prepare_sleep(self, k)
+    }
+
+    fn draw_sleep(&self, strip: &mut StripBuffer) {
+        AppManager::draw_sleep(self, strip);
+    }
+
     fn suppress_deferred_input(&self) -> bool {
         self.quick_menu.open
     }
@@ -2,6 +2,7 @@
 //
 // AppId is defined here (the distro side) the kernel attempts to be generic
 
+pub mod collections;
 pub mod files;
 pub mod home;
 pub mod manager;
@@ -9,6 +10,8 @@ pub mod reader;
 pub mod widgets;
 
 pub mod settings;
+pub mod sleep;
+pub mod thumbs;
 pub mod upload;
 
 use crate::kernel::app::AppIdType;
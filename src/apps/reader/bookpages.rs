@@ -0,0 +1,233 @@
+// whole-book page numbers for epub-style books
+//
+// once background caching is idle every cached chapter is wrapped for
+// the current layout, a few pages per tick, and its page count kept in
+// the book's cache dir as PAGES.BIN: magic, layout key, chapter count,
+// then a u16 per chapter (0 = not counted yet). a layout change starts
+// the counts over. chapters wrap like preindex_all_pages, without
+// image heights and capped at MAX_PAGES; a chapter the reader has
+// fully indexed replaces its count with the real one
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+
+use super::paging::PageScan;
+use super::{LAYOUT_KEY_LEN, MAX_PAGES, ReaderApp};
+
+const PAGES_FILE: &str = "PAGES.BIN";
+const MAGIC: &[u8; 4] = b"PGC1";
+const HEADER: usize = 4 + LAYOUT_KEY_LEN + 2;
+
+pub(super) struct BookPages {
+    counts: [u16; cache::MAX_CACHE_CHAPTERS],
+    // layout the counts belong to; loaded is false until the file
+    // has been checked against it
+    key: [u8; LAYOUT_KEY_LEN],
+    loaded: bool,
+    complete: bool,
+    // a step failed; numbers stay per chapter for this session
+    halted: bool,
+    // chapter being wrapped, where in it, and pages found so far
+    ch: usize,
+    offset: u32,
+    pages: u16,
+    scan: PageScan,
+}
+
+impl BookPages {
+    pub(super) const fn new() -> Self {
+        Self {
+            counts: [0u16; cache::MAX_CACHE_CHAPTERS],
+            key: [0u8; LAYOUT_KEY_LEN],
+            loaded: false,
+            complete: false,
+            halted: false,
+            ch: 0,
+            offset: 0,
+            pages: 0,
+            scan: PageScan::new(),
+        }
+    }
+}
+
+impl ReaderApp {
+    // (page, total) across the whole book, once every chapter is counted
+    pub(super) fn book_page(&self) -> Option<(usize, usize)> {
+        let n = self.epub.chapter_count();
+        let ch = self.epub.chapter as usize;
+        if !self.bp.complete || self.bp.key != self.layout_key() || ch >= n {
+            return None;
+        }
+        let before: usize = self.bp.counts[..ch].iter().map(|&c| c as usize).sum();
+        let total: usize = self.bp.counts[..n].iter().map(|&c| c as usize).sum();
+        let page = before + self.pg.page;
+        Some((page, total.max(page + 1)))
+    }
+
+    // chapter and page within it of a whole-book page
+    pub(super) fn locate_book_page(&self, page: usize) -> (usize, usize) {
+        let n = self.epub.chapter_count();
+        let mut left = page;
+        for (ch, &c) in self.bp.counts[..n].iter().enumerate() {
+            if left < c as usize || ch + 1 == n {
+                return (ch, left.min((c as usize).saturating_sub(1)));
+            }
+            left -= c as usize;
+        }
+        (0, 0)
+    }
+
+    #[inline]
+    pub(super) fn book_pages_pending(&self) -> bool {
+        let n = self.epub.chapter_count();
+        self.is_epub
+            && self.epub.chapters_cached
+            && n > 0
+            && n <= cache::MAX_CACHE_CHAPTERS
+            && !self.bp.halted
+            && (!self.bp.complete || self.bp.key != self.layout_key())
+    }
+
+    // one background step; true when the counts just became complete
+    pub(super) fn book_pages_step(&mut self, k: &mut KernelHandle<'_>) -> bool {
+        match self.count_pages(k) {
+            Ok(done) => done,
+            Err(e) => {
+                log::warn!("bookpages: counting stopped: {}", e);
+                self.bp.halted = true;
+                self.bp.scan.free();
+                false
+            }
+        }
+    }
+
+    fn count_pages(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        let n = self.epub.chapter_count();
+        let key = self.layout_key();
+        if !self.bp.loaded || self.bp.key != key {
+            self.load_counts(k, key)?;
+        }
+
+        // the reader's own index of the open chapter is exact
+        let cur = self.epub.chapter as usize;
+        if self.pg.fully_indexed && cur < n && self.bp.counts[cur] as usize != self.pg.total_pages {
+            self.bp.counts[cur] = self.pg.total_pages as u16;
+            if self.bp.ch == cur {
+                self.bp.offset = 0;
+                self.bp.pages = 0;
+            }
+            self.save_counts(k)?;
+        }
+
+        while self.bp.ch < n && self.bp.counts[self.bp.ch] != 0 {
+            self.bp.ch += 1;
+        }
+        if self.bp.ch >= n {
+            self.bp.complete = true;
+            self.bp.scan.free();
+            log::info!("bookpages: {} chapters counted", n);
+            return Ok(true);
+        }
+
+        let ch = self.bp.ch;
+        let (ch_off, size) = self.epub.chapter_table[ch];
+        if !self.epub.ch_cached[ch] || size == 0 {
+            self.finish_chapter(k, 1)?;
+            return Ok(false);
+        }
+
+        let cf = self.epub.cache_file;
+        let cf_str = cache::cache_filename_str(&cf);
+        let geom = self.wrap_geom();
+        let full = self.page_lines();
+        let bp = &mut self.bp;
+        let done = bp.scan.wrap_pages(
+            &geom,
+            &self.paged,
+            full,
+            bp.offset,
+            size,
+            |at, buf| k.read_cache_chunk(cf_str, ch_off + at, buf),
+            |next| {
+                if bp.pages as usize + 1 >= MAX_PAGES {
+                    return false;
+                }
+                bp.pages += 1;
+                bp.offset = next;
+                true
+            },
+        )?;
+        if done {
+            self.finish_chapter(k, self.bp.pages + 1)?;
+        }
+        Ok(false)
+    }
+
+    fn finish_chapter(&mut self, k: &mut KernelHandle<'_>, pages: u16) -> crate::error::Result<()> {
+        self.bp.counts[self.bp.ch] = pages;
+        self.bp.ch += 1;
+        self.bp.offset = 0;
+        self.bp.pages = 0;
+        self.save_counts(k)
+    }
+
+    // counts from PAGES.BIN when it was written for this layout,
+    // otherwise none
+    fn load_counts(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        key: [u8; LAYOUT_KEY_LEN],
+    ) -> crate::error::Result<()> {
+        let n = self.epub.chapter_count();
+        self.bp.counts = [0u16; cache::MAX_CACHE_CHAPTERS];
+        self.bp.key = key;
+        self.bp.loaded = true;
+        self.bp.complete = false;
+        self.bp.ch = 0;
+        self.bp.offset = 0;
+        self.bp.pages = 0;
+
+        let dir = self.epub.cache_dir;
+        let dir_str = cache::dir_name_str(&dir);
+        k.ensure_app_subdir(dir_str)?;
+        let want = HEADER + n * 2;
+        let mut buf = [0u8; HEADER + cache::MAX_CACHE_CHAPTERS * 2];
+        let got = k
+            .read_app_subdir_chunk(dir_str, PAGES_FILE, 0, &mut buf[..want])
+            .unwrap_or(0);
+        let hdr = &buf[..HEADER];
+        if got < want
+            || &hdr[..4] != MAGIC
+            || hdr[4..4 + LAYOUT_KEY_LEN] != key
+            || u16::from_le_bytes([hdr[HEADER - 2], hdr[HEADER - 1]]) as usize != n
+        {
+            return Ok(());
+        }
+        for (c, b) in self.bp.counts[..n]
+            .iter_mut()
+            .zip(buf[HEADER..want].chunks_exact(2))
+        {
+            *c = u16::from_le_bytes([b[0], b[1]]);
+        }
+        Ok(())
+    }
+
+    fn save_counts(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let n = self.epub.chapter_count();
+        let mut out = Vec::new();
+        out.try_reserve_exact(HEADER + n * 2)
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "bookpages: save"))?;
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.bp.key);
+        out.extend_from_slice(&(n as u16).to_le_bytes());
+        for &c in &self.bp.counts[..n] {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+        let dir = self.epub.cache_dir;
+        k.write_app_subdir(cache::dir_name_str(&dir), PAGES_FILE, &out)
+    }
+}
@@ -0,0 +1,343 @@
+// cbz comic books: a zip of jpeg/png pages
+//
+// image entries are sorted by name with digit runs compared as
+// numbers, so page2 comes before page10. each page is decoded to fit
+// the panel; with spreads split on, a stored entry wider than it is
+// tall is decoded at twice the screen width and shown one half per
+// screen, right half first for manga. deflated entries can't be
+// peeked and are always fitted whole.
+//
+// while a page is on screen the next one is decoded ahead: small
+// entries on the work queue, large ones by streaming on the main
+// loop like epub images. no read-ahead runs beside a decoded spread
+// so the two buffers stay within the heap budget
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::board::{SCREEN_H, SCREEN_W};
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+use crate::kernel::work_queue::{self, DecodedImage};
+
+use super::images::{decode_image_streaming, peek_entry_dimensions};
+use super::{
+    NO_PREFETCH, PRECACHE_IMG_MAX, ReaderApp, State, extract_zip_entry, is_image_ext_jpeg,
+    is_image_ext_png,
+};
+
+pub(super) fn is_cbz_filename(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("CBZ"))
+}
+
+// jpeg/png entries, minus directories, dotfiles and macos metadata
+fn is_page_entry(path: &str) -> bool {
+    let file = path.rsplit_once('/').map_or(path, |(_, f)| f);
+    !file.is_empty()
+        && !file.starts_with('.')
+        && !path.starts_with("__MACOSX/")
+        && (is_image_ext_jpeg(file) || is_image_ext_png(file))
+}
+
+// case-insensitive, digit runs by value
+fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let (si, sj) = (i, j);
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            let x = trim_zeros(&a[si..i]);
+            let y = trim_zeros(&b[sj..j]);
+            let ord = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        } else {
+            let ord = a[i].to_ascii_lowercase().cmp(&b[j].to_ascii_lowercase());
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let n = digits.iter().take_while(|&&d| d == b'0').count();
+    &digits[n..]
+}
+
+pub(super) struct CbzBook {
+    // zip entry of each page, in reading order
+    pages: Vec<u16>,
+    // spreads setting the shown page was decoded with
+    split: bool,
+    // the shown page is a spread; half counts in reading order
+    spread: bool,
+    half: u8,
+    // stepped back onto this page: open on its last half
+    from_end: bool,
+    // read-ahead: decoded page, page the worker is decoding, and the
+    // page last attempted so a failure is not retried every tick
+    next: Option<(usize, DecodedImage)>,
+    pending: Option<usize>,
+    ahead: usize,
+}
+
+impl CbzBook {
+    pub(super) const fn new() -> Self {
+        Self {
+            pages: Vec::new(),
+            split: false,
+            spread: false,
+            half: 0,
+            from_end: false,
+            next: None,
+            pending: None,
+            ahead: NO_PREFETCH,
+        }
+    }
+}
+
+impl ReaderApp {
+    // central directory and page order; pages decode one at a time
+    pub(super) fn cbz_open(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        self.epub.init_zip(k, name, &mut self.pg.buf)?;
+
+        let zip = &self.epub.zip;
+        let mut pages = Vec::new();
+        for i in 0..zip.count() {
+            if is_page_entry(zip.entry_name(i)) {
+                pages.push(i as u16);
+            }
+        }
+        if pages.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, "cbz: no images"));
+        }
+        pages.sort_unstable_by(|&a, &b| {
+            natural_cmp(
+                zip.entry_name(a as usize).as_bytes(),
+                zip.entry_name(b as usize).as_bytes(),
+            )
+        });
+        log::info!("cbz: {} pages", pages.len());
+
+        self.pg.page = 0;
+        self.pg.total_pages = pages.len();
+        self.pg.fully_indexed = true;
+        self.cbz = CbzBook::new();
+        self.cbz.pages = pages;
+        Ok(())
+    }
+
+    // decode budget for a page: twice the screen width for a spread
+    fn cbz_budget(&self, k: &mut KernelHandle<'_>, name: &str, zip_idx: usize) -> (u16, u16) {
+        let entry = self.epub.zip.entry(zip_idx);
+        let path = self.epub.zip.entry_name(zip_idx);
+        if self.comic_split
+            && peek_entry_dimensions(k, name, entry, path).is_some_and(|(w, h)| w > h)
+        {
+            (SCREEN_W * 2, SCREEN_H)
+        } else {
+            (SCREEN_W, SCREEN_H)
+        }
+    }
+
+    fn cbz_decode(
+        &self,
+        k: &mut KernelHandle<'_>,
+        name: &str,
+        page: usize,
+    ) -> crate::error::Result<DecodedImage> {
+        let zip_idx = self.cbz.pages[page] as usize;
+        let (max_w, max_h) = self.cbz_budget(k, name, zip_idx);
+        let entry = *self.epub.zip.entry(zip_idx);
+        let is_jpeg = is_image_ext_jpeg(self.epub.zip.entry_name(zip_idx));
+        decode_image_streaming(k, name, &entry, is_jpeg, max_w, max_h)
+    }
+
+    // show pg.page (or the restored bookmark page); Ok(false) while
+    // the worker is still decoding it
+    pub(super) fn cbz_load_page(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        if let Some(p) = self.restore_offset.take() {
+            self.pg.page = (p as usize).min(self.pg.total_pages - 1);
+        }
+        let page = self.pg.page;
+
+        if self.cbz.pending == Some(page) {
+            self.cbz_poll();
+            if self.cbz.pending == Some(page) {
+                return Ok(false);
+            }
+        }
+
+        let ready = match self.cbz.next.take() {
+            Some((p, img)) if p == page => Some(img),
+            _ => None,
+        };
+        let img = match ready {
+            Some(img) => img,
+            None => {
+                // release the old page before decoding the new one
+                self.page_img = None;
+                let (nb, nl) = self.name_copy();
+                let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+                self.cbz_decode(k, name, page)?
+            }
+        };
+
+        let from_end = core::mem::take(&mut self.cbz.from_end);
+        self.cbz.split = self.comic_split;
+        self.cbz.spread = img.width > SCREEN_W;
+        self.cbz.half = if self.cbz.spread && from_end { 1 } else { 0 };
+        self.page_img = Some(img);
+        self.rebuild_quick_actions();
+        Ok(true)
+    }
+
+    // decode the page after the shown one; runs while Ready
+    pub(super) fn cbz_prefetch(&mut self, k: &mut KernelHandle<'_>) {
+        self.cbz_poll();
+
+        let want = self.pg.page + 1;
+        if want >= self.pg.total_pages
+            || self.cbz.ahead == want
+            || self.cbz.pending.is_some()
+            || self.cbz.spread
+        {
+            return;
+        }
+        self.cbz.ahead = want;
+        self.cbz.next = None;
+
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let zip_idx = self.cbz.pages[want] as usize;
+        let entry = *self.epub.zip.entry(zip_idx);
+
+        // large pages: streaming decode on the main loop
+        if entry.uncomp_size > PRECACHE_IMG_MAX {
+            match self.cbz_decode(k, name, want) {
+                Ok(img) => self.cbz.next = Some((want, img)),
+                Err(e) => log::warn!("cbz: read-ahead of page {} failed: {}", want + 1, e),
+            }
+            return;
+        }
+
+        // small pages: extract and hand to the worker; retry next tick
+        // if it is busy
+        if !work_queue::is_idle() || !work_queue::can_submit() {
+            self.cbz.ahead = NO_PREFETCH;
+            return;
+        }
+        let (max_w, max_h) = self.cbz_budget(k, name, zip_idx);
+        let is_jpeg = is_image_ext_jpeg(self.epub.zip.entry_name(zip_idx));
+        let data = match extract_zip_entry(k, name, &self.epub.zip, zip_idx) {
+            Ok(d) => d,
+            Err(e) => {
+                log::warn!("cbz: extract of page {} failed: {}", want + 1, e);
+                return;
+            }
+        };
+        let task = work_queue::WorkTask::DecodeImage {
+            path_hash: want as u32,
+            data,
+            is_jpeg,
+            max_w,
+            max_h,
+        };
+        if work_queue::submit(self.epub.work_gen, task) {
+            self.cbz.pending = Some(want);
+        } else {
+            self.cbz.ahead = NO_PREFETCH;
+        }
+    }
+
+    // collect a finished worker decode; path_hash carries the page
+    fn cbz_poll(&mut self) {
+        match work_queue::try_recv() {
+            Some(r) if r.is_current() => {
+                self.cbz.pending = None;
+                match r.outcome {
+                    work_queue::WorkOutcome::ImageReady { path_hash, image } => {
+                        if path_hash as usize == self.cbz.ahead {
+                            self.cbz.next = Some((self.cbz.ahead, image));
+                        }
+                    }
+                    work_queue::WorkOutcome::ImageFailed { path_hash, error } => {
+                        log::warn!("cbz: page {} decode failed: {}", path_hash + 1, error);
+                    }
+                }
+            }
+            Some(_) => {}
+            None => {
+                if self.cbz.pending.is_some() && work_queue::is_idle() {
+                    // the worker dropped the item; decode on demand
+                    self.cbz.pending = None;
+                }
+            }
+        }
+    }
+
+    // second half of a spread before the next page
+    pub(super) fn cbz_forward(&mut self) -> bool {
+        if self.cbz.spread && self.cbz.half == 0 {
+            self.cbz.half = 1;
+            return true;
+        }
+        if self.pg.page + 1 < self.pg.total_pages {
+            self.pg.page += 1;
+            self.state = State::NeedPage;
+            return true;
+        }
+        false
+    }
+
+    pub(super) fn cbz_backward(&mut self) -> bool {
+        if self.cbz.spread && self.cbz.half == 1 {
+            self.cbz.half = 0;
+            return true;
+        }
+        if self.pg.page > 0 {
+            self.pg.page -= 1;
+            self.cbz.from_end = true;
+            self.state = State::NeedPage;
+            return true;
+        }
+        false
+    }
+
+    // the spreads setting changed while the book was put away
+    #[inline]
+    pub(super) fn cbz_needs_reload(&self) -> bool {
+        self.is_cbz && self.cbz.split != self.comic_split
+    }
+
+    // visible part of the page bitmap as (byte offset, width); a
+    // spread is cut on a byte boundary so each half blits cleanly
+    pub(super) fn cbz_crop(&self, width: u16) -> (usize, u16) {
+        if !self.is_cbz || !self.cbz.spread {
+            return (0, width);
+        }
+        let cut = (width / 2) & !7;
+        if (self.cbz.half == 1) != self.comic_rtl {
+            (cut as usize / 8, width - cut)
+        } else {
+            (0, cut)
+        }
+    }
+
+    pub(super) fn cbz_close(&mut self) {
+        self.cbz = CbzBook::new();
+    }
+}
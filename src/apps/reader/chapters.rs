@@ -0,0 +1,429 @@
+// chapter headings found in plain .txt books
+//
+// the text is scanned once in the background, one buffer a tick,
+// for lines that open a chapter: a capitalised keyword line such as
+// "Chapter 12", "CHAPTER XII" or "Part One" (after a blank line
+// unless it is short), or a short title-like line with two blank
+// lines before it and one after. extra keywords come from
+// _PULP/CHAPTERS.TXT, one per line ('#' starts a comment); a trailing
+// '*' matches any line starting with the word. results go to _PULP
+// as XXXXXXXX.CHX keyed on encoding, text size, source key and
+// keywords: a header and count, then per heading its offset, title
+// length and title. once the scan is done they become the book's toc
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+
+use super::textcache;
+use super::toc::Toc;
+use super::{PAGE_BUF, ReaderApp, State, img_cache_name, img_cache_str};
+
+const PATTERN_FILE: &str = "CHAPTERS.TXT";
+const PATTERN_CAP: usize = 1024;
+const MAGIC: &[u8; 4] = b"CHX2";
+const KEY_LEN: usize = 9 + textcache::KEY_LEN;
+const HEADER: usize = 4 + KEY_LEN + 2;
+
+const KEYWORDS: &[&str] = &["chapter", "part", "book", "volume", "prologue", "epilogue"];
+const NUMBER_WORDS: &[&str] = &[
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+    "twenty",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+];
+// longest line taken for a keyword heading, and for a bare title
+const KEYWORD_LINE_MAX: usize = 80;
+const TITLE_LINE_MAX: usize = 48;
+
+pub(super) struct TxtChapters {
+    // keywords read and the index checked since the book was opened
+    open: bool,
+    complete: bool,
+    // a step failed; the book stays without chapters this session
+    halted: bool,
+    file: [u8; 12],
+    key: [u8; KEY_LEN],
+    words: Vec<u8>,
+    words_hash: u32,
+    // scan position and blank lines right before it
+    offset: u32,
+    blanks: u8,
+    found: Toc,
+    buf: Vec<u8>,
+}
+
+impl TxtChapters {
+    pub(super) const fn new() -> Self {
+        Self {
+            open: false,
+            complete: false,
+            halted: false,
+            file: [0u8; 12],
+            key: [0u8; KEY_LEN],
+            words: Vec::new(),
+            words_hash: 0,
+            offset: 0,
+            blanks: 0,
+            found: Toc::new(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+fn chapters_name(name: &str) -> [u8; 12] {
+    let mut n = img_cache_name(cache::fnv1a(name.as_bytes()));
+    n[9..].copy_from_slice(b"CHX");
+    n
+}
+
+#[inline]
+fn is_blank(line: &[u8]) -> bool {
+    line.trim_ascii().is_empty()
+}
+
+// digits, a roman numeral or a number word at the start of s
+fn starts_with_number(s: &[u8]) -> bool {
+    if s.first().is_some_and(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    let word = s
+        .iter()
+        .position(|b| !b.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    let word = &s[..word];
+    !word.is_empty()
+        && (word
+            .iter()
+            .all(|b| b"IVXLCDM".contains(&b.to_ascii_uppercase()))
+            || NUMBER_WORDS
+                .iter()
+                .any(|w| word.eq_ignore_ascii_case(w.as_bytes())))
+}
+
+// "Chapter", "Chapter 3", "PART TWO", "Prologue: The Storm"
+fn keyword_heading(text: &[u8], word: &[u8]) -> bool {
+    if let Some(prefix) = word.strip_suffix(b"*") {
+        return !prefix.is_empty()
+            && text.len() >= prefix.len()
+            && text[..prefix.len()].eq_ignore_ascii_case(prefix);
+    }
+    if text.len() < word.len() || !text[..word.len()].eq_ignore_ascii_case(word) {
+        return false;
+    }
+    let rest = &text[word.len()..];
+    match rest.first() {
+        None | Some(b':') => true,
+        Some(b' ' | b'\t' | b'.') => {
+            let rest = rest.trim_ascii_start();
+            let rest = rest.strip_prefix(b".").unwrap_or(rest).trim_ascii_start();
+            rest.is_empty() || rest[0] == b':' || starts_with_number(rest)
+        }
+        _ => false,
+    }
+}
+
+// a line standing on its own that reads like a title rather than a
+// sentence, a scene break or dialogue
+fn title_like(text: &[u8]) -> bool {
+    let first = text[0];
+    let last = text[text.len() - 1];
+    text.len() <= TITLE_LINE_MAX
+        && (first.is_ascii_uppercase() || first.is_ascii_digit() || first >= 0x80)
+        && !b".,;:!?\"'-".contains(&last)
+}
+
+// built-in keywords, then the ones from CHAPTERS.TXT
+fn keywords(words: &[u8]) -> impl Iterator<Item = &[u8]> {
+    KEYWORDS.iter().map(|w| w.as_bytes()).chain(
+        words
+            .split(|&b| b == b'\n')
+            .map(|l| l.trim_ascii())
+            .filter(|l| !l.is_empty() && l[0] != b'#'),
+    )
+}
+
+// the heading text when the line opens a chapter
+fn heading<'a>(line: &'a [u8], words: &[u8], blanks: u8, next_blank: bool) -> Option<&'a [u8]> {
+    let text = line.trim_ascii();
+    let text = text.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(text);
+    if text.is_empty() || text.len() > KEYWORD_LINE_MAX || text[0].is_ascii_lowercase() {
+        return None;
+    }
+    // without a blank line before, only a short line can open a chapter
+    let spaced = blanks > 0 || text.len() <= TITLE_LINE_MAX;
+    let found = (spaced && keywords(words).any(|w| keyword_heading(text, w)))
+        || (blanks >= 2 && next_blank && title_like(text));
+    found.then_some(text)
+}
+
+impl ReaderApp {
+    // headings are still being looked for
+    #[inline]
+    pub(super) fn chapters_pending(&self) -> bool {
+        self.is_plain_text()
+            && self.file_size > 0
+            && !self.chap.halted
+            && (!self.chap.complete || self.chap.key != self.chapters_key())
+    }
+
+    // the scan is done and found at least one heading
+    #[inline]
+    pub(super) fn has_txt_chapters(&self) -> bool {
+        self.is_plain_text() && self.epub.toc.as_ref().is_some_and(|t| !t.is_empty())
+    }
+
+    fn chapters_key(&self) -> [u8; KEY_LEN] {
+        let mut key = [0u8; KEY_LEN];
+        key[0] = self.encoding.menu_value();
+        key[1..5].copy_from_slice(&self.file_size.to_le_bytes());
+        key[5..9].copy_from_slice(&self.chap.words_hash.to_le_bytes());
+        key[9..].copy_from_slice(&self.text_key);
+        key
+    }
+
+    // toc entry of the chapter the current page starts in
+    pub(super) fn txt_chapter(&self) -> Option<usize> {
+        if !self.is_plain_text() {
+            return None;
+        }
+        let at = self.byte_offset();
+        let toc = self.epub.toc.as_ref()?;
+        toc.entries.iter().rposition(|e| e.offset <= at)
+    }
+
+    // start of the first chapter past the current page, or of the
+    // last one that starts before it
+    pub(super) fn txt_chapter_target(&self, forward: bool) -> Option<u32> {
+        if !self.is_plain_text() {
+            return None;
+        }
+        let toc = self.epub.toc.as_ref()?;
+        let start = self.byte_offset();
+        let next = self.pg.page + 1;
+        let end = if next < self.pg.total_pages && self.pg.in_window(next) {
+            self.pg.offset(next)
+        } else {
+            self.file_size
+        };
+        if forward {
+            toc.entries.iter().map(|e| e.offset).find(|&o| o >= end)
+        } else {
+            toc.entries.iter().map(|e| e.offset).rfind(|&o| o < start)
+        }
+    }
+
+    // land on the page holding offset
+    pub(super) fn txt_goto(&mut self, offset: u32) {
+        self.restore_offset = Some(offset);
+        self.state = State::NeedPage;
+    }
+
+    // one background step; true when the toc changed
+    pub(super) fn chapters_step(&mut self, k: &mut KernelHandle<'_>) -> bool {
+        let result = if !self.chap.open || self.chap.key != self.chapters_key() {
+            self.chapters_open(k)
+        } else {
+            self.chapters_scan(k)
+        };
+        match result {
+            Ok(changed) => changed,
+            Err(e) => {
+                log::warn!("chapters: scan stopped: {}", e);
+                self.chap.halted = true;
+                self.chap.buf = Vec::new();
+                false
+            }
+        }
+    }
+
+    // load a matching index, else start scanning from the top
+    fn chapters_open(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        let had_toc = self.epub.toc.take().is_some();
+        if !self.chap.open {
+            self.chap.open = true;
+            let mut words = [0u8; PATTERN_CAP];
+            let n = k
+                .read_app_data_start(PATTERN_FILE, &mut words)
+                .map_or(0, |(_, n)| n);
+            self.chap.words = words[..n].to_vec();
+            self.chap.words_hash = cache::fnv1a(&words[..n]);
+            let (nb, nl) = self.name_copy();
+            let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+            self.chap.file = chapters_name(name);
+        }
+        self.chap.key = self.chapters_key();
+        self.chap.complete = false;
+        self.chap.offset = 0;
+        self.chap.blanks = 2;
+        self.chap.found = Toc::new();
+
+        if let Some(toc) = self.chapters_load(k) {
+            log::info!("chapters: {} headings from index", toc.len());
+            self.chap.complete = true;
+            let found = !toc.is_empty();
+            if found {
+                self.epub.toc = Some(toc);
+            }
+            return Ok(found || had_toc);
+        }
+        if self.chap.buf.is_empty() {
+            self.chap
+                .buf
+                .try_reserve_exact(PAGE_BUF)
+                .map_err(|_| Error::new(ErrorKind::OutOfMemory, "chapters: buffer"))?;
+            self.chap.buf.resize(PAGE_BUF, 0);
+        }
+        Ok(had_toc)
+    }
+
+    fn chapters_load(&mut self, k: &mut KernelHandle<'_>) -> Option<Toc> {
+        let f = self.chap.file;
+        let f_str = img_cache_str(&f);
+        let size = k.cache_file_size(f_str).ok()? as usize;
+        if size < HEADER {
+            return None;
+        }
+        let mut data = Vec::new();
+        data.try_reserve_exact(size).ok()?;
+        data.resize(size, 0);
+        if k.read_cache_chunk(f_str, 0, &mut data).ok()? < size
+            || &data[..4] != MAGIC
+            || data[4..4 + KEY_LEN] != self.chap.key
+        {
+            return None;
+        }
+        let count = u16::from_le_bytes([data[HEADER - 2], data[HEADER - 1]]) as usize;
+        let mut toc = Toc::new();
+        let mut pos = HEADER;
+        for _ in 0..count {
+            let rec = data.get(pos..pos + 5)?;
+            let offset = u32::from_le_bytes([rec[0], rec[1], rec[2], rec[3]]);
+            let title = data.get(pos + 5..pos + 5 + rec[4] as usize)?;
+            toc.push_offset(title, offset);
+            pos += 5 + title.len();
+        }
+        Some(toc)
+    }
+
+    fn chapters_save(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let found = &self.chap.found;
+        let size = found
+            .entries
+            .iter()
+            .fold(HEADER, |n, e| n + 5 + e.title_str().len());
+        let mut out = Vec::new();
+        out.try_reserve_exact(size)
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "chapters: save"))?;
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.chap.key);
+        out.extend_from_slice(&(found.len() as u16).to_le_bytes());
+        for e in &found.entries {
+            let title = e.title_str().as_bytes();
+            out.extend_from_slice(&e.offset.to_le_bytes());
+            out.push(title.len() as u8);
+            out.extend_from_slice(title);
+        }
+        let f = self.chap.file;
+        k.write_cache(img_cache_str(&f), &out)
+    }
+
+    // look through one buffer of text; a line is only judged once the
+    // line after it is in the buffer too
+    fn chapters_scan(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let base = self.chap.offset;
+        let n = if self.txt_converted {
+            let cf = self.text_cache;
+            k.read_cache_chunk(
+                img_cache_str(&cf),
+                self.text_header() + base,
+                &mut self.chap.buf,
+            )?
+        } else {
+            k.read_chunk(name, base, &mut self.chap.buf)?
+        };
+        let eof = n == 0 || base + n as u32 >= self.file_size;
+
+        let buf = &self.chap.buf[..n];
+        let mut pos = 0usize;
+        let mut blanks = self.chap.blanks;
+        loop {
+            let (line, next) = match buf[pos..].iter().position(|&b| b == b'\n') {
+                Some(e) => (&buf[pos..pos + e], pos + e + 1),
+                None if eof && pos < n => (&buf[pos..], n),
+                None => break,
+            };
+            let next_blank = match buf[next..].iter().position(|&b| b == b'\n') {
+                Some(e) => is_blank(&buf[next..next + e]),
+                None if eof => is_blank(&buf[next..]),
+                // the line after is longer than the rest of the buffer
+                None if pos == 0 => false,
+                None => break,
+            };
+            let title = heading(line, &self.chap.words, blanks, next_blank);
+            if title.is_some_and(|t| !self.chap.found.push_offset(t, base + pos as u32)) {
+                log::warn!("chapters: toc full at offset {}", base + pos as u32);
+            }
+            blanks = if is_blank(line) {
+                blanks.saturating_add(1)
+            } else {
+                0
+            };
+            pos = next;
+        }
+        // a line longer than the buffer is no heading; skip past it
+        if pos == 0 && !eof {
+            pos = n;
+            blanks = 0;
+        }
+        self.chap.offset = base + pos as u32;
+        self.chap.blanks = blanks;
+
+        if !eof {
+            return Ok(false);
+        }
+        self.chap.complete = true;
+        self.chap.buf = Vec::new();
+        log::info!("chapters: {} headings found", self.chap.found.len());
+        if let Err(e) = self.chapters_save(k) {
+            log::warn!("chapters: failed to save index: {}", e);
+        }
+        let toc = core::mem::replace(&mut self.chap.found, Toc::new());
+        if toc.is_empty() {
+            return Ok(false);
+        }
+        self.epub.toc = Some(toc);
+        Ok(true)
+    }
+}
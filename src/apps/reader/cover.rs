@@ -0,0 +1,120 @@
+// epub cover lookup
+//
+// EPUB 3 marks the cover with properties="cover-image" on a manifest
+// item, EPUB 2 with <meta name="cover" content="item-id">; books with
+// neither usually still ship an image entry named cover.jpg or similar
+
+use smol_epub::epub;
+use smol_epub::zip::ZipIndex;
+
+use crate::kernel::KernelHandle;
+use crate::kernel::work_queue::DecodedImage;
+
+use super::images::{decode_image_streaming, is_image_ext_jpeg, is_image_ext_png};
+
+fn is_image(path: &str) -> bool {
+    is_image_ext_jpeg(path) || is_image_ext_png(path)
+}
+
+// zip index of the cover image, resolved against the OPF directory
+pub(crate) fn find_cover(opf: &[u8], opf_dir: &str, zip: &ZipIndex) -> Option<usize> {
+    if let Some(href) = cover_href(opf).and_then(|h| core::str::from_utf8(h).ok()) {
+        let mut path_buf = [0u8; 512];
+        let len = epub::resolve_path(opf_dir, href, &mut path_buf);
+        if let Ok(path) = core::str::from_utf8(&path_buf[..len]) {
+            let found = zip.find(path).or_else(|| zip.find_icase(path));
+            if is_image(path) && found.is_some() {
+                return found;
+            }
+        }
+    }
+
+    (0..zip.count()).find(|&i| {
+        let path = zip.entry_name(i);
+        let file = path.rsplit('/').next().unwrap_or(path).as_bytes();
+        is_image(path) && file.len() >= 5 && file[..5].eq_ignore_ascii_case(b"cover")
+    })
+}
+
+// decode a cover found by find_cover to fit max_w x max_h
+pub(crate) fn decode_cover(
+    k: &mut KernelHandle<'_>,
+    epub_name: &str,
+    zip: &ZipIndex,
+    idx: usize,
+    max_w: u16,
+    max_h: u16,
+) -> crate::error::Result<DecodedImage> {
+    let entry = *zip.entry(idx);
+    let is_jpeg = is_image_ext_jpeg(zip.entry_name(idx));
+    decode_image_streaming(k, epub_name, &entry, is_jpeg, max_w, max_h)
+}
+
+fn cover_href(opf: &[u8]) -> Option<&[u8]> {
+    // EPUB 3
+    for tag in tags(opf, b"item") {
+        let is_cover = attr(tag, b"properties").is_some_and(|props| {
+            props
+                .split(|b| b.is_ascii_whitespace())
+                .any(|p| p == b"cover-image")
+        });
+        if is_cover {
+            return attr(tag, b"href");
+        }
+    }
+
+    // EPUB 2
+    let id = tags(opf, b"meta")
+        .find(|t| attr(t, b"name") == Some(&b"cover"[..]))
+        .and_then(|t| attr(t, b"content"))?;
+    tags(opf, b"item")
+        .find(|t| attr(t, b"id") == Some(id))
+        .and_then(|t| attr(t, b"href"))
+}
+
+// start tags with the given local name (any namespace prefix),
+// without the leading '<' and trailing '>'
+fn tags<'a>(opf: &'a [u8], name: &'static [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+    opf.split(|&b| b == b'<').filter_map(move |seg| {
+        let end = seg.iter().position(|&b| b == b'>').unwrap_or(seg.len());
+        let tag = &seg[..end];
+        let name_end = tag
+            .iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'/')
+            .unwrap_or(tag.len());
+        let tname = &tag[..name_end];
+        let local = match tname.iter().rposition(|&b| b == b':') {
+            Some(p) => &tname[p + 1..],
+            None => tname,
+        };
+        (local == name).then_some(tag)
+    })
+}
+
+// quoted attribute value; the name must follow whitespace so "id"
+// doesn't match inside "media-overlay-id" style names
+fn attr<'a>(tag: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let mut i = 1;
+    while i + name.len() < tag.len() {
+        if tag[i - 1].is_ascii_whitespace() && tag[i..].starts_with(name) {
+            let mut j = i + name.len();
+            while j < tag.len() && tag[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j < tag.len() && tag[j] == b'=' {
+                j += 1;
+                while j < tag.len() && tag[j].is_ascii_whitespace() {
+                    j += 1;
+                }
+                let quote = *tag.get(j)?;
+                if quote == b'"' || quote == b'\'' {
+                    let rest = &tag[j + 1..];
+                    let end = rest.iter().position(|&b| b == quote)?;
+                    return Some(&rest[..end]);
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
@@ -0,0 +1,502 @@
+// text encodings for plain .txt books
+//
+// sniff() looks at the start of the file: a BOM wins, then zero-byte
+// patterns for bom-less UTF-16, then UTF-8 validity. what is left is
+// a single-byte code page: mostly-ascii text with a few high bytes is
+// taken as Windows-1252, text that is mostly high bytes is scored as
+// each cyrillic page by letter case (lowercase runs score, a capital
+// after a lowercase letter costs) and the best one wins.
+//
+// anything but UTF-8 is transcoded once into _PULP/XXXXXXXX.U8T,
+// whose header holds the encoding and the source key, and read from
+// there like a markdown cache (see textcache.rs). a manual choice
+// from the quick menu is kept per book in _PULP/TXTENC.TXT
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+use smol_epub::html_strip::MARKER;
+
+use crate::kernel::KernelHandle;
+
+use super::textcache::{self, Feed, KEY_LEN, TextFeed};
+use super::{ReaderApp, img_cache_name};
+
+pub(super) const TXT_HEADER: u32 = 4 + KEY_LEN as u32;
+
+const OVERRIDE_FILE: &str = "TXTENC.TXT";
+const OVERRIDE_CAP: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    Latin1,
+    Latin2,
+    Iso8859_5,
+    Windows1251,
+    Koi8R,
+}
+
+// quick menu order; value 0 is "Auto", value n is ALL[n - 1]
+pub(super) const ENCODING_NAMES: &[&str] = &[
+    "Auto", "UTF-8", "UTF-16LE", "UTF-16BE", "Win-1252", "8859-1", "8859-2", "8859-5", "Win-1251",
+    "KOI8-R",
+];
+
+impl Encoding {
+    pub(super) const ALL: [Encoding; 9] = [
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Windows1252,
+        Encoding::Latin1,
+        Encoding::Latin2,
+        Encoding::Iso8859_5,
+        Encoding::Windows1251,
+        Encoding::Koi8R,
+    ];
+
+    // menu value of this encoding
+    pub(super) fn menu_value(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|&e| e == self)
+            .map_or(0, |i| i as u8 + 1)
+    }
+
+    pub(super) fn from_menu_value(v: u8) -> Option<Self> {
+        Self::ALL.get((v as usize).checked_sub(1)?).copied()
+    }
+
+    // names as written in TXTENC.TXT or an fb2 xml declaration,
+    // plus common aliases
+    pub(super) fn from_label(label: &[u8]) -> Option<Self> {
+        const LABELS: &[(&str, Encoding)] = &[
+            ("utf-8", Encoding::Utf8),
+            ("utf8", Encoding::Utf8),
+            ("us-ascii", Encoding::Utf8),
+            ("utf-16le", Encoding::Utf16Le),
+            ("utf-16", Encoding::Utf16Le),
+            ("utf-16be", Encoding::Utf16Be),
+            ("windows-1252", Encoding::Windows1252),
+            ("cp1252", Encoding::Windows1252),
+            ("iso-8859-1", Encoding::Latin1),
+            ("latin1", Encoding::Latin1),
+            ("iso-8859-2", Encoding::Latin2),
+            ("latin2", Encoding::Latin2),
+            ("iso-8859-5", Encoding::Iso8859_5),
+            ("windows-1251", Encoding::Windows1251),
+            ("cp1251", Encoding::Windows1251),
+            ("koi8-r", Encoding::Koi8R),
+        ];
+        let label = label.trim_ascii();
+        LABELS
+            .iter()
+            .find(|(l, _)| label.eq_ignore_ascii_case(l.as_bytes()))
+            .map(|&(_, e)| e)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Windows1252 => "windows-1252",
+            Encoding::Latin1 => "iso-8859-1",
+            Encoding::Latin2 => "iso-8859-2",
+            Encoding::Iso8859_5 => "iso-8859-5",
+            Encoding::Windows1251 => "windows-1251",
+            Encoding::Koi8R => "koi8-r",
+        }
+    }
+
+    // a byte >= 0x80 of a single-byte code page
+    pub(super) fn high_char(self, b: u8) -> char {
+        let u = match self {
+            Encoding::Latin1 => b as u16,
+            Encoding::Windows1252 if b < 0xA0 => CP1252_80[(b - 0x80) as usize],
+            Encoding::Windows1252 => b as u16,
+            Encoding::Latin2 if b < 0xA0 => b as u16,
+            Encoding::Latin2 => LATIN2_A0[(b - 0xA0) as usize],
+            Encoding::Iso8859_5 => match b {
+                0x80..=0xA0 | 0xAD => b as u16,
+                0xF0 => 0x2116,
+                0xFD => 0x00A7,
+                _ => 0x0400 + (b - 0xA0) as u16,
+            },
+            Encoding::Windows1251 if b < 0xC0 => CP1251_80[(b - 0x80) as usize],
+            Encoding::Windows1251 => 0x0410 + (b - 0xC0) as u16,
+            Encoding::Koi8R if b < 0xC0 => KOI8R_80[(b - 0x80) as usize],
+            Encoding::Koi8R if b < 0xE0 => KOI8R_C0[(b - 0xC0) as usize],
+            // the capitals repeat the lowercase layout
+            Encoding::Koi8R => KOI8R_C0[(b - 0xE0) as usize] - 0x20,
+            Encoding::Utf8 | Encoding::Utf16Le | Encoding::Utf16Be => 0xFFFD,
+        };
+        char::from_u32(u as u32).unwrap_or('\u{FFFD}')
+    }
+}
+
+// windows-1252 0x80..0x9F; undefined slots keep their C1 value
+const CP1252_80: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x008D, 0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+// iso-8859-2 0xA0..0xFF
+const LATIN2_A0: [u16; 96] = [
+    0x00A0, 0x0104, 0x02D8, 0x0141, 0x00A4, 0x013D, 0x015A, 0x00A7, 0x00A8, 0x0160, 0x015E, 0x0164,
+    0x0179, 0x00AD, 0x017D, 0x017B, 0x00B0, 0x0105, 0x02DB, 0x0142, 0x00B4, 0x013E, 0x015B, 0x02C7,
+    0x00B8, 0x0161, 0x015F, 0x0165, 0x017A, 0x02DD, 0x017E, 0x017C, 0x0154, 0x00C1, 0x00C2, 0x0102,
+    0x00C4, 0x0139, 0x0106, 0x00C7, 0x010C, 0x00C9, 0x0118, 0x00CB, 0x011A, 0x00CD, 0x00CE, 0x010E,
+    0x0110, 0x0143, 0x0147, 0x00D3, 0x00D4, 0x0150, 0x00D6, 0x00D7, 0x0158, 0x016E, 0x00DA, 0x0170,
+    0x00DC, 0x00DD, 0x0162, 0x00DF, 0x0155, 0x00E1, 0x00E2, 0x0103, 0x00E4, 0x013A, 0x0107, 0x00E7,
+    0x010D, 0x00E9, 0x0119, 0x00EB, 0x011B, 0x00ED, 0x00EE, 0x010F, 0x0111, 0x0144, 0x0148, 0x00F3,
+    0x00F4, 0x0151, 0x00F6, 0x00F7, 0x0159, 0x016F, 0x00FA, 0x0171, 0x00FC, 0x00FD, 0x0163, 0x02D9,
+];
+
+// windows-1251 0x80..0xBF; 0xC0.. is А..я in order
+const CP1251_80: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021, 0x20AC, 0x2030, 0x0409, 0x2039,
+    0x040A, 0x040C, 0x040B, 0x040F, 0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x0098, 0x2122, 0x0459, 0x203A, 0x045A, 0x045C, 0x045B, 0x045F, 0x00A0, 0x040E, 0x045E, 0x0408,
+    0x00A4, 0x0490, 0x00A6, 0x00A7, 0x0401, 0x00A9, 0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407,
+    0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7, 0x0451, 0x2116, 0x0454, 0x00BB,
+    0x0458, 0x0405, 0x0455, 0x0457,
+];
+
+// koi8-r 0x80..0xBF: box drawing, a few symbols, Ё and ё
+const KOI8R_80: [u16; 64] = [
+    0x2500, 0x2502, 0x250C, 0x2510, 0x2514, 0x2518, 0x251C, 0x2524, 0x252C, 0x2534, 0x253C, 0x2580,
+    0x2584, 0x2588, 0x258C, 0x2590, 0x2591, 0x2592, 0x2593, 0x2320, 0x25A0, 0x2219, 0x221A, 0x2248,
+    0x2264, 0x2265, 0x00A0, 0x2321, 0x00B0, 0x00B2, 0x00B7, 0x00F7, 0x2550, 0x2551, 0x2552, 0x0451,
+    0x2553, 0x2554, 0x2555, 0x2556, 0x2557, 0x2558, 0x2559, 0x255A, 0x255B, 0x255C, 0x255D, 0x255E,
+    0x255F, 0x2560, 0x2561, 0x0401, 0x2562, 0x2563, 0x2564, 0x2565, 0x2566, 0x2567, 0x2568, 0x2569,
+    0x256A, 0x256B, 0x256C, 0x00A9,
+];
+
+// koi8-r 0xC0..0xDF, lowercase in the ю а б ц order
+const KOI8R_C0: [u16; 32] = [
+    0x044E, 0x0430, 0x0431, 0x0446, 0x0434, 0x0435, 0x0444, 0x0433, 0x0445, 0x0438, 0x0439, 0x043A,
+    0x043B, 0x043C, 0x043D, 0x043E, 0x043F, 0x044F, 0x0440, 0x0441, 0x0442, 0x0443, 0x0436, 0x0432,
+    0x044C, 0x044B, 0x0437, 0x0448, 0x044D, 0x0449, 0x0447, 0x044A,
+];
+
+// best guess for the encoding of a file starting with sample
+fn sniff(sample: &[u8]) -> Encoding {
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return Encoding::Utf16Le;
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return Encoding::Utf16Be;
+    }
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Encoding::Utf8;
+    }
+
+    // ascii as utf-16 has every other byte zero
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+    let half = sample.len() / 2;
+    if half >= 8 {
+        if odd_zeros > half / 2 && even_zeros < half / 8 {
+            return Encoding::Utf16Le;
+        }
+        if even_zeros > half / 2 && odd_zeros < half / 8 {
+            return Encoding::Utf16Be;
+        }
+    }
+
+    // a multi-byte sequence cut off by the end of the sample is fine
+    match core::str::from_utf8(sample) {
+        Ok(_) => return Encoding::Utf8,
+        Err(e) if e.error_len().is_none() => return Encoding::Utf8,
+        Err(_) => {}
+    }
+
+    let high = sample.iter().filter(|&&b| b >= 0x80).count();
+    let ascii_letters = sample.iter().filter(|b| b.is_ascii_alphabetic()).count();
+    if high <= ascii_letters {
+        return Encoding::Windows1252;
+    }
+    let mut best = Encoding::Windows1251;
+    let mut best_score = i32::MIN;
+    for enc in [Encoding::Windows1251, Encoding::Koi8R, Encoding::Iso8859_5] {
+        let score = cyrillic_score(sample, enc);
+        if score > best_score {
+            best = enc;
+            best_score = score;
+        }
+    }
+    best
+}
+
+// lowercase letters score, capitals score only at the start of a
+// word, everything else in the high half counts against
+fn cyrillic_score(sample: &[u8], enc: Encoding) -> i32 {
+    let mut score = 0i32;
+    let mut prev_lower = false;
+    for &b in sample {
+        if b < 0x80 {
+            prev_lower = false;
+            continue;
+        }
+        let c = enc.high_char(b) as u32;
+        let lower = matches!(c, 0x0430..=0x044F | 0x0451);
+        let upper = matches!(c, 0x0410..=0x042F | 0x0401);
+        score += if lower {
+            2
+        } else if upper && !prev_lower {
+            1
+        } else if upper {
+            -4
+        } else {
+            -2
+        };
+        prev_lower = lower;
+    }
+    score
+}
+
+// streaming conversion to UTF-8; UTF-16 units may straddle chunks
+pub(super) struct Transcoder {
+    enc: Encoding,
+    carry: u8,
+    has_carry: bool,
+    high_surrogate: u16,
+    started: bool,
+}
+
+impl Transcoder {
+    pub(super) fn new(enc: Encoding) -> Self {
+        Self {
+            enc,
+            carry: 0,
+            has_carry: false,
+            high_surrogate: 0,
+            started: false,
+        }
+    }
+
+    fn unit(&mut self, unit: u16, out: &mut Vec<u8>) {
+        let first = !self.started;
+        self.started = true;
+        match unit {
+            0xD800..=0xDBFF => {
+                if self.high_surrogate != 0 {
+                    push_char(out, '\u{FFFD}');
+                }
+                self.high_surrogate = unit;
+            }
+            0xDC00..=0xDFFF => {
+                let hi = core::mem::take(&mut self.high_surrogate);
+                let c = if hi != 0 {
+                    0x10000 + (((hi as u32) - 0xD800) << 10) + (unit as u32 - 0xDC00)
+                } else {
+                    0xFFFD
+                };
+                push_char(out, char::from_u32(c).unwrap_or('\u{FFFD}'));
+            }
+            _ => {
+                if core::mem::take(&mut self.high_surrogate) != 0 {
+                    push_char(out, '\u{FFFD}');
+                }
+                // the byte order mark is not text
+                if first && unit == 0xFEFF {
+                    return;
+                }
+                if unit != MARKER as u16 {
+                    push_char(out, char::from_u32(unit as u32).unwrap_or('\u{FFFD}'));
+                }
+            }
+        }
+    }
+}
+
+impl TextFeed for Transcoder {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        match self.enc {
+            Encoding::Utf8 => {
+                for &b in input {
+                    if b != MARKER {
+                        out.push(b);
+                    }
+                }
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                for &b in input {
+                    if !self.has_carry {
+                        self.carry = b;
+                        self.has_carry = true;
+                        continue;
+                    }
+                    self.has_carry = false;
+                    let unit = if self.enc == Encoding::Utf16Le {
+                        u16::from_le_bytes([self.carry, b])
+                    } else {
+                        u16::from_be_bytes([self.carry, b])
+                    };
+                    self.unit(unit, out);
+                }
+            }
+            _ => {
+                for &b in input {
+                    if b < 0x80 {
+                        if b != MARKER {
+                            out.push(b);
+                        }
+                    } else {
+                        push_char(out, self.enc.high_char(b));
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        if self.has_carry || self.high_surrogate != 0 {
+            push_char(out, '\u{FFFD}');
+        }
+        self.has_carry = false;
+        self.high_surrogate = 0;
+    }
+}
+
+#[inline]
+fn push_char(out: &mut Vec<u8>, c: char) {
+    let mut utf8 = [0u8; 4];
+    out.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+}
+
+fn txt_cache_name(name: &str) -> [u8; 12] {
+    let mut n = img_cache_name(cache::fnv1a(name.as_bytes()));
+    n[9..].copy_from_slice(b"U8T");
+    n
+}
+
+fn txt_header(enc: Encoding, key: &[u8; KEY_LEN]) -> [u8; TXT_HEADER as usize] {
+    let mut h = [0u8; TXT_HEADER as usize];
+    h[..3].copy_from_slice(b"U8T");
+    h[3] = enc.menu_value();
+    h[4..].copy_from_slice(key);
+    h
+}
+
+// manual choice for a book, from "NAME.TXT\tlabel" lines
+fn load_override(k: &mut KernelHandle<'_>, name: &str, scratch: &mut [u8]) -> Option<Encoding> {
+    let cap = scratch.len().min(OVERRIDE_CAP);
+    let (_, n) = k
+        .read_app_data_start(OVERRIDE_FILE, &mut scratch[..cap])
+        .ok()?;
+    scratch[..n].split(|&b| b == b'\n').find_map(|line| {
+        let tab = line.iter().position(|&b| b == b'\t')?;
+        if line[..tab].eq_ignore_ascii_case(name.as_bytes()) {
+            Encoding::from_label(&line[tab + 1..])
+        } else {
+            None
+        }
+    })
+}
+
+// rewrite the override list with this book's choice (None for auto)
+// first; the oldest entries drop off once the file is full
+fn save_override(
+    k: &mut KernelHandle<'_>,
+    name: &str,
+    enc: Option<Encoding>,
+    scratch: &mut [u8],
+) -> crate::error::Result<()> {
+    let cap = scratch.len().min(OVERRIDE_CAP);
+    let n = match k.read_app_data_start(OVERRIDE_FILE, &mut scratch[..cap]) {
+        Ok((_, n)) => n,
+        Err(_) => 0,
+    };
+    let mut out = Vec::with_capacity(OVERRIDE_CAP);
+    if let Some(enc) = enc {
+        out.extend_from_slice(name.as_bytes());
+        out.push(b'\t');
+        out.extend_from_slice(enc.label().as_bytes());
+        out.push(b'\n');
+    }
+    for line in scratch[..n].split(|&b| b == b'\n') {
+        let Some(tab) = line.iter().position(|&b| b == b'\t') else {
+            continue;
+        };
+        if line[..tab].eq_ignore_ascii_case(name.as_bytes()) {
+            continue;
+        }
+        if out.len() + line.len() + 1 > OVERRIDE_CAP {
+            break;
+        }
+        out.extend_from_slice(line);
+        out.push(b'\n');
+    }
+    k.write_app_data(OVERRIDE_FILE, &out)
+}
+
+impl ReaderApp {
+    // pick the encoding and, unless it is UTF-8, make sure a current
+    // UTF-8 copy exists, a few chunks per call; true once done.
+    // file_size is the length paging works on
+    pub(super) fn txt_prepare(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        if self.text_conv.is_none() {
+            self.txt_detect(k)?;
+        }
+        if self.txt_converted {
+            let header = txt_header(self.encoding, &self.text_key);
+            let enc = self.encoding;
+            match self.convert_text(k, &header, || Feed::Text(Transcoder::new(enc)))? {
+                Some(len) => self.file_size = len,
+                None => return Ok(false),
+            }
+        }
+
+        // an encoding switch keeps the reader near the same place
+        let old_size = core::mem::take(&mut self.reencode_from);
+        if let Some(off) = self.restore_offset.filter(|_| old_size > 0) {
+            let scaled = off as u64 * self.file_size as u64 / old_size as u64;
+            self.restore_offset = Some(scaled as u32);
+        }
+        self.rebuild_quick_actions();
+        Ok(true)
+    }
+
+    // encoding, source key and cache name; file_size is the source
+    // size until a conversion replaces it
+    fn txt_detect(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+
+        if self.enc_dirty {
+            self.enc_dirty = false;
+            let chosen = Encoding::from_menu_value(self.enc_choice);
+            if let Err(e) = save_override(k, name, chosen, &mut self.pg.buf) {
+                log::warn!("reader: failed to save encoding choice: {}", e);
+            }
+        } else {
+            self.enc_choice =
+                load_override(k, name, &mut self.pg.buf).map_or(0, |e| e.menu_value());
+        }
+
+        let (size, n) = k.read_file_start(name, &mut self.pg.buf)?;
+        let enc =
+            Encoding::from_menu_value(self.enc_choice).unwrap_or_else(|| sniff(&self.pg.buf[..n]));
+        self.encoding = enc;
+        self.txt_converted = enc != Encoding::Utf8;
+        self.text_key = textcache::source_key(k, name, size, &mut self.pg.buf)?;
+        if self.txt_converted {
+            self.text_cache = txt_cache_name(name);
+        }
+        self.file_size = size;
+        log::info!("reader: {} is {:?} ({} bytes)", name, enc, size);
+        Ok(())
+    }
+}
@@ -10,13 +10,23 @@ use core::cell::RefCell;
 
 use smol_epub::cache;
 use smol_epub::epub;
+use smol_epub::html_strip::HtmlStripStream;
 
 use crate::error::{Error, ErrorKind};
 use crate::kernel::KernelHandle;
 use crate::kernel::work_queue;
+use pulp_kernel::util::nfc_compose_in_place;
 
+use super::fb2::Fb2Source;
+use super::toc::frag_hash;
+use super::xml::attr;
 use super::{BgCacheState, CHAPTER_CACHE_MAX, EOCD_TAIL, EpubState, PAGE_BUF, ReaderApp, ZipIndex};
 
+// read and strip buffers of strip_scan
+const SCAN_BUF: usize = 4096;
+// anchor of an entry still being looked up
+const SEEKING: u32 = u32::MAX;
+
 // one cell shared between reader and writer; safe because
 // stream_strip_entry_async never borrows both simultaneously
 struct CellReader<'a, 'k>(&'a RefCell<&'a mut KernelHandle<'k>>, &'a str);
@@ -165,7 +175,7 @@ impl EpubState {
     ) -> crate::error::Result<bool> {
         let cf = self.cache_file;
         let cf_str = cache::cache_filename_str(&cf);
-        let spine_len = self.spine.len();
+        let spine_len = self.chapter_count();
 
         // build v3 header with chapters_complete flag
         let mut hdr = cache::CacheHeader::empty();
@@ -326,10 +336,15 @@ impl EpubState {
             }
         }
 
+        // html_strip passes decomposed (NFD) text through untouched;
+        // compose once here so preindex and page loads agree
+        let merged = nfc_compose_in_place(&mut self.ch_cache);
+
         log::info!(
-            "chapter cache: loaded ch{} ({} bytes) into RAM",
+            "chapter cache: loaded ch{} ({} bytes, {} composed) into RAM",
             self.chapter,
             ch_size,
+            merged,
         );
         true
     }
@@ -389,6 +404,8 @@ impl ReaderApp {
 
         // defer TOC to NeedToc to avoid stack overflow while OPF is live
         self.epub.toc_source = epub::find_toc_source(&opf_data, opf_dir, &self.epub.zip);
+        self.epub.cover =
+            super::cover::find_cover(&opf_data, opf_dir, &self.epub.zip).map(|i| i as u16);
         drop(opf_data);
 
         log::info!(
@@ -414,6 +431,71 @@ impl ReaderApp {
         Ok(())
     }
 
+    #[inline]
+    pub(super) fn toc_anchors_pending(&self) -> bool {
+        let ch = self.epub.chapter;
+        self.is_epub
+            && (ch as usize) < self.epub.spine.len()
+            && self
+                .epub
+                .toc
+                .as_ref()
+                .is_some_and(|t| t.anchors_for != ch && t.has_fragments(ch))
+    }
+
+    // offsets of the toc fragments that point into the current
+    // chapter, by the text before each element whose id an entry
+    // wants; a fragment that is not found leaves its entry at the
+    // chapter start
+    pub(super) fn toc_anchors_step(&mut self, k: &mut KernelHandle<'_>) {
+        let ch = self.epub.chapter;
+        let Some(toc) = self.epub.toc.as_mut() else {
+            return;
+        };
+        toc.anchors_for = ch;
+        for e in toc.entries.iter_mut() {
+            e.anchor = if e.spine_idx == ch && e.frag != 0 {
+                SEEKING
+            } else {
+                0
+            };
+        }
+        match self.toc_find_anchors(k) {
+            Ok(found) => log::info!("toc: {} anchors in chapter {}", found, ch + 1),
+            Err(e) => log::warn!("toc: anchor lookup failed: {}", e),
+        }
+        if let Some(toc) = self.epub.toc.as_mut() {
+            for e in toc.entries.iter_mut().filter(|e| e.anchor == SEEKING) {
+                e.anchor = 0;
+            }
+        }
+    }
+
+    fn toc_find_anchors(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<usize> {
+        let ch = self.epub.chapter;
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let idx = self.epub.spine.items[ch as usize] as usize;
+        let Some(toc) = self.epub.toc.as_mut() else {
+            return Ok(0);
+        };
+
+        let wanted = toc.entries.iter().filter(|e| e.anchor == SEEKING).count();
+        let mut left = wanted;
+        strip_scan(k, name, &self.epub.zip, idx, |tag, text| {
+            if let Some(h) = tag_id(tag).map(frag_hash) {
+                for e in toc.entries.iter_mut() {
+                    if e.anchor == SEEKING && e.frag == h {
+                        e.anchor = text;
+                        left -= 1;
+                    }
+                }
+            }
+            left > 0
+        })?;
+        Ok(wanted - left)
+    }
+
     pub(super) fn epub_index_chapter(&mut self) {
         self.reset_paging();
         // force reload; ch_cache may hold a different chapter's data
@@ -546,3 +628,85 @@ impl ReaderApp {
         }
     }
 }
+
+// id attribute of a start tag given without its brackets
+pub(super) fn tag_id(tag: &[u8]) -> Option<&[u8]> {
+    let end = tag
+        .iter()
+        .position(|&b| b.is_ascii_whitespace() || b == b'/')
+        .unwrap_or(tag.len());
+    attr(&tag[end..], b"id")
+}
+
+// strip zip entry idx of the book the way its chapter was cached and
+// hand each tag (without its brackets) to on_tag with the length of
+// the text before it; on_tag returns false to stop. a tag cut off at
+// the end of the read buffer waits for the next read unless it fills
+// the buffer
+pub(super) fn strip_scan(
+    k: &mut KernelHandle<'_>,
+    name: &str,
+    zip: &ZipIndex,
+    idx: usize,
+    mut on_tag: impl FnMut(&[u8], u32) -> bool,
+) -> crate::error::Result<()> {
+    let mut src = Fb2Source::open_entry(k, name, zip, idx)?;
+    let mut buf = Vec::new();
+    let mut out = Vec::new();
+    buf.try_reserve_exact(SCAN_BUF)
+        .and_then(|()| out.try_reserve_exact(SCAN_BUF))
+        .map_err(|_| Error::new(ErrorKind::OutOfMemory, "epub: scan buffers"))?;
+    buf.resize(SCAN_BUF, 0);
+    out.resize(SCAN_BUF, 0);
+
+    let mut strip = HtmlStripStream::new();
+    let mut text = 0u32;
+    let mut len = 0usize;
+    loop {
+        let n = src.read(k, &mut buf[len..])?;
+        len += n;
+        let mut fed = 0;
+        let mut at = 0;
+        let mut keep = len;
+        while let Some(lt) = buf[at..len].iter().position(|&b| b == b'<') {
+            let start = at + lt;
+            let Some(gt) = buf[start..len].iter().position(|&b| b == b'>') else {
+                if start > 0 || len < buf.len() {
+                    keep = start;
+                }
+                break;
+            };
+            at = start + gt + 1;
+            text += strip_count(&mut strip, &buf[fed..start], &mut out)?;
+            fed = start;
+            if !on_tag(&buf[start + 1..start + gt], text) {
+                return Ok(());
+            }
+        }
+        if n == 0 {
+            return Ok(());
+        }
+        text += strip_count(&mut strip, &buf[fed..keep], &mut out)?;
+        buf.copy_within(keep..len, 0);
+        len -= keep;
+    }
+}
+
+// feed all of input to the stripper; the number of text bytes out
+fn strip_count(
+    strip: &mut HtmlStripStream,
+    input: &[u8],
+    out: &mut Vec<u8>,
+) -> crate::error::Result<u32> {
+    let mut pos = 0;
+    let mut total = 0u32;
+    while pos < input.len() {
+        let (used, wrote) = strip.feed(&input[pos..], out);
+        if used == 0 && wrote == 0 {
+            return Err(Error::new(ErrorKind::ParseFailed, "epub: strip stalled"));
+        }
+        pos += used;
+        total += wrote as u32;
+    }
+    Ok(total)
+}
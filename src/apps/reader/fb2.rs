@@ -0,0 +1,1022 @@
+// fictionbook (fb2) books, plain or inside a zip
+//
+// the xml is parsed in one streaming pass into the same v3 chapter
+// cache epub uses, so paging, bookmarks and chapter navigation are
+// shared: top-level sections (and the sections one level below them)
+// start chapters, <title> becomes a heading and a toc entry, and
+// <emphasis>/<strong> map to the italic/bold markers. base64
+// <binary> images are decoded to _PULP/<book dir>/XXXXXXXX.IMG and
+// referenced by that name, the toc is kept next to them in FB2TOC.TXT.
+// a book declared in a single-byte code page (windows-1251 is common)
+// is decoded to utf-8 as it is read
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::inflate_flags::TINFL_FLAG_HAS_MORE_INPUT;
+use miniz_oxide::inflate::core::{DecompressorOxide, decompress};
+use smol_epub::cache;
+use smol_epub::epub::EpubSpine;
+use smol_epub::html_strip::{
+    BOLD_OFF, BOLD_ON, HEADING_OFF, HEADING_ON, IMG_REF, ITALIC_OFF, ITALIC_ON, MARKER, QUOTE_OFF,
+    QUOTE_ON,
+};
+use smol_epub::zip::{self, ZipIndex};
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+use crate::ui::StackFmt;
+
+use super::encoding::Encoding;
+use super::toc::{TOC_TITLE_CAP, Toc};
+use super::xml::attr;
+use super::{EpubState, ReaderApp, img_cache_name};
+
+pub(super) const TOC_FILE: &str = "FB2TOC.TXT";
+
+const TAG_CAP: usize = 256;
+const ENT_CAP: usize = 12;
+const TITLE_CAP: usize = 64;
+// converted text and image bytes are written out in pieces this big
+const FLUSH_AT: usize = 4096;
+// deflate window; the inflater uses it as a ring buffer
+const RING: usize = 32 * 1024;
+
+pub(super) fn is_fb2_filename(name: &str) -> bool {
+    ext_eq(name, b"FB2") || ext_eq(name, b"ZIP")
+}
+
+fn ext_eq(name: &str, ext: &[u8]) -> bool {
+    let b = name.as_bytes();
+    b.len() > ext.len()
+        && b[b.len() - ext.len() - 1] == b'.'
+        && b[b.len() - ext.len()..].eq_ignore_ascii_case(ext)
+}
+
+// binary ids become 8.3 names; the format is sniffed when decoding
+pub(super) fn fb2_image_name(id: &[u8]) -> [u8; 12] {
+    let mut n = img_cache_name(cache::fnv1a(id));
+    n[9..].copy_from_slice(b"IMG");
+    n
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Text,
+    Tag,
+    Entity,
+    Comment,
+    Cdata,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Fb2Event {
+    // a <binary> closed; its bytes are in bin, its file name in bin_name
+    BinaryEnd,
+}
+
+pub(super) struct Fb2Strip {
+    mode: Mode,
+    tag: [u8; TAG_CAP],
+    tag_len: usize,
+    ent: [u8; ENT_CAP],
+    ent_len: usize,
+    // trailing '-' or ']' while inside a comment or cdata
+    run: u8,
+    // from the xml declaration; None is utf-8
+    code_page: Option<Encoding>,
+    pub(super) bad_encoding: bool,
+
+    in_description: bool,
+    in_book_title: bool,
+    pub(super) title: [u8; TITLE_CAP],
+    pub(super) title_len: usize,
+
+    in_body: bool,
+    body_count: u8,
+    section_depth: u8,
+    title_depth: u8,
+    toc_title: [u8; TOC_TITLE_CAP],
+    toc_len: usize,
+    pub(super) toc: Toc,
+
+    // open paragraph-like element and the marker that closes it
+    in_block: bool,
+    block_close: u8,
+    line_text: bool,
+    space: bool,
+    bold: u8,
+    italic: u8,
+
+    in_binary: bool,
+    b64: u32,
+    b64_bits: u8,
+    pub(super) bin: Vec<u8>,
+    pub(super) bin_name: [u8; 12],
+
+    pub(super) out: Vec<u8>,
+    drained: u32,
+    // chapter start offsets into the converted text
+    pub(super) chapters: Vec<u32>,
+}
+
+impl Fb2Strip {
+    pub(super) fn new() -> Self {
+        let mut chapters = Vec::new();
+        chapters.push(0);
+        Self {
+            mode: Mode::Text,
+            tag: [0u8; TAG_CAP],
+            tag_len: 0,
+            ent: [0u8; ENT_CAP],
+            ent_len: 0,
+            run: 0,
+            code_page: None,
+            bad_encoding: false,
+            in_description: false,
+            in_book_title: false,
+            title: [0u8; TITLE_CAP],
+            title_len: 0,
+            in_body: false,
+            body_count: 0,
+            section_depth: 0,
+            title_depth: 0,
+            toc_title: [0u8; TOC_TITLE_CAP],
+            toc_len: 0,
+            toc: Toc::new(),
+            in_block: false,
+            block_close: 0,
+            line_text: false,
+            space: false,
+            bold: 0,
+            italic: 0,
+            in_binary: false,
+            b64: 0,
+            b64_bits: 0,
+            bin: Vec::new(),
+            bin_name: [0u8; 12],
+            out: Vec::new(),
+            drained: 0,
+            chapters,
+        }
+    }
+
+    // converted bytes so far, written out or not
+    #[inline]
+    pub(super) fn pos(&self) -> u32 {
+        self.drained + self.out.len() as u32
+    }
+
+    // the caller wrote out and is about to clear it
+    pub(super) fn mark_drained(&mut self) {
+        self.drained += self.out.len() as u32;
+        self.out.clear();
+    }
+
+    // consumes input up to and including the next event
+    pub(super) fn feed(&mut self, input: &[u8]) -> (usize, Option<Fb2Event>) {
+        let mut i = 0;
+        while i < input.len() {
+            let b = input[i];
+            i += 1;
+            match self.mode {
+                Mode::Text => match b {
+                    b'<' => {
+                        self.mode = Mode::Tag;
+                        self.tag_len = 0;
+                    }
+                    b'&' if !self.in_binary => {
+                        self.mode = Mode::Entity;
+                        self.ent_len = 0;
+                    }
+                    _ => self.text(b),
+                },
+                Mode::Entity => {
+                    if b == b';' {
+                        self.mode = Mode::Text;
+                        self.entity();
+                    } else if self.ent_len < ENT_CAP && (b.is_ascii_alphanumeric() || b == b'#') {
+                        self.ent[self.ent_len] = b;
+                        self.ent_len += 1;
+                    } else {
+                        // a bare '&': keep it and what followed
+                        self.mode = Mode::Text;
+                        self.text(b'&');
+                        for j in 0..self.ent_len {
+                            self.text(self.ent[j]);
+                        }
+                        i -= 1;
+                    }
+                }
+                Mode::Tag => {
+                    if b == b'>' {
+                        self.mode = Mode::Text;
+                        if let Some(ev) = self.tag() {
+                            return (i, Some(ev));
+                        }
+                    } else if self.tag_len < TAG_CAP {
+                        self.tag[self.tag_len] = b;
+                        self.tag_len += 1;
+                        if self.tag_len == 3 && &self.tag[..3] == b"!--" {
+                            self.mode = Mode::Comment;
+                            self.run = 0;
+                        } else if self.tag_len == 8 && &self.tag[..8] == b"![CDATA[" {
+                            self.mode = Mode::Cdata;
+                            self.run = 0;
+                        }
+                    }
+                }
+                Mode::Comment => {
+                    if b == b'>' && self.run >= 2 {
+                        self.mode = Mode::Text;
+                    } else if b == b'-' {
+                        self.run = self.run.saturating_add(1);
+                    } else {
+                        self.run = 0;
+                    }
+                }
+                Mode::Cdata => {
+                    if b == b']' {
+                        self.run = self.run.saturating_add(1);
+                    } else if b == b'>' && self.run >= 2 {
+                        for _ in 2..self.run {
+                            self.text(b']');
+                        }
+                        self.mode = Mode::Text;
+                    } else {
+                        for _ in 0..self.run {
+                            self.text(b']');
+                        }
+                        self.run = 0;
+                        self.text(b);
+                    }
+                }
+            }
+        }
+        (i, None)
+    }
+
+    pub(super) fn finish(&mut self) {
+        self.close_block();
+    }
+
+    // a byte of the source; code page bytes are decoded first
+    fn text(&mut self, b: u8) {
+        match self.code_page {
+            Some(enc) if b >= 0x80 && !self.in_binary => {
+                let mut utf8 = [0u8; 4];
+                for &u in enc.high_char(b).encode_utf8(&mut utf8).as_bytes() {
+                    self.put(u);
+                }
+            }
+            _ => self.put(b),
+        }
+    }
+
+    // a byte of utf-8 text
+    fn put(&mut self, b: u8) {
+        if self.in_binary {
+            self.base64(b);
+            return;
+        }
+        if self.in_book_title {
+            push_collapsed(&mut self.title, &mut self.title_len, b);
+            return;
+        }
+        if !self.in_body || !self.in_block || b == MARKER {
+            return;
+        }
+        if self.title_depth > 0 {
+            push_collapsed(&mut self.toc_title, &mut self.toc_len, b);
+        }
+        if b.is_ascii_whitespace() {
+            if self.line_text {
+                self.space = true;
+            }
+            return;
+        }
+        if self.space {
+            self.out.push(b' ');
+            self.space = false;
+        }
+        self.out.push(b);
+        self.line_text = true;
+    }
+
+    fn entity(&mut self) {
+        let ch = entity_char(&self.ent[..self.ent_len]);
+        let mut utf8 = [0u8; 4];
+        match ch {
+            Some(c) => {
+                for &b in c.encode_utf8(&mut utf8).as_bytes() {
+                    self.put(b);
+                }
+            }
+            None => {
+                let mut raw = [0u8; ENT_CAP];
+                let n = self.ent_len;
+                raw[..n].copy_from_slice(&self.ent[..n]);
+                self.text(b'&');
+                for &b in &raw[..n] {
+                    self.text(b);
+                }
+                self.text(b';');
+            }
+        }
+    }
+
+    fn base64(&mut self, b: u8) {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return,
+        };
+        self.b64 = (self.b64 << 6) | v as u32;
+        self.b64_bits += 6;
+        if self.b64_bits >= 8 {
+            self.b64_bits -= 8;
+            self.bin.push((self.b64 >> self.b64_bits) as u8);
+        }
+    }
+
+    fn tag(&mut self) -> Option<Fb2Event> {
+        let mut buf = [0u8; TAG_CAP];
+        let len = self.tag_len;
+        buf[..len].copy_from_slice(&self.tag[..len]);
+        let t = &buf[..len];
+        if let Some(decl) = t.strip_prefix(b"?xml") {
+            self.declaration(decl.strip_suffix(b"?").unwrap_or(decl));
+            return None;
+        }
+        if t.is_empty() || t[0] == b'?' || t[0] == b'!' {
+            return None;
+        }
+
+        let closing = t[0] == b'/';
+        let body = if closing { &t[1..] } else { t };
+        let self_closing = body.last() == Some(&b'/');
+        let name_end = body
+            .iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'/')
+            .unwrap_or(body.len());
+        let name = &body[..name_end];
+        // fb2 elements are unprefixed, but some writers add one
+        let name = match name.iter().rposition(|&b| b == b':') {
+            Some(p) => &name[p + 1..],
+            None => name,
+        };
+
+        if closing {
+            return self.close(name);
+        }
+        self.open(name, &body[name_end..]);
+        if self_closing {
+            return self.close(name);
+        }
+        None
+    }
+
+    // anything but utf-8 or a known single-byte code page is refused
+    fn declaration(&mut self, attrs: &[u8]) {
+        let Some(label) = attr(attrs, b"encoding") else {
+            return;
+        };
+        match Encoding::from_label(label) {
+            Some(Encoding::Utf8) => {}
+            None | Some(Encoding::Utf16Le | Encoding::Utf16Be) => self.bad_encoding = true,
+            enc => self.code_page = enc,
+        }
+    }
+
+    fn open(&mut self, name: &[u8], attrs: &[u8]) {
+        match name {
+            b"description" => self.in_description = true,
+            b"book-title" if self.in_description && self.title_len == 0 => {
+                self.in_book_title = true;
+            }
+            b"body" => {
+                self.close_block();
+                self.in_body = true;
+                self.body_count = self.body_count.saturating_add(1);
+                // notes and comments bodies follow the main text
+                if self.body_count > 1 {
+                    self.chapter_break();
+                }
+            }
+            b"section" if self.in_body => {
+                self.close_block();
+                self.section_depth = self.section_depth.saturating_add(1);
+                if self.section_depth <= 2 {
+                    self.chapter_break();
+                }
+            }
+            b"title" if self.in_body => {
+                self.close_block();
+                if self.title_depth == 0 {
+                    self.toc_len = 0;
+                }
+                self.title_depth += 1;
+            }
+            b"p" | b"v" | b"subtitle" | b"text-author" | b"td" | b"th" if self.in_body => {
+                let style = if self.title_depth > 0 {
+                    HEADING_ON
+                } else if name == b"subtitle" {
+                    BOLD_ON
+                } else if name == b"text-author" {
+                    ITALIC_ON
+                } else {
+                    0
+                };
+                if self.title_depth > 0 && self.toc_len > 0 {
+                    push_collapsed(&mut self.toc_title, &mut self.toc_len, b' ');
+                }
+                self.open_block(style);
+            }
+            b"emphasis" => {
+                self.italic = self.italic.saturating_add(1);
+                if self.italic == 1 {
+                    self.style(ITALIC_ON);
+                }
+            }
+            b"strong" => {
+                self.bold = self.bold.saturating_add(1);
+                if self.bold == 1 {
+                    self.style(BOLD_ON);
+                }
+            }
+            b"cite" | b"epigraph" | b"poem" | b"annotation" if self.in_body => {
+                self.close_block();
+                self.out.push(MARKER);
+                self.out.push(QUOTE_ON);
+            }
+            b"empty-line" if self.in_body => {
+                self.close_block();
+                self.out.push(b'\n');
+            }
+            b"image" if self.in_body => {
+                if let Some(href) = attr(attrs, b"href") {
+                    let id = href.strip_prefix(b"#").unwrap_or(href);
+                    let file = fb2_image_name(id);
+                    self.out.push(MARKER);
+                    self.out.push(IMG_REF);
+                    self.out.push(file.len() as u8);
+                    self.out.extend_from_slice(&file);
+                }
+            }
+            b"binary" => {
+                let id = attr(attrs, b"id").unwrap_or(b"");
+                self.bin_name = fb2_image_name(id);
+                self.bin.clear();
+                self.b64 = 0;
+                self.b64_bits = 0;
+                self.in_binary = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &[u8]) -> Option<Fb2Event> {
+        match name {
+            b"description" => self.in_description = false,
+            b"book-title" => self.in_book_title = false,
+            b"body" => {
+                self.close_block();
+                self.in_body = false;
+                self.section_depth = 0;
+                self.title_depth = 0;
+            }
+            b"section" if self.in_body => {
+                self.close_block();
+                self.section_depth = self.section_depth.saturating_sub(1);
+            }
+            b"title" if self.in_body && self.title_depth > 0 => {
+                self.close_block();
+                self.title_depth -= 1;
+                if self.title_depth == 0 {
+                    let listed = (1..=2).contains(&self.section_depth)
+                        || (self.section_depth == 0 && self.body_count > 1);
+                    if listed && self.toc_len > 0 {
+                        let ch = (self.chapters.len() - 1) as u16;
+                        self.toc.push(&self.toc_title[..self.toc_len], ch);
+                    }
+                    self.out.push(b'\n');
+                }
+            }
+            b"p" | b"v" | b"subtitle" | b"text-author" | b"td" | b"th" => self.close_block(),
+            b"emphasis" if self.italic > 0 => {
+                self.italic -= 1;
+                if self.italic == 0 {
+                    self.style(ITALIC_OFF);
+                }
+            }
+            b"strong" if self.bold > 0 => {
+                self.bold -= 1;
+                if self.bold == 0 {
+                    self.style(BOLD_OFF);
+                }
+            }
+            b"cite" | b"epigraph" | b"poem" | b"annotation" if self.in_body => {
+                self.close_block();
+                self.out.push(MARKER);
+                self.out.push(QUOTE_OFF);
+            }
+            b"stanza" if self.in_body => {
+                self.close_block();
+                self.out.push(b'\n');
+            }
+            b"binary" if self.in_binary => {
+                self.in_binary = false;
+                return Some(Fb2Event::BinaryEnd);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // emphasis inside a heading would drop back to the body font
+    fn style(&mut self, code: u8) {
+        if self.in_body && self.title_depth == 0 {
+            self.out.push(MARKER);
+            self.out.push(code);
+        }
+    }
+
+    fn open_block(&mut self, style: u8) {
+        self.close_block();
+        if style != 0 {
+            self.out.push(MARKER);
+            self.out.push(style);
+        }
+        self.in_block = true;
+        self.block_close = match style {
+            HEADING_ON => HEADING_OFF,
+            BOLD_ON => BOLD_OFF,
+            ITALIC_ON => ITALIC_OFF,
+            _ => 0,
+        };
+        self.line_text = false;
+        self.space = false;
+    }
+
+    fn close_block(&mut self) {
+        if !self.in_block {
+            return;
+        }
+        if self.block_close != 0 {
+            self.out.push(MARKER);
+            self.out.push(self.block_close);
+        }
+        self.out.push(b'\n');
+        self.in_block = false;
+        self.block_close = 0;
+    }
+
+    // a section starts a chapter unless the current one is still empty
+    fn chapter_break(&mut self) {
+        let pos = self.pos();
+        let last = *self.chapters.last().unwrap_or(&0);
+        if pos > last && self.chapters.len() < cache::MAX_CACHE_CHAPTERS {
+            self.chapters.push(pos);
+        }
+    }
+}
+
+// a character that does not fit closes the buffer: the rest is
+// filled with spaces, which every reader of these buffers trims
+fn push_collapsed(buf: &mut [u8], len: &mut usize, b: u8) {
+    let need = match b {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    };
+    if b.is_ascii_whitespace() {
+        if *len > 0 && buf[*len - 1] != b' ' && *len < buf.len() {
+            buf[*len] = b' ';
+            *len += 1;
+        }
+    } else if *len + need > buf.len() {
+        buf[*len..].fill(b' ');
+        *len = buf.len();
+    } else {
+        buf[*len] = b;
+        *len += 1;
+    }
+}
+
+// the character an entity (without & and ;) stands for: the xml
+// ones, nbsp and numeric references
+pub(super) fn entity_char(ent: &[u8]) -> Option<char> {
+    match ent {
+        b"amp" => Some('&'),
+        b"lt" => Some('<'),
+        b"gt" => Some('>'),
+        b"quot" => Some('"'),
+        b"apos" => Some('\''),
+        b"nbsp" => Some('\u{a0}'),
+        [b'#', b'x' | b'X', hex @ ..] => parse_num(hex, 16).and_then(char::from_u32),
+        [b'#', dec @ ..] => parse_num(dec, 10).and_then(char::from_u32),
+        _ => None,
+    }
+}
+
+fn parse_num(digits: &[u8], radix: u32) -> Option<u32> {
+    let s = core::str::from_utf8(digits).ok()?;
+    u32::from_str_radix(s, radix).ok()
+}
+
+struct Inflate {
+    state: DecompressorOxide,
+    ring: Vec<u8>,
+    ring_pos: usize,
+    input: [u8; 4096],
+    in_pos: usize,
+    in_len: usize,
+    // decoded bytes not yet handed out: ring[out_start..out_start + avail]
+    out_start: usize,
+    avail: usize,
+    done: bool,
+}
+
+// the xml bytes of the book: a plain file, or a stored or deflated
+// zip entry read through a 32 KB window. epub chapters are read the
+// same way when toc anchors are looked up
+pub(super) struct Fb2Source<'a> {
+    name: &'a str,
+    offset: u32,
+    end: u32,
+    inflate: Option<Box<Inflate>>,
+}
+
+impl<'a> Fb2Source<'a> {
+    fn open(
+        k: &mut KernelHandle<'_>,
+        name: &'a str,
+        zip: Option<&ZipIndex>,
+    ) -> crate::error::Result<Self> {
+        let Some(zip) = zip else {
+            let size = k.file_size(name)?;
+            return Ok(Self {
+                name,
+                offset: 0,
+                end: size,
+                inflate: None,
+            });
+        };
+
+        let idx = (0..zip.count())
+            .find(|&i| ext_eq(zip.entry_name(i), b"FB2"))
+            .ok_or(Error::new(ErrorKind::NotFound, "fb2: no .fb2 in zip"))?;
+        Self::open_entry(k, name, zip, idx)
+    }
+
+    // entry idx of the zip archive name
+    pub(super) fn open_entry(
+        k: &mut KernelHandle<'_>,
+        name: &'a str,
+        zip: &ZipIndex,
+        idx: usize,
+    ) -> crate::error::Result<Self> {
+        let entry = *zip.entry(idx);
+        let mut hdr = [0u8; 30];
+        k.read_chunk(name, entry.local_offset, &mut hdr)?;
+        let skip = ZipIndex::local_header_data_skip(&hdr)
+            .map_err(|_| Error::new(ErrorKind::ParseFailed, "fb2: local header"))?;
+        let offset = entry.local_offset + skip;
+
+        if entry.method == zip::METHOD_STORED {
+            return Ok(Self {
+                name,
+                offset,
+                end: offset + entry.uncomp_size,
+                inflate: None,
+            });
+        }
+
+        let mut ring = Vec::new();
+        ring.try_reserve_exact(RING)
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "fb2: inflate window"))?;
+        ring.resize(RING, 0);
+        Ok(Self {
+            name,
+            offset,
+            end: offset + entry.comp_size,
+            inflate: Some(Box::new(Inflate {
+                state: DecompressorOxide::new(),
+                ring,
+                ring_pos: 0,
+                input: [0u8; 4096],
+                in_pos: 0,
+                in_len: 0,
+                out_start: 0,
+                avail: 0,
+                done: false,
+            })),
+        })
+    }
+
+    pub(super) fn read(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        buf: &mut [u8],
+    ) -> crate::error::Result<usize> {
+        let Some(inf) = self.inflate.as_mut() else {
+            let n = (self.end.saturating_sub(self.offset) as usize).min(buf.len());
+            if n == 0 {
+                return Ok(0);
+            }
+            let n = k.read_chunk(self.name, self.offset, &mut buf[..n])?;
+            self.offset += n as u32;
+            return Ok(n);
+        };
+
+        loop {
+            if inf.avail > 0 {
+                let n = inf.avail.min(buf.len());
+                buf[..n].copy_from_slice(&inf.ring[inf.out_start..inf.out_start + n]);
+                inf.out_start += n;
+                inf.avail -= n;
+                return Ok(n);
+            }
+            if inf.done {
+                return Ok(0);
+            }
+            if inf.in_pos == inf.in_len && self.offset < self.end {
+                let want = (self.end - self.offset) as usize;
+                let want = want.min(inf.input.len());
+                let n = k.read_chunk(self.name, self.offset, &mut inf.input[..want])?;
+                if n == 0 {
+                    return Err(Error::new(ErrorKind::ReadFailed, "fb2: short read"));
+                }
+                self.offset += n as u32;
+                inf.in_pos = 0;
+                inf.in_len = n;
+            }
+            let flags = if self.offset < self.end {
+                TINFL_FLAG_HAS_MORE_INPUT
+            } else {
+                0
+            };
+            let (status, used, produced) = decompress(
+                &mut inf.state,
+                &inf.input[inf.in_pos..inf.in_len],
+                &mut inf.ring,
+                inf.ring_pos,
+                flags,
+            );
+            inf.in_pos += used;
+            inf.out_start = inf.ring_pos;
+            inf.avail = produced;
+            inf.ring_pos = (inf.ring_pos + produced) & (RING - 1);
+            match status {
+                TINFLStatus::Done => inf.done = true,
+                TINFLStatus::NeedsMoreInput | TINFLStatus::HasMoreOutput => {}
+                _ => return Err(Error::new(ErrorKind::InvalidData, "fb2: inflate")),
+            }
+        }
+    }
+}
+
+impl EpubState {
+    // cache names for a book that is not a zip
+    pub(super) fn init_plain(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        name: &str,
+    ) -> crate::error::Result<()> {
+        self.archive_size = k.file_size(name)?;
+        self.name_hash = cache::fnv1a(name.as_bytes());
+        self.cache_file = cache::cache_filename(self.name_hash);
+        self.cache_dir = cache::dir_name_for_hash(self.name_hash);
+        Ok(())
+    }
+
+    // a complete cache from an earlier conversion: loads the chapter
+    // table and returns the title length copied into title
+    pub(super) fn load_converted(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        scratch: &mut [u8],
+        title: &mut [u8],
+    ) -> crate::error::Result<Option<usize>> {
+        let cf = self.cache_file;
+        let cf_str = cache::cache_filename_str(&cf);
+
+        let mut hdr_buf = [0u8; cache::HEADER_SIZE];
+        let hdr = match k.read_cache_chunk(cf_str, 0, &mut hdr_buf) {
+            Ok(n) if n == cache::HEADER_SIZE => cache::parse_v3_header(&hdr_buf).ok(),
+            _ => None,
+        };
+        let Some(hdr) = hdr else {
+            return Ok(None);
+        };
+        let count = hdr.chapter_count as usize;
+        let valid =
+            cache::validate_v3_header(&hdr, self.archive_size, self.name_hash, count).is_ok();
+        let tbl_bytes = count * cache::CHAPTER_ENTRY_SIZE;
+        if !valid || !hdr.chapters_complete() || count == 0 || tbl_bytes > scratch.len() {
+            return Ok(None);
+        }
+
+        let n = k.read_cache_chunk(cf_str, hdr.table_offset(), &mut scratch[..tbl_bytes])?;
+        if n < tbl_bytes
+            || cache::parse_chapter_table(&scratch[..tbl_bytes], count, &mut self.chapter_table)
+                .is_err()
+        {
+            return Ok(None);
+        }
+        self.sections = count as u16;
+
+        let tlen = (hdr.title_len as usize).min(title.len());
+        title[..tlen].copy_from_slice(&hdr.title[..tlen]);
+        Ok(Some(tlen))
+    }
+}
+
+impl ReaderApp {
+    // open the book, converting it into the chapter cache unless a
+    // complete one exists; leaves the epub state ready for NeedIndex
+    pub(super) fn fb2_prepare(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let zipped = ext_eq(name, b"ZIP");
+
+        self.epub.spine = EpubSpine::new();
+        self.epub.toc = None;
+        self.epub.sections = 0;
+        self.epub.chapters_cached = false;
+        self.epub.ch_cached = [false; cache::MAX_CACHE_CHAPTERS];
+        if zipped {
+            self.epub.init_zip(k, name, &mut self.pg.buf)?;
+        } else {
+            self.epub.init_plain(k, name)?;
+        }
+        let dir_buf = self.epub.cache_dir;
+        let dir = cache::dir_name_str(&dir_buf);
+        k.ensure_app_subdir(dir)?;
+
+        let mut title = [0u8; 64];
+        match self.epub.load_converted(k, &mut self.pg.buf, &mut title)? {
+            Some(tlen) => {
+                if tlen > 0 {
+                    self.title[..tlen].copy_from_slice(&title[..tlen]);
+                    self.title_len = tlen as u8;
+                }
+                self.fb2_load_toc(k, dir);
+                log::info!("fb2: cache hit ({} chapters)", self.epub.sections);
+            }
+            None => self.fb2_convert(k, name, zipped, dir)?,
+        }
+
+        let count = self.epub.sections as usize;
+        self.epub.ch_cached[..count].fill(true);
+        self.epub.chapters_cached = true;
+        if self.epub.chapter as usize >= count {
+            self.epub.chapter = (count - 1) as u16;
+        }
+        log::info!(
+            "fb2: \"{}\" -- {} chapters, {} toc entries",
+            core::str::from_utf8(&self.title[..self.title_len as usize]).unwrap_or(""),
+            count,
+            self.epub.toc.as_ref().map_or(0, |t| t.len())
+        );
+        Ok(())
+    }
+
+    // the toc is a nicety; a missing file leaves the book readable
+    fn fb2_load_toc(&mut self, k: &mut KernelHandle<'_>, dir: &str) {
+        let Ok(n) = k.read_app_subdir_chunk(dir, TOC_FILE, 0, &mut self.pg.buf) else {
+            return;
+        };
+        let mut toc = Toc::new();
+        for line in self.pg.buf[..n].split(|&b| b == b'\n') {
+            let Some(tab) = line.iter().position(|&b| b == b'\t') else {
+                continue;
+            };
+            if let Some(ch) = parse_num(&line[..tab], 10) {
+                toc.push(&line[tab + 1..], ch as u16);
+            }
+        }
+        if !toc.is_empty() {
+            self.epub.toc = Some(toc);
+        }
+    }
+
+    fn fb2_convert(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        name: &str,
+        zipped: bool,
+        dir: &str,
+    ) -> crate::error::Result<()> {
+        let cf = self.epub.cache_file;
+        let cf_str = cache::cache_filename_str(&cf);
+
+        // placeholder header and a table with room for every chapter;
+        // finish_cache fills both in once the chapter count is known
+        let mut hdr = cache::CacheHeader::empty();
+        hdr.version = cache::CACHE_V3;
+        hdr.epub_size = self.epub.archive_size;
+        hdr.name_hash = self.epub.name_hash;
+        let mut hdr_buf = [0u8; cache::HEADER_SIZE];
+        cache::encode_v3_header(&hdr, &mut hdr_buf);
+        k.write_cache(cf_str, &hdr_buf)?;
+        let zeros = [0u8; 64];
+        let mut remaining = cache::MAX_CACHE_CHAPTERS * cache::CHAPTER_ENTRY_SIZE;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len());
+            k.append_cache(cf_str, &zeros[..chunk])?;
+            remaining -= chunk;
+        }
+        let data_base = k.cache_file_size(cf_str)?;
+
+        let zip = if zipped { Some(&self.epub.zip) } else { None };
+        let mut src = Fb2Source::open(k, name, zip)?;
+        let mut conv = Fb2Strip::new();
+        let mut bin_started = false;
+        loop {
+            let n = src.read(k, &mut self.pg.buf)?;
+            if n == 0 {
+                break;
+            }
+            let mut pos = 0;
+            while pos < n {
+                let (used, ev) = conv.feed(&self.pg.buf[pos..n]);
+                pos += used;
+                let ended = ev == Some(Fb2Event::BinaryEnd);
+                if ended || conv.bin.len() >= FLUSH_AT {
+                    let file = super::img_cache_str(&conv.bin_name);
+                    if bin_started {
+                        k.append_app_subdir(dir, file, &conv.bin)?;
+                    } else {
+                        k.write_app_subdir(dir, file, &conv.bin)?;
+                    }
+                    conv.bin.clear();
+                    bin_started = !ended;
+                }
+            }
+            if conv.bad_encoding {
+                return Err(Error::new(
+                    ErrorKind::BadEncoding,
+                    "fb2: unsupported encoding",
+                ));
+            }
+            if conv.out.len() >= FLUSH_AT {
+                k.append_cache(cf_str, &conv.out)?;
+                conv.mark_drained();
+            }
+        }
+        conv.finish();
+        if !conv.out.is_empty() {
+            k.append_cache(cf_str, &conv.out)?;
+            conv.mark_drained();
+        }
+
+        let total = conv.pos();
+        let count = conv.chapters.len();
+        for i in 0..count {
+            let start = conv.chapters[i];
+            let end = conv.chapters.get(i + 1).copied().unwrap_or(total);
+            self.epub.chapter_table[i] = (data_base + start, end - start);
+        }
+        self.epub.sections = count as u16;
+
+        if conv.title_len > 0 {
+            let title = core::str::from_utf8(&conv.title[..conv.title_len])
+                .unwrap_or("")
+                .trim();
+            let n = title.len().min(self.title.len());
+            self.title[..n].copy_from_slice(&title.as_bytes()[..n]);
+            self.title_len = n as u8;
+            if let Err(e) = k.save_title(name, title) {
+                log::warn!("fb2: failed to save title mapping: {}", e);
+            }
+        }
+
+        let mut toc_txt = Vec::new();
+        for entry in &conv.toc.entries {
+            let mut num = StackFmt::<8>::new();
+            let _ = write!(num, "{}\t", entry.spine_idx);
+            toc_txt.extend_from_slice(num.as_str().as_bytes());
+            toc_txt.extend_from_slice(entry.title_str().as_bytes());
+            toc_txt.push(b'\n');
+        }
+        k.write_app_subdir(dir, TOC_FILE, &toc_txt)?;
+        if !conv.toc.is_empty() {
+            self.epub.toc = Some(conv.toc);
+        }
+
+        self.epub.finish_cache(
+            k,
+            &self.title[..self.title_len as usize],
+            &self.filename[..self.filename_len],
+        )?;
+        log::info!("fb2: converted {} ({} bytes of text)", name, total);
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+// standalone html / xhtml pages (saved web articles)
+//
+// the page is run through the same html_strip streamer that fills the
+// epub chapter cache and stored as a one-chapter v3 cache, so paging,
+// bookmarks and styles behave as in an epub. <title> names the book.
+// <img> sources are resolved against the card root, where the page
+// lives: a file beside it or in a folder one level down
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+use smol_epub::epub::{self, EpubSpine};
+use smol_epub::html_strip::HtmlStripStream;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+use pulp_kernel::util::floor_char_boundary;
+
+use super::ReaderApp;
+use super::fb2::entity_char;
+
+// stripped text is appended to the cache in pieces this big
+const OUT_BUF: usize = 4096;
+
+// the <title> is decoded into this much before it is cut to fit
+const TITLE_BUF: usize = 256;
+// longest entity name looked for between & and ;
+const ENT_MAX: usize = 10;
+
+pub(super) fn is_html_filename(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, ext)| {
+        ["HTM", "HTML", "XHT", "XHTML"]
+            .iter()
+            .any(|e| ext.eq_ignore_ascii_case(e))
+    })
+}
+
+// folder and file name of an image source on the card; None for urls,
+// data: sources and anything nested deeper than one folder
+pub(super) fn sibling_path<'a>(src: &str, buf: &'a mut [u8]) -> Option<(&'a str, &'a str)> {
+    if src.contains(':') {
+        return None;
+    }
+    let n = epub::resolve_path("", src, buf);
+    let path = core::str::from_utf8(&buf[..n]).ok()?;
+    let path = path.trim_start_matches('/');
+    match path.rsplit_once('/') {
+        None if !path.is_empty() => Some(("", path)),
+        Some((dir, file)) if !dir.is_empty() && !dir.contains('/') && !file.is_empty() => {
+            Some((dir, file))
+        }
+        _ => None,
+    }
+}
+
+// text of the first <title> element in the head of the page
+fn page_title(head: &[u8]) -> Option<&[u8]> {
+    let open = find_icase(head, b"<title")?;
+    let start = open + head[open..].iter().position(|&b| b == b'>')? + 1;
+    let len = find_icase(&head[start..], b"</title")?;
+    let title = head[start..start + len].trim_ascii();
+    if title.is_empty() { None } else { Some(title) }
+}
+
+// copy text with its character references decoded, as far as it fits;
+// references that do not decode are copied as written
+fn decode_entities(text: &[u8], out: &mut [u8]) -> usize {
+    let mut n = 0;
+    let mut i = 0;
+    while i < text.len() && n < out.len() {
+        let ent = match text[i] {
+            b'&' => text[i + 1..]
+                .iter()
+                .take(ENT_MAX)
+                .position(|&b| b == b';')
+                .and_then(|len| entity_char(&text[i + 1..i + 1 + len]).map(|ch| (len, ch))),
+            _ => None,
+        };
+        match ent {
+            Some((len, ch)) => {
+                let w = ch.len_utf8();
+                if n + w > out.len() {
+                    break;
+                }
+                ch.encode_utf8(&mut out[n..n + w]);
+                n += w;
+                i += len + 2;
+            }
+            None => {
+                out[n] = text[i];
+                n += 1;
+                i += 1;
+            }
+        }
+    }
+    n
+}
+
+fn find_icase(hay: &[u8], needle: &[u8]) -> Option<usize> {
+    hay.windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+}
+
+impl ReaderApp {
+    // open the page, stripping it into the chapter cache unless a
+    // complete one exists; leaves the epub state ready for NeedIndex
+    pub(super) fn html_prepare(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+
+        self.epub.spine = EpubSpine::new();
+        self.epub.toc = None;
+        self.epub.sections = 0;
+        self.epub.chapters_cached = false;
+        self.epub.ch_cached = [false; cache::MAX_CACHE_CHAPTERS];
+        self.epub.init_plain(k, name)?;
+
+        let mut title = [0u8; 64];
+        match self.epub.load_converted(k, &mut self.pg.buf, &mut title)? {
+            Some(tlen) => {
+                if tlen > 0 {
+                    self.title[..tlen].copy_from_slice(&title[..tlen]);
+                    self.title_len = tlen as u8;
+                }
+                log::info!("html: cache hit");
+            }
+            None => self.html_convert(k, name)?,
+        }
+
+        self.epub.ch_cached[0] = true;
+        self.epub.chapters_cached = true;
+        self.epub.chapter = 0;
+        Ok(())
+    }
+
+    fn html_convert(&mut self, k: &mut KernelHandle<'_>, name: &str) -> crate::error::Result<()> {
+        let cf = self.epub.cache_file;
+        let cf_str = cache::cache_filename_str(&cf);
+
+        // placeholder header and a one-entry table; finish_cache fills
+        // both in
+        let mut hdr = cache::CacheHeader::empty();
+        hdr.version = cache::CACHE_V3;
+        hdr.chapter_count = 1;
+        hdr.epub_size = self.epub.archive_size;
+        hdr.name_hash = self.epub.name_hash;
+        let mut hdr_buf = [0u8; cache::HEADER_SIZE];
+        cache::encode_v3_header(&hdr, &mut hdr_buf);
+        k.write_cache(cf_str, &hdr_buf)?;
+        k.append_cache(cf_str, &[0u8; cache::CHAPTER_ENTRY_SIZE])?;
+        let data_base = k.cache_file_size(cf_str)?;
+
+        let size = self.epub.archive_size;
+        let mut strip = HtmlStripStream::new();
+        let mut out = Vec::new();
+        out.try_reserve_exact(OUT_BUF)
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "html: strip buffer"))?;
+        out.resize(OUT_BUF, 0);
+
+        let mut total = 0u32;
+        let mut offset = 0u32;
+        while offset < size {
+            let n = k.read_chunk(name, offset, &mut self.pg.buf)?;
+            if n == 0 {
+                break;
+            }
+            let head_title = if offset == 0 {
+                page_title(&self.pg.buf[..n])
+            } else {
+                None
+            };
+            if let Some(t) = head_title {
+                let mut text = [0u8; TITLE_BUF];
+                let tn = decode_entities(t, &mut text);
+                let t = match core::str::from_utf8(&text[..tn]) {
+                    Ok(t) => t,
+                    Err(e) => core::str::from_utf8(&text[..e.valid_up_to()]).unwrap_or(""),
+                }
+                .trim();
+                let tn = floor_char_boundary(t, self.title.len());
+                self.title[..tn].copy_from_slice(&t.as_bytes()[..tn]);
+                self.title_len = tn as u8;
+                if let Err(e) = k.save_title(name, t) {
+                    log::warn!("html: failed to save title mapping: {}", e);
+                }
+            }
+            offset += n as u32;
+
+            let mut pos = 0;
+            while pos < n {
+                let (used, wrote) = strip.feed(&self.pg.buf[pos..n], &mut out);
+                if used == 0 && wrote == 0 {
+                    return Err(Error::new(ErrorKind::ParseFailed, "html: strip stalled"));
+                }
+                pos += used;
+                if wrote > 0 {
+                    k.append_cache(cf_str, &out[..wrote])?;
+                    total += wrote as u32;
+                }
+            }
+        }
+        let wrote = strip.finish(&mut out);
+        if wrote > 0 {
+            k.append_cache(cf_str, &out[..wrote])?;
+            total += wrote as u32;
+        }
+
+        self.epub.chapter_table[0] = (data_base, total);
+        self.epub.sections = 1;
+        self.epub.finish_cache(
+            k,
+            &self.title[..self.title_len as usize],
+            &self.filename[..self.filename_len],
+        )?;
+        log::info!("html: converted {} ({} -> {} bytes)", name, size, total);
+        Ok(())
+    }
+}
@@ -20,7 +20,7 @@ use crate::kernel::KernelHandle;
 use crate::kernel::work_queue;
 
 use super::{
-    DEFAULT_IMG_H, MAX_IMAGES_PER_PAGE, NO_PREFETCH, PAGE_BUF, PRECACHE_IMG_MAX, ReaderApp,
+    DEFAULT_IMG_H, MAX_IMAGES_PER_PAGE, NO_PREFETCH, PAGE_BUF, PRECACHE_IMG_MAX, ReaderApp, html,
 };
 
 fn from_smol_image(img: smol_epub::DecodedImage) -> DecodedImage {
@@ -48,7 +48,7 @@ impl ReaderApp {
         self.page_img = None;
         self.fullscreen_img = false;
 
-        if !self.is_epub || self.epub.spine.is_empty() {
+        if !self.is_epub || self.epub.chapter_count() == 0 {
             return;
         }
 
@@ -94,6 +94,41 @@ impl ReaderApp {
 
         log::info!("reader: decoding image: {}", src_str);
 
+        // inline images are capped to a fraction of the text area so
+        // they feel proportional to surrounding text, and to the width
+        // of one column.  fullscreen images (sole content on the page)
+        // get the full budget.
+        let (img_budget_w, img_budget_h) = if self.fullscreen_img {
+            (self.text_w as u16, self.text_area_h)
+        } else {
+            (self.col_w as u16, super::inline_img_max_h(self.text_area_h))
+        };
+
+        // fb2 binaries were saved as plain image files next to the cache
+        if self.is_fb2 {
+            let dir_buf = self.epub.cache_dir;
+            let dir = cache::dir_name_str(&dir_buf);
+            match decode_app_image(k, dir, src_str, img_budget_w, img_budget_h) {
+                Ok(img) => self.page_img = Some(img),
+                Err(e) => log::warn!("reader: fb2 image {}: {}", src_str, e),
+            }
+            return;
+        }
+
+        // html images are files beside the book
+        if self.is_html {
+            let mut path_buf = [0u8; 128];
+            let Some((dir, file)) = html::sibling_path(src_str, &mut path_buf) else {
+                log::warn!("reader: html image {} is not on the card", src_str);
+                return;
+            };
+            match decode_image_file(k, ImageDir::Card(dir), file, img_budget_w, img_budget_h) {
+                Ok(img) => self.page_img = Some(img),
+                Err(e) => log::warn!("reader: html image {}: {}", src_str, e),
+            }
+            return;
+        }
+
         let ch_zip_idx = self.epub.spine.items[self.epub.chapter as usize] as usize;
         let ch_path = self.epub.zip.entry_name(ch_zip_idx);
         let ch_dir = ch_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
@@ -110,20 +145,12 @@ impl ReaderApp {
         let img_name = img_cache_name(cache::fnv1a(full_path.as_bytes()));
         let img_file = img_cache_str(&img_name);
 
-        // inline images are capped to a fraction of the text area so
-        // they feel proportional to surrounding text.  fullscreen
-        // images (sole content on the page) get the full budget.
-        let img_budget_h = if self.fullscreen_img {
-            self.text_area_h
-        } else {
-            super::inline_img_max_h(self.text_area_h)
-        };
-
         if let Ok(img) = load_cached_image(k, dir, img_file) {
             // use the cache if the image already fits the budget;
             // if the cached image is too tall (precache used full
-            // text_area_h) fall through to re-decode at inline budget
-            if img.height <= img_budget_h {
+            // text_area_h) or too wide for a column, fall through
+            // to re-decode at inline budget
+            if img.height <= img_budget_h && img.width <= img_budget_w {
                 log::info!(
                     "reader: image cache hit {} ({}x{})",
                     img_file,
@@ -221,7 +248,7 @@ impl ReaderApp {
         // images use the full text area
         let img_max_h = img_budget_h;
 
-        let img_max_w = self.text_w as u16;
+        let img_max_w = img_budget_w;
         let do_decode = |k_ref: &mut KernelHandle<'_>| -> Result<DecodedImage, &'static str> {
             let k_cell = RefCell::new(k_ref);
             let read_err = |e: Error| -> &'static str { e.into() };
@@ -327,7 +354,7 @@ impl ReaderApp {
     pub(super) fn prescan_image_heights(&mut self, k: &mut KernelHandle<'_>, buf_len: usize) {
         self.img_height_count = 0;
 
-        if !self.is_epub || self.epub.spine.is_empty() {
+        if !self.is_epub || self.epub.chapter_count() == 0 {
             return;
         }
 
@@ -337,16 +364,22 @@ impl ReaderApp {
             return;
         }
 
-        let ch_zip_idx = self.epub.spine.items[self.epub.chapter as usize] as usize;
-        let ch_path = self.epub.zip.entry_name(ch_zip_idx);
-        let ch_dir = ch_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+        // fb2 image references are already file names in the cache dir;
+        // html ones are resolved against the card below
+        let ch_dir = if self.is_fb2 || self.is_html {
+            ""
+        } else {
+            let ch_zip_idx = self.epub.spine.items[self.epub.chapter as usize] as usize;
+            let ch_path = self.epub.zip.entry_name(ch_zip_idx);
+            ch_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("")
+        };
 
         let dir = self.epub.cache_dir_str();
 
         let (nb, nl) = self.name_copy();
         let epub_name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
 
-        let text_w = self.text_w;
+        let col_w = self.col_w;
         let text_area_h = self.text_area_h;
         let max_inline_h = super::inline_img_max_h(text_area_h);
 
@@ -390,17 +423,39 @@ impl ReaderApp {
                 }
             };
 
+            if self.is_fb2 || self.is_html {
+                let mut sib_buf = [0u8; 128];
+                let file = if self.is_fb2 {
+                    Some((ImageDir::App(dir), src_str))
+                } else {
+                    html::sibling_path(src_str, &mut sib_buf).map(|(d, f)| (ImageDir::Card(d), f))
+                };
+                let out_h = match file {
+                    Some((d, f)) => peek_image_height(k, d, f, col_w, text_area_h),
+                    None => DEFAULT_IMG_H,
+                };
+                self.img_heights[self.img_height_count as usize] = out_h.min(max_inline_h);
+                self.img_height_count += 1;
+                i = path_start + path_len;
+                continue;
+            }
+
             let path_hash = cache::fnv1a(full_path.as_bytes());
             let img_name = img_cache_name(path_hash);
             let img_file = img_cache_str(&img_name);
 
             // try 1: read cached image header (4 bytes, very fast)
-            let out_h = if let Some((_w, h)) = peek_cached_image_size(k, dir, img_file) {
-                // cached image is already at the final decoded size
-                h
+            let out_h = if let Some((w, h)) = peek_cached_image_size(k, dir, img_file) {
+                // cached image is at its final decoded size, unless it
+                // was decoded for a wider column than the current one
+                if w as u32 > col_w {
+                    (h as u32 * col_w / w as u32) as u16
+                } else {
+                    h
+                }
             } else {
                 // try 2: peek source dimensions from the ZIP entry
-                peek_source_dimensions(k, epub_name, &self.epub.zip, full_path, text_w, text_area_h)
+                peek_source_dimensions(k, epub_name, &self.epub.zip, full_path, col_w, text_area_h)
             };
 
             // cap to the inline budget; fullscreen images bypass line
@@ -558,7 +613,7 @@ impl ReaderApp {
                         full_path,
                         entry.uncomp_size,
                     );
-                    let img_w = self.text_w as u16;
+                    let img_w = self.col_w as u16;
                     let img_h = self.text_area_h;
                     let result =
                         decode_image_streaming(k, epub_name, &entry, is_jpeg, img_w, img_h);
@@ -631,7 +686,7 @@ impl ReaderApp {
                     path_hash,
                     data,
                     is_jpeg,
-                    max_w: self.text_w as u16,
+                    max_w: self.col_w as u16,
                     max_h: self.text_area_h,
                 };
                 if work_queue::submit(self.epub.work_gen, task) {
@@ -772,7 +827,7 @@ impl ReaderApp {
     }
 }
 
-pub(super) fn img_cache_name(hash: u32) -> [u8; 12] {
+pub(crate) fn img_cache_name(hash: u32) -> [u8; 12] {
     let mut n = *b"00000000.BIN";
     for (i, byte) in n.iter_mut().enumerate().take(8) {
         let nibble = ((hash >> (28 - i * 4)) & 0xF) as u8;
@@ -786,7 +841,7 @@ pub(super) fn img_cache_name(hash: u32) -> [u8; 12] {
 }
 
 #[inline]
-pub(super) fn img_cache_str(buf: &[u8; 12]) -> &str {
+pub(crate) fn img_cache_str(buf: &[u8; 12]) -> &str {
     core::str::from_utf8(buf).unwrap_or("00000000.BIN")
 }
 
@@ -798,11 +853,11 @@ fn path_ext_eq(path: &str, ext: &[u8]) -> bool {
         && p[p.len() - ext.len()..].eq_ignore_ascii_case(ext)
 }
 
-pub(super) fn is_image_ext_jpeg(path: &str) -> bool {
+pub(crate) fn is_image_ext_jpeg(path: &str) -> bool {
     path_ext_eq(path, b"jpg") || path_ext_eq(path, b"jpeg")
 }
 
-pub(super) fn is_image_ext_png(path: &str) -> bool {
+pub(crate) fn is_image_ext_png(path: &str) -> bool {
     path_ext_eq(path, b"png")
 }
 
@@ -863,6 +918,85 @@ pub(super) fn decode_image_streaming(
         .map_err(|msg| Error::from(msg).with_source("decode_image_streaming"))
 }
 
+// where a plain image file lives: a _PULP subdir (sleep images, fb2
+// binaries) or a folder on the card, empty for the root (html images)
+#[derive(Clone, Copy)]
+pub(super) enum ImageDir<'a> {
+    App(&'a str),
+    Card(&'a str),
+}
+
+impl ImageDir<'_> {
+    fn size(self, k: &mut KernelHandle<'_>, name: &str) -> crate::error::Result<u32> {
+        match self {
+            ImageDir::App(dir) => k.file_size_app_subdir(dir, name),
+            ImageDir::Card(dir) => k.file_size_in(dir, name),
+        }
+    }
+
+    fn read(
+        self,
+        k: &mut KernelHandle<'_>,
+        name: &str,
+        offset: u32,
+        buf: &mut [u8],
+    ) -> crate::error::Result<usize> {
+        match self {
+            ImageDir::App(dir) => k.read_app_subdir_chunk(dir, name, offset, buf),
+            ImageDir::Card(dir) => k.read_chunk_in(dir, name, offset, buf),
+        }
+    }
+}
+
+// decode a plain JPEG or PNG file from _PULP/<dir>/
+pub(crate) fn decode_app_image(
+    k: &mut KernelHandle<'_>,
+    dir: &str,
+    name: &str,
+    max_w: u16,
+    max_h: u16,
+) -> crate::error::Result<DecodedImage> {
+    decode_image_file(k, ImageDir::App(dir), name, max_w, max_h)
+}
+
+pub(super) fn decode_image_file(
+    k: &mut KernelHandle<'_>,
+    dir: ImageDir<'_>,
+    name: &str,
+    max_w: u16,
+    max_h: u16,
+) -> crate::error::Result<DecodedImage> {
+    let size = dir.size(k, name)?;
+    let (is_jpeg, is_png) = sniff_image(k, dir, name);
+    let read_err = |_: Error| -> &'static str { "read failed" };
+
+    let result = if is_jpeg {
+        smol_epub::jpeg::decode_jpeg_sd(
+            |off, buf| dir.read(k, name, off, buf).map_err(read_err),
+            0,
+            size,
+            max_w,
+            max_h,
+        )
+    } else if is_png {
+        smol_epub::png::decode_png_sd(
+            |off, buf| dir.read(k, name, off, buf).map_err(read_err),
+            0,
+            size,
+            max_w,
+            max_h,
+        )
+    } else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "decode_image_file: not an image",
+        ));
+    };
+    result
+        .map(from_smol_image)
+        .map_err(|msg| Error::from(msg).with_source("decode_image_file"))
+}
+
 pub(super) fn load_cached_image(
     k: &mut KernelHandle<'_>,
     dir: &str,
@@ -948,36 +1082,41 @@ fn peek_source_dimensions(
     };
     let entry = *zip.entry(zip_idx);
 
+    match peek_entry_dimensions(k, epub_name, &entry, full_path) {
+        Some((src_w, src_h)) => fit_height(src_w, src_h, text_w as u16, text_area_h),
+        None => DEFAULT_IMG_H,
+    }
+}
+
+// source dimensions of a stored ZIP image entry; None for deflated
+// entries, unknown formats or read errors
+pub(super) fn peek_entry_dimensions(
+    k: &mut KernelHandle<'_>,
+    zip_name: &str,
+    entry: &zip::ZipEntry,
+    path: &str,
+) -> Option<(u16, u16)> {
     // deflate-compressed images: can't peek dimensions cheaply
     if entry.method != zip::METHOD_STORED {
-        return DEFAULT_IMG_H;
+        return None;
     }
 
     // read local header to find data offset
     let data_offset = {
         let mut hdr = [0u8; 30];
-        if k.read_chunk(epub_name, entry.local_offset, &mut hdr)
-            .is_err()
-        {
-            return DEFAULT_IMG_H;
-        }
-        match ZipIndex::local_header_data_skip(&hdr) {
-            Ok(skip) => entry.local_offset + skip,
-            Err(_) => return DEFAULT_IMG_H,
-        }
+        k.read_chunk(zip_name, entry.local_offset, &mut hdr).ok()?;
+        entry.local_offset + ZipIndex::local_header_data_skip(&hdr).ok()?
     };
 
-    let is_jpeg = is_image_ext_jpeg(full_path);
-    let is_png = is_image_ext_png(full_path);
+    let is_jpeg = is_image_ext_jpeg(path);
+    let is_png = is_image_ext_png(path);
 
     // fall back to magic-byte detection if extension is ambiguous
     let (is_jpeg, is_png) = if is_jpeg || is_png {
         (is_jpeg, is_png)
     } else {
         let mut magic = [0u8; 8];
-        let n = k
-            .read_chunk(epub_name, data_offset, &mut magic)
-            .unwrap_or(0);
+        let n = k.read_chunk(zip_name, data_offset, &mut magic).unwrap_or(0);
         (
             n >= 2 && magic[0] == 0xFF && magic[1] == 0xD8,
             n >= 8 && magic[..8] == [137, 80, 78, 71, 13, 10, 26, 10],
@@ -988,36 +1127,83 @@ fn peek_source_dimensions(
 
     let dims = if is_png {
         smol_epub::png::peek_png_dimensions_streaming(
-            |off, buf| k.read_chunk(epub_name, off, buf).map_err(read_err),
+            |off, buf| k.read_chunk(zip_name, off, buf).map_err(read_err),
             data_offset,
             entry.uncomp_size,
         )
         .map(|(w, h)| (w as u16, h as u16))
     } else if is_jpeg {
         smol_epub::jpeg::peek_jpeg_dimensions_streaming(
-            |off, buf| k.read_chunk(epub_name, off, buf).map_err(read_err),
+            |off, buf| k.read_chunk(zip_name, off, buf).map_err(read_err),
             data_offset,
             entry.uncomp_size,
         )
     } else {
-        return DEFAULT_IMG_H;
+        return None;
     };
+    dims.ok()
+}
 
+// replicate the decoder's integer downscale logic:
+// scale = max(ceil(src_w/max_w), ceil(src_h/max_h), 1)
+fn fit_height(src_w: u16, src_h: u16, max_w: u16, max_h: u16) -> u16 {
+    if src_w == 0 || src_h == 0 {
+        return DEFAULT_IMG_H;
+    }
+    let sw = src_w.div_ceil(max_w);
+    let sh = src_h.div_ceil(max_h);
+    let scale = sw.max(sh).max(1);
+    src_h / scale
+}
+
+// decoded height of a plain image file (fb2 binaries, html images)
+pub(super) fn peek_image_height(
+    k: &mut KernelHandle<'_>,
+    dir: ImageDir<'_>,
+    name: &str,
+    text_w: u32,
+    text_area_h: u16,
+) -> u16 {
+    let Ok(size) = dir.size(k, name) else {
+        return DEFAULT_IMG_H;
+    };
+    let (is_jpeg, is_png) = sniff_image(k, dir, name);
+    let read_err = |_: Error| -> &'static str { "read failed" };
+    let dims = if is_png {
+        smol_epub::png::peek_png_dimensions_streaming(
+            |off, buf| dir.read(k, name, off, buf).map_err(read_err),
+            0,
+            size,
+        )
+        .map(|(w, h)| (w as u16, h as u16))
+    } else if is_jpeg {
+        smol_epub::jpeg::peek_jpeg_dimensions_streaming(
+            |off, buf| dir.read(k, name, off, buf).map_err(read_err),
+            0,
+            size,
+        )
+    } else {
+        return DEFAULT_IMG_H;
+    };
     match dims {
-        Ok((src_w, src_h)) if src_w > 0 && src_h > 0 => {
-            // replicate the decoder's integer downscale logic:
-            // scale = max(ceil(src_w/max_w), ceil(src_h/max_h), 1)
-            let max_w = text_w as u16;
-            let max_h = text_area_h;
-            let sw = src_w.div_ceil(max_w);
-            let sh = src_h.div_ceil(max_h);
-            let scale = sw.max(sh).max(1);
-            src_h / scale
-        }
+        Ok((src_w, src_h)) => fit_height(src_w, src_h, text_w as u16, text_area_h),
         _ => DEFAULT_IMG_H,
     }
 }
 
+// format of a plain image file: by extension, else by magic bytes
+fn sniff_image(k: &mut KernelHandle<'_>, dir: ImageDir<'_>, name: &str) -> (bool, bool) {
+    if is_image_ext_jpeg(name) || is_image_ext_png(name) {
+        return (is_image_ext_jpeg(name), is_image_ext_png(name));
+    }
+    let mut magic = [0u8; 8];
+    let n = dir.read(k, name, 0, &mut magic).unwrap_or(0);
+    (
+        n >= 2 && magic[0] == 0xFF && magic[1] == 0xD8,
+        n >= 8 && magic[..8] == [137, 80, 78, 71, 13, 10, 26, 10],
+    )
+}
+
 pub(super) fn save_cached_image(
     k: &mut KernelHandle<'_>,
     dir: &str,
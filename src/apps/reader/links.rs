@@ -0,0 +1,420 @@
+// links in epub chapters
+//
+// html_strip drops <a href> when a chapter is cached, so the links of
+// the open chapter are found by stripping its xhtml once more
+// (epubs::strip_scan) and noting the text each <a> spans and where its
+// href points: a spine chapter and a fragment hash. the table holds
+// one chapter at a time
+//
+// Links in the quick menu picks among the links on the page. a note
+// reference (epub:type="noteref", role="doc-noteref", or link text of
+// a few bytes such as "3" or "[12]") shows the note in place of the
+// page; any other link jumps to its target and leaves the position it
+// came from on a short back stack, which Back pops before it closes
+// the book
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use smol_epub::cache;
+use smol_epub::epub::{self, EpubSpine};
+use smol_epub::zip::ZipIndex;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+use pulp_kernel::util::nfc_compose_in_place;
+
+use super::epubs::{strip_scan, tag_id};
+use super::paging::wrap_page;
+use super::toc::{NO_CHAPTER, frag_hash};
+use super::xml::attr;
+use super::{LINE_SLOTS, LineSpan, ReaderApp, State};
+
+const MAX_LINKS: usize = 128;
+const BACK_DEPTH: usize = 8;
+// a note is read up to this much
+const NOTE_CAP: usize = 1024;
+// and ends at the first paragraph break after this much
+const NOTE_MIN: usize = 24;
+// link text this short is taken for a note reference
+const NOTE_REF_MAX: u32 = 6;
+// underlines while picking: the selected link and the others
+const MARK_SELECTED: u32 = 3;
+const MARK_LINK: u32 = 1;
+
+#[derive(Clone, Copy)]
+pub(super) struct Link {
+    // the link text in the chapter
+    pub(super) start: u32,
+    pub(super) end: u32,
+    // target chapter and fragment hash, 0 for its start
+    chapter: u16,
+    frag: u32,
+    note: bool,
+}
+
+pub(super) struct Links {
+    pub(super) table: Vec<Link>,
+    // chapter the table was built for
+    chapter: u16,
+    pub(super) selected: usize,
+    // (chapter, offset) a link was followed from, newest last
+    back: [(u16, u32); BACK_DEPTH],
+    back_len: usize,
+    // the note shown, wrapped like a page
+    pub(super) note: Vec<u8>,
+    pub(super) note_lines: Vec<LineSpan>,
+    pub(super) note_count: usize,
+}
+
+impl Links {
+    pub(super) const fn new() -> Self {
+        Self {
+            table: Vec::new(),
+            chapter: NO_CHAPTER,
+            selected: 0,
+            back: [(0, 0); BACK_DEPTH],
+            back_len: 0,
+            note: Vec::new(),
+            note_lines: Vec::new(),
+            note_count: 0,
+        }
+    }
+
+    // a full stack drops its oldest position
+    fn push_back(&mut self, chapter: u16, offset: u32) {
+        if self.back_len == BACK_DEPTH {
+            self.back.copy_within(1.., 0);
+            self.back_len -= 1;
+        }
+        self.back[self.back_len] = (chapter, offset);
+        self.back_len += 1;
+    }
+
+    pub(super) fn free_note(&mut self) {
+        self.note = Vec::new();
+        self.note_lines = Vec::new();
+        self.note_count = 0;
+    }
+}
+
+impl ReaderApp {
+    #[inline]
+    pub(super) fn links_pending(&self) -> bool {
+        self.is_epub
+            && (self.epub.chapter as usize) < self.epub.spine.len()
+            && self.links.chapter != self.epub.chapter
+    }
+
+    // whether the open chapter has links to pick from
+    #[inline]
+    pub(super) fn has_links(&self) -> bool {
+        self.links.chapter == self.epub.chapter && !self.links.table.is_empty()
+    }
+
+    // build the link table of the open chapter
+    pub(super) fn links_step(&mut self, k: &mut KernelHandle<'_>) {
+        let ch = self.epub.chapter;
+        self.links.chapter = ch;
+        self.links.table.clear();
+        self.links.selected = 0;
+        match self.links_scan(k) {
+            Ok(()) => log::info!("links: {} in chapter {}", self.links.table.len(), ch + 1),
+            Err(e) => {
+                log::warn!("links: scan failed: {}", e);
+                self.links.table.clear();
+            }
+        }
+    }
+
+    fn links_scan(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let ch = self.epub.chapter;
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let idx = self.epub.spine.items[ch as usize] as usize;
+        let zip = &self.epub.zip;
+        let spine = &self.epub.spine;
+        let path = zip.entry_name(idx);
+        let dir = path.rsplit_once('/').map_or("", |(d, _)| d);
+        let table = &mut self.links.table;
+
+        let mut open: Option<Link> = None;
+        strip_scan(k, name, zip, idx, |tag, text| {
+            let (close, body) = match tag.strip_prefix(b"/") {
+                Some(rest) => (true, rest),
+                None => (false, tag),
+            };
+            let end = body
+                .iter()
+                .position(|&b| b.is_ascii_whitespace() || b == b'/')
+                .unwrap_or(body.len());
+            let tname = body[..end].rsplit(|&b| b == b':').next().unwrap_or(&[]);
+            if !tname.eq_ignore_ascii_case(b"a") {
+                return true;
+            }
+            if !close {
+                let attrs = &body[end..];
+                open = attr(attrs, b"href")
+                    .and_then(|href| link_target(href, dir, ch, zip, spine))
+                    .map(|(chapter, frag)| Link {
+                        start: text,
+                        end: text,
+                        chapter,
+                        frag,
+                        note: is_noteref(attrs),
+                    });
+                return true;
+            }
+            if let Some(mut link) = open.take() {
+                link.end = text;
+                link.note |= link.end - link.start <= NOTE_REF_MAX;
+                if link.end > link.start {
+                    table.push(link);
+                }
+            }
+            table.len() < MAX_LINKS
+        })
+    }
+
+    // chapter offset where the page after the shown one starts
+    pub(super) fn page_end(&self) -> u32 {
+        let next = self.pg.page + 1;
+        if next < self.pg.total_pages && self.pg.in_window(next) {
+            self.pg.offset(next)
+        } else {
+            self.file_size
+        }
+    }
+
+    // links with text on the shown page, in order
+    pub(super) fn page_links(&self) -> impl Iterator<Item = usize> + '_ {
+        let (from, to) = (self.byte_offset(), self.page_end());
+        let on_page = self.has_links();
+        (0..self.links.table.len()).filter(move |&i| {
+            let l = &self.links.table[i];
+            on_page && l.start < to && l.end > from
+        })
+    }
+
+    // the links on the page as a run of the table, which is in text
+    // order
+    pub(super) fn page_link_range(&self) -> Range<usize> {
+        match self.page_links().next() {
+            Some(first) => first..self.page_links().last().unwrap_or(first) + 1,
+            None => 0..0,
+        }
+    }
+
+    // underline under the text at chapter offset off, for the links
+    // in on_page
+    pub(super) fn link_mark(&self, on_page: &Range<usize>, off: u32) -> u32 {
+        let table = &self.links.table[on_page.clone()];
+        match table.iter().position(|l| l.start <= off && off < l.end) {
+            Some(i) if on_page.start + i == self.links.selected => MARK_SELECTED,
+            Some(_) => MARK_LINK,
+            None => 0,
+        }
+    }
+
+    // text and wrapped lines drawn in the text area: the note while
+    // one is shown, else the page
+    pub(super) fn shown_text(&self) -> (&[u8], &[LineSpan]) {
+        if self.state == State::ShowNote {
+            (
+                &self.links.note[..],
+                &self.links.note_lines[..self.links.note_count],
+            )
+        } else {
+            (&self.pg.buf[..], &self.pg.lines[..self.pg.line_count])
+        }
+    }
+
+    // start picking a link on the page; false when it has none
+    pub(super) fn links_select(&mut self) -> bool {
+        match self.page_links().next() {
+            Some(i) => {
+                self.links.selected = i;
+                self.state = State::SelectLink;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // next or previous link on the page, wrapping around
+    pub(super) fn links_step_selection(&mut self, forward: bool) {
+        let sel = self.links.selected;
+        let next = if forward {
+            self.page_links()
+                .find(|&i| i > sel)
+                .or_else(|| self.page_links().next())
+        } else {
+            self.page_links()
+                .take_while(|&i| i < sel)
+                .last()
+                .or_else(|| self.page_links().last())
+        };
+        self.links.selected = next.unwrap_or(sel);
+    }
+
+    // NeedLink: show the selected note or jump to the link target
+    pub(super) fn link_follow(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let Some(&link) = self.links.table.get(self.links.selected) else {
+            self.state = State::Ready;
+            return Ok(());
+        };
+        let at = if link.frag == 0 {
+            0
+        } else {
+            self.find_anchor(k, link.chapter, link.frag)?
+        };
+        if link.note && self.note_load(k, link.chapter, at)? {
+            self.state = State::ShowNote;
+            return Ok(());
+        }
+        log::info!("links: jump to chapter {} at {}", link.chapter + 1, at);
+        self.links.push_back(self.epub.chapter, self.byte_offset());
+        self.link_jump(link.chapter, at);
+        Ok(())
+    }
+
+    // back to where the last link was followed from; false when no
+    // link was
+    pub(super) fn link_back(&mut self) -> bool {
+        if self.links.back_len == 0 {
+            return false;
+        }
+        self.links.back_len -= 1;
+        let (chapter, offset) = self.links.back[self.links.back_len];
+        self.link_jump(chapter, offset);
+        true
+    }
+
+    fn link_jump(&mut self, chapter: u16, offset: u32) {
+        self.epub.chapter = chapter;
+        self.pg.page = 0;
+        self.restore_offset = if offset > 0 { Some(offset) } else { None };
+        self.goto_last_page = false;
+        self.goto_page = None;
+        self.state = State::NeedIndex;
+    }
+
+    // text offset of the element with id hash frag in chapter ch;
+    // its start when there is none
+    fn find_anchor(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        ch: u16,
+        frag: u32,
+    ) -> crate::error::Result<u32> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let idx = self.epub.spine.items[ch as usize] as usize;
+        let mut found = 0;
+        strip_scan(k, name, &self.epub.zip, idx, |tag, text| {
+            if tag_id(tag).map(frag_hash) == Some(frag) {
+                found = text;
+                return false;
+            }
+            true
+        })?;
+        Ok(found)
+    }
+
+    // read the note at offset into chapter ch and wrap it; false when
+    // the chapter is not cached yet
+    fn note_load(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        ch: u16,
+        offset: u32,
+    ) -> crate::error::Result<bool> {
+        if !self.epub.ch_cached[ch as usize] {
+            return Ok(false);
+        }
+        let (ch_off, size) = self.epub.chapter_table[ch as usize];
+        let len = (size.saturating_sub(offset) as usize).min(NOTE_CAP);
+        if len == 0 {
+            return Ok(false);
+        }
+        let note = &mut self.links.note;
+        note.clear();
+        note.try_reserve_exact(len)
+            .and_then(|()| self.links.note_lines.try_reserve_exact(LINE_SLOTS))
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "links: note"))?;
+        note.resize(len, 0);
+        self.links.note_lines.resize(LINE_SLOTS, LineSpan::EMPTY);
+
+        let cf = self.epub.cache_file;
+        let n = k.read_cache_chunk(cache::cache_filename_str(&cf), ch_off + offset, note)?;
+        nfc_compose_in_place(&mut note[..n]);
+        let n = note_end(&note[..n]);
+
+        let geom = self.wrap_geom();
+        let (_, lines) = wrap_page(
+            &geom,
+            &self.paged,
+            &self.links.note,
+            n,
+            &mut self.links.note_lines,
+            &[],
+        );
+        self.links.note_count = lines;
+        Ok(true)
+    }
+}
+
+// chapter and fragment hash an href points to; None for other books
+// and for files outside the spine
+fn link_target(
+    href: &[u8],
+    dir: &str,
+    ch: u16,
+    zip: &ZipIndex,
+    spine: &EpubSpine,
+) -> Option<(u16, u32)> {
+    let hash = href.iter().position(|&b| b == b'#').unwrap_or(href.len());
+    let (path, frag) = href.split_at(hash);
+    let frag = match frag.get(1..) {
+        Some(f) if !f.is_empty() => frag_hash(f),
+        _ => 0,
+    };
+    if path.is_empty() {
+        return Some((ch, frag));
+    }
+    // a scheme (http:, mailto:) leaves the book
+    if path.contains(&b':') {
+        return None;
+    }
+    let path = core::str::from_utf8(path).ok()?;
+    let mut buf = [0u8; 512];
+    let len = epub::resolve_path(dir, path, &mut buf);
+    let full = core::str::from_utf8(&buf[..len]).ok()?;
+    let idx = zip.find(full).or_else(|| zip.find_icase(full))?;
+    let chapter = (0..spine.len()).find(|&s| spine.items[s] as usize == idx)?;
+    Some((chapter as u16, frag))
+}
+
+fn is_noteref(attrs: &[u8]) -> bool {
+    let says = |key: &[u8]| {
+        attr(attrs, key).is_some_and(|v| {
+            v.split(|b| b.is_ascii_whitespace())
+                .any(|w| w == b"noteref" || w == b"doc-noteref")
+        })
+    };
+    says(b"type") || says(b"role")
+}
+
+// a note runs to the first paragraph break past NOTE_MIN bytes of
+// text; a longer one is cut where the read stopped, as a page is
+fn note_end(text: &[u8]) -> usize {
+    let start = text
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(text.len());
+    let from = (start + NOTE_MIN).min(text.len());
+    text[from..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(text.len(), |p| from + p)
+}
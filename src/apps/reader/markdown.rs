@@ -0,0 +1,505 @@
+// markdown to styled text, cached in _PULP as XXXXXXXX.MDT
+//
+// the converter is line based and streams: feed() takes the file in
+// chunks of any size and appends the same MARKER codes html_strip
+// produces, so the pager and draw code treat the result like an
+// epub chapter. paragraphs are joined onto one line, headings,
+// emphasis, quotes, lists and fenced code keep their shape, links
+// keep their text. the cache is kept by textcache.rs under a header
+// of MD_MAGIC and the source key
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+use smol_epub::html_strip::{
+    BOLD_OFF, BOLD_ON, HEADING_OFF, HEADING_ON, ITALIC_OFF, ITALIC_ON, MARKER, QUOTE_OFF, QUOTE_ON,
+};
+
+use crate::kernel::KernelHandle;
+
+use super::textcache::{self, Feed, KEY_LEN, TextFeed};
+use super::{ReaderApp, img_cache_name};
+
+pub(super) const MD_MAGIC: &[u8; 4] = b"MDT2";
+pub(super) const MD_HEADER: u32 = 4 + KEY_LEN as u32;
+
+// longest source line kept whole; longer ones are split as-is
+const LINE_CAP: usize = 2048;
+
+pub(super) fn is_markdown_filename(name: &str) -> bool {
+    let b = name.as_bytes();
+    b.len() > 3 && b[b.len() - 3..].eq_ignore_ascii_case(b".MD")
+}
+
+pub(super) fn md_cache_name(name: &str) -> [u8; 12] {
+    let mut n = img_cache_name(cache::fnv1a(name.as_bytes()));
+    n[9..].copy_from_slice(b"MDT");
+    n
+}
+
+pub(super) fn md_header(key: &[u8; KEY_LEN]) -> [u8; MD_HEADER as usize] {
+    let mut h = [0u8; MD_HEADER as usize];
+    h[..4].copy_from_slice(MD_MAGIC);
+    h[4..].copy_from_slice(key);
+    h
+}
+
+pub(super) struct MdStrip {
+    line: Vec<u8>,
+    // last paragraph line, held back in case a setext underline follows
+    held: Vec<u8>,
+    has_held: bool,
+    // paragraph state: text emitted, quote/list depth to close
+    in_para: bool,
+    para_indent: u8,
+    bold: bool,
+    italic: bool,
+    // fence char while inside ``` or ~~~ code, else 0
+    fence: u8,
+    // a blank line was already emitted for the current gap
+    blank: bool,
+}
+
+impl MdStrip {
+    pub(super) fn new() -> Self {
+        Self {
+            line: Vec::new(),
+            held: Vec::new(),
+            has_held: false,
+            in_para: false,
+            para_indent: 0,
+            bold: false,
+            italic: false,
+            fence: 0,
+            blank: true,
+        }
+    }
+
+    fn process(&mut self, raw: &[u8], out: &mut Vec<u8>) {
+        let line = raw.strip_suffix(b"\r").unwrap_or(raw);
+
+        if self.fence != 0 {
+            if fence_char(line) == Some(self.fence) {
+                self.fence = 0;
+            } else {
+                // code keeps its line breaks and leading spaces
+                out.push(MARKER);
+                out.push(QUOTE_ON);
+                out.extend_from_slice(line);
+                out.push(b'\n');
+                out.push(MARKER);
+                out.push(QUOTE_OFF);
+                self.blank = false;
+            }
+            return;
+        }
+
+        let lead = line
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        let text = &line[lead..];
+
+        if self.has_held && !self.in_para && is_setext(text) {
+            let held = core::mem::take(&mut self.held);
+            self.has_held = false;
+            self.heading(&held, out);
+            self.held = held;
+            return;
+        }
+        self.flush_held(out);
+
+        if text.is_empty() {
+            self.end_para(out);
+            if !self.blank {
+                out.push(b'\n');
+                self.blank = true;
+            }
+            return;
+        }
+
+        if let Some(c) = fence_char(text) {
+            self.end_para(out);
+            self.fence = c;
+            return;
+        }
+
+        if let Some(level) = atx_level(text) {
+            self.end_para(out);
+            let body = text[level..].trim_ascii();
+            let body = trim_closing_hashes(body);
+            self.heading(body, out);
+            return;
+        }
+
+        if is_rule(text) {
+            self.end_para(out);
+            out.extend_from_slice(b"* * *\n");
+            self.blank = false;
+            return;
+        }
+
+        if text[0] == b'>' {
+            let mut depth = 0u8;
+            let mut rest = text;
+            while let Some(r) = rest.strip_prefix(b">") {
+                depth = depth.saturating_add(1);
+                rest = r.trim_ascii_start();
+            }
+            if rest.is_empty() {
+                self.end_para(out);
+                return;
+            }
+            if !self.in_para || self.para_indent != depth {
+                self.end_para(out);
+                self.open_para(depth, out);
+            }
+            self.inline_line(rest, out);
+            return;
+        }
+
+        if let Some((marker, body)) = list_item(text) {
+            self.end_para(out);
+            // nested items indent one step per two spaces
+            let depth = 1 + (lead / 2).min(3) as u8;
+            self.open_para(depth, out);
+            out.extend_from_slice(marker);
+            self.in_para = true;
+            self.inline(body, out, true);
+            return;
+        }
+
+        // paragraph text, including lazy continuations of list items
+        self.held.clear();
+        self.held.extend_from_slice(text);
+        self.has_held = true;
+    }
+
+    fn flush_held(&mut self, out: &mut Vec<u8>) {
+        if !self.has_held {
+            return;
+        }
+        let held = core::mem::take(&mut self.held);
+        self.has_held = false;
+        self.inline_line(&held, out);
+        self.held = held;
+    }
+
+    fn open_para(&mut self, depth: u8, out: &mut Vec<u8>) {
+        for _ in 0..depth {
+            out.push(MARKER);
+            out.push(QUOTE_ON);
+        }
+        self.para_indent = depth;
+    }
+
+    // one source line of a paragraph; soft breaks become spaces
+    fn inline_line(&mut self, text: &[u8], out: &mut Vec<u8>) {
+        if self.in_para {
+            out.push(b' ');
+        }
+        self.in_para = true;
+        self.inline(text.trim_ascii_end(), out, true);
+    }
+
+    // closes open emphasis before the newline so the next block starts
+    // plain; quote depth is closed after it so the last line keeps it
+    fn end_para(&mut self, out: &mut Vec<u8>) {
+        if !self.in_para && self.para_indent == 0 {
+            return;
+        }
+        if self.bold {
+            out.push(MARKER);
+            out.push(BOLD_OFF);
+            self.bold = false;
+        }
+        if self.italic {
+            out.push(MARKER);
+            out.push(ITALIC_OFF);
+            self.italic = false;
+        }
+        if self.in_para {
+            out.push(b'\n');
+        }
+        for _ in 0..self.para_indent {
+            out.push(MARKER);
+            out.push(QUOTE_OFF);
+        }
+        self.in_para = false;
+        self.para_indent = 0;
+        self.blank = false;
+    }
+
+    fn heading(&mut self, text: &[u8], out: &mut Vec<u8>) {
+        self.end_para(out);
+        out.push(MARKER);
+        out.push(HEADING_ON);
+        // emphasis inside a heading would drop back to the body font
+        self.inline(text, out, false);
+        out.push(MARKER);
+        out.push(HEADING_OFF);
+        out.push(b'\n');
+        self.blank = false;
+    }
+
+    fn inline(&mut self, text: &[u8], out: &mut Vec<u8>, styled: bool) {
+        self.blank = false;
+        let n = text.len();
+        let mut i = 0;
+        while i < n {
+            let b = text[i];
+            match b {
+                b'\\' if i + 1 < n && text[i + 1].is_ascii_punctuation() => {
+                    out.push(text[i + 1]);
+                    i += 2;
+                }
+                b'`' => {
+                    let ticks = text[i..].iter().take_while(|&&c| c == b'`').count();
+                    let body = i + ticks;
+                    match find_run(&text[body..], b'`', ticks) {
+                        Some(end) => {
+                            out.extend_from_slice(text[body..body + end].trim_ascii());
+                            i = body + end + ticks;
+                        }
+                        None => {
+                            out.extend_from_slice(&text[i..body]);
+                            i = body;
+                        }
+                    }
+                }
+                b'!' if i + 1 < n && text[i + 1] == b'[' => match link(&text[i + 1..]) {
+                    Some((label, len)) => {
+                        self.inline(label, out, styled);
+                        i += 1 + len;
+                    }
+                    None => {
+                        out.push(b);
+                        i += 1;
+                    }
+                },
+                b'[' => match link(&text[i..]) {
+                    Some((label, len)) => {
+                        self.inline(label, out, styled);
+                        i += len;
+                    }
+                    None => {
+                        out.push(b);
+                        i += 1;
+                    }
+                },
+                b'*' | b'_' => {
+                    let run = text[i..].iter().take_while(|&&c| c == b).count();
+                    let prev = if i > 0 { text[i - 1] } else { b' ' };
+                    let next = text.get(i + run).copied().unwrap_or(b' ');
+                    // snake_case stays literal, as does a lone * between spaces
+                    let intraword = prev.is_ascii_alphanumeric() && next.is_ascii_alphanumeric();
+                    let opens = !next.is_ascii_whitespace();
+                    let closes = !prev.is_ascii_whitespace();
+                    if intraword && b == b'_' || !(opens || closes) {
+                        out.extend_from_slice(&text[i..i + run]);
+                        i += run;
+                        continue;
+                    }
+                    if styled {
+                        let mut left = run;
+                        if left >= 2 && (self.bold && closes || !self.bold && opens) {
+                            out.push(MARKER);
+                            out.push(if self.bold { BOLD_OFF } else { BOLD_ON });
+                            self.bold = !self.bold;
+                            left -= 2;
+                        }
+                        if left >= 1 && (self.italic && closes || !self.italic && opens) {
+                            out.push(MARKER);
+                            out.push(if self.italic { ITALIC_OFF } else { ITALIC_ON });
+                            self.italic = !self.italic;
+                        }
+                    }
+                    i += run;
+                }
+                b'<' => match text[i..].iter().position(|&c| c == b'>') {
+                    // autolinks keep the address, inline html tags are
+                    // dropped; anything else (a < b > c) is plain text
+                    Some(end) if end > 1 => {
+                        let inner = &text[i + 1..i + end];
+                        let name = inner.strip_prefix(b"/").unwrap_or(inner);
+                        let autolink = (inner.contains(&b':') || inner.contains(&b'@'))
+                            && !inner.iter().any(|c| c.is_ascii_whitespace());
+                        if autolink {
+                            out.extend_from_slice(inner);
+                            i += end + 1;
+                        } else if name.first().is_some_and(|c| c.is_ascii_alphabetic()) {
+                            i += end + 1;
+                        } else {
+                            out.push(b);
+                            i += 1;
+                        }
+                    }
+                    _ => {
+                        out.push(b);
+                        i += 1;
+                    }
+                },
+                _ => {
+                    out.push(b);
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+impl TextFeed for MdStrip {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &b in input {
+            match b {
+                b'\n' => {
+                    let line = core::mem::take(&mut self.line);
+                    self.process(&line, out);
+                    self.line = line;
+                    self.line.clear();
+                }
+                // stray marker bytes would be read as style codes
+                MARKER => {}
+                _ => {
+                    if self.line.len() >= LINE_CAP {
+                        let line = core::mem::take(&mut self.line);
+                        self.process(&line, out);
+                        self.line = line;
+                        self.line.clear();
+                    }
+                    self.line.push(b);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        if !self.line.is_empty() {
+            let line = core::mem::take(&mut self.line);
+            self.process(&line, out);
+        }
+        self.flush_held(out);
+        self.end_para(out);
+    }
+}
+
+// ``` or ~~~ (three or more) opens or closes a code fence
+fn fence_char(line: &[u8]) -> Option<u8> {
+    let t = line.trim_ascii_start();
+    let c = *t.first()?;
+    if (c == b'`' || c == b'~') && t.len() >= 3 && t[..3].iter().all(|&b| b == c) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+fn atx_level(text: &[u8]) -> Option<usize> {
+    let level = text.iter().take_while(|&&b| b == b'#').count();
+    if (1..=6).contains(&level) && (text.len() == level || text[level] == b' ') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+fn trim_closing_hashes(text: &[u8]) -> &[u8] {
+    let end = text.iter().rposition(|&b| b != b'#').map_or(0, |p| p + 1);
+    if end == 0 {
+        return &text[..0];
+    }
+    if end < text.len() && text[end - 1] == b' ' {
+        text[..end].trim_ascii_end()
+    } else {
+        text
+    }
+}
+
+fn is_setext(text: &[u8]) -> bool {
+    let t = text.trim_ascii_end();
+    !t.is_empty() && (t.iter().all(|&b| b == b'=') || t.iter().all(|&b| b == b'-'))
+}
+
+// ***, --- or ___, spaces allowed between
+fn is_rule(text: &[u8]) -> bool {
+    let c = text[0];
+    if c != b'*' && c != b'-' && c != b'_' {
+        return false;
+    }
+    let mut count = 0;
+    for &b in text {
+        if b == c {
+            count += 1;
+        } else if b != b' ' && b != b'\t' {
+            return false;
+        }
+    }
+    count >= 3
+}
+
+// bullet or number prefix to print, and the item text
+fn list_item(text: &[u8]) -> Option<(&[u8], &[u8])> {
+    let c = text[0];
+    if (c == b'-' || c == b'*' || c == b'+') && text.get(1) == Some(&b' ') {
+        return Some(("\u{2022} ".as_bytes(), text[2..].trim_ascii_start()));
+    }
+    let digits = text.iter().take_while(|b| b.is_ascii_digit()).count();
+    if (1..=9).contains(&digits)
+        && matches!(text.get(digits), Some(b'.') | Some(b')'))
+        && text.get(digits + 1) == Some(&b' ')
+    {
+        return Some((&text[..digits + 2], text[digits + 2..].trim_ascii_start()));
+    }
+    None
+}
+
+// [label](target) or [label][ref]; label and bytes consumed
+fn link(text: &[u8]) -> Option<(&[u8], usize)> {
+    let close = text.iter().position(|&b| b == b']')?;
+    let label = &text[1..close];
+    let shut = match text.get(close + 1) {
+        Some(b'(') => b')',
+        Some(b'[') => b']',
+        _ => return None,
+    };
+    let end = text[close + 2..].iter().position(|&b| b == shut)?;
+    Some((label, close + 2 + end + 1))
+}
+
+// start of the next run of exactly `len` copies of c
+fn find_run(text: &[u8], c: u8, len: usize) -> Option<usize> {
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == c {
+            let run = text[i..].iter().take_while(|&&b| b == c).count();
+            if run == len {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+impl ReaderApp {
+    // convert the book into its cache unless a current copy exists,
+    // a few chunks per call; true once done. file_size becomes the
+    // converted length, so paging and bookmarks work on offsets into
+    // the cache
+    pub(super) fn md_prepare(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        if self.text_conv.is_none() {
+            let (nb, nl) = self.name_copy();
+            let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+            let src_size = k.file_size(name)?;
+            self.text_key = textcache::source_key(k, name, src_size, &mut self.pg.buf)?;
+        }
+        let header = md_header(&self.text_key);
+        let done = self.convert_text(k, &header, || Feed::Markdown(MdStrip::new()))?;
+        if let Some(len) = done {
+            self.file_size = len;
+        }
+        Ok(done.is_some())
+    }
+}
@@ -1,9 +1,28 @@
+mod bookpages;
+mod cbz;
+mod chapters;
+mod cover;
+mod encoding;
 mod epubs;
+mod fb2;
+mod html;
 mod images;
+mod links;
+mod markdown;
+mod pageindex;
 mod paging;
+mod textcache;
+mod toc;
+mod xml;
+mod xtc;
 
 pub use pulp_kernel::util::decode_utf8_char;
 
+pub(crate) use cover::{decode_cover, find_cover};
+pub(crate) use images::{
+    decode_app_image, img_cache_name, img_cache_str, is_image_ext_jpeg, is_image_ext_png,
+};
+
 use crate::apps::PendingSetting;
 use crate::fonts::bitmap::{self, BitmapFont};
 
@@ -20,7 +39,7 @@ use embedded_graphics::text::Text;
 
 use crate::apps::{App, AppContext, AppId, RECENT_FILE, Transition};
 use crate::board::action::{Action, ActionEvent};
-use crate::board::{SCREEN_H, SCREEN_W};
+use crate::board::{Orientation, SCREEN_H, SCREEN_W};
 use crate::drivers::strip::StripBuffer;
 use crate::error::{Error, ErrorKind};
 use crate::fonts;
@@ -29,13 +48,19 @@ use crate::kernel::QuickAction;
 use crate::kernel::bookmarks;
 use crate::kernel::work_queue;
 use crate::kernel::work_queue::DecodedImage;
-use crate::ui::{Alignment, CONTENT_TOP, HEADER_W, Region, StackFmt, TITLE_Y_OFFSET};
+use crate::ui::{Alignment, BUTTON_BAR_W, CONTENT_TOP, HEADER_W, Region, StackFmt, TITLE_Y_OFFSET};
+use bookpages::BookPages;
+use chapters::TxtChapters;
+use encoding::{ENCODING_NAMES, Encoding};
+use links::Links;
+use pageindex::PageIndex;
 use smol_epub::cache;
 use smol_epub::epub::{self, EpubMeta, EpubSpine, EpubToc, TocSource};
 use smol_epub::html_strip::{
     BOLD_OFF, BOLD_ON, HEADING_OFF, HEADING_ON, ITALIC_OFF, ITALIC_ON, MARKER,
 };
 use smol_epub::zip::{self, ZipIndex};
+use toc::Toc;
 
 // chrome margin: used for header, status, progress bar, loading indicator.
 // this never changes; only the text content area responds to the reading theme.
@@ -48,21 +73,53 @@ pub(super) const TEXT_Y: u16 = HEADER_Y + HEADER_H + 2;
 
 pub(super) const LINE_H: u16 = 20;
 
-pub(super) const CHARS_PER_LINE: usize = 51;
+// FONT_9X18 advance; the monospace fallback fills text_w with it
+pub(super) const MONO_CHAR_W: u32 = 9;
 
 pub(super) const LINES_PER_PAGE: usize = 37;
 
+// multi-column pages: line slots are filled one column after another,
+// max_lines per column, so a page holds up to LINES_PER_PAGE per column
+pub(super) const MAX_COLUMNS: usize = crate::kernel::config::MAX_READING_COLUMNS as usize;
+pub(super) const LINE_SLOTS: usize = LINES_PER_PAGE * MAX_COLUMNS;
+pub(super) const COLUMN_GUTTER: u32 = 24;
+
+// auto columns: split the text width into as many columns as keep
+// at least this many 'n' advances per line
+const AUTO_COLUMN_CHARS: u32 = 32;
+
 pub(super) const PAGE_BUF: usize = 8192;
 
 pub(super) const MAX_PAGES: usize = 512;
+pub(super) const LAYOUT_KEY_LEN: usize = 7;
 
 pub(super) const HEADER_REGION: Region = Region::new(MARGIN, HEADER_Y, HEADER_W, HEADER_H);
 
 const STATUS_X: u16 = MARGIN + HEADER_W + 8;
-const STATUS_W: u16 = SCREEN_W - STATUS_X - MARGIN;
-pub(super) const STATUS_REGION: Region = Region::new(STATUS_X, HEADER_Y, STATUS_W, HEADER_H);
 
-pub(super) const PAGE_REGION: Region = Region::new(0, HEADER_Y, SCREEN_W, SCREEN_H - HEADER_Y);
+// regions that follow the screen size; apply_theme_layout recomputes
+// them when the orientation changes
+const fn page_region_for(o: Orientation) -> Region {
+    Region::new(0, HEADER_Y, o.screen_w(), o.screen_h() - HEADER_Y)
+}
+
+const fn status_region_for(o: Orientation) -> Region {
+    Region::new(
+        STATUS_X,
+        HEADER_Y,
+        o.screen_w() - STATUS_X - MARGIN,
+        HEADER_H,
+    )
+}
+
+const fn position_overlay_for(o: Orientation) -> Region {
+    Region::new(
+        (o.screen_w() - POSITION_OVERLAY_W) / 2,
+        (o.screen_h() - POSITION_OVERLAY_H) / 2,
+        POSITION_OVERLAY_W,
+        POSITION_OVERLAY_H,
+    )
+}
 
 pub(super) const NO_PREFETCH: usize = usize::MAX;
 
@@ -101,27 +158,33 @@ pub(super) const PRECACHE_IMG_MAX: u32 = 30 * 1024;
 
 const POSITION_OVERLAY_W: u16 = 280;
 const POSITION_OVERLAY_H: u16 = 40;
-pub(super) const POSITION_OVERLAY: Region = Region::new(
-    (SCREEN_W - POSITION_OVERLAY_W) / 2,
-    (SCREEN_H - POSITION_OVERLAY_H) / 2,
-    POSITION_OVERLAY_W,
-    POSITION_OVERLAY_H,
-);
 
 const LOADING_W: u16 = SCREEN_W - 2 * MARGIN - 16;
 const LOADING_H: u16 = 24;
 pub(super) const LOADING_REGION: Region = Region::new(MARGIN, TEXT_Y, LOADING_W, LOADING_H);
 
+pub(super) const QA_ENCODING: u8 = 0;
 pub const QA_FONT_SIZE: u8 = 1;
+pub(super) const QA_GOTO: u8 = 2;
 pub(super) const QA_PREV_CHAPTER: u8 = 3;
 pub(super) const QA_NEXT_CHAPTER: u8 = 4;
 pub(super) const QA_TOC: u8 = 5;
+pub(super) const QA_LINKS: u8 = 6;
+
+pub(super) const QA_MAX: usize = crate::kernel::app::MAX_APP_ACTIONS;
 
-pub(super) const QA_MAX: usize = 4;
+// go-to targets for page-image books, as a share of the book
+const GOTO_STEPS: &[&str] = &[
+    "0%", "10%", "20%", "30%", "40%", "50%", "60%", "70%", "80%", "90%", "100%",
+];
 
 // reader state machine:
 // NeedBookmark -> NeedInit -> NeedOpf -> NeedToc -> NeedCache -> NeedIndex -> NeedPage -> Ready
+// fb2, html: NeedBookmark -> NeedCache (convert) -> NeedIndex -> NeedPage
+// markdown, txt: NeedBookmark -> NeedCache (convert) -> NeedPage
+// xtc, cbz: NeedBookmark -> NeedInit (page table) -> NeedPage
 // Ready <-> ShowToc (toc overlay); any state -> Error on failure
+// Ready -> SelectLink -> NeedLink -> ShowNote or NeedIndex (link jump)
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub(super) enum State {
     NeedBookmark,
@@ -133,6 +196,10 @@ pub(super) enum State {
     NeedPage,
     Ready,
     ShowToc,
+    // picking a link on the page, then following it
+    SelectLink,
+    NeedLink,
+    ShowNote,
     Error,
 }
 
@@ -197,15 +264,20 @@ impl LineSpan {
 }
 
 // page index, content buffer, and read-ahead state
+//
+// page numbers are absolute; offsets holds pages base..base + MAX_PAGES.
+// epubs index one chapter and never move base, plain text books slide
+// the window over the index kept in _PULP (see pageindex.rs)
 pub(super) struct PageState {
     pub(super) offsets: [u32; MAX_PAGES],
+    pub(super) base: usize,
     pub(super) total_pages: usize,
     pub(super) fully_indexed: bool,
 
     pub(super) page: usize,
     pub(super) buf: [u8; PAGE_BUF],
     pub(super) buf_len: usize,
-    pub(super) lines: [LineSpan; LINES_PER_PAGE],
+    pub(super) lines: [LineSpan; LINE_SLOTS],
     pub(super) line_count: usize,
 
     pub(super) prefetch: Vec<u8>,
@@ -217,18 +289,30 @@ impl PageState {
     pub(super) const fn new() -> Self {
         Self {
             offsets: [0u32; MAX_PAGES],
+            base: 0,
             total_pages: 0,
             fully_indexed: false,
             page: 0,
             buf: [0u8; PAGE_BUF],
             buf_len: 0,
-            lines: [LineSpan::EMPTY; LINES_PER_PAGE],
+            lines: [LineSpan::EMPTY; LINE_SLOTS],
             line_count: 0,
             prefetch: Vec::new(),
             prefetch_len: 0,
             prefetch_page: NO_PREFETCH,
         }
     }
+
+    #[inline]
+    pub(super) fn in_window(&self, page: usize) -> bool {
+        page >= self.base && page - self.base < MAX_PAGES
+    }
+
+    // start of a page held in the window
+    #[inline]
+    pub(super) fn offset(&self, page: usize) -> u32 {
+        self.offsets[page - self.base]
+    }
 }
 
 // epub-specific state: zip index, metadata, spine, toc, chapter
@@ -258,11 +342,16 @@ pub(super) struct EpubState {
     pub(super) img_found_count: u16,
     pub(super) img_cached_count: u16,
 
-    pub(super) toc: Option<Box<EpubToc>>,
+    pub(super) toc: Option<Toc>,
     pub(super) toc_source: Option<TocSource>,
     pub(super) toc_selected: usize,
     pub(super) toc_scroll: usize,
 
+    // zip index of the cover image, found while the OPF is parsed
+    pub(super) cover: Option<u16>,
+    // chapter count of a book converted without a spine (fb2)
+    pub(super) sections: u16,
+
     // --- private: only accessed by impl EpubState methods ---
     name_hash: u32,
     archive_size: u32,
@@ -296,6 +385,8 @@ impl EpubState {
             toc_source: None,
             toc_selected: 0,
             toc_scroll: 0,
+            cover: None,
+            sections: 0,
         }
     }
 
@@ -309,6 +400,11 @@ impl EpubState {
         cache::dir_name_str(&self.cache_dir)
     }
 
+    #[inline]
+    pub(super) fn chapter_count(&self) -> usize {
+        self.spine.len().max(self.sections as usize)
+    }
+
     #[inline]
     pub(super) fn chapter_size(&self, ch: usize) -> u32 {
         if ch < cache::MAX_CACHE_CHAPTERS {
@@ -333,21 +429,60 @@ pub struct ReaderApp {
     pub(super) file_size: u32,
 
     pub(super) pg: PageState,
+    pub(super) pidx: PageIndex,
+    pub(super) bp: BookPages,
+    pub(super) links: Links,
+    pub(super) chap: TxtChapters,
     pub(super) epub: EpubState,
 
     pub(super) state: State,
     pub(super) error: Option<Error>,
     pub(super) show_position: bool,
 
+    // epub or fb2: both read from the chaptered v3 cache
     pub(super) is_epub: bool,
+    pub(super) is_fb2: bool,
+    // standalone .htm/.html pages, cached as a one-chapter book
+    pub(super) is_html: bool,
+    // .md files, and .txt files not in UTF-8, are read from a
+    // converted copy in _PULP
+    pub(super) is_markdown: bool,
+    pub(super) text_cache: [u8; 12],
+    pub(super) txt_converted: bool,
+    // source key of a txt or markdown book, for its caches and indexes
+    pub(super) text_key: [u8; textcache::KEY_LEN],
+    // the cache conversion NeedCache is working through
+    pub(super) text_conv: Option<textcache::Convert>,
+    // detected or chosen encoding; enc_choice is the quick menu value
+    // (0 = auto) and enc_dirty asks txt_prepare to save it
+    pub(super) encoding: Encoding,
+    pub(super) enc_choice: u8,
+    pub(super) enc_dirty: bool,
+    // converted size before an encoding switch, to rescale the position
+    pub(super) reencode_from: u32,
+    // pre-rendered page bitmaps; pg.page indexes the page table
+    pub(super) is_xtc: bool,
+    pub(super) xtc: xtc::XtcBook,
+    // comic pages in a zip; the spread settings come from the system
+    pub(super) is_cbz: bool,
+    pub(super) cbz: cbz::CbzBook,
+    pub(super) comic_split: bool,
+    pub(super) comic_rtl: bool,
     pub(super) goto_last_page: bool,
+    // page to land on once the chapter is indexed (whole-book go-to)
+    pub(super) goto_page: Option<usize>,
     pub(super) restore_offset: Option<u32>,
 
     pub(super) page_img: Option<DecodedImage>,
     pub(super) fullscreen_img: bool,
     pub(super) defer_image_decode: bool,
+    // next page shown starts a chapter; clear ghosting with a full GC
+    pub(super) clean_next: bool,
 
     pub(super) fonts: Option<fonts::FontSet>,
+    // on-SD glyph tables for codepoints the flash fonts lack (CJK)
+    pub(super) paged: fonts::paged::PagedFont,
+    pub(super) missing: fonts::MissingGlyphs,
     pub(super) font_line_h: u16,
     pub(super) font_ascent: u16,
     pub(super) max_lines: u8,
@@ -355,10 +490,23 @@ pub struct ReaderApp {
     // reading theme: runtime layout derived from READING_THEMES
     pub(super) text_margin: u16, // horizontal margin for text content (from theme)
     pub(super) text_y: u16,      // top of text area (TEXT_Y + theme vertical margin)
-    pub(super) text_w: u32,      // text content width (SCREEN_W - 2 * text_margin)
-    pub(super) text_area_h: u16, // height of text area (SCREEN_H - text_y - bottom_pad)
+    pub(super) text_w: u32,      // text content width (screen w - 2 * text_margin)
+    pub(super) text_area_h: u16, // height of text area (screen h - text_y - bottom_pad)
     pub(super) reading_theme_idx: u8,
 
+    // orientation and columns: requested from settings; col_w and
+    // columns are derived in apply_font_metrics, laid_out_* record what
+    // the current page offsets were wrapped for
+    pub(super) landscape: bool,
+    pub(super) columns_setting: u8,
+    pub(super) columns: u8,
+    pub(super) col_w: u32,
+    pub(super) laid_out_w: u32,
+    pub(super) laid_out_cols: u8,
+    pub(super) page_region: Region,
+    pub(super) status_region: Region,
+    pub(super) position_overlay: Region,
+
     // pre-scanned image heights for the current page buffer;
     // populated before wrapping so the pager can reserve the exact
     // number of lines each image needs at its natural aspect ratio
@@ -383,6 +531,10 @@ impl ReaderApp {
             file_size: 0,
 
             pg: PageState::new(),
+            pidx: PageIndex::new(),
+            bp: BookPages::new(),
+            links: Links::new(),
+            chap: TxtChapters::new(),
             epub: EpubState::new(),
 
             state: State::NeedPage,
@@ -390,14 +542,35 @@ impl ReaderApp {
             show_position: false,
 
             is_epub: false,
+            is_fb2: false,
+            is_html: false,
+            is_markdown: false,
+            text_cache: [0u8; 12],
+            txt_converted: false,
+            text_key: [0u8; textcache::KEY_LEN],
+            text_conv: None,
+            encoding: Encoding::Utf8,
+            enc_choice: 0,
+            enc_dirty: false,
+            reencode_from: 0,
+            is_xtc: false,
+            xtc: xtc::XtcBook::new(),
+            is_cbz: false,
+            cbz: cbz::CbzBook::new(),
+            comic_split: false,
+            comic_rtl: false,
             goto_last_page: false,
+            goto_page: None,
             restore_offset: None,
 
             page_img: None,
             fullscreen_img: false,
+            clean_next: false,
             defer_image_decode: false,
 
             fonts: None,
+            paged: fonts::paged::PagedFont::new(),
+            missing: fonts::MissingGlyphs::new(),
             font_line_h: LINE_H,
             font_ascent: LINE_H,
             max_lines: LINES_PER_PAGE as u8,
@@ -408,6 +581,16 @@ impl ReaderApp {
             text_area_h: TEXT_AREA_H,
             reading_theme_idx: 0,
 
+            landscape: false,
+            columns_setting: 1,
+            columns: 1,
+            col_w: TEXT_W,
+            laid_out_w: TEXT_W,
+            laid_out_cols: 1,
+            page_region: page_region_for(Orientation::Portrait),
+            status_region: status_region_for(Orientation::Portrait),
+            position_overlay: position_overlay_for(Orientation::Portrait),
+
             img_heights: [0u16; MAX_IMAGES_PER_PAGE],
             img_height_count: 0,
 
@@ -434,12 +617,51 @@ impl ReaderApp {
         self.apply_font_metrics();
     }
 
+    // layout follows via apply_theme_layout; pages are rewrapped on resume
+    pub fn set_landscape(&mut self, landscape: bool) {
+        self.landscape = landscape;
+    }
+
+    // 0 = auto; takes effect at the next apply_font_metrics
+    pub fn set_columns(&mut self, columns: u8) {
+        self.columns_setting = columns;
+    }
+
     fn apply_theme_layout(&mut self) {
         let theme = crate::kernel::config::reading_theme(self.reading_theme_idx);
+        let o = if self.landscape && !self.fixed_layout() {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        };
+        // landscape button tabs sit along the right edge; keep text clear
+        let tabs_w = if self.landscape { BUTTON_BAR_W } else { 0 };
         self.text_margin = theme.margin_h;
         self.text_y = TEXT_Y + theme.margin_v;
-        self.text_w = (SCREEN_W - 2 * self.text_margin) as u32;
-        self.text_area_h = SCREEN_H.saturating_sub(self.text_y + 4);
+        self.text_w = (o.screen_w() - tabs_w - 2 * self.text_margin) as u32;
+        self.text_area_h = o.screen_h().saturating_sub(self.text_y + 4);
+        self.page_region = page_region_for(o);
+        self.status_region = status_region_for(o);
+        self.position_overlay = position_overlay_for(o);
+    }
+
+    // page-image books (xtc, cbz): portrait only, no reflow, and
+    // bookmarks hold the page number
+    #[inline]
+    pub fn fixed_layout(&self) -> bool {
+        self.is_xtc || self.is_cbz
+    }
+
+    // plain .txt: not an epub-style book, markdown or page images
+    #[inline]
+    pub(super) fn is_plain_text(&self) -> bool {
+        !self.is_epub && !self.is_markdown && !self.fixed_layout()
+    }
+
+    // takes effect from the next page decoded
+    pub fn set_comic_layout(&mut self, split: bool, rtl: bool) {
+        self.comic_split = split;
+        self.comic_rtl = rtl;
     }
 
     pub fn set_chrome_font(&mut self, font: &'static BitmapFont) {
@@ -451,7 +673,7 @@ impl ReaderApp {
     }
 
     pub(super) fn cached_chapter_count(&self) -> usize {
-        let n = self.epub.spine.len().min(cache::MAX_CACHE_CHAPTERS);
+        let n = self.epub.chapter_count().min(cache::MAX_CACHE_CHAPTERS);
         self.epub.ch_cached[..n].iter().filter(|&&c| c).count()
     }
 
@@ -459,7 +681,7 @@ impl ReaderApp {
     // uses a unified percentage: chapters contribute 0-80%, images 80-100%.
     fn set_cache_loading(&self, ctx: &mut AppContext) {
         let cached_ch = self.cached_chapter_count();
-        let total_ch = self.epub.spine.len();
+        let total_ch = self.epub.chapter_count();
         let img_found = self.epub.img_found_count as usize;
         let img_cached = self.epub.img_cached_count as usize;
 
@@ -497,7 +719,7 @@ impl ReaderApp {
         self.error = Some(e);
         self.state = State::Error;
         ctx.clear_loading();
-        ctx.mark_dirty(PAGE_REGION);
+        ctx.mark_dirty(self.page_region);
     }
 
     // run one step of image work queue polling while suspended;
@@ -538,6 +760,12 @@ impl ReaderApp {
     }
 
     fn rebuild_quick_actions(&mut self) {
+        if self.fixed_layout() {
+            self.qa_buf[0] = QuickAction::cycle(QA_GOTO, "Go To", self.goto_step(), GOTO_STEPS);
+            self.qa_count = 1;
+            return;
+        }
+
         let mut n = 0usize;
 
         self.qa_buf[n] = QuickAction::cycle(
@@ -548,18 +776,34 @@ impl ReaderApp {
         );
         n += 1;
 
-        if self.is_epub && self.epub.spine.len() > 1 {
+        if self.is_plain_text() {
+            self.qa_buf[n] =
+                QuickAction::cycle(QA_ENCODING, "Encoding", self.enc_choice, ENCODING_NAMES);
+            n += 1;
+        }
+
+        if (self.is_epub && self.epub.chapter_count() > 1) || self.has_txt_chapters() {
             self.qa_buf[n] = QuickAction::trigger(QA_PREV_CHAPTER, "Prev Ch", "<<<");
             n += 1;
             self.qa_buf[n] = QuickAction::trigger(QA_NEXT_CHAPTER, "Next Ch", ">>>");
             n += 1;
         }
 
-        if self.is_epub && self.epub.toc.as_ref().map_or(false, |t| !t.is_empty()) {
+        if self.epub.toc.as_ref().map_or(false, |t| !t.is_empty()) {
             self.qa_buf[n] = QuickAction::trigger(QA_TOC, "Contents", "Open");
             n += 1;
         }
 
+        if self.has_links() {
+            self.qa_buf[n] = QuickAction::trigger(QA_LINKS, "Links", "Select");
+            n += 1;
+        }
+
+        if self.is_epub && self.book_page().is_some() {
+            self.qa_buf[n] = QuickAction::cycle(QA_GOTO, "Go To", self.goto_step(), GOTO_STEPS);
+            n += 1;
+        }
+
         self.qa_count = n as u8;
     }
 
@@ -568,6 +812,9 @@ impl ReaderApp {
         self.font_line_h = LINE_H;
         self.font_ascent = LINE_H;
         self.max_lines = LINES_PER_PAGE as u8;
+        // the monospace fallback always lays out a single column
+        self.columns = 1;
+        self.col_w = self.text_w;
 
         let theme = crate::kernel::config::reading_theme(self.reading_theme_idx);
         let spacing_pct = theme.line_spacing_pct;
@@ -580,8 +827,11 @@ impl ReaderApp {
             self.font_ascent = fs.ascent(fonts::Style::Regular);
             self.max_lines =
                 ((self.text_area_h / self.font_line_h) as usize).min(LINES_PER_PAGE) as u8;
+            self.columns = self.column_count(&fs);
+            let gutters = COLUMN_GUTTER * (self.columns as u32 - 1);
+            self.col_w = (self.text_w - gutters) / self.columns as u32;
             log::info!(
-                "font: size_idx={} line_h={} (native {} x {}%) ascent={} max_lines={} margin={}",
+                "font: size_idx={} line_h={} (native {} x {}%) ascent={} max_lines={} margin={} columns={}x{}",
                 self.book_font_size_idx,
                 self.font_line_h,
                 native_h,
@@ -589,12 +839,62 @@ impl ReaderApp {
                 self.font_ascent,
                 self.max_lines,
                 self.text_margin,
+                self.columns,
+                self.col_w,
             );
             self.fonts = Some(fs);
+            self.paged.set_size(self.book_font_size_idx);
         }
         self.applied_font_idx = self.book_font_size_idx;
     }
 
+    fn column_count(&self, fs: &fonts::FontSet) -> u8 {
+        let cols = if self.columns_setting == crate::kernel::config::AUTO_READING_COLUMNS {
+            let em = fs.advance('n', fonts::Style::Regular).max(1) as u32;
+            (self.text_w / em / AUTO_COLUMN_CHARS) as u8
+        } else {
+            self.columns_setting
+        };
+        cols.clamp(1, MAX_COLUMNS as u8)
+    }
+
+    // a freshly loaded page is on screen; the first page of a chapter
+    // and image-only pages get a full GC so the previous text doesn't
+    // ghost through
+    fn mark_page_shown(&mut self, ctx: &mut AppContext) {
+        if core::mem::take(&mut self.clean_next) || self.fullscreen_img {
+            ctx.mark_dirty_clean(self.page_region);
+        } else {
+            ctx.mark_dirty(self.page_region);
+        }
+    }
+
+    // line slots a full page fills across all its columns
+    pub(super) fn page_lines(&self) -> usize {
+        self.max_lines as usize * self.columns as usize
+    }
+
+    // what wrapping depends on; page counts and offsets kept on SD
+    // are only reused under the same key
+    pub(super) fn layout_key(&self) -> [u8; LAYOUT_KEY_LEN] {
+        let w = (self.col_w as u16).to_le_bytes();
+        [
+            self.book_font_size_idx,
+            self.reading_theme_idx,
+            self.columns,
+            self.max_lines,
+            w[0],
+            w[1],
+            self.fonts.is_some() as u8,
+        ]
+    }
+
+    // record the geometry the page offsets are about to be built for
+    fn note_layout(&mut self) {
+        self.laid_out_w = self.col_w;
+        self.laid_out_cols = self.columns;
+    }
+
     fn name(&self) -> &str {
         core::str::from_utf8(&self.filename[..self.filename_len]).unwrap_or("???")
     }
@@ -633,8 +933,11 @@ impl ReaderApp {
 
     #[inline]
     pub fn byte_offset(&self) -> u32 {
-        if self.pg.page < self.pg.total_pages {
-            self.pg.offsets[self.pg.page]
+        if self.fixed_layout() {
+            return self.pg.page as u32;
+        }
+        if self.pg.page < self.pg.total_pages && self.pg.in_window(self.pg.page) {
+            self.pg.offset(self.pg.page)
         } else {
             0
         }
@@ -678,8 +981,9 @@ impl ReaderApp {
         if self.state == State::Ready {
             bm.save(
                 &self.filename[..self.filename_len],
-                self.pg.offsets[self.pg.page],
+                self.byte_offset(),
                 self.epub.chapter,
+                self.progress_pct(),
             );
         }
     }
@@ -704,6 +1008,304 @@ impl ReaderApp {
         }
     }
 
+    // sleep screen accessors; only meaningful once a book is open
+    #[inline]
+    pub fn has_open_book(&self) -> bool {
+        self.filename_len > 0
+            && matches!(
+                self.state,
+                State::Ready | State::ShowToc | State::SelectLink | State::ShowNote
+            )
+    }
+
+    #[inline]
+    pub fn book_title(&self) -> &str {
+        self.display_name()
+    }
+
+    #[inline]
+    pub fn book_progress(&self) -> u8 {
+        self.progress_pct()
+    }
+
+    // decode the open epub's cover to fit max_w x max_h
+    pub fn decode_cover(
+        &self,
+        k: &mut KernelHandle<'_>,
+        max_w: u16,
+        max_h: u16,
+    ) -> crate::error::Result<DecodedImage> {
+        let idx = match self.epub.cover {
+            Some(idx) if self.is_epub => idx as usize,
+            _ => return Err(Error::new(ErrorKind::NotFound, "decode_cover")),
+        };
+        cover::decode_cover(k, self.name(), &self.epub.zip, idx, max_w, max_h)
+    }
+
+    // current page and last page go-to works over: the whole book
+    // for counted epubs, else the page table
+    fn goto_span(&self) -> (usize, usize) {
+        match self.book_page() {
+            Some((page, total)) if self.is_epub => (page, total - 1),
+            _ => (self.pg.page, self.pg.total_pages.saturating_sub(1)),
+        }
+    }
+
+    // go-to step nearest the current page
+    fn goto_step(&self) -> u8 {
+        let (page, last) = self.goto_span();
+        if last == 0 {
+            return 0;
+        }
+        let steps = GOTO_STEPS.len() - 1;
+        ((page * steps + last / 2) / last) as u8
+    }
+
+    // moves to the step's page and picks the state that loads it
+    fn goto(&mut self, step: u8) -> bool {
+        let (page, last) = self.goto_span();
+        let steps = GOTO_STEPS.len() - 1;
+        let target = (step as usize).min(steps) * last / steps;
+        if target == page {
+            return false;
+        }
+        if !self.is_epub {
+            self.pg.page = target;
+            self.state = State::NeedPage;
+            return true;
+        }
+        if self.book_page().is_none() {
+            return false;
+        }
+        let (ch, p) = self.locate_book_page(target);
+        if ch == self.epub.chapter as usize && p < self.pg.total_pages {
+            self.pg.page = p;
+            self.state = State::NeedPage;
+        } else {
+            self.epub.chapter = ch as u16;
+            self.goto_last_page = false;
+            self.goto_page = Some(p);
+            self.state = State::NeedIndex;
+        }
+        true
+    }
+
+    // toc entry covering the reading position: for chaptered books
+    // the last entry starting on or before the page, so a chapter
+    // without its own entry marks the one it continues. entries of
+    // earlier chapters count from their chapter start; entries into
+    // the current one from their anchors once those are looked up
+    fn toc_current(&self) -> Option<usize> {
+        if self.is_plain_text() {
+            return self.txt_chapter();
+        }
+        let toc = self.epub.toc.as_ref()?;
+        let ch = self.epub.chapter;
+        let page_end = self.page_end();
+        let mut best: Option<(usize, (u16, u32))> = None;
+        for (i, e) in toc.entries.iter().enumerate() {
+            if e.spine_idx == toc::NO_CHAPTER || e.spine_idx > ch {
+                continue;
+            }
+            let at = if e.spine_idx == ch && toc.anchors_for == ch {
+                e.anchor
+            } else {
+                0
+            };
+            let key = (e.spine_idx, at);
+            if at < page_end && best.is_none_or(|(_, b)| key > b) {
+                best = Some((i, key));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    // toc rows that fit the text area
+    fn toc_rows(&self) -> usize {
+        let line_h = if self.fonts.is_some() {
+            fonts::body_font(self.book_font_size_idx).line_height
+        } else {
+            LINE_H
+        };
+        (self.text_area_h / line_h.max(1)).max(1) as usize
+    }
+
+    // scroll the toc so the selection is listed, without running past
+    // the last row
+    fn toc_keep_visible(&mut self) {
+        let Some(toc) = self.epub.toc.as_ref() else {
+            return;
+        };
+        let rows = self.toc_rows();
+        let row = toc.row_of(self.epub.toc_selected);
+        let listed = toc.visible().count();
+        let mut scroll = self.epub.toc_scroll.min(listed.saturating_sub(rows));
+        if row < scroll {
+            scroll = row;
+        } else if row >= scroll + rows {
+            scroll = row + 1 - rows;
+        }
+        self.epub.toc_scroll = scroll;
+    }
+
+    // page-image books cover the whole panel; no header or status line
+    fn draw_fixed_page(&self, strip: &mut StripBuffer) {
+        if let Some(ref img) = self.page_img {
+            let (offset, w) = self.cbz_crop(img.width);
+            let x = (SCREEN_W as i32 - w as i32) / 2;
+            let y = (SCREEN_H as i32 - img.height as i32) / 2;
+            strip.blit_1bpp(
+                &img.data,
+                offset,
+                w as usize,
+                img.height as usize,
+                img.stride,
+                x,
+                y,
+                true,
+            );
+        }
+        self.draw_position(strip);
+    }
+
+    // one wrapped line from x, switching style at its markers;
+    // mark(j) is the underline thickness under byte j, 0 for none
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text_line(
+        &self,
+        strip: &mut StripBuffer,
+        fs: &fonts::FontSet,
+        line: &[u8],
+        mut sty: fonts::Style,
+        x: i32,
+        baseline: i32,
+        mark: impl Fn(usize) -> u32,
+    ) {
+        let mut cx = x;
+        // underline in progress: where it starts and how thick
+        let mut ul: Option<(i32, u32)> = None;
+        let mut j = 0usize;
+        while j < line.len() {
+            let b = line[j];
+            if b == MARKER && j + 1 < line.len() {
+                sty = match line[j + 1] {
+                    BOLD_ON => fonts::Style::Bold,
+                    ITALIC_ON => fonts::Style::Italic,
+                    HEADING_ON => fonts::Style::Heading,
+                    BOLD_OFF | ITALIC_OFF | HEADING_OFF => fonts::Style::Regular,
+                    _ => sty,
+                };
+                j += 2;
+                continue;
+            }
+            let (x0, at) = (cx, j);
+            if b >= 0xC0 {
+                let (ch, seq_len) = decode_utf8_char(line, j);
+                cx += if !fs.has_glyph(ch, sty) && self.paged.covers(ch) {
+                    self.paged.draw_char(strip, ch, cx, baseline)
+                } else {
+                    fs.draw_char(strip, ch, sty, cx, baseline)
+                } as i32;
+                j += seq_len;
+            } else if b >= 0x80 {
+                // continuation byte mid-stream (already consumed
+                // by a lead byte above, or stray), skip
+                j += 1;
+                continue;
+            } else if b < bitmap::FIRST_CHAR {
+                j += 1;
+                continue; // control char
+            } else {
+                cx += fs.draw_char(strip, b as char, sty, cx, baseline) as i32;
+                j += 1;
+            }
+
+            let m = mark(at);
+            if let Some((ux, t)) = ul.filter(|&(_, t)| t != m) {
+                underline(strip, ux, x0, baseline, t);
+                ul = None;
+            }
+            if m > 0 && ul.is_none() {
+                ul = Some((x0, m));
+            }
+        }
+        if let Some((ux, t)) = ul {
+            underline(strip, ux, cx, baseline, t);
+        }
+    }
+
+    // page / chapter overlay while a page turn button is held
+    fn draw_position(&self, strip: &mut StripBuffer) {
+        let cf = self.chrome_font;
+        if self.show_position
+            && self.state == State::Ready
+            && self.position_overlay.intersects(strip.logical_window())
+        {
+            let mut pbuf = StackFmt::<48>::new();
+            if self.is_epub && self.epub.chapter_count() > 1 {
+                if let Some((page, total)) = self.book_page() {
+                    let _ = write!(
+                        pbuf,
+                        "Ch {}/{}  p. {} / {}",
+                        self.epub.chapter + 1,
+                        self.epub.chapter_count(),
+                        page + 1,
+                        total
+                    );
+                } else if self.pg.fully_indexed {
+                    let _ = write!(
+                        pbuf,
+                        "Ch {}/{}  Page {}/{}",
+                        self.epub.chapter + 1,
+                        self.epub.chapter_count(),
+                        self.pg.page + 1,
+                        self.pg.total_pages
+                    );
+                } else {
+                    let _ = write!(
+                        pbuf,
+                        "Ch {}/{}  Page {}",
+                        self.epub.chapter + 1,
+                        self.epub.chapter_count(),
+                        self.pg.page + 1
+                    );
+                }
+            } else if self.pg.fully_indexed {
+                let _ = write!(pbuf, "Page {}/{}", self.pg.page + 1, self.pg.total_pages);
+            } else {
+                let _ = write!(
+                    pbuf,
+                    "Page {}  ({}%)",
+                    self.pg.page + 1,
+                    self.progress_pct()
+                );
+            }
+
+            self.position_overlay
+                .to_rect()
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(strip)
+                .unwrap();
+            let text = pbuf.as_str();
+            if let Some(f) = cf {
+                f.draw_aligned(
+                    strip,
+                    self.position_overlay,
+                    text,
+                    Alignment::Center,
+                    BinaryColor::Off,
+                );
+            } else {
+                let tw = text.len() as u32 * 9;
+                let pos = Alignment::Center.position(self.position_overlay, Size::new(tw, 18));
+                let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::Off);
+                Text::new(text, Point::new(pos.x, pos.y + 18), style)
+                    .draw(strip)
+                    .unwrap();
+            }
+        }
+    }
+
     fn display_name(&self) -> &str {
         if self.title_len > 0 {
             core::str::from_utf8(&self.title[..self.title_len as usize]).unwrap_or(self.name())
@@ -713,8 +1315,14 @@ impl ReaderApp {
     }
 
     fn progress_pct(&self) -> u8 {
-        if self.is_epub && !self.epub.spine.is_empty() {
-            let spine_len = self.epub.spine.len() as u64;
+        if self.fixed_layout() {
+            return ((self.pg.page + 1) * 100 / self.pg.total_pages.max(1)) as u8;
+        }
+        if let Some((page, total)) = self.book_page() {
+            return ((page + 1) * 100 / total) as u8;
+        }
+        if self.is_epub && self.epub.chapter_count() > 0 {
+            let spine_len = self.epub.chapter_count() as u64;
             let ch = self.epub.chapter as u64;
 
             if ch + 1 >= spine_len
@@ -727,7 +1335,7 @@ impl ReaderApp {
             let in_ch = if self.file_size == 0 {
                 0u64
             } else {
-                let pos = self.pg.offsets[self.pg.page] as u64;
+                let pos = self.byte_offset() as u64;
                 let size = self.file_size as u64;
                 ((pos * 100) / size).min(100)
             };
@@ -742,7 +1350,7 @@ impl ReaderApp {
         if self.pg.fully_indexed && self.pg.page + 1 >= self.pg.total_pages {
             return 100;
         }
-        let pos = self.pg.offsets[self.pg.page] as u64;
+        let pos = self.byte_offset() as u64;
         let size = self.file_size as u64;
         ((pos * 100) / size).min(100) as u8
     }
@@ -786,6 +1394,17 @@ pub(super) fn extract_zip_entry(
     })
 }
 
+// a bar t pixels thick just under the baseline, from x0 to x1
+fn underline(strip: &mut StripBuffer, x0: i32, x1: i32, baseline: i32, t: u32) {
+    Rectangle::new(
+        Point::new(x0, baseline + 2),
+        Size::new((x1 - x0).max(0) as u32, t),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(strip)
+    .unwrap();
+}
+
 fn draw_chrome_text(
     strip: &mut StripBuffer,
     region: Region,
@@ -832,8 +1451,30 @@ impl App<AppId> for ReaderApp {
         self.epub.ch_cached = [false; cache::MAX_CACHE_CHAPTERS];
         self.epub.img_scan_wrapped = false;
         self.epub.skip_large_img = false;
-
-        self.is_epub = epub::is_epub_filename(self.name());
+        self.epub.cover = None;
+
+        self.is_xtc = xtc::is_xtc_filename(self.name());
+        self.is_cbz = cbz::is_cbz_filename(self.name());
+        self.is_fb2 = fb2::is_fb2_filename(self.name());
+        self.is_html = html::is_html_filename(self.name());
+        self.is_epub = epub::is_epub_filename(self.name()) || self.is_fb2 || self.is_html;
+        self.epub.sections = 0;
+        self.is_markdown = markdown::is_markdown_filename(self.name());
+        if self.is_markdown {
+            self.text_cache = markdown::md_cache_name(self.name());
+        }
+        self.pidx = PageIndex::new();
+        self.bp = BookPages::new();
+        self.links = Links::new();
+        self.chap = TxtChapters::new();
+        self.epub.toc = None;
+        self.txt_converted = false;
+        self.text_key = [0u8; textcache::KEY_LEN];
+        self.text_conv = None;
+        self.encoding = Encoding::Utf8;
+        self.enc_choice = 0;
+        self.enc_dirty = false;
+        self.reencode_from = 0;
         self.rebuild_quick_actions();
         self.apply_theme_layout();
         self.reset_paging();
@@ -844,25 +1485,42 @@ impl App<AppId> for ReaderApp {
         self.show_position = false;
         self.defer_image_decode = true;
         self.goto_last_page = false;
+        self.goto_page = None;
         self.restore_offset = None;
+        self.missing.clear();
 
         self.apply_font_metrics();
+        self.note_layout();
 
         self.state = State::NeedBookmark;
 
         log::info!("reader: opening {}", self.name());
 
         ctx.set_loading(LOADING_REGION, "Opening", 0);
-        ctx.mark_dirty(PAGE_REGION);
+        ctx.mark_dirty(self.page_region);
     }
 
     fn on_exit(&mut self) {
+        if self.missing.total() > 0 {
+            log::info!(
+                "font: {} needed {} glyphs no font covers:",
+                self.name(),
+                self.missing.total()
+            );
+            for (cp, count) in self.missing.iter() {
+                log::info!("font:   U+{:04X} x{}", cp, count);
+            }
+        }
+
         // Cancel any in-flight background cache work so the worker
         // doesn't write stale results after we switch books.
-        if self.is_epub {
+        if self.is_epub || self.is_cbz {
             work_queue::reset();
             self.epub.bg_cache = BgCacheState::Idle;
         }
+        if self.is_cbz {
+            self.cbz_close();
+        }
 
         self.pg.line_count = 0;
         self.pg.buf_len = 0;
@@ -872,9 +1530,13 @@ impl App<AppId> for ReaderApp {
         self.show_position = false;
         self.epub.ch_cache = Vec::new();
         self.page_img = None;
+        self.pidx = PageIndex::new();
+        self.bp = BookPages::new();
+        self.links = Links::new();
+        self.chap = TxtChapters::new();
 
+        self.epub.toc = None;
         if self.is_epub {
-            self.epub.toc = None;
             self.epub.toc_source = None;
         }
     }
@@ -897,15 +1559,25 @@ impl App<AppId> for ReaderApp {
 
         let font_changed = self.book_font_size_idx != self.applied_font_idx;
         self.apply_font_metrics();
-        if font_changed {
+        // orientation or column changes rewrap the chapter at a new
+        // width; land back on the page holding the same text
+        let rewrap = self.col_w != self.laid_out_w || self.columns != self.laid_out_cols;
+        self.note_layout();
+        if (font_changed || rewrap) && !self.fixed_layout() {
+            if rewrap && self.state == State::Ready {
+                let off = self.byte_offset();
+                self.restore_offset = if off > 0 { Some(off) } else { None };
+            }
             self.reset_paging();
             if self.is_epub && self.epub.chapters_cached {
                 self.state = State::NeedIndex;
             } else {
                 self.state = State::NeedPage;
             }
+        } else if self.cbz_needs_reload() && self.state == State::Ready {
+            self.state = State::NeedPage;
         }
-        ctx.mark_dirty(PAGE_REGION);
+        ctx.mark_dirty(self.page_region);
     }
 
     async fn background(&mut self, ctx: &mut AppContext, k: &mut KernelHandle<'_>) {
@@ -916,7 +1588,14 @@ impl App<AppId> for ReaderApp {
 
                     let _ = k.write_app_data(RECENT_FILE, &self.filename[..self.filename_len]);
 
-                    if self.is_epub {
+                    if self.fixed_layout() {
+                        self.state = State::NeedInit;
+                        ctx.set_loading(LOADING_REGION, "Loading", 30);
+                    } else if self.is_fb2 || self.is_html {
+                        self.goto_last_page = false;
+                        self.state = State::NeedCache;
+                        ctx.set_loading(LOADING_REGION, "Converting", 20);
+                    } else if self.is_epub {
                         self.epub.zip.clear();
                         self.epub.meta = EpubMeta::new();
                         self.epub.spine = EpubSpine::new();
@@ -924,13 +1603,38 @@ impl App<AppId> for ReaderApp {
                         self.goto_last_page = false;
                         self.state = State::NeedInit;
                         ctx.set_loading(LOADING_REGION, "Loading", 10);
+                    } else if self.is_markdown {
+                        self.state = State::NeedCache;
+                        ctx.set_loading(LOADING_REGION, "Converting", 30);
                     } else {
-                        self.state = State::NeedPage;
-                        ctx.set_loading(LOADING_REGION, "Loading", 50);
+                        self.state = State::NeedCache;
+                        ctx.set_loading(LOADING_REGION, "Loading", 40);
                     }
                     continue;
                 }
 
+                State::NeedInit if self.is_xtc => match self.xtc_open(k) {
+                    Ok(()) => {
+                        self.state = State::NeedPage;
+                        ctx.set_loading(LOADING_REGION, "Loading page", 80);
+                    }
+                    Err(e) => {
+                        log::info!("reader: xtc open failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                State::NeedInit if self.is_cbz => match self.cbz_open(k) {
+                    Ok(()) => {
+                        self.state = State::NeedPage;
+                        ctx.set_loading(LOADING_REGION, "Loading page", 80);
+                    }
+                    Err(e) => {
+                        log::info!("reader: cbz open failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
                 State::NeedInit => {
                     let (nb, nl) = self.name_copy();
                     let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
@@ -949,7 +1653,7 @@ impl App<AppId> for ReaderApp {
                 State::NeedOpf => match self.epub_init_opf(k) {
                     Ok(()) => {
                         // clamp restored chapter to valid spine range
-                        let spine_len = self.epub.spine.len();
+                        let spine_len = self.epub.chapter_count();
                         if spine_len > 0 && self.epub.chapter as usize >= spine_len {
                             self.epub.chapter = (spine_len - 1) as u16;
                         }
@@ -991,7 +1695,16 @@ impl App<AppId> for ReaderApp {
                                     &mut toc,
                                 );
                                 log::info!("epub: TOC has {} entries", toc.len());
-                                self.epub.toc = Some(toc);
+                                let mut entries = Toc::from_epub(&toc);
+                                let links = toc::entry_links(&toc_data);
+                                if !entries.set_links(&links, toc.len()) {
+                                    log::warn!(
+                                        "epub: TOC nesting not matched ({} vs {}), flat",
+                                        links.len(),
+                                        toc.len()
+                                    );
+                                }
+                                self.epub.toc = Some(entries);
                             }
                             Err(_e) => {
                                 log::warn!("epub: failed to read TOC");
@@ -1003,6 +1716,56 @@ impl App<AppId> for ReaderApp {
                     ctx.set_loading(LOADING_REGION, "Caching", 55);
                 }
 
+                State::NeedCache if self.is_fb2 => match self.fb2_prepare(k) {
+                    Ok(()) => {
+                        self.rebuild_quick_actions();
+                        self.state = State::NeedIndex;
+                        ctx.set_loading(LOADING_REGION, "Indexing", 75);
+                    }
+                    Err(e) => {
+                        log::info!("reader: fb2 open failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                State::NeedCache if self.is_html => match self.html_prepare(k) {
+                    Ok(()) => {
+                        self.rebuild_quick_actions();
+                        self.state = State::NeedIndex;
+                        ctx.set_loading(LOADING_REGION, "Indexing", 75);
+                    }
+                    Err(e) => {
+                        log::info!("reader: html open failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                State::NeedCache if self.is_markdown => match self.md_prepare(k) {
+                    Ok(true) => {
+                        self.state = State::NeedPage;
+                        ctx.set_loading(LOADING_REGION, "Loading page", 90);
+                    }
+                    // still converting; more next tick
+                    Ok(false) => {}
+                    Err(e) => {
+                        log::info!("reader: markdown convert failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                State::NeedCache if !self.is_epub => match self.txt_prepare(k) {
+                    Ok(true) => {
+                        self.state = State::NeedPage;
+                        ctx.set_loading(LOADING_REGION, "Loading page", 90);
+                    }
+                    // still converting; more next tick
+                    Ok(false) => {}
+                    Err(e) => {
+                        log::info!("reader: txt open failed: {}", e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
                 State::NeedCache => match self.epub.check_cache(k, &mut self.pg.buf) {
                     Ok(true) => {
                         self.state = State::NeedIndex;
@@ -1045,6 +1808,7 @@ impl App<AppId> for ReaderApp {
                 },
 
                 State::NeedIndex => {
+                    self.clean_next = true;
                     // ensure the target chapter is cached before
                     // indexing (it may not be if background caching
                     // hasn't reached it yet)
@@ -1065,20 +1829,26 @@ impl App<AppId> for ReaderApp {
 
                     let want_last = self.goto_last_page;
                     self.goto_last_page = false;
+                    let target = if want_last {
+                        Some(usize::MAX)
+                    } else {
+                        self.goto_page.take()
+                    };
 
                     self.epub_index_chapter();
 
                     if self.is_epub && self.epub.try_cache_chapter(k) {
+                        self.paged.sync(k);
                         self.preindex_all_pages();
                     }
 
-                    if want_last {
-                        match self.scan_to_last_page(k) {
+                    if let Some(target) = target {
+                        match self.scan_to_page(k, target) {
                             Ok(()) => {
                                 self.defer_image_decode = false;
                                 self.state = State::Ready;
                                 ctx.clear_loading();
-                                ctx.mark_dirty(PAGE_REGION);
+                                self.mark_page_shown(ctx);
                             }
                             Err(e) => self.enter_error(ctx, e),
                         }
@@ -1088,9 +1858,48 @@ impl App<AppId> for ReaderApp {
                     }
                 }
 
+                State::NeedPage if self.is_xtc => match self.xtc_load_page(k) {
+                    Ok(()) => {
+                        self.state = State::Ready;
+                        ctx.clear_loading();
+                        self.mark_page_shown(ctx);
+                    }
+                    Err(e) => {
+                        log::info!("reader: xtc page {} failed: {}", self.pg.page, e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                State::NeedPage if self.is_cbz => match self.cbz_load_page(k) {
+                    Ok(true) => {
+                        self.state = State::Ready;
+                        ctx.clear_loading();
+                        self.mark_page_shown(ctx);
+                    }
+                    // still on the work queue; poll again next tick
+                    Ok(false) => {}
+                    Err(e) => {
+                        log::info!("reader: cbz page {} failed: {}", self.pg.page, e);
+                        self.enter_error(ctx, e);
+                    }
+                },
+
+                // read ahead only once the shown page has been drawn
+                State::Ready if self.is_cbz => self.cbz_prefetch(k),
+
+                State::NeedLink => {
+                    if let Err(e) = self.link_follow(k) {
+                        log::warn!("links: cannot follow: {}", e);
+                        self.state = State::Ready;
+                    }
+                    ctx.mark_dirty(self.page_region);
+                }
+
                 State::NeedPage => {
                     if let Some(target_off) = self.restore_offset.take() {
-                        self.pg.page = 0;
+                        // an index jumps straight to the page; without
+                        // one the pages are wrapped up to it
+                        self.pg.page = self.index_seek(k, target_off);
                         loop {
                             match self.load_and_prefetch(k) {
                                 Ok(()) => {}
@@ -1102,7 +1911,7 @@ impl App<AppId> for ReaderApp {
                             if self.pg.page + 1 >= self.pg.total_pages {
                                 break;
                             }
-                            if self.pg.offsets[self.pg.page + 1] > target_off {
+                            if self.pg.offset(self.pg.page + 1) > target_off {
                                 break;
                             }
                             self.pg.page += 1;
@@ -1111,7 +1920,7 @@ impl App<AppId> for ReaderApp {
                             self.defer_image_decode = false;
                             self.state = State::Ready;
                             ctx.clear_loading();
-                            ctx.mark_dirty(PAGE_REGION);
+                            self.mark_page_shown(ctx);
                         }
                     } else {
                         match self.load_and_prefetch(k) {
@@ -1119,7 +1928,7 @@ impl App<AppId> for ReaderApp {
                                 self.defer_image_decode = false;
                                 self.state = State::Ready;
                                 ctx.clear_loading();
-                                ctx.mark_dirty(PAGE_REGION);
+                                self.mark_page_shown(ctx);
                             }
                             Err(e) => {
                                 log::info!("reader: load failed: {}", e);
@@ -1134,6 +1943,35 @@ impl App<AppId> for ReaderApp {
             break;
         }
 
+        // plain text books look for chapter headings, then find their
+        // remaining pages a few at a time while the reader sits on a page
+        if self.state == State::Ready && self.chapters_pending() {
+            if self.chapters_step(k) {
+                self.rebuild_quick_actions();
+            }
+        } else if self.state == State::Ready && self.index_pending() && self.index_step(k) {
+            ctx.mark_dirty(self.status_region);
+        }
+
+        // epub toc entries that point into the open chapter get their
+        // offsets in it, and its links are listed
+        if self.state == State::Ready && self.toc_anchors_pending() {
+            self.toc_anchors_step(k);
+        } else if self.state == State::Ready && self.links_pending() {
+            self.links_step(k);
+            self.rebuild_quick_actions();
+        }
+
+        // epubs count the pages of every chapter once caching is done
+        if self.state == State::Ready
+            && self.epub.bg_cache == BgCacheState::Idle
+            && self.book_pages_pending()
+            && self.book_pages_step(k)
+        {
+            self.rebuild_quick_actions();
+            ctx.mark_dirty(self.status_region);
+        }
+
         // background caching; runs whenever the page content is
         // settled and there is work to do. NeedIndex is included so
         // adjacent-chapter caching can overlap with page indexing
@@ -1142,7 +1980,12 @@ impl App<AppId> for ReaderApp {
         // bg_cache_step is interruptible by user input.
         if matches!(
             self.state,
-            State::Ready | State::ShowToc | State::NeedIndex | State::NeedPage
+            State::Ready
+                | State::ShowToc
+                | State::SelectLink
+                | State::ShowNote
+                | State::NeedIndex
+                | State::NeedPage
         ) && self.epub.bg_cache != BgCacheState::Idle
         {
             // ensure caching indicator is visible (covers resume
@@ -1168,52 +2011,103 @@ impl App<AppId> for ReaderApp {
     }
 
     fn on_event(&mut self, event: ActionEvent, ctx: &mut AppContext) -> Transition {
+        match self.state {
+            State::SelectLink => {
+                match event {
+                    ActionEvent::Press(Action::Back) => {
+                        self.state = State::Ready;
+                        ctx.mark_dirty_fast(self.page_region);
+                    }
+                    ActionEvent::Press(Action::Next)
+                    | ActionEvent::Repeat(Action::Next)
+                    | ActionEvent::Press(Action::Prev)
+                    | ActionEvent::Repeat(Action::Prev) => {
+                        let forward = matches!(
+                            event,
+                            ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next)
+                        );
+                        self.links_step_selection(forward);
+                        ctx.mark_dirty_fast(self.page_region);
+                    }
+                    ActionEvent::Press(Action::Select) => self.state = State::NeedLink,
+                    _ => {}
+                }
+                return Transition::None;
+            }
+            State::ShowNote => {
+                if matches!(
+                    event,
+                    ActionEvent::Press(Action::Back) | ActionEvent::Press(Action::Select)
+                ) {
+                    self.links.free_note();
+                    self.state = State::Ready;
+                    ctx.mark_dirty(self.page_region);
+                }
+                return Transition::None;
+            }
+            State::NeedLink => return Transition::None,
+            _ => {}
+        }
+
         if self.state == State::ShowToc {
             match event {
                 ActionEvent::Press(Action::Back) => {
                     self.state = State::Ready;
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                     return Transition::None;
                 }
-                ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next) => {
-                    let len = self.epub.toc.as_ref().map_or(0, |t| t.len());
-                    if len > 0 {
-                        if self.epub.toc_selected + 1 < len {
-                            self.epub.toc_selected += 1;
-                        } else {
-                            self.epub.toc_selected = 0;
-                            self.epub.toc_scroll = 0;
-                        }
-                        let vis = (self.text_area_h / self.font_line_h) as usize;
-                        if self.epub.toc_selected >= self.epub.toc_scroll + vis {
-                            self.epub.toc_scroll = self.epub.toc_selected + 1 - vis;
-                        }
-                        ctx.mark_dirty(PAGE_REGION);
+                ActionEvent::Press(Action::Next)
+                | ActionEvent::Repeat(Action::Next)
+                | ActionEvent::Press(Action::Prev)
+                | ActionEvent::Repeat(Action::Prev) => {
+                    let forward = matches!(
+                        event,
+                        ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next)
+                    );
+                    if let Some(toc) = self.epub.toc.as_ref() {
+                        self.epub.toc_selected = toc.step(self.epub.toc_selected, forward);
+                        self.toc_keep_visible();
+                        // selection move only; the fast waveform is enough
+                        ctx.mark_dirty_fast(self.page_region);
                     }
                     return Transition::None;
                 }
-                ActionEvent::Press(Action::Prev) | ActionEvent::Repeat(Action::Prev) => {
-                    let len = self.epub.toc.as_ref().map_or(0, |t| t.len());
-                    if len > 0 {
-                        if self.epub.toc_selected > 0 {
-                            self.epub.toc_selected -= 1;
-                        } else {
-                            self.epub.toc_selected = len - 1;
-                            let vis = (self.text_area_h / self.font_line_h) as usize;
-                            if self.epub.toc_selected >= vis {
-                                self.epub.toc_scroll = self.epub.toc_selected + 1 - vis;
-                            }
-                        }
-                        if self.epub.toc_selected < self.epub.toc_scroll {
-                            self.epub.toc_scroll = self.epub.toc_selected;
-                        }
-                        ctx.mark_dirty(PAGE_REGION);
+                // NextJump opens a folded entry, PrevJump folds the
+                // entry or the one it sits under
+                ActionEvent::Press(Action::NextJump)
+                    if self
+                        .epub
+                        .toc
+                        .as_ref()
+                        .is_some_and(|t| t.is_collapsed(self.epub.toc_selected)) =>
+                {
+                    if let Some(toc) = self.epub.toc.as_mut() {
+                        toc.expand(self.epub.toc_selected);
+                    }
+                    self.toc_keep_visible();
+                    ctx.mark_dirty_fast(self.page_region);
+                    return Transition::None;
+                }
+                ActionEvent::Press(Action::PrevJump) => {
+                    if let Some(toc) = self.epub.toc.as_mut() {
+                        self.epub.toc_selected = toc.collapse(self.epub.toc_selected);
                     }
+                    self.toc_keep_visible();
+                    ctx.mark_dirty_fast(self.page_region);
                     return Transition::None;
                 }
                 ActionEvent::Press(Action::Select) | ActionEvent::Press(Action::NextJump) => {
                     let entry = &self.epub.toc.as_ref().unwrap().entries[self.epub.toc_selected];
-                    if entry.spine_idx != 0xFFFF {
+                    if self.is_plain_text() {
+                        let offset = entry.offset;
+                        log::info!(
+                            "toc: jumping to \"{}\" -> offset {}",
+                            entry.title_str(),
+                            offset
+                        );
+                        self.txt_goto(offset);
+                        ctx.mark_dirty(self.page_region);
+                    } else if entry.spine_idx != 0xFFFF {
                         log::info!(
                             "toc: jumping to \"{}\" -> spine {}",
                             entry.title_str(),
@@ -1223,14 +2117,14 @@ impl App<AppId> for ReaderApp {
                         self.pg.page = 0;
                         self.goto_last_page = false;
                         self.state = State::NeedIndex;
-                        ctx.mark_dirty(PAGE_REGION);
+                        ctx.mark_dirty(self.page_region);
                     } else {
                         log::warn!(
                             "toc: entry \"{}\" unresolved (spine_idx=0xFFFF), ignoring",
                             entry.title_str()
                         );
                         self.state = State::Ready;
-                        ctx.mark_dirty(PAGE_REGION);
+                        ctx.mark_dirty(self.page_region);
                     }
                     return Transition::None;
                 }
@@ -1239,6 +2133,11 @@ impl App<AppId> for ReaderApp {
         }
 
         match event {
+            // back from a followed link before leaving the book
+            ActionEvent::Press(Action::Back) if self.state == State::Ready && self.link_back() => {
+                ctx.mark_dirty(self.page_region);
+                Transition::None
+            }
             ActionEvent::Press(Action::Back) => Transition::Pop,
             ActionEvent::LongPress(Action::Back) => Transition::Home,
 
@@ -1247,7 +2146,7 @@ impl App<AppId> for ReaderApp {
                     self.show_position = true;
                 }
                 if self.page_forward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
@@ -1256,7 +2155,7 @@ impl App<AppId> for ReaderApp {
                     self.show_position = true;
                 }
                 if self.page_backward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
@@ -1264,35 +2163,35 @@ impl App<AppId> for ReaderApp {
             ActionEvent::Release(Action::Next) | ActionEvent::Release(Action::Prev) => {
                 if self.show_position {
                     self.show_position = false;
-                    ctx.mark_dirty(POSITION_OVERLAY);
+                    ctx.mark_dirty(self.position_overlay);
                 }
                 Transition::None
             }
 
             ActionEvent::Press(Action::Next) | ActionEvent::Repeat(Action::Next) => {
                 if self.page_forward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
 
             ActionEvent::Press(Action::Prev) | ActionEvent::Repeat(Action::Prev) => {
                 if self.page_backward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
 
             ActionEvent::Press(Action::NextJump) | ActionEvent::Repeat(Action::NextJump) => {
                 if self.jump_forward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
 
             ActionEvent::Press(Action::PrevJump) | ActionEvent::Repeat(Action::PrevJump) => {
                 if self.jump_backward() {
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
@@ -1301,7 +2200,8 @@ impl App<AppId> for ReaderApp {
             ActionEvent::LongPress(Action::NextJump) => {
                 if self.state == State::Ready && self.pg.total_pages > 0 {
                     self.pg.page = self.pg.total_pages - 1;
-                    ctx.mark_dirty(PAGE_REGION);
+                    self.state = State::NeedPage;
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
@@ -1310,7 +2210,8 @@ impl App<AppId> for ReaderApp {
             ActionEvent::LongPress(Action::PrevJump) => {
                 if self.state == State::Ready {
                     self.pg.page = 0;
-                    ctx.mark_dirty(PAGE_REGION);
+                    self.state = State::NeedPage;
+                    ctx.mark_dirty(self.page_region);
                 }
                 Transition::None
             }
@@ -1332,33 +2233,36 @@ impl App<AppId> for ReaderApp {
                     self.epub.chapter -= 1;
                     self.goto_last_page = false;
                     self.state = State::NeedIndex;
+                } else if let Some(offset) = self.txt_chapter_target(false) {
+                    self.txt_goto(offset);
                 }
             }
             QA_NEXT_CHAPTER => {
-                if self.is_epub && (self.epub.chapter as usize + 1) < self.epub.spine.len() {
+                if self.is_epub && (self.epub.chapter as usize + 1) < self.epub.chapter_count() {
                     self.epub.chapter += 1;
                     self.goto_last_page = false;
                     self.state = State::NeedIndex;
+                } else if let Some(offset) = self.txt_chapter_target(true) {
+                    self.txt_goto(offset);
                 }
             }
             QA_TOC => {
-                if self.is_epub && self.epub.toc.as_ref().map_or(false, |t| !t.is_empty()) {
-                    let toc = self.epub.toc.as_ref().unwrap();
+                if self.epub.toc.as_ref().map_or(false, |t| !t.is_empty()) {
+                    // open on the current entry, everything else folded
+                    let current = self.toc_current();
+                    let toc = self.epub.toc.as_mut().unwrap();
                     log::info!("toc: opening ({} entries)", toc.len());
-                    self.epub.toc_selected = 0;
+                    toc.fold_to(current);
+                    self.epub.toc_selected = current.unwrap_or(0);
                     self.epub.toc_scroll = 0;
-                    for i in 0..toc.len() {
-                        if toc.entries[i].spine_idx == self.epub.chapter {
-                            self.epub.toc_selected = i;
-                            let vis = (self.text_area_h / self.font_line_h) as usize;
-                            if self.epub.toc_selected >= vis {
-                                self.epub.toc_scroll = self.epub.toc_selected + 1 - vis;
-                            }
-                            break;
-                        }
-                    }
+                    self.toc_keep_visible();
                     self.state = State::ShowToc;
-                    ctx.mark_dirty(PAGE_REGION);
+                    ctx.mark_dirty(self.page_region);
+                }
+            }
+            QA_LINKS => {
+                if self.state == State::Ready && self.links_select() {
+                    ctx.mark_dirty(self.page_region);
                 }
             }
             _ => {}
@@ -1366,9 +2270,31 @@ impl App<AppId> for ReaderApp {
     }
 
     fn on_quick_cycle_update(&mut self, id: u8, value: u8, _ctx: &mut AppContext) {
+        // every cycle is reported when the menu closes; only move when
+        // the value was changed away from the current page
+        if id == QA_GOTO
+            && self.state == State::Ready
+            && value != self.goto_step()
+            && self.goto(value)
+        {
+            return;
+        }
+        if id == QA_ENCODING && value != self.enc_choice && self.is_plain_text() {
+            self.enc_choice = value;
+            self.enc_dirty = true;
+            if self.state == State::Ready {
+                let off = self.byte_offset();
+                self.restore_offset = if off > 0 { Some(off) } else { None };
+                self.reencode_from = self.file_size;
+                self.reset_paging();
+                self.state = State::NeedCache;
+            }
+            return;
+        }
         if id == QA_FONT_SIZE {
             self.book_font_size_idx = value;
             self.apply_font_metrics();
+            self.note_layout();
             if self.state == State::Ready {
                 if self.is_epub && self.epub.chapters_cached {
                     self.state = State::NeedIndex;
@@ -1397,6 +2323,11 @@ impl App<AppId> for ReaderApp {
     }
 
     fn draw(&self, strip: &mut StripBuffer) {
+        if self.fixed_layout() && self.state == State::Ready {
+            self.draw_fixed_page(strip);
+            return;
+        }
+
         let cf = self.chrome_font;
 
         draw_chrome_text(
@@ -1407,17 +2338,32 @@ impl App<AppId> for ReaderApp {
             cf,
         );
 
-        if self.state == State::ShowToc {
-            draw_chrome_text(strip, STATUS_REGION, "Contents", Alignment::CenterRight, cf);
-        } else if self.is_epub && !self.epub.spine.is_empty() {
+        let overlay = match self.state {
+            State::ShowToc => Some("Contents"),
+            State::SelectLink => Some("Links"),
+            State::ShowNote => Some("Note"),
+            _ => None,
+        };
+        if let Some(label) = overlay {
+            draw_chrome_text(strip, self.status_region, label, Alignment::CenterRight, cf);
+        } else if self.is_epub && self.epub.chapter_count() > 0 {
             let mut sbuf = StackFmt::<40>::new();
-            if self.epub.spine.len() > 1 {
-                if self.pg.fully_indexed {
+            if self.epub.chapter_count() > 1 {
+                if let Some((page, total)) = self.book_page() {
+                    let _ = write!(
+                        sbuf,
+                        "Ch{}/{} p.{}/{}",
+                        self.epub.chapter + 1,
+                        self.epub.chapter_count(),
+                        page + 1,
+                        total
+                    );
+                } else if self.pg.fully_indexed {
                     let _ = write!(
                         sbuf,
                         "Ch{}/{} {}/{}",
                         self.epub.chapter + 1,
-                        self.epub.spine.len(),
+                        self.epub.chapter_count(),
                         self.pg.page + 1,
                         self.pg.total_pages
                     );
@@ -1426,7 +2372,7 @@ impl App<AppId> for ReaderApp {
                         sbuf,
                         "Ch{}/{} p{}",
                         self.epub.chapter + 1,
-                        self.epub.spine.len(),
+                        self.epub.chapter_count(),
                         self.pg.page + 1
                     );
                 }
@@ -1437,7 +2383,7 @@ impl App<AppId> for ReaderApp {
             }
             if self.epub.bg_cache != BgCacheState::Idle {
                 let cached = self.cached_chapter_count();
-                let total = self.epub.spine.len();
+                let total = self.epub.chapter_count();
                 if cached < total {
                     let _ = write!(sbuf, " [{}/{}]", cached, total);
                 } else if self.epub.img_found_count > 0 {
@@ -1452,7 +2398,7 @@ impl App<AppId> for ReaderApp {
             }
             draw_chrome_text(
                 strip,
-                STATUS_REGION,
+                self.status_region,
                 sbuf.as_str(),
                 Alignment::CenterRight,
                 cf,
@@ -1466,7 +2412,7 @@ impl App<AppId> for ReaderApp {
             }
             draw_chrome_text(
                 strip,
-                STATUS_REGION,
+                self.status_region,
                 sbuf.as_str(),
                 Alignment::CenterRight,
                 cf,
@@ -1488,24 +2434,41 @@ impl App<AppId> for ReaderApp {
 
         // loading states: the kernel loading indicator (drawn by
         // AppManager) handles feedback text; nothing else to draw
-        if self.state != State::Ready && self.state != State::Error && self.state != State::ShowToc
-        {
+        if !matches!(
+            self.state,
+            State::Ready | State::Error | State::ShowToc | State::SelectLink | State::ShowNote
+        ) {
             return;
         }
 
         if self.state == State::ShowToc {
             let toc_ref = self.epub.toc.as_ref().unwrap();
-            let toc_len = toc_ref.len();
+            // the current entry, or the folded entry hiding it
+            let current = self.toc_current();
+            let marked = |idx: usize| {
+                current.is_some_and(|c| {
+                    c == idx || (toc_ref.is_collapsed(idx) && toc_ref.in_subtree(idx, c))
+                })
+            };
+            // + folded, - open, nothing for entries without children
+            let fold = |idx: usize| {
+                if toc_ref.is_collapsed(idx) {
+                    Some('+')
+                } else if toc_ref.has_children(idx) {
+                    Some('-')
+                } else {
+                    None
+                }
+            };
+            let rows = self.toc_rows();
+            let listed = toc_ref.visible().skip(self.epub.toc_scroll).take(rows);
             let tx = self.text_margin as i32;
             let ty = self.text_y as i32;
             if self.fonts.is_some() {
                 let font = fonts::body_font(self.book_font_size_idx);
                 let line_h = font.line_height as i32;
                 let ascent = font.ascent as i32;
-                let vis_max = (self.text_area_h / font.line_height) as usize;
-                let visible = vis_max.min(toc_len.saturating_sub(self.epub.toc_scroll));
-                for i in 0..visible {
-                    let idx = self.epub.toc_scroll + i;
+                for (i, idx) in listed.enumerate() {
                     let entry = &toc_ref.entries[idx];
                     let y_top = ty + i as i32 * line_h;
                     let baseline = y_top + ascent;
@@ -1514,7 +2477,7 @@ impl App<AppId> for ReaderApp {
                     if selected {
                         Rectangle::new(
                             Point::new(0, y_top),
-                            Size::new(SCREEN_W as u32, line_h as u32),
+                            Size::new(self.page_region.w as u32, line_h as u32),
                         )
                         .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
                         .draw(strip)
@@ -1526,19 +2489,20 @@ impl App<AppId> for ReaderApp {
                     } else {
                         BinaryColor::On
                     };
-                    let mut cx = tx;
-                    if entry.spine_idx != 0xFFFF && entry.spine_idx == self.epub.chapter {
+                    let mut cx = tx + entry.depth as i32 * INDENT_PX as i32;
+                    if marked(idx) {
                         cx += font.draw_char_fg(strip, '>', fg, cx, baseline) as i32;
                         cx += font.draw_char_fg(strip, ' ', fg, cx, baseline) as i32;
                     }
+                    if let Some(c) = fold(idx) {
+                        cx += font.draw_char_fg(strip, c, fg, cx, baseline) as i32;
+                        cx += font.draw_char_fg(strip, ' ', fg, cx, baseline) as i32;
+                    }
                     font.draw_str_fg(strip, entry.title_str(), fg, cx, baseline);
                 }
             } else {
                 let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::On);
-                let vis_max = (self.text_area_h / LINE_H) as usize;
-                let visible = vis_max.min(toc_len.saturating_sub(self.epub.toc_scroll));
-                for i in 0..visible {
-                    let idx = self.epub.toc_scroll + i;
+                for (i, idx) in listed.enumerate() {
                     let entry = &toc_ref.entries[idx];
                     let y = ty + i as i32 * LINE_H as i32 + LINE_H as i32;
                     let marker = if idx == self.epub.toc_selected {
@@ -1549,7 +2513,15 @@ impl App<AppId> for ReaderApp {
                     Text::new(marker, Point::new(0, y), style)
                         .draw(strip)
                         .unwrap();
-                    Text::new(entry.title_str(), Point::new(tx, y), style)
+                    let mut x = tx + entry.depth as i32 * INDENT_PX as i32;
+                    if let Some(c) = fold(idx) {
+                        let mut cbuf = [0u8; 4];
+                        Text::new(c.encode_utf8(&mut cbuf), Point::new(x, y), style)
+                            .draw(strip)
+                            .unwrap();
+                        x += 2 * MONO_CHAR_W as i32;
+                    }
+                    Text::new(entry.title_str(), Point::new(x, y), style)
                         .draw(strip)
                         .unwrap();
                 }
@@ -1562,7 +2534,7 @@ impl App<AppId> for ReaderApp {
             let ascent = self.font_ascent as i32;
 
             // fullscreen image: centre in text area, skip normal line layout
-            if self.fullscreen_img {
+            if self.fullscreen_img && self.state != State::ShowNote {
                 if let Some(ref img) = self.page_img {
                     let img_x = self.text_margin as i32
                         + ((self.text_w as i32 - img.width as i32) / 2).max(0);
@@ -1581,20 +2553,34 @@ impl App<AppId> for ReaderApp {
                 }
             } else {
                 let mut img_rendered = false;
-                for i in 0..self.pg.line_count {
-                    let span = &self.pg.lines[i];
+                let max_lines = self.max_lines.max(1) as usize;
+                let (buf, lines) = self.shown_text();
+                // links on the page while one is being picked
+                let marks = if self.state == State::SelectLink {
+                    self.page_link_range()
+                } else {
+                    0..0
+                };
+                let page_start = self.byte_offset();
+                for (i, span) in lines.iter().enumerate() {
+                    // line slots fill column by column
+                    let row = (i % max_lines) as i32;
+                    let col_x = self.text_margin as i32
+                        + (i / max_lines) as i32 * (self.col_w + COLUMN_GUTTER) as i32;
+                    let col_end = (i / max_lines + 1) * max_lines;
 
                     if span.is_image() {
-                        if span.is_image_origin() && !img_rendered {
-                            let y_top = self.text_y as i32 + i as i32 * line_h;
+                        if span.is_image_origin() && !img_rendered && self.state != State::ShowNote
+                        {
+                            let y_top = self.text_y as i32 + row * line_h;
                             if let Some(ref img) = self.page_img {
-                                let img_x = self.text_margin as i32
-                                    + ((self.text_w as i32 - img.width as i32) / 2).max(0);
+                                let img_x =
+                                    col_x + ((self.col_w as i32 - img.width as i32) / 2).max(0);
 
                                 // count reserved image lines for vertical centering
                                 let mut img_line_count = 0i32;
-                                for j in i..self.pg.line_count {
-                                    if self.pg.lines[j].is_image() {
+                                for j in i..lines.len().min(col_end) {
+                                    if lines[j].is_image() {
                                         img_line_count += 1;
                                     } else {
                                         break;
@@ -1605,8 +2591,7 @@ impl App<AppId> for ReaderApp {
                                 // the image is already decoded at the correct
                                 // budget (inline or fullscreen); just clamp to
                                 // remaining vertical space as a safety net
-                                let space_below =
-                                    (self.text_area_h as i32 - i as i32 * line_h).max(0);
+                                let space_below = (self.text_area_h as i32 - row * line_h).max(0);
                                 let blit_h = (img.height as i32).min(space_below).max(0) as usize;
 
                                 // center vertically within reserved lines
@@ -1629,7 +2614,7 @@ impl App<AppId> for ReaderApp {
                                     strip,
                                     "[image]",
                                     fonts::Style::Italic,
-                                    self.text_margin as i32,
+                                    col_x,
                                     baseline,
                                 );
                             }
@@ -1639,54 +2624,27 @@ impl App<AppId> for ReaderApp {
 
                     let start = span.start as usize;
                     let end = start + span.len as usize;
-                    let baseline = self.text_y as i32 + i as i32 * line_h + ascent;
+                    let baseline = self.text_y as i32 + row * line_h + ascent;
                     let x_indent = INDENT_PX as i32 * span.indent as i32;
 
-                    let line = &self.pg.buf[start..end];
-                    let mut cx = self.text_margin as i32 + x_indent;
-                    let mut sty = span.style();
-                    let mut j = 0usize;
-                    while j < line.len() {
-                        let b = line[j];
-                        if b == MARKER && j + 1 < line.len() {
-                            sty = match line[j + 1] {
-                                BOLD_ON => fonts::Style::Bold,
-                                ITALIC_ON => fonts::Style::Italic,
-                                HEADING_ON => fonts::Style::Heading,
-                                BOLD_OFF | ITALIC_OFF | HEADING_OFF => fonts::Style::Regular,
-                                _ => sty,
-                            };
-                            j += 2;
-                            continue;
-                        }
-                        if b >= 0xC0 {
-                            let (ch, seq_len) = decode_utf8_char(line, j);
-                            cx += fs.draw_char(strip, ch, sty, cx, baseline) as i32;
-                            j += seq_len;
-                            continue;
-                        }
-                        if b >= 0x80 {
-                            // continuation byte mid-stream (already consumed
-                            // by a lead byte above, or stray), skip
-                            j += 1;
-                            continue;
-                        }
-                        if b < bitmap::FIRST_CHAR {
-                            j += 1;
-                            continue; // control char
-                        }
-                        cx += fs.draw_char(strip, b as char, sty, cx, baseline) as i32;
-                        j += 1;
-                    }
+                    self.draw_text_line(
+                        strip,
+                        fs,
+                        &buf[start..end],
+                        span.style(),
+                        col_x + x_indent,
+                        baseline,
+                        |j| self.link_mark(&marks, page_start + (start + j) as u32),
+                    );
                 }
             }
         } else {
             let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::On);
-            for i in 0..self.pg.line_count {
-                let span = self.pg.lines[i];
+            let (buf, lines) = self.shown_text();
+            for (i, span) in lines.iter().enumerate() {
                 let start = span.start as usize;
                 let end = start + span.len as usize;
-                let text = core::str::from_utf8(&self.pg.buf[start..end]).unwrap_or("");
+                let text = core::str::from_utf8(&buf[start..end]).unwrap_or("");
                 let y = self.text_y as i32 + i as i32 * LINE_H as i32 + LINE_H as i32;
                 Text::new(text, Point::new(self.text_margin as i32, y), style)
                     .draw(strip)
@@ -1694,63 +2652,6 @@ impl App<AppId> for ReaderApp {
             }
         }
 
-        if self.show_position
-            && self.state == State::Ready
-            && POSITION_OVERLAY.intersects(strip.logical_window())
-        {
-            let mut pbuf = StackFmt::<48>::new();
-            if self.is_epub && self.epub.spine.len() > 1 {
-                if self.pg.fully_indexed {
-                    let _ = write!(
-                        pbuf,
-                        "Ch {}/{}  Page {}/{}",
-                        self.epub.chapter + 1,
-                        self.epub.spine.len(),
-                        self.pg.page + 1,
-                        self.pg.total_pages
-                    );
-                } else {
-                    let _ = write!(
-                        pbuf,
-                        "Ch {}/{}  Page {}",
-                        self.epub.chapter + 1,
-                        self.epub.spine.len(),
-                        self.pg.page + 1
-                    );
-                }
-            } else if self.pg.fully_indexed {
-                let _ = write!(pbuf, "Page {}/{}", self.pg.page + 1, self.pg.total_pages);
-            } else {
-                let _ = write!(
-                    pbuf,
-                    "Page {}  ({}%)",
-                    self.pg.page + 1,
-                    self.progress_pct()
-                );
-            }
-
-            POSITION_OVERLAY
-                .to_rect()
-                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
-                .draw(strip)
-                .unwrap();
-            let text = pbuf.as_str();
-            if let Some(f) = cf {
-                f.draw_aligned(
-                    strip,
-                    POSITION_OVERLAY,
-                    text,
-                    Alignment::Center,
-                    BinaryColor::Off,
-                );
-            } else {
-                let tw = text.len() as u32 * 9;
-                let pos = Alignment::Center.position(POSITION_OVERLAY, Size::new(tw, 18));
-                let style = MonoTextStyle::new(&FONT_9X18, BinaryColor::Off);
-                Text::new(text, Point::new(pos.x, pos.y + 18), style)
-                    .draw(strip)
-                    .unwrap();
-            }
-        }
+        self.draw_position(strip);
     }
 }
@@ -0,0 +1,370 @@
+// persisted page index for plain text books (txt, markdown)
+//
+// page start offsets are kept in _PULP as XXXXXXXX.PGX: a 28-byte
+// header (magic, layout key, complete flag) and one u32 per page. the
+// key holds what wrapping depends on -- font size, theme, columns,
+// lines per page, column width, encoding, text size -- and the
+// source key from textcache.rs, so a book reopened under the same
+// layout resumes with its full page count. PageState keeps
+// MAX_PAGES offsets of it in memory and slides that window over the
+// file. pages found while reading are held in the window until
+// flushed; the rest of the book is indexed a few pages per tick in
+// the background
+
+use smol_epub::cache;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+
+use super::paging::{PageScan, STEP_PAGES};
+use super::textcache;
+use super::{LAYOUT_KEY_LEN, MAX_PAGES, ReaderApp, img_cache_name, img_cache_str};
+
+const MAGIC: &[u8; 4] = b"PGX2";
+const KEY_LEN: usize = 12 + textcache::KEY_LEN;
+const COMPLETE_AT: u32 = 4 + KEY_LEN as u32;
+const HEADER: u32 = COMPLETE_AT + 4;
+
+pub(super) struct PageIndex {
+    // an index file backs the page state of this book
+    pub(super) active: bool,
+    // checked against the current layout since the last reset_paging
+    pub(super) open: bool,
+    // a background step failed; pages are still found while reading
+    halted: bool,
+    file: [u8; 12],
+    // pages 0..saved are in the file, the rest only in the window
+    saved: usize,
+    // start of the last known page
+    tail: u32,
+    // background wrapping buffers, dropped once the book is indexed
+    scan: PageScan,
+}
+
+impl PageIndex {
+    pub(super) const fn new() -> Self {
+        Self {
+            active: false,
+            open: false,
+            halted: false,
+            file: [0u8; 12],
+            saved: 0,
+            tail: 0,
+            scan: PageScan::new(),
+        }
+    }
+}
+
+fn index_name(name: &str) -> [u8; 12] {
+    let mut n = img_cache_name(cache::fnv1a(name.as_bytes()));
+    n[9..].copy_from_slice(b"PGX");
+    n
+}
+
+impl ReaderApp {
+    #[inline]
+    pub(super) fn uses_page_index(&self) -> bool {
+        !self.is_epub && !self.fixed_layout()
+    }
+
+    // background indexing has pages left to find
+    #[inline]
+    pub(super) fn index_pending(&self) -> bool {
+        self.pidx.active && !self.pidx.halted && !self.pg.fully_indexed
+    }
+
+    fn index_key(&self) -> [u8; KEY_LEN] {
+        let mut key = [0u8; KEY_LEN];
+        key[..LAYOUT_KEY_LEN].copy_from_slice(&self.layout_key());
+        key[LAYOUT_KEY_LEN] = self.encoding.menu_value();
+        key[8..12].copy_from_slice(&self.file_size.to_le_bytes());
+        key[12..].copy_from_slice(&self.text_key);
+        key
+    }
+
+    // open the index on the first load after a reset and keep the
+    // current page and the one after it in the window
+    pub(super) fn index_sync(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        if !self.pidx.open {
+            self.index_open(k);
+        }
+        if !self.pidx.active {
+            return Ok(());
+        }
+        let page = self.pg.page;
+        let next_known = page + 1 < self.pg.total_pages;
+        if !self.pg.in_window(page) || (next_known && !self.pg.in_window(page + 1)) {
+            self.index_window(k, page)?;
+        }
+        Ok(())
+    }
+
+    fn index_open(&mut self, k: &mut KernelHandle<'_>) {
+        self.pidx.open = true;
+        self.pidx.active = false;
+        self.pidx.halted = false;
+        if self.file_size == 0 {
+            return;
+        }
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        self.pidx.file = index_name(name);
+        let key = self.index_key();
+
+        // a missing, stale or unreadable index is started over
+        let result = match self.index_load(k, &key) {
+            Ok(true) => Ok(()),
+            _ => self.index_create(k, &key),
+        };
+        if let Err(e) = result {
+            log::warn!("pageindex: cannot create index: {}", e);
+            return;
+        }
+        self.pidx.active = true;
+        log::info!(
+            "pageindex: {} pages{}",
+            self.pg.total_pages,
+            if self.pg.fully_indexed { "" } else { " so far" }
+        );
+    }
+
+    fn index_load(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        key: &[u8; KEY_LEN],
+    ) -> crate::error::Result<bool> {
+        let f = self.pidx.file;
+        let f_str = img_cache_str(&f);
+        let mut hdr = [0u8; HEADER as usize];
+        let n = k.read_cache_chunk(f_str, 0, &mut hdr)?;
+        if n < hdr.len() || &hdr[..4] != MAGIC || hdr[4..4 + KEY_LEN] != key[..] {
+            return Ok(false);
+        }
+        let saved = (k.cache_file_size(f_str)?.saturating_sub(HEADER) / 4) as usize;
+        if saved == 0 {
+            return Ok(false);
+        }
+        let mut last = [0u8; 4];
+        if k.read_cache_chunk(f_str, HEADER + (saved as u32 - 1) * 4, &mut last)? < 4 {
+            return Ok(false);
+        }
+
+        self.pidx.saved = saved;
+        self.pidx.tail = u32::from_le_bytes(last);
+        self.pg.total_pages = saved;
+        self.pg.fully_indexed = hdr[COMPLETE_AT as usize] == 1;
+        self.pg.page = self.pg.page.min(saved - 1);
+        self.index_window(k, self.pg.page)?;
+        Ok(true)
+    }
+
+    fn index_create(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        key: &[u8; KEY_LEN],
+    ) -> crate::error::Result<()> {
+        let f = self.pidx.file;
+        let f_str = img_cache_str(&f);
+        let mut hdr = [0u8; HEADER as usize];
+        hdr[..4].copy_from_slice(MAGIC);
+        hdr[4..4 + KEY_LEN].copy_from_slice(key);
+        k.write_cache(f_str, &hdr)?;
+        k.append_cache(f_str, &0u32.to_le_bytes())?;
+
+        self.pidx.saved = 1;
+        self.pidx.tail = 0;
+        self.pg.base = 0;
+        self.pg.offsets[0] = 0;
+        self.pg.total_pages = 1;
+        self.pg.page = 0;
+        self.pg.fully_indexed = false;
+        Ok(())
+    }
+
+    // write the pages only the window holds
+    fn index_flush(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let f = self.pidx.file;
+        let f_str = img_cache_str(&f);
+        let mut bytes = [0u8; 256];
+        while self.pidx.saved < self.pg.total_pages {
+            let from = self.pidx.saved;
+            let to = self.pg.total_pages.min(from + bytes.len() / 4);
+            for (i, p) in (from..to).enumerate() {
+                bytes[i * 4..i * 4 + 4].copy_from_slice(&self.pg.offset(p).to_le_bytes());
+            }
+            k.append_cache(f_str, &bytes[..(to - from) * 4])?;
+            self.pidx.saved = to;
+        }
+        Ok(())
+    }
+
+    // reload the window with page near its middle; read through its
+    // own buffer, as pg.buf may hold the page on screen
+    fn index_window(&mut self, k: &mut KernelHandle<'_>, page: usize) -> crate::error::Result<()> {
+        self.index_flush(k)?;
+        let f = self.pidx.file;
+        let f_str = img_cache_str(&f);
+        let base = page.saturating_sub(MAX_PAGES / 2);
+        let count = self.pidx.saved.saturating_sub(base).min(MAX_PAGES);
+        let mut bytes = [0u8; 256];
+        let mut i = 0;
+        while i < count {
+            let len = (count - i).min(bytes.len() / 4) * 4;
+            let at = HEADER + (base + i) as u32 * 4;
+            let n = k.read_cache_chunk(f_str, at, &mut bytes[..len])?;
+            if n < len {
+                return Err(Error::new(ErrorKind::ReadFailed, "pageindex: short read"));
+            }
+            for b in bytes[..len].chunks_exact(4) {
+                self.pg.offsets[i] = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                i += 1;
+            }
+        }
+        self.pg.base = base;
+        Ok(())
+    }
+
+    // a page found past the last known one; without an index the
+    // window is all there is
+    pub(super) fn push_page(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        offset: u32,
+    ) -> crate::error::Result<()> {
+        let p = self.pg.total_pages;
+        if !self.pg.in_window(p) {
+            if !self.pidx.active {
+                self.pg.fully_indexed = true;
+                return Ok(());
+            }
+            self.index_flush(k)?;
+            self.pg.offsets.copy_within(MAX_PAGES / 2.., 0);
+            self.pg.base += MAX_PAGES / 2;
+        }
+        self.pg.offsets[p - self.pg.base] = offset;
+        self.pg.total_pages += 1;
+        self.pidx.tail = offset;
+        Ok(())
+    }
+
+    // the last page is known: save the rest and mark the file complete
+    pub(super) fn index_done(&mut self, k: &mut KernelHandle<'_>) {
+        if !self.pidx.active {
+            return;
+        }
+        self.pidx.scan.free();
+        let f = self.pidx.file;
+        let result = self
+            .index_flush(k)
+            .and_then(|()| k.write_cache_at(img_cache_str(&f), COMPLETE_AT, &[1]));
+        match result {
+            Ok(()) => log::info!("pageindex: complete, {} pages", self.pg.total_pages),
+            Err(e) => log::warn!("pageindex: failed to save: {}", e),
+        }
+    }
+
+    // last page starting at or before offset; 0 without an index
+    pub(super) fn index_seek(&mut self, k: &mut KernelHandle<'_>, offset: u32) -> usize {
+        if !self.uses_page_index() {
+            return 0;
+        }
+        if let Err(e) = self.index_sync(k) {
+            log::warn!("pageindex: {}", e);
+            return 0;
+        }
+        if !self.pidx.active {
+            return 0;
+        }
+        let f = self.pidx.file;
+        let f_str = img_cache_str(&f);
+        let (mut lo, mut hi) = (0usize, self.pg.total_pages);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            let start = if self.pg.in_window(mid) {
+                self.pg.offset(mid)
+            } else {
+                let mut b = [0u8; 4];
+                match k.read_cache_chunk(f_str, HEADER + mid as u32 * 4, &mut b) {
+                    Ok(4) => u32::from_le_bytes(b),
+                    _ => return lo,
+                }
+            };
+            if start <= offset {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    // wrap a few pages past the last known one; true once the last
+    // page of the book has been found
+    pub(super) fn index_step(&mut self, k: &mut KernelHandle<'_>) -> bool {
+        match self.index_pages(k) {
+            Ok(done) => done,
+            Err(e) => {
+                log::warn!("pageindex: background indexing stopped: {}", e);
+                self.pidx.halted = true;
+                self.pidx.scan.free();
+                false
+            }
+        }
+    }
+
+    fn index_pages(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<bool> {
+        self.index_flush(k)?;
+
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let converted = self.is_markdown || self.txt_converted;
+        let cf = self.text_cache;
+        let header = self.text_header();
+        let geom = self.wrap_geom();
+        let full = self.page_lines();
+
+        let mut found = [0u8; STEP_PAGES * 4];
+        let mut count = 0usize;
+        let mut offset = self.pidx.tail;
+        let done = self.pidx.scan.wrap_pages(
+            &geom,
+            &self.paged,
+            full,
+            offset,
+            self.file_size,
+            |at, buf| {
+                if converted {
+                    k.read_cache_chunk(img_cache_str(&cf), header + at, buf)
+                } else {
+                    k.read_chunk(name, at, buf)
+                }
+            },
+            |next| {
+                found[count * 4..count * 4 + 4].copy_from_slice(&next.to_le_bytes());
+                count += 1;
+                offset = next;
+                true
+            },
+        )?;
+
+        if count > 0 {
+            let f = self.pidx.file;
+            k.append_cache(img_cache_str(&f), &found[..count * 4])?;
+            for b in found[..count * 4].chunks_exact(4) {
+                let p = self.pg.total_pages;
+                if self.pg.in_window(p) {
+                    self.pg.offsets[p - self.pg.base] =
+                        u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                }
+                self.pg.total_pages += 1;
+            }
+            self.pidx.saved = self.pg.total_pages;
+            self.pidx.tail = offset;
+        }
+        if done {
+            self.pg.fully_indexed = true;
+            self.index_done(k);
+        }
+        Ok(done)
+    }
+}
@@ -1,99 +1,70 @@
 // text wrapping, page navigation, and load/prefetch
 
+use alloc::vec::Vec;
+
 use smol_epub::html_strip::{
     BOLD_OFF, BOLD_ON, HEADING_OFF, HEADING_ON, IMG_REF, ITALIC_OFF, ITALIC_ON, MARKER, QUOTE_OFF,
     QUOTE_ON,
 };
 
+use crate::error::{Error, ErrorKind};
 use crate::fonts;
 use crate::fonts::bitmap::FIRST_CHAR;
+use crate::fonts::paged::PagedFont;
 use crate::kernel::KernelHandle;
+use pulp_kernel::util::nfc_compose_in_place;
 
 use super::{
-    DEFAULT_IMG_H, INDENT_PX, LINES_PER_PAGE, LineSpan, MAX_PAGES, NO_PREFETCH, PAGE_BUF,
-    ReaderApp, State, decode_utf8_char,
+    DEFAULT_IMG_H, INDENT_PX, LINE_SLOTS, LineSpan, MAX_PAGES, MONO_CHAR_W, NO_PREFETCH, PAGE_BUF,
+    ReaderApp, State, decode_utf8_char, img_cache_str,
 };
 
 impl ReaderApp {
     pub(super) fn wrap_lines_counted(&mut self, n: usize) -> usize {
-        let fonts_copy = self.fonts;
-
-        if let Some(fs) = fonts_copy {
-            let heights = &self.img_heights[..self.img_height_count as usize];
-            let (c, count) = wrap_proportional(
-                &self.pg.buf,
-                n,
-                &fs,
-                &mut self.pg.lines,
-                self.max_lines as usize,
-                self.text_w,
-                heights,
-            );
-            self.pg.line_count = count;
-            c
-        } else {
-            self.wrap_monospace(n)
-        }
+        let geom = self.wrap_geom();
+        let heights = &self.img_heights[..self.img_height_count as usize];
+        let (consumed, count) = wrap_page(
+            &geom,
+            &self.paged,
+            &self.pg.buf,
+            n,
+            &mut self.pg.lines,
+            heights,
+        );
+        self.pg.line_count = count;
+        consumed
     }
 
-    pub(super) fn wrap_monospace(&mut self, n: usize) -> usize {
-        use super::CHARS_PER_LINE;
-
-        let max = self.max_lines as usize;
-        self.pg.line_count = 0;
-        let mut col: usize = 0;
-        let mut line_start: usize = 0;
-
-        for i in 0..n {
-            let b = self.pg.buf[i];
-            match b {
-                b'\r' => {}
-                b'\n' => {
-                    let end = trim_trailing_cr(&self.pg.buf, line_start, i);
-                    self.push_line(line_start, end);
-                    line_start = i + 1;
-                    col = 0;
-                    if self.pg.line_count >= max {
-                        return line_start;
-                    }
-                }
-                _ => {
-                    col += 1;
-                    if col >= CHARS_PER_LINE {
-                        self.push_line(line_start, i + 1);
-                        line_start = i + 1;
-                        col = 0;
-                        if self.pg.line_count >= max {
-                            return line_start;
-                        }
-                    }
-                }
-            }
-        }
-
-        if line_start < n && self.pg.line_count < max {
-            let end = trim_trailing_cr(&self.pg.buf, line_start, n);
-            self.push_line(line_start, end);
+    pub(super) fn wrap_geom(&self) -> WrapGeom {
+        WrapGeom {
+            fonts: self.fonts,
+            max_lines: self.max_lines as usize,
+            columns: self.columns,
+            col_w: self.col_w,
+            mono_per_line: (self.text_w / MONO_CHAR_W).max(1) as usize,
         }
-
-        n
     }
 
-    pub(super) fn push_line(&mut self, start: usize, end: usize) {
-        if self.pg.line_count < LINES_PER_PAGE {
-            self.pg.lines[self.pg.line_count] = LineSpan {
-                start: start as u16,
-                len: (end - start) as u16,
-                flags: 0,
-                indent: 0,
-            };
-            self.pg.line_count += 1;
+    // page in any SD-resident glyphs the wrapped page will draw, and
+    // count codepoints neither the flash chain nor the SD tables cover
+    fn prepare_page_glyphs(&mut self, k: &mut KernelHandle<'_>, n: usize) {
+        let Some(fs) = self.fonts else {
+            return;
+        };
+        let text = &self.pg.buf[..n];
+        self.paged.ensure(k, styled_chars(text), &fs);
+
+        for (ch, sty) in styled_chars(text) {
+            if !fs.has_glyph(ch, sty) && !self.paged.covers(ch) && self.missing.note(ch) {
+                log::info!("font: no glyph for U+{:04X}", ch as u32);
+            }
         }
     }
 
     pub(super) fn reset_paging(&mut self) {
         self.pg.page = 0;
         self.pg.offsets[0] = 0;
+        self.pg.base = 0;
         self.pg.total_pages = 1;
         self.pg.fully_indexed = false;
         self.pg.buf_len = 0;
@@ -102,14 +73,18 @@ impl ReaderApp {
         self.pg.prefetch_len = 0;
         self.page_img = None;
         self.fullscreen_img = false;
+        // the layout may have changed; the index is checked again
+        self.pidx.open = false;
     }
 
     pub(super) fn load_and_prefetch(
         &mut self,
         k: &mut KernelHandle<'_>,
     ) -> crate::error::Result<()> {
+        self.paged.sync(k);
+
         if !self.epub.ch_cache.is_empty() {
-            let start = (self.pg.offsets[self.pg.page] as usize).min(self.epub.ch_cache.len());
+            let start = (self.pg.offset(self.pg.page) as usize).min(self.epub.ch_cache.len());
             let end = (start + PAGE_BUF).min(self.epub.ch_cache.len());
             let n = end - start;
             if n > 0 {
@@ -119,7 +94,8 @@ impl ReaderApp {
             self.pg.prefetch_page = NO_PREFETCH;
             self.pg.prefetch_len = 0;
             self.prescan_image_heights(k, n);
-            self.wrap_lines_counted(n);
+            let consumed = self.wrap_lines_counted(n);
+            self.prepare_page_glyphs(k, consumed);
             self.decode_page_images(k);
             return Ok(());
         }
@@ -127,6 +103,10 @@ impl ReaderApp {
         let (nb, nl) = self.name_copy();
         let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
 
+        if self.uses_page_index() {
+            self.index_sync(k)?;
+        }
+
         if self.pg.prefetch_page == self.pg.page {
             let pf_len = self.pg.prefetch_len;
             self.pg.buf[..pf_len].copy_from_slice(&self.pg.prefetch[..pf_len]);
@@ -139,7 +119,20 @@ impl ReaderApp {
             let ch_base = self.epub.chapter_table[ch].0;
             let n = k.read_cache_chunk(
                 cf_str,
-                ch_base + self.pg.offsets[self.pg.page],
+                ch_base + self.pg.offset(self.pg.page),
+                &mut self.pg.buf,
+            )?;
+            self.pg.buf_len = n;
+        } else if self.is_markdown || self.txt_converted {
+            if self.file_size == 0 {
+                self.pg.fully_indexed = true;
+                self.pg.line_count = 0;
+                return Ok(());
+            }
+            let cf = self.text_cache;
+            let n = k.read_cache_chunk(
+                img_cache_str(&cf),
+                self.text_header() + self.pg.offset(self.pg.page),
                 &mut self.pg.buf,
             )?;
             self.pg.buf_len = n;
@@ -155,24 +148,24 @@ impl ReaderApp {
                 return Ok(());
             }
         } else {
-            let n = k.read_chunk(name, self.pg.offsets[self.pg.page], &mut self.pg.buf)?;
+            let n = k.read_chunk(name, self.pg.offset(self.pg.page), &mut self.pg.buf)?;
             self.pg.buf_len = n;
         }
 
+        // length-preserving, so page offsets still index the source
+        nfc_compose_in_place(&mut self.pg.buf[..self.pg.buf_len]);
+
         self.prescan_image_heights(k, self.pg.buf_len);
         let consumed = self.wrap_lines_counted(self.pg.buf_len);
-        let next_offset = self.pg.offsets[self.pg.page] + consumed as u32;
+        self.prepare_page_glyphs(k, consumed);
+        let next_offset = self.pg.offset(self.pg.page) + consumed as u32;
 
         if self.pg.page + 1 >= self.pg.total_pages && !self.pg.fully_indexed {
-            if self.pg.line_count >= self.max_lines as usize && next_offset < self.file_size {
-                if self.pg.total_pages < MAX_PAGES {
-                    self.pg.offsets[self.pg.total_pages] = next_offset;
-                    self.pg.total_pages += 1;
-                } else {
-                    self.pg.fully_indexed = true;
-                }
+            if self.pg.line_count >= self.page_lines() && next_offset < self.file_size {
+                self.push_page(k, next_offset)?;
             } else {
                 self.pg.fully_indexed = true;
+                self.index_done(k);
             }
         }
 
@@ -180,12 +173,19 @@ impl ReaderApp {
             if self.pg.prefetch.len() < PAGE_BUF {
                 self.pg.prefetch.resize(PAGE_BUF, 0);
             }
-            let pf_offset = self.pg.offsets[self.pg.page + 1];
+            let pf_offset = self.pg.offset(self.pg.page + 1);
             let pf_result = if self.is_epub && self.epub.chapters_cached {
                 let cf_str = self.epub.cache_file_str();
                 let ch = self.epub.chapter as usize;
                 let ch_base = self.epub.chapter_table[ch].0;
                 k.read_cache_chunk(cf_str, ch_base + pf_offset, &mut self.pg.prefetch)
+            } else if self.is_markdown || self.txt_converted {
+                let cf = self.text_cache;
+                k.read_cache_chunk(
+                    img_cache_str(&cf),
+                    self.text_header() + pf_offset,
+                    &mut self.pg.prefetch,
+                )
             } else {
                 k.read_chunk(name, pf_offset, &mut self.pg.prefetch)
             };
@@ -227,7 +227,7 @@ impl ReaderApp {
             let consumed = self.wrap_lines_counted(n);
             let next_offset = offset + consumed;
 
-            if self.pg.line_count >= self.max_lines as usize && next_offset < total {
+            if self.pg.line_count >= self.page_lines() && next_offset < total {
                 self.pg.offsets[self.pg.total_pages] = next_offset as u32;
                 self.pg.total_pages += 1;
                 offset = next_offset;
@@ -240,11 +240,17 @@ impl ReaderApp {
         log::info!("chapter pre-indexed: {} pages", self.pg.total_pages);
     }
 
-    pub(super) fn scan_to_last_page(
+    // wrap forward until target is known (or the chapter ends) and
+    // load it; usize::MAX for the last page
+    pub(super) fn scan_to_page(
         &mut self,
         k: &mut KernelHandle<'_>,
+        target: usize,
     ) -> crate::error::Result<()> {
-        while !self.pg.fully_indexed && self.pg.total_pages < MAX_PAGES {
+        while !self.pg.fully_indexed
+            && self.pg.total_pages <= target
+            && self.pg.total_pages < MAX_PAGES
+        {
             self.pg.page = self.pg.total_pages - 1;
             self.load_and_prefetch(k)?;
             if self.pg.page + 1 < self.pg.total_pages {
@@ -254,7 +260,7 @@ impl ReaderApp {
             }
         }
         if self.pg.total_pages > 0 {
-            self.pg.page = self.pg.total_pages - 1;
+            self.pg.page = target.min(self.pg.total_pages - 1);
         }
         self.pg.prefetch_page = NO_PREFETCH;
         self.load_and_prefetch(k)
@@ -264,6 +270,9 @@ impl ReaderApp {
         if self.state != State::Ready {
             return false;
         }
+        if self.is_cbz {
+            return self.cbz_forward();
+        }
 
         if self.pg.page + 1 < self.pg.total_pages {
             self.pg.page += 1;
@@ -273,7 +282,7 @@ impl ReaderApp {
 
         if self.is_epub
             && self.pg.fully_indexed
-            && (self.epub.chapter as usize + 1) < self.epub.spine.len()
+            && (self.epub.chapter as usize + 1) < self.epub.chapter_count()
         {
             self.epub.chapter += 1;
             self.goto_last_page = false;
@@ -288,6 +297,9 @@ impl ReaderApp {
         if self.state != State::Ready {
             return false;
         }
+        if self.is_cbz {
+            return self.cbz_backward();
+        }
 
         if self.pg.page > 0 {
             self.pg.page -= 1;
@@ -305,18 +317,23 @@ impl ReaderApp {
         false
     }
 
-    // next chapter (EPUB) or +10 pages (TXT)
+    // next chapter (EPUB, TXT with headings) or +10 pages (TXT)
     pub(super) fn jump_forward(&mut self) -> bool {
         if self.state != State::Ready {
             return false;
         }
         if self.is_epub {
-            if (self.epub.chapter as usize + 1) < self.epub.spine.len() {
+            if (self.epub.chapter as usize + 1) < self.epub.chapter_count() {
                 self.epub.chapter += 1;
                 self.goto_last_page = false;
                 self.state = State::NeedIndex;
                 return true;
             }
+        } else if self.has_txt_chapters() {
+            if let Some(offset) = self.txt_chapter_target(true) {
+                self.txt_goto(offset);
+                return true;
+            }
         } else {
             let last = if self.pg.total_pages > 0 {
                 self.pg.total_pages - 1
@@ -333,7 +350,7 @@ impl ReaderApp {
         false
     }
 
-    // prev chapter (EPUB) or -10 pages (TXT)
+    // prev chapter (EPUB, TXT with headings) or -10 pages (TXT)
     pub(super) fn jump_backward(&mut self) -> bool {
         if self.state != State::Ready {
             return false;
@@ -345,6 +362,11 @@ impl ReaderApp {
                 self.state = State::NeedIndex;
                 return true;
             }
+        } else if self.has_txt_chapters() {
+            if let Some(offset) = self.txt_chapter_target(false) {
+                self.txt_goto(offset);
+                return true;
+            }
         } else {
             let target = self.pg.page.saturating_sub(10);
             if target != self.pg.page {
@@ -360,6 +382,186 @@ impl ReaderApp {
 // UTF-8 decoding is provided by pulp_kernel::util::decode_utf8_char
 // (re-exported via super::decode_utf8_char)
 
+// what a page wraps against; copied out so a page can be wrapped
+// into buffers other than the page state's
+#[derive(Clone, Copy)]
+pub(super) struct WrapGeom {
+    pub(super) fonts: Option<fonts::FontSet>,
+    pub(super) max_lines: usize,
+    pub(super) columns: u8,
+    pub(super) col_w: u32,
+    pub(super) mono_per_line: usize,
+}
+
+// pages wrapped per background tick
+pub(super) const STEP_PAGES: usize = 4;
+
+// buffers for wrapping text in the background, away from the page
+// state: the page index of a text book and the page counts of an
+// epub. allocated on first use, dropped with free() when done
+pub(super) struct PageScan {
+    buf: Vec<u8>,
+    lines: Vec<LineSpan>,
+}
+
+impl PageScan {
+    pub(super) const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub(super) fn free(&mut self) {
+        self.buf = Vec::new();
+        self.lines = Vec::new();
+    }
+
+    // wrap up to STEP_PAGES pages of text that runs from start to end;
+    // read fills a buffer from a text offset. the start of every page
+    // after the first goes to page, which returns false to stop there.
+    // true once the text ran out or page stopped the scan
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn wrap_pages(
+        &mut self,
+        geom: &WrapGeom,
+        paged: &PagedFont,
+        full: usize,
+        start: u32,
+        end: u32,
+        mut read: impl FnMut(u32, &mut [u8]) -> crate::error::Result<usize>,
+        mut page: impl FnMut(u32) -> bool,
+    ) -> crate::error::Result<bool> {
+        if self.buf.is_empty() {
+            self.buf
+                .try_reserve_exact(PAGE_BUF)
+                .and_then(|()| self.lines.try_reserve_exact(LINE_SLOTS))
+                .map_err(|_| Error::new(ErrorKind::OutOfMemory, "page scan: buffers"))?;
+            self.buf.resize(PAGE_BUF, 0);
+            self.lines.resize(LINE_SLOTS, LineSpan::EMPTY);
+        }
+
+        let mut offset = start;
+        for _ in 0..STEP_PAGES {
+            let len = (end.saturating_sub(offset) as usize).min(PAGE_BUF);
+            let n = read(offset, &mut self.buf[..len])?;
+            nfc_compose_in_place(&mut self.buf[..n]);
+            let (consumed, lines) = wrap_page(geom, paged, &self.buf, n, &mut self.lines, &[]);
+            let next = offset + consumed as u32;
+            if lines < full || next >= end || consumed == 0 || !page(next) {
+                return Ok(true);
+            }
+            offset = next;
+        }
+        Ok(false)
+    }
+}
+
+// wrap one page from the start of buf; (bytes consumed, lines used)
+pub(super) fn wrap_page(
+    geom: &WrapGeom,
+    paged: &PagedFont,
+    buf: &[u8],
+    n: usize,
+    lines: &mut [LineSpan],
+    heights: &[u16],
+) -> (usize, usize) {
+    let Some(fs) = geom.fonts else {
+        return wrap_monospace(buf, n, lines, geom.max_lines, geom.mono_per_line);
+    };
+
+    // each column wraps the text the previous one left over; the
+    // page ends where the last column stops
+    let max = geom.max_lines;
+    let mut consumed = 0usize;
+    let mut count = 0usize;
+    let mut img_used = 0usize;
+    for _ in 0..geom.columns {
+        let first = count;
+        let (c, col_count) = wrap_proportional(
+            &buf[consumed..],
+            n - consumed,
+            &fs,
+            paged,
+            &mut lines[first..],
+            max,
+            geom.col_w,
+            &heights[img_used.min(heights.len())..],
+        );
+        for span in &mut lines[first..first + col_count] {
+            if span.is_image_origin() {
+                img_used += 1;
+            }
+            span.start += consumed as u16;
+        }
+        consumed += c;
+        count = first + col_count;
+        // a short column means the text ran out before the page did
+        if col_count < max || consumed >= n {
+            break;
+        }
+    }
+    (consumed, count)
+}
+
+fn wrap_monospace(
+    buf: &[u8],
+    n: usize,
+    lines: &mut [LineSpan],
+    max: usize,
+    per_line: usize,
+) -> (usize, usize) {
+    let max = max.min(lines.len());
+    let mut count = 0usize;
+    let mut col: usize = 0;
+    let mut line_start: usize = 0;
+
+    let mut push = |start: usize, end: usize, count: &mut usize| {
+        if *count < max {
+            lines[*count] = LineSpan {
+                start: start as u16,
+                len: (end - start) as u16,
+                flags: 0,
+                indent: 0,
+            };
+            *count += 1;
+        }
+    };
+
+    for i in 0..n {
+        match buf[i] {
+            b'\r' => {}
+            b'\n' => {
+                let end = trim_trailing_cr(buf, line_start, i);
+                push(line_start, end, &mut count);
+                line_start = i + 1;
+                col = 0;
+                if count >= max {
+                    return (line_start, count);
+                }
+            }
+            _ => {
+                col += 1;
+                if col >= per_line {
+                    push(line_start, i + 1, &mut count);
+                    line_start = i + 1;
+                    col = 0;
+                    if count >= max {
+                        return (line_start, count);
+                    }
+                }
+            }
+        }
+    }
+
+    if line_start < n && count < max {
+        let end = trim_trailing_cr(buf, line_start, n);
+        push(line_start, end, &mut count);
+    }
+
+    (n, count)
+}
+
 pub(super) fn trim_trailing_cr(buf: &[u8], start: usize, end: usize) -> usize {
     if end > start && buf[end - 1] == b'\r' {
         end - 1
@@ -374,10 +576,97 @@ fn is_wrap_space(ch: char) -> bool {
     matches!(ch, ' ' | '\u{00A0}')
 }
 
+// scripts written without spaces: a line may break between any two
+// characters. hangul keeps word spacing, so it wraps like latin
+#[inline]
+fn is_cjk_break(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x2E80..=0x2FFF // radicals, kangxi
+            | 0x3000..=0x31FF // CJK punctuation, kana, bopomofo
+            | 0x3400..=0x4DBF // ideographs ext A
+            | 0x4E00..=0x9FFF // unified ideographs
+            | 0xF900..=0xFAFF // compatibility ideographs
+            | 0xFF00..=0xFFEF // fullwidth forms
+    )
+}
+
+// kinsoku: closing punctuation, small kana and iteration marks
+// must not start a line
+const NO_LINE_START: &[char] = &[
+    '、', '。', '，', '．', '・', '：', '；', '？', '！', 'ー', '～', '…', '‥', '）', '］', '｝',
+    '」', '』', '】', '〕', '〉', '》', '〗', '〙', 'ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ', 'っ', 'ゃ', 'ゅ',
+    'ょ', 'ゎ', 'ゕ', 'ゖ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ッ', 'ャ', 'ュ', 'ョ', 'ヮ', 'ヵ', 'ヶ',
+    '々', 'ゝ', 'ゞ', 'ヽ', 'ヾ', ')', ']', '}', ',', '.', '!', '?', ':', ';', '\u{2019}',
+    '\u{201D}',
+];
+
+// kinsoku: opening brackets must not end a line
+const NO_LINE_END: &[char] = &[
+    '（', '［', '｛', '「', '『', '【', '〔', '〈', '《', '〖', '〘', '(', '[', '{', '\u{2018}',
+    '\u{201C}',
+];
+
+// break opportunity between two adjacent characters when either
+// side is CJK, subject to kinsoku
+#[inline]
+fn cjk_break_between(prev: char, next: char) -> bool {
+    (is_cjk_break(prev) || is_cjk_break(next))
+        && !NO_LINE_END.contains(&prev)
+        && !NO_LINE_START.contains(&next)
+}
+
+// non-ASCII characters of a page with the style each is drawn in,
+// following the style markers the way the draw loop does
+fn styled_chars(text: &[u8]) -> impl Iterator<Item = (char, fonts::Style)> + '_ {
+    let mut sty = fonts::Style::Regular;
+    let mut i = 0;
+    core::iter::from_fn(move || {
+        while i < text.len() {
+            let b = text[i];
+            if b == MARKER && i + 1 < text.len() {
+                sty = match text[i + 1] {
+                    BOLD_ON => fonts::Style::Bold,
+                    ITALIC_ON => fonts::Style::Italic,
+                    HEADING_ON => fonts::Style::Heading,
+                    BOLD_OFF | ITALIC_OFF | HEADING_OFF => fonts::Style::Regular,
+                    // the image path is not drawn as text
+                    IMG_REF if i + 2 < text.len() => {
+                        i += 3 + text[i + 2] as usize;
+                        continue;
+                    }
+                    _ => sty,
+                };
+                i += 2;
+                continue;
+            }
+            if b < 0xC0 {
+                i += 1;
+                continue;
+            }
+            let (ch, seq_len) = decode_utf8_char(text, i);
+            i += seq_len;
+            return Some((ch, sty));
+        }
+        None
+    })
+}
+
+// flash fonts first; codepoints they lack measure from the paged
+// table (fixed pitch, so independent of what is resident)
+#[inline]
+fn char_advance(fonts: &fonts::FontSet, paged: &PagedFont, ch: char, sty: fonts::Style) -> u32 {
+    match paged.advance(ch) {
+        Some(adv) if !fonts.has_glyph(ch, sty) => adv as u32,
+        _ => fonts.advance(ch, sty) as u32,
+    }
+}
+
 pub(super) fn wrap_proportional(
     buf: &[u8],
     n: usize,
     fonts: &fonts::FontSet,
+    paged: &PagedFont,
     lines: &mut [LineSpan],
     max_lines: usize,
     max_width_px: u32,
@@ -390,6 +679,8 @@ pub(super) fn wrap_proportional(
     let mut cursor_x: u32 = 0;
     let mut last_space: usize = 0;
     let mut cursor_at_space: u32 = 0;
+    // previous visible character, for CJK break opportunities
+    let mut prev_ch = '\n';
 
     let mut bold = false;
     let mut italic = false;
@@ -481,6 +772,7 @@ pub(super) fn wrap_proportional(
                     cursor_x = 0;
                     last_space = line_start;
                     cursor_at_space = 0;
+                    prev_ch = '\n';
                     if line_count >= max_l {
                         return (line_start, line_count);
                     }
@@ -520,6 +812,7 @@ pub(super) fn wrap_proportional(
             cursor_x = 0;
             last_space = line_start;
             cursor_at_space = 0;
+            prev_ch = '\n';
             if line_count >= max_l {
                 return (line_start, line_count);
             }
@@ -543,6 +836,7 @@ pub(super) fn wrap_proportional(
             // NBSP and regular spaces: word-break opportunity
             if is_wrap_space(ch) {
                 let sty = current_style(bold, italic, heading);
+                prev_ch = ' ';
                 cursor_x += fonts.advance(' ', sty) as u32;
                 last_space = i + seq_len;
                 cursor_at_space = cursor_x;
@@ -560,8 +854,14 @@ pub(super) fn wrap_proportional(
                 continue;
             }
 
+            if cjk_break_between(prev_ch, ch) {
+                last_space = i;
+                cursor_at_space = cursor_x;
+            }
+            prev_ch = ch;
+
             let sty = current_style(bold, italic, heading);
-            let adv = fonts.advance(ch, sty) as u32;
+            let adv = char_advance(fonts, paged, ch, sty);
             cursor_x += adv;
             if cursor_x > max_w {
                 if last_space > line_start {
@@ -595,6 +895,7 @@ pub(super) fn wrap_proportional(
 
         if b == b' ' {
             let adv = glyphs[(b' ' - FIRST_CHAR) as usize].advance as u32;
+            prev_ch = ' ';
             cursor_x += adv;
             last_space = i + 1;
             cursor_at_space = cursor_x;
@@ -615,6 +916,10 @@ pub(super) fn wrap_proportional(
         // Printable non-space ASCII (0x21..=0x7E): batch-scan the word run.
         // Find end of contiguous printable non-space ASCII bytes, sum advances.
         let word_start = i;
+        if b > b' ' && b <= 0x7E && cjk_break_between(prev_ch, b as char) {
+            last_space = i;
+            cursor_at_space = cursor_x;
+        }
         let remaining = max_w.saturating_sub(cursor_x);
         let mut run_adv: u32 = 0;
         let mut j = i;
@@ -635,6 +940,7 @@ pub(super) fn wrap_proportional(
 
         if j > i {
             // consumed j - i bytes as a batch
+            prev_ch = buf[j - 1] as char;
             cursor_x += run_adv;
             i = j;
             if cursor_x > max_w {
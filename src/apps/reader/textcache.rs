@@ -0,0 +1,223 @@
+// converted copies of plain text books in _PULP
+//
+// markdown (XXXXXXXX.MDT) and text not in UTF-8 (XXXXXXXX.U8T) are
+// converted once and then paged from the copy. each copy starts with
+// a small header that its converter builds around the source key;
+// a copy whose header does not match is converted again
+//
+// the source key is the file size and a hash of the first and last
+// KEY_SAMPLE bytes. the card has no clock, so file times cannot tell
+// an edited book apart: a re-saved, grown or trimmed file is noticed,
+// an edit in the middle that keeps the size is not. the page and
+// chapter indexes of a book carry the same key
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+
+use super::encoding::{TXT_HEADER, Transcoder};
+use super::markdown::{MD_HEADER, MdStrip};
+use super::{ReaderApp, img_cache_str};
+
+pub(super) const KEY_LEN: usize = 8;
+const KEY_SAMPLE: usize = 2048;
+// output is appended to the cache in pieces about this size
+const FLUSH_AT: usize = 4096;
+// source reads converted per background tick
+const READS_PER_TICK: usize = 4;
+
+// a streaming converter: input in chunks of any size, then finish
+pub(super) trait TextFeed {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>);
+    fn finish(&mut self, out: &mut Vec<u8>);
+}
+
+// the converters behind the text caches
+pub(super) enum Feed {
+    Markdown(MdStrip),
+    Text(Transcoder),
+}
+
+impl TextFeed for Feed {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        match self {
+            Feed::Markdown(md) => md.feed(input, out),
+            Feed::Text(tc) => tc.feed(input, out),
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        match self {
+            Feed::Markdown(md) => md.finish(out),
+            Feed::Text(tc) => tc.finish(out),
+        }
+    }
+}
+
+impl ReaderApp {
+    // where the text starts in this book's converted copy
+    pub(super) fn text_header(&self) -> u32 {
+        if self.is_markdown {
+            MD_HEADER
+        } else {
+            TXT_HEADER
+        }
+    }
+}
+
+// size and sampled hash of a source file of src_size bytes
+pub(super) fn source_key(
+    k: &mut KernelHandle<'_>,
+    src: &str,
+    src_size: u32,
+    buf: &mut [u8],
+) -> crate::error::Result<[u8; KEY_LEN]> {
+    let sample = buf.len().min(KEY_SAMPLE);
+    let n = k.read_chunk(src, 0, &mut buf[..sample])?;
+    let head = cache::fnv1a(&buf[..n]);
+    let tail_at = src_size.saturating_sub(sample as u32);
+    let n = k.read_chunk(src, tail_at, &mut buf[..sample])?;
+    let tail = cache::fnv1a(&buf[..n]);
+
+    let mut key = [0u8; KEY_LEN];
+    key[..4].copy_from_slice(&src_size.to_le_bytes());
+    key[4..].copy_from_slice(&(head ^ tail.rotate_left(16)).to_le_bytes());
+    Ok(key)
+}
+
+// a conversion under way: a few reads per background tick, so a
+// large book does not hold up input while it converts
+pub(super) struct Convert {
+    feed: Feed,
+    out: Vec<u8>,
+    offset: u32,
+    src_size: u32,
+    total: u32,
+}
+
+impl ReaderApp {
+    // convert some more of the book into text_cache; Some(len) once
+    // the cache is complete, len excluding the header. the first call
+    // finds the cache current or starts over with new_feed
+    pub(super) fn convert_text(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        header: &[u8],
+        new_feed: impl FnOnce() -> Feed,
+    ) -> crate::error::Result<Option<u32>> {
+        let (nb, nl) = self.name_copy();
+        let src = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+        let cf = self.text_cache;
+        let cache = img_cache_str(&cf);
+
+        let mut conv = match self.text_conv.take() {
+            Some(conv) => conv,
+            None => {
+                if let Some(len) = current_len(k, cache, header, &mut self.pg.buf)? {
+                    return Ok(Some(len));
+                }
+                Convert::new(k, src, cache, header.len(), &mut self.pg.buf, new_feed())?
+            }
+        };
+        let done = conv.step(k, src, cache, header, &mut self.pg.buf);
+        if matches!(done, Ok(None)) {
+            self.text_conv = Some(conv);
+        }
+        done
+    }
+}
+
+// the converted length when the cache already starts with header
+fn current_len(
+    k: &mut KernelHandle<'_>,
+    cache: &str,
+    header: &[u8],
+    buf: &mut [u8],
+) -> crate::error::Result<Option<u32>> {
+    let hlen = header.len();
+    let n = k.read_cache_chunk(cache, 0, &mut buf[..hlen]).unwrap_or(0);
+    if n == hlen && buf[..hlen] == *header {
+        return Ok(Some(k.cache_file_size(cache)?.saturating_sub(hlen as u32)));
+    }
+    Ok(None)
+}
+
+impl Convert {
+    // start over a zeroed header, so an interrupted conversion is
+    // redone next time
+    fn new(
+        k: &mut KernelHandle<'_>,
+        src: &str,
+        cache: &str,
+        hlen: usize,
+        buf: &mut [u8],
+        feed: Feed,
+    ) -> crate::error::Result<Self> {
+        buf[..hlen].fill(0);
+        k.write_cache(cache, &buf[..hlen])?;
+        Ok(Self {
+            feed,
+            out: Vec::new(),
+            offset: 0,
+            src_size: k.file_size(src)?,
+            total: 0,
+        })
+    }
+
+    // feed up to READS_PER_TICK reads of src; Some(total) once the
+    // source is used up and the header is in place
+    fn step(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        src: &str,
+        cache: &str,
+        header: &[u8],
+        buf: &mut [u8],
+    ) -> crate::error::Result<Option<u32>> {
+        for _ in 0..READS_PER_TICK {
+            if self.offset >= self.src_size {
+                break;
+            }
+            let n = k.read_chunk(src, self.offset, buf)?;
+            if n == 0 {
+                self.src_size = self.offset;
+                break;
+            }
+            self.offset += n as u32;
+            // up to three bytes of output per byte read
+            self.out
+                .try_reserve(n * 3)
+                .map_err(|_| Error::new(ErrorKind::OutOfMemory, "textcache: convert"))?;
+            self.feed.feed(&buf[..n], &mut self.out);
+            if self.out.len() >= FLUSH_AT {
+                self.flush(k, cache)?;
+            }
+        }
+        if self.offset < self.src_size {
+            return Ok(None);
+        }
+
+        self.feed.finish(&mut self.out);
+        self.flush(k, cache)?;
+        k.write_cache_at(cache, 0, header)?;
+        log::info!(
+            "reader: converted {} ({} -> {} bytes)",
+            src,
+            self.src_size,
+            self.total
+        );
+        Ok(Some(self.total))
+    }
+
+    fn flush(&mut self, k: &mut KernelHandle<'_>, cache: &str) -> crate::error::Result<()> {
+        if !self.out.is_empty() {
+            k.append_cache(cache, &self.out)?;
+            self.total += self.out.len() as u32;
+            self.out.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+// table of contents shown by the toc overlay
+//
+// epub tocs are copied in from the parsed nav/ncx so the overlay
+// does not depend on where the entries came from; fb2 books build
+// one from their section titles and plain text books from the
+// headings chapters.rs finds
+//
+// entries stay in document order with a nesting depth; an entry's
+// subtree is the run of deeper entries after it. the overlay lists
+// the entries no collapsed ancestor hides
+//
+// epub entries also keep a hash of their #fragment. for the chapter
+// being read the fragments are looked up in its xhtml and turned into
+// offsets in the stripped text (epubs.rs), so entries that point into
+// the middle of one chapter can be told apart
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+use smol_epub::epub::EpubToc;
+
+use super::xml::attr;
+
+pub(super) const TOC_TITLE_CAP: usize = 64;
+pub(super) const MAX_TOC: usize = 256;
+// deeper levels are drawn at this indent
+const MAX_DEPTH: u8 = 5;
+
+// spine_idx for entries that did not resolve to a chapter
+pub(super) const NO_CHAPTER: u16 = 0xFFFF;
+
+pub(super) struct TocEntry {
+    title: [u8; TOC_TITLE_CAP],
+    title_len: u8,
+    pub(super) spine_idx: u16,
+    // where a plain text entry starts in the text; 0 for chapters
+    pub(super) offset: u32,
+    pub(super) depth: u8,
+    collapsed: bool,
+    // fnv1a of the href fragment, 0 without one
+    pub(super) frag: u32,
+    // where the fragment starts in the chapter text; valid for the
+    // chapter in Toc::anchors_for, 0 when not found
+    pub(super) anchor: u32,
+}
+
+impl TocEntry {
+    pub(super) fn title_str(&self) -> &str {
+        core::str::from_utf8(&self.title[..self.title_len as usize]).unwrap_or("")
+    }
+}
+
+pub(super) struct Toc {
+    pub(super) entries: Vec<TocEntry>,
+    // chapter whose fragments are resolved into anchors
+    pub(super) anchors_for: u16,
+}
+
+// nesting depth and fragment hash of a raw toc entry
+pub(super) struct TocLink {
+    depth: u8,
+    frag: u32,
+}
+
+impl Toc {
+    pub(super) const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            anchors_for: NO_CHAPTER,
+        }
+    }
+
+    pub(super) fn from_epub(toc: &EpubToc) -> Self {
+        let mut out = Self::new();
+        for i in 0..toc.len() {
+            let entry = &toc.entries[i];
+            out.push(entry.title_str().as_bytes(), entry.spine_idx);
+        }
+        out
+    }
+
+    // depths and fragments from entry_links, taken only when they
+    // line up with the parsed entries one for one
+    pub(super) fn set_links(&mut self, links: &[TocLink], parsed: usize) -> bool {
+        if links.len() != parsed {
+            return false;
+        }
+        for (entry, link) in self.entries.iter_mut().zip(links) {
+            entry.depth = link.depth.min(MAX_DEPTH);
+            entry.frag = link.frag;
+        }
+        true
+    }
+
+    // whether any entry points into the middle of chapter ch
+    pub(super) fn has_fragments(&self, ch: u16) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.spine_idx == ch && e.frag != 0)
+    }
+
+    #[inline]
+    pub(super) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub(super) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(super) fn push(&mut self, title: &[u8], spine_idx: u16) -> bool {
+        self.push_entry(title, spine_idx, 0)
+    }
+
+    pub(super) fn push_offset(&mut self, title: &[u8], offset: u32) -> bool {
+        self.push_entry(title, 0, offset)
+    }
+
+    // titles are cut at a char boundary; false once the toc is full
+    fn push_entry(&mut self, title: &[u8], spine_idx: u16, offset: u32) -> bool {
+        if self.entries.len() >= MAX_TOC {
+            return false;
+        }
+        let title = title.trim_ascii();
+        let mut n = title.len().min(TOC_TITLE_CAP);
+        while n > 0 && n < title.len() && title[n] & 0xC0 == 0x80 {
+            n -= 1;
+        }
+        let mut entry = TocEntry {
+            title: [0u8; TOC_TITLE_CAP],
+            title_len: n as u8,
+            spine_idx,
+            offset,
+            depth: 0,
+            collapsed: false,
+            frag: 0,
+            anchor: 0,
+        };
+        entry.title[..n].copy_from_slice(&title[..n]);
+        self.entries.push(entry);
+        true
+    }
+
+    #[inline]
+    pub(super) fn has_children(&self, i: usize) -> bool {
+        self.entries
+            .get(i + 1)
+            .is_some_and(|e| e.depth > self.entries[i].depth)
+    }
+
+    #[inline]
+    pub(super) fn is_collapsed(&self, i: usize) -> bool {
+        self.entries[i].collapsed && self.has_children(i)
+    }
+
+    // first entry past i's subtree
+    fn subtree_end(&self, i: usize) -> usize {
+        let depth = self.entries[i].depth;
+        (i + 1..self.len())
+            .find(|&j| self.entries[j].depth <= depth)
+            .unwrap_or(self.len())
+    }
+
+    #[inline]
+    pub(super) fn in_subtree(&self, i: usize, j: usize) -> bool {
+        j > i && j < self.subtree_end(i)
+    }
+
+    fn parent(&self, i: usize) -> Option<usize> {
+        let depth = self.entries[i].depth;
+        (0..i).rev().find(|&j| self.entries[j].depth < depth)
+    }
+
+    // next listed entry after a listed one
+    fn next_visible(&self, i: usize) -> Option<usize> {
+        let next = if self.is_collapsed(i) {
+            self.subtree_end(i)
+        } else {
+            i + 1
+        };
+        (next < self.len()).then_some(next)
+    }
+
+    // entries the overlay lists, in order
+    pub(super) fn visible(&self) -> impl Iterator<Item = usize> + '_ {
+        core::iter::successors((!self.is_empty()).then_some(0), |&i| self.next_visible(i))
+    }
+
+    // list row of a listed entry
+    pub(super) fn row_of(&self, i: usize) -> usize {
+        self.visible().position(|j| j == i).unwrap_or(0)
+    }
+
+    // listed entry after or before i, wrapping around
+    pub(super) fn step(&self, i: usize, forward: bool) -> usize {
+        if forward {
+            self.next_visible(i).unwrap_or(0)
+        } else {
+            match self.visible().take_while(|&j| j < i).last() {
+                Some(j) => j,
+                None => self.visible().last().unwrap_or(0),
+            }
+        }
+    }
+
+    pub(super) fn expand(&mut self, i: usize) {
+        self.entries[i].collapsed = false;
+    }
+
+    // fold i's subtree, or the one i sits in; the entry folded
+    pub(super) fn collapse(&mut self, i: usize) -> usize {
+        let at = if self.has_children(i) && !self.entries[i].collapsed {
+            i
+        } else {
+            match self.parent(i) {
+                Some(p) => p,
+                None => return i,
+            }
+        };
+        self.entries[at].collapsed = true;
+        at
+    }
+
+    // fold every subtree except the ones leading down to entry
+    pub(super) fn fold_to(&mut self, entry: Option<usize>) {
+        for e in self.entries.iter_mut() {
+            e.collapsed = true;
+        }
+        let mut at = entry;
+        while let Some(p) = at.and_then(|i| self.parent(i)) {
+            self.entries[p].collapsed = false;
+            at = Some(p);
+        }
+    }
+}
+
+// fnv1a of an id or fragment; 0 is kept for none
+pub(super) fn frag_hash(id: &[u8]) -> u32 {
+    cache::fnv1a(id).max(1)
+}
+
+// fragment hash of an href, 0 without one
+fn href_frag(href: &[u8]) -> u32 {
+    match href.iter().position(|&b| b == b'#') {
+        Some(p) if p + 1 < href.len() => frag_hash(&href[p + 1..]),
+        _ => 0,
+    }
+}
+
+// nesting depth and fragment of each entry of a raw NCX or nav
+// document, in document order: navPoint nesting and <content src> in
+// an NCX, list nesting and href of the links in the nav marked as the
+// toc
+pub(super) fn entry_links(data: &[u8]) -> Vec<TocLink> {
+    let ncx = data.windows(7).any(|w| w == b"<navMap");
+    let mut out = Vec::new();
+    let mut depth = 0u8;
+    let mut in_toc = false;
+    let mut pos = 0usize;
+    while let Some(lt) = data[pos..].iter().position(|&b| b == b'<') {
+        let start = pos + lt + 1;
+        let Some(len) = data[start..].iter().position(|&b| b == b'>') else {
+            break;
+        };
+        let tag = &data[start..start + len];
+        pos = start + len + 1;
+
+        let (close, body) = match tag.strip_prefix(b"/") {
+            Some(rest) => (true, rest),
+            None => (false, tag),
+        };
+        let end = body
+            .iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'/')
+            .unwrap_or(body.len());
+        // drop a namespace prefix such as ncx: or html:
+        let name = body[..end].rsplit(|&b| b == b':').next().unwrap_or(&[]);
+        let self_closing = tag.ends_with(b"/");
+
+        if ncx {
+            if name.eq_ignore_ascii_case(b"navPoint") {
+                if close {
+                    depth = depth.saturating_sub(1);
+                } else if !self_closing {
+                    out.push(TocLink { depth, frag: 0 });
+                    depth = depth.saturating_add(1);
+                }
+            } else if !close && name.eq_ignore_ascii_case(b"content") {
+                let frag = attr(&body[end..], b"src").map_or(0, href_frag);
+                if let Some(last) = out.last_mut() {
+                    last.frag = frag;
+                }
+            }
+        } else if name.eq_ignore_ascii_case(b"nav") {
+            if close && in_toc {
+                break;
+            }
+            in_toc = !close
+                && tag
+                    .windows(10)
+                    .any(|w| w == b"type=\"toc\"" || w == b"type='toc'");
+        } else if in_toc && name.eq_ignore_ascii_case(b"ol") {
+            depth = if close {
+                depth.saturating_sub(1)
+            } else {
+                depth.saturating_add(1)
+            };
+        } else if in_toc && !close && name.eq_ignore_ascii_case(b"a") {
+            out.push(TocLink {
+                depth: depth.saturating_sub(1),
+                frag: attr(&body[end..], b"href").map_or(0, href_frag),
+            });
+        }
+    }
+    out
+}
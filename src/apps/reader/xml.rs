@@ -0,0 +1,35 @@
+// attribute lookup for the xml and xhtml the readers parse: fb2
+// books, epub toc files and chapter links
+
+// value of the first attribute whose local name is `key`
+pub(super) fn attr<'a>(attrs: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut i = 0;
+    while i < attrs.len() {
+        while i < attrs.len() && (attrs[i].is_ascii_whitespace() || attrs[i] == b'/') {
+            i += 1;
+        }
+        let name_start = i;
+        while i < attrs.len() && attrs[i] != b'=' && !attrs[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let name = &attrs[name_start..i];
+        while i < attrs.len() && (attrs[i].is_ascii_whitespace() || attrs[i] == b'=') {
+            i += 1;
+        }
+        let quote = *attrs.get(i)?;
+        if quote != b'"' && quote != b'\'' {
+            return None;
+        }
+        let val_start = i + 1;
+        let val_len = attrs[val_start..].iter().position(|&b| b == quote)?;
+        i = val_start + val_len + 1;
+        let local = match name.iter().rposition(|&b| b == b':') {
+            Some(p) => &name[p + 1..],
+            None => name,
+        };
+        if local == key {
+            return Some(&attrs[val_start..val_start + val_len]);
+        }
+    }
+    None
+}
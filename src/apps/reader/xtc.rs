@@ -0,0 +1,243 @@
+// xtc / xtch pre-rendered books (xteink)
+//
+// the container holds one bitmap per page, already laid out for the
+// 480x800 panel. a 56-byte header points at a page table of 16-byte
+// entries; each page starts with a 22-byte XTG (1 bit, row-major,
+// 0 = black) or XTH (2 bit) header. XTH pages are two bit planes
+// stored column by column, right to left, 8 pixels per byte going
+// down; they are thresholded to 1 bit with light grey dithered.
+//
+// the page shown is read into a screen-sized 1-bit buffer and blitted
+// into each strip; bookmarks hold the page number instead of an offset
+
+use alloc::vec::Vec;
+
+use crate::board::{SCREEN_H, SCREEN_W};
+use crate::error::{Error, ErrorKind};
+use crate::kernel::KernelHandle;
+use crate::kernel::work_queue::DecodedImage;
+
+use super::{ReaderApp, read_full};
+
+const HEADER_LEN: usize = 56;
+const ENTRY_LEN: u32 = 16;
+const PAGE_HEADER_LEN: usize = 22;
+
+// the metadata block opens with a nul-padded utf-8 title
+const META_TITLE_LEN: usize = 64;
+
+// an .xtch file shows up under its 8.3 name as .XTC
+pub(super) fn is_xtc_filename(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("XTC") || ext.eq_ignore_ascii_case("XTCH"))
+}
+
+// container offsets are u64 on disk; FAT files stay below 4 GB
+#[inline]
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+#[inline]
+fn le16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+pub(super) struct XtcBook {
+    table: u32,
+}
+
+impl XtcBook {
+    pub(super) const fn new() -> Self {
+        Self { table: 0 }
+    }
+}
+
+impl ReaderApp {
+    // header, page count and title; pages load one at a time after
+    pub(super) fn xtc_open(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+
+        let mut hdr = [0u8; HEADER_LEN];
+        read_full(k, name, 0, &mut hdr)?;
+        if &hdr[..3] != b"XTC" || !matches!(hdr[3], 0 | b'H') {
+            return Err(Error::new(ErrorKind::InvalidData, "xtc: bad magic"));
+        }
+
+        let pages = le16(&hdr, 0x06) as u32;
+        if pages == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "xtc: no pages"));
+        }
+        self.xtc.table = le32(&hdr, 0x18);
+        self.file_size = k.file_size(name)?;
+
+        if hdr[0x09] != 0 {
+            let mut meta = [0u8; META_TITLE_LEN];
+            read_full(k, name, le32(&hdr, 0x10), &mut meta)?;
+            let end = meta.iter().position(|&b| b == 0).unwrap_or(meta.len());
+            let title = core::str::from_utf8(&meta[..end]).unwrap_or("").trim();
+            if !title.is_empty() {
+                let n = title.len().min(self.title.len());
+                self.title[..n].copy_from_slice(&title.as_bytes()[..n]);
+                self.title_len = n as u8;
+                if let Err(e) = k.save_title(name, title) {
+                    log::warn!("xtc: failed to save title mapping: {}", e);
+                }
+            }
+        }
+
+        self.pg.page = 0;
+        self.pg.total_pages = pages as usize;
+        self.pg.fully_indexed = true;
+        log::info!(
+            "xtc: {} pages, {}",
+            pages,
+            if hdr[3] == b'H' { "2-bit" } else { "1-bit" }
+        );
+        Ok(())
+    }
+
+    // read pg.page (or the restored bookmark page) into page_img
+    pub(super) fn xtc_load_page(&mut self, k: &mut KernelHandle<'_>) -> crate::error::Result<()> {
+        if let Some(p) = self.restore_offset.take() {
+            self.pg.page = (p as usize).min(self.pg.total_pages - 1);
+        }
+        let (nb, nl) = self.name_copy();
+        let name = core::str::from_utf8(&nb[..nl]).unwrap_or("");
+
+        let mut entry = [0u8; ENTRY_LEN as usize];
+        read_full(
+            k,
+            name,
+            self.xtc.table + self.pg.page as u32 * ENTRY_LEN,
+            &mut entry,
+        )?;
+        let at = le32(&entry, 0);
+
+        let mut ph = [0u8; PAGE_HEADER_LEN];
+        read_full(k, name, at, &mut ph)?;
+        let two_bit = match &ph[..4] {
+            b"XTG\0" => false,
+            b"XTH\0" => true,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "xtc: bad page")),
+        };
+        if ph[9] != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "xtc: compressed page"));
+        }
+        let (w, h) = (le16(&ph, 4), le16(&ph, 6));
+        if w == 0 || h == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "xtc: empty page"));
+        }
+
+        // oversized pages are clipped to the panel
+        let out_w = w.min(SCREEN_W);
+        let out_h = h.min(SCREEN_H);
+        let stride = (out_w as usize).div_ceil(8);
+        let len = stride * out_h as usize;
+
+        let mut data = self.page_img.take().map(|img| img.data).unwrap_or_default();
+        data.clear();
+        data.try_reserve_exact(len)
+            .map_err(|_| Error::new(ErrorKind::OutOfMemory, "xtc: page buffer"))?;
+        data.resize(len, 0);
+
+        let src = at + PAGE_HEADER_LEN as u32;
+        if two_bit {
+            read_planes(k, name, src, w, h, out_w, &mut data, &mut self.pg.buf)?;
+        } else {
+            read_rows(k, name, src, w, stride, &mut data, &mut self.pg.buf)?;
+        }
+
+        self.page_img = Some(DecodedImage {
+            width: out_w,
+            height: out_h,
+            data,
+            stride,
+        });
+        self.rebuild_quick_actions();
+        Ok(())
+    }
+}
+
+// XTG: whole rows at a time, inverted so set bits are black
+fn read_rows(
+    k: &mut KernelHandle<'_>,
+    name: &str,
+    src: u32,
+    w: u16,
+    stride: usize,
+    out: &mut [u8],
+    scratch: &mut [u8],
+) -> crate::error::Result<()> {
+    let src_stride = (w as usize).div_ceil(8);
+    let rows = out.len() / stride;
+    let per_read = (scratch.len() / src_stride).max(1);
+    let mut y = 0;
+    while y < rows {
+        let n = per_read.min(rows - y);
+        let chunk = &mut scratch[..n * src_stride];
+        read_full(k, name, src + (y * src_stride) as u32, chunk)?;
+        for r in 0..n {
+            let row = &chunk[r * src_stride..r * src_stride + stride];
+            let dst = &mut out[(y + r) * stride..(y + r + 1) * stride];
+            for (d, s) in dst.iter_mut().zip(row) {
+                *d = !s;
+            }
+        }
+        y += n;
+    }
+    Ok(())
+}
+
+// XTH: pixel = (plane0 bit << 1) | plane1 bit, 0 white .. 3 black with
+// 1 dark grey and 2 light grey. the second plane alone decides black
+// (dark grey and black); the first plane alone is light grey and gets
+// a sparse 1-in-4 dither. out starts white and is only ever or-ed into,
+// so the planes can be streamed one after the other
+#[allow(clippy::too_many_arguments)]
+fn read_planes(
+    k: &mut KernelHandle<'_>,
+    name: &str,
+    src: u32,
+    w: u16,
+    h: u16,
+    out_w: u16,
+    out: &mut [u8],
+    scratch: &mut [u8],
+) -> crate::error::Result<()> {
+    let (w, out_w) = (w as usize, out_w as usize);
+    let col_bytes = (h as usize).div_ceil(8);
+    let plane_len = (w * h as usize).div_ceil(8);
+    let stride = out_w.div_ceil(8);
+    let rows = out.len() / stride;
+    let per_read = (scratch.len() / col_bytes).max(1);
+
+    for plane in 0..2 {
+        let base = src + (plane * plane_len) as u32;
+        let mut c = 0;
+        while c < w {
+            let n = per_read.min(w - c);
+            let chunk = &mut scratch[..n * col_bytes];
+            read_full(k, name, base + (c * col_bytes) as u32, chunk)?;
+            for i in 0..n {
+                // columns run right to left
+                let x = w - 1 - (c + i);
+                if x >= out_w {
+                    continue;
+                }
+                let col = &chunk[i * col_bytes..(i + 1) * col_bytes];
+                for y in 0..rows {
+                    if (col[y / 8] >> (7 - y % 8)) & 1 == 0 {
+                        continue;
+                    }
+                    if plane == 1 || (x | y) & 1 == 0 {
+                        out[y * stride + x / 8] |= 0x80 >> (x % 8);
+                    }
+                }
+            }
+            c += n;
+        }
+    }
+    Ok(())
+}
@@ -1,12 +1,17 @@
 // settings app UI; configuration types live in kernel::config
 //
-// settings items (6 total, all fit on one screen at default font):
+// settings items (11 total, all fit on one screen at default font):
 //   0: Sleep After    – power management
 //   1: Ghost Clear    – e-paper refresh interval
 //   2: Book Font      – reading font size
 //   3: UI Font        – chrome font size
 //   4: Reading Theme  – Compact / Default / Relaxed / Spacious
 //   5: Swap Buttons   – swap Back/OK with Left/Right for left-handed use
+//   6: Orientation    – Portrait / Landscape while reading
+//   7: Columns        – Auto / 1 / 2 / 3 text columns per page
+//   8: Sleep Screen   – Cover / Page / Image / Card shown while asleep
+//   9: Spreads        – Whole / Split double-page comic spreads
+//  10: Comic Order    – Left-Right / Right-Left halves of a spread
 
 use core::fmt::Write as _;
 
@@ -18,9 +23,10 @@ use crate::fonts;
 use crate::fonts::max_size_idx;
 use crate::kernel::KernelHandle;
 use crate::kernel::config::{
-    self, GHOST_CLEAR_STEP, MAX_GHOST_CLEAR, MAX_SLEEP_TIMEOUT, MIN_GHOST_CLEAR,
-    NUM_READING_THEMES, SLEEP_TIMEOUT_STEP, SystemSettings, WifiConfig, parse_settings_txt,
-    reading_theme, write_settings_txt,
+    self, AUTO_READING_COLUMNS, GHOST_CLEAR_STEP, MAX_GHOST_CLEAR, MAX_READING_COLUMNS,
+    MAX_SLEEP_TIMEOUT, MIN_GHOST_CLEAR, NUM_READING_THEMES, NUM_SLEEP_SCREENS, SLEEP_TIMEOUT_STEP,
+    SystemSettings, WifiConfig, parse_settings_txt, reading_theme, sleep_screen_name,
+    write_settings_txt,
 };
 use crate::ui::{
     Alignment, BUTTON_BAR_H, BitmapLabel, CONTENT_TOP, FULL_CONTENT_W, LARGE_MARGIN, Region,
@@ -38,7 +44,7 @@ const COL_GAP: u16 = 8;
 const VALUE_X: u16 = LABEL_X + LABEL_W + COL_GAP;
 const VALUE_W: u16 = FULL_CONTENT_W - LABEL_W - COL_GAP;
 
-const NUM_ITEMS: usize = 6;
+const NUM_ITEMS: usize = 11;
 const HEADING_ITEMS_GAP: u16 = SECTION_GAP;
 
 impl Default for SettingsApp {
@@ -155,6 +161,11 @@ impl SettingsApp {
             3 => "UI Font",
             4 => "Theme",
             5 => "Swap Buttons",
+            6 => "Orientation",
+            7 => "Columns",
+            8 => "Sleep Screen",
+            9 => "Spreads",
+            10 => "Comic Order",
             _ => "",
         }
     }
@@ -201,6 +212,49 @@ impl SettingsApp {
                     }
                 );
             }
+            6 => {
+                let _ = write!(
+                    buf,
+                    "{}",
+                    if self.settings.landscape {
+                        "Landscape"
+                    } else {
+                        "Portrait"
+                    }
+                );
+            }
+            7 => {
+                if self.settings.columns == AUTO_READING_COLUMNS {
+                    let _ = write!(buf, "Auto");
+                } else {
+                    let _ = write!(buf, "{}", self.settings.columns);
+                }
+            }
+            8 => {
+                let _ = write!(buf, "{}", sleep_screen_name(self.settings.sleep_screen));
+            }
+            9 => {
+                let _ = write!(
+                    buf,
+                    "{}",
+                    if self.settings.comic_split {
+                        "Split"
+                    } else {
+                        "Whole"
+                    }
+                );
+            }
+            10 => {
+                let _ = write!(
+                    buf,
+                    "{}",
+                    if self.settings.comic_rtl {
+                        "Right-Left"
+                    } else {
+                        "Left-Right"
+                    }
+                );
+            }
             _ => {}
         }
     }
@@ -241,6 +295,23 @@ impl SettingsApp {
             5 => {
                 self.settings.swap_buttons = !self.settings.swap_buttons;
             }
+            6 => {
+                self.settings.landscape = !self.settings.landscape;
+            }
+            7 => {
+                if self.settings.columns < MAX_READING_COLUMNS {
+                    self.settings.columns += 1;
+                }
+            }
+            8 => {
+                self.settings.sleep_screen = (self.settings.sleep_screen + 1) % NUM_SLEEP_SCREENS;
+            }
+            9 => {
+                self.settings.comic_split = !self.settings.comic_split;
+            }
+            10 => {
+                self.settings.comic_rtl = !self.settings.comic_rtl;
+            }
             _ => return,
         }
         self.save_needed = true;
@@ -279,6 +350,24 @@ impl SettingsApp {
             5 => {
                 self.settings.swap_buttons = !self.settings.swap_buttons;
             }
+            6 => {
+                self.settings.landscape = !self.settings.landscape;
+            }
+            7 => {
+                if self.settings.columns > AUTO_READING_COLUMNS {
+                    self.settings.columns -= 1;
+                }
+            }
+            8 => {
+                self.settings.sleep_screen =
+                    (self.settings.sleep_screen + NUM_SLEEP_SCREENS - 1) % NUM_SLEEP_SCREENS;
+            }
+            9 => {
+                self.settings.comic_split = !self.settings.comic_split;
+            }
+            10 => {
+                self.settings.comic_rtl = !self.settings.comic_rtl;
+            }
             _ => return,
         }
         self.save_needed = true;
@@ -368,12 +457,12 @@ impl App<AppId> for SettingsApp {
                     self.scroll_into_view();
                 }
                 if self.scroll != old_scroll {
-                    ctx.mark_dirty(self.list_region());
+                    ctx.mark_dirty_fast(self.list_region());
                 } else if self.selected != old_selected {
                     let old_vis = old_selected - old_scroll;
                     let new_vis = self.selected - self.scroll;
-                    ctx.mark_dirty(self.row_region(old_vis));
-                    ctx.mark_dirty(self.row_region(new_vis));
+                    ctx.mark_dirty_fast(self.row_region(old_vis));
+                    ctx.mark_dirty_fast(self.row_region(new_vis));
                 }
                 Transition::None
             }
@@ -388,12 +477,12 @@ impl App<AppId> for SettingsApp {
                     self.scroll_into_view();
                 }
                 if self.scroll != old_scroll {
-                    ctx.mark_dirty(self.list_region());
+                    ctx.mark_dirty_fast(self.list_region());
                 } else if self.selected != old_selected {
                     let old_vis = old_selected - old_scroll;
                     let new_vis = self.selected - self.scroll;
-                    ctx.mark_dirty(self.row_region(old_vis));
-                    ctx.mark_dirty(self.row_region(new_vis));
+                    ctx.mark_dirty_fast(self.row_region(old_vis));
+                    ctx.mark_dirty_fast(self.row_region(new_vis));
                 }
                 Transition::None
             }
@@ -0,0 +1,299 @@
+// sleep screen: what stays on the panel while the device sleeps
+//
+// prepare runs from AppLayer::prepare_sleep while the SD card is
+// still up and does every read and decode; draw is then a plain
+// strip render with no I/O, called once per strip like any frame
+//
+// Cover and Image fall back to the info card when there is no open
+// book, no cover, or nothing decodable in _PULP/SLEEP/; Page leaves
+// the last frame on the panel and refreshes only a small badge
+
+use core::fmt::Write as _;
+
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+use crate::apps::reader::{ReaderApp, decode_app_image, is_image_ext_jpeg, is_image_ext_png};
+use crate::board::Orientation;
+use crate::drivers::battery;
+use crate::drivers::storage::DirEntry;
+use crate::drivers::strip::StripBuffer;
+use crate::fonts::{self, bitmap::BitmapFont};
+use crate::kernel::KernelHandle;
+use crate::kernel::SleepScreen;
+use crate::kernel::config::{SLEEP_SCREEN_COVER, SLEEP_SCREEN_IMAGE, SLEEP_SCREEN_PAGE};
+use crate::kernel::work_queue::DecodedImage;
+use crate::ui::{Alignment, LARGE_MARGIN, Region, StackFmt};
+
+pub const SLEEP_DIR: &str = "SLEEP";
+
+// images considered per sleep; the rest of the folder is ignored
+const MAX_SLEEP_IMAGES: usize = 32;
+
+// sleep screens are always portrait
+const W: u16 = Orientation::Portrait.screen_w();
+const H: u16 = Orientation::Portrait.screen_h();
+
+const BADGE_W: u16 = 104;
+const BADGE_H: u16 = 32;
+const BADGE_MARGIN: u16 = 12;
+
+const TITLE_CAP: usize = 64;
+const BAR_W: u16 = 320;
+const BAR_H: u16 = 12;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Image,
+    Badge,
+    Card,
+}
+
+pub struct SleepView {
+    mode: Mode,
+    image: Option<DecodedImage>,
+    badge: Region,
+    title: [u8; TITLE_CAP],
+    title_len: u8,
+    // None when no book is open
+    progress: Option<u8>,
+    battery_pct: u8,
+    ui_font_idx: u8,
+}
+
+impl Default for SleepView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SleepView {
+    pub const fn new() -> Self {
+        Self {
+            mode: Mode::Card,
+            image: None,
+            badge: Region::new(0, 0, 0, 0),
+            title: [0u8; TITLE_CAP],
+            title_len: 0,
+            progress: None,
+            battery_pct: 0,
+            ui_font_idx: 0,
+        }
+    }
+
+    // reader is passed only when it holds an open book
+    pub fn prepare(
+        &mut self,
+        setting: u8,
+        reader: Option<&ReaderApp>,
+        orientation: Orientation,
+        ui_font_idx: u8,
+        k: &mut KernelHandle<'_>,
+    ) -> SleepScreen {
+        self.image = None;
+        self.ui_font_idx = ui_font_idx;
+        self.battery_pct = battery::battery_percentage(k.battery_mv());
+        self.title_len = 0;
+        self.progress = None;
+        if let Some(r) = reader {
+            let t = r.book_title().as_bytes();
+            let n = t.len().min(TITLE_CAP);
+            self.title[..n].copy_from_slice(&t[..n]);
+            self.title_len = n as u8;
+            self.progress = Some(r.book_progress());
+        }
+
+        self.mode = match setting {
+            SLEEP_SCREEN_COVER => match reader.map(|r| r.decode_cover(k, W, H)) {
+                Some(Ok(img)) => {
+                    self.image = Some(img);
+                    Mode::Image
+                }
+                Some(Err(e)) => {
+                    log::warn!("sleep: no cover: {}", e);
+                    Mode::Card
+                }
+                None => Mode::Card,
+            },
+            SLEEP_SCREEN_IMAGE => match random_image(k) {
+                Some(img) => {
+                    self.image = Some(img);
+                    Mode::Image
+                }
+                None => Mode::Card,
+            },
+            SLEEP_SCREEN_PAGE => Mode::Badge,
+            _ => Mode::Card,
+        };
+
+        match self.mode {
+            Mode::Badge => {
+                self.badge = Region::new(
+                    orientation.screen_w() - BADGE_W - BADGE_MARGIN,
+                    orientation.screen_h() - BADGE_H - BADGE_MARGIN,
+                    BADGE_W,
+                    BADGE_H,
+                );
+                SleepScreen::Badge(self.badge)
+            }
+            Mode::Image | Mode::Card => SleepScreen::Full,
+        }
+    }
+
+    pub fn draw(&self, strip: &mut StripBuffer) {
+        let font = fonts::chrome_font();
+        match self.mode {
+            Mode::Badge => self.draw_badge(strip, font),
+            Mode::Image => {
+                if let Some(ref img) = self.image {
+                    let x = ((W as i32 - img.width as i32) / 2).max(0);
+                    let y = ((H as i32 - img.height as i32) / 2).max(0);
+                    strip.blit_1bpp(
+                        &img.data,
+                        0,
+                        img.width as usize,
+                        img.height as usize,
+                        img.stride,
+                        x,
+                        y,
+                        true,
+                    );
+                }
+            }
+            Mode::Card => self.draw_card(strip, font),
+        }
+    }
+
+    fn draw_badge(&self, strip: &mut StripBuffer, font: &'static BitmapFont) {
+        let _ = self
+            .badge
+            .to_rect()
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+            .draw(strip);
+        font.draw_aligned(
+            strip,
+            self.badge,
+            "Sleeping",
+            Alignment::Center,
+            BinaryColor::Off,
+        );
+    }
+
+    fn draw_card(&self, strip: &mut StripBuffer, font: &'static BitmapFont) {
+        let heading = fonts::heading_font(self.ui_font_idx);
+        let body = fonts::body_font(self.ui_font_idx);
+        let line_w = W - 2 * LARGE_MARGIN;
+        let mut y = H / 3;
+
+        if self.title_len > 0 {
+            let title = core::str::from_utf8(&self.title[..self.title_len as usize]).unwrap_or("");
+            let (first, second) = split_title(heading, title, line_w);
+            for line in [first, second] {
+                if line.is_empty() {
+                    continue;
+                }
+                let r = Region::new(LARGE_MARGIN, y, line_w, heading.line_height);
+                heading.draw_aligned(strip, r, line, Alignment::Center, BinaryColor::On);
+                y += heading.line_height;
+            }
+        } else {
+            let r = Region::new(LARGE_MARGIN, y, line_w, heading.line_height);
+            heading.draw_aligned(strip, r, "Sleeping", Alignment::Center, BinaryColor::On);
+            y += heading.line_height;
+        }
+
+        if let Some(pct) = self.progress {
+            y += 2 * body.line_height;
+            let bar_x = (W - BAR_W) / 2;
+            let _ = Rectangle::new(
+                Point::new(bar_x as i32, y as i32),
+                Size::new(BAR_W as u32, BAR_H as u32),
+            )
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(strip);
+            let fill_w = (BAR_W as u32 - 4) * pct.min(100) as u32 / 100;
+            if fill_w > 0 {
+                let _ = Rectangle::new(
+                    Point::new(bar_x as i32 + 2, y as i32 + 2),
+                    Size::new(fill_w, BAR_H as u32 - 4),
+                )
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(strip);
+            }
+            y += BAR_H + body.line_height / 2;
+
+            let mut buf = StackFmt::<20>::new();
+            let _ = write!(buf, "{}% read", pct);
+            let r = Region::new(LARGE_MARGIN, y, line_w, body.line_height);
+            body.draw_aligned(strip, r, buf.as_str(), Alignment::Center, BinaryColor::On);
+        }
+
+        let mut buf = StackFmt::<20>::new();
+        let _ = write!(buf, "Battery {}%", self.battery_pct);
+        let r = Region::new(
+            LARGE_MARGIN,
+            H - LARGE_MARGIN - font.line_height,
+            line_w,
+            font.line_height,
+        );
+        font.draw_aligned(strip, r, buf.as_str(), Alignment::Center, BinaryColor::On);
+    }
+}
+
+// pick one JPEG/PNG from _PULP/SLEEP/; uptime since wake is the only
+// entropy there is, so it is mixed before taking the modulus
+fn random_image(k: &mut KernelHandle<'_>) -> Option<DecodedImage> {
+    let mut entries = [DirEntry::EMPTY; MAX_SLEEP_IMAGES];
+    let count = match k.list_app_subdir(SLEEP_DIR, &mut entries) {
+        Ok(n) => n,
+        Err(e) => {
+            log::info!("sleep: no {} folder: {}", SLEEP_DIR, e);
+            return None;
+        }
+    };
+
+    let mut images = [0u8; MAX_SLEEP_IMAGES];
+    let mut n = 0;
+    for (i, e) in entries[..count].iter().enumerate() {
+        let name = e.name_str();
+        if is_image_ext_jpeg(name) || is_image_ext_png(name) {
+            images[n] = i as u8;
+            n += 1;
+        }
+    }
+    if n == 0 {
+        return None;
+    }
+
+    let seed = k.uptime_secs() ^ (k.battery_mv() as u32);
+    let start = (seed.wrapping_mul(0x9E37_79B9) >> 16) as usize % n;
+
+    // a file that fails to decode shouldn't blank the screen; try the rest
+    for step in 0..n {
+        let entry = &entries[images[(start + step) % n] as usize];
+        match decode_app_image(k, SLEEP_DIR, entry.name_str(), W, H) {
+            Ok(img) => return Some(img),
+            Err(e) => log::warn!("sleep: {} failed: {}", entry.name_str(), e),
+        }
+    }
+    None
+}
+
+// break a title that doesn't fit into two lines at the space nearest
+// the middle; the second line is clipped by the strip if still too wide
+fn split_title<'a>(font: &BitmapFont, title: &'a str, width: u16) -> (&'a str, &'a str) {
+    if font.measure_str(title) <= width {
+        return (title, "");
+    }
+    let mid = title.len() / 2;
+    let split = title
+        .char_indices()
+        .filter(|&(_, c)| c == ' ')
+        .min_by_key(|&(i, _)| i.abs_diff(mid))
+        .map(|(i, _)| i);
+    match split {
+        Some(i) => (&title[..i], &title[i + 1..]),
+        None => (title, ""),
+    }
+}
@@ -0,0 +1,156 @@
+// epub cover thumbnails cached in _PULP/THUMBS/
+//
+// FilesApp builds them in the background, one book per tick after
+// the title scan: the cover is found through the OPF and decoded
+// straight to THUMB_W x THUMB_H (the decoders scale and dither to
+// 1-bit), then stored under the fnv1a of the file name together with
+// the OPF author
+//
+// file layout: [w u16][h u16][1-bit rows, stride (w+7)/8][author];
+// w = h = 0 when the book has no usable cover, so it is not rescanned
+//
+// load_thumb is the read side; any screen can show a cover and its
+// author with one small read and without reopening the ZIP
+
+use alloc::vec::Vec;
+
+use smol_epub::cache;
+use smol_epub::epub::{self, EpubMeta, EpubSpine};
+use smol_epub::zip::ZipIndex;
+
+use crate::apps::reader::{decode_cover, find_cover, img_cache_name, img_cache_str};
+use crate::kernel::KernelHandle;
+use crate::kernel::work_queue::DecodedImage;
+use pulp_kernel::util::floor_char_boundary;
+
+pub const THUMB_DIR: &str = "THUMBS";
+pub const THUMB_W: u16 = 96;
+pub const THUMB_H: u16 = 144;
+
+const AUTHOR_CAP: usize = 48;
+
+pub struct BookThumb {
+    pub image: Option<DecodedImage>,
+    author: [u8; AUTHOR_CAP],
+    author_len: u8,
+}
+
+impl BookThumb {
+    pub fn author(&self) -> &str {
+        core::str::from_utf8(&self.author[..self.author_len as usize]).unwrap_or("")
+    }
+}
+
+fn thumb_name(epub_name: &str) -> [u8; 12] {
+    img_cache_name(cache::fnv1a(epub_name.as_bytes()))
+}
+
+// true once the book has been scanned, cover or not
+pub fn has_thumb(k: &mut KernelHandle<'_>, epub_name: &str) -> bool {
+    let name = thumb_name(epub_name);
+    k.file_size_app_subdir(THUMB_DIR, img_cache_str(&name))
+        .is_ok()
+}
+
+pub fn load_thumb(k: &mut KernelHandle<'_>, epub_name: &str) -> Option<BookThumb> {
+    let name = thumb_name(epub_name);
+    let file = img_cache_str(&name);
+    let size = k.file_size_app_subdir(THUMB_DIR, file).ok()? as usize;
+    if size < 4 {
+        return None;
+    }
+
+    let mut header = [0u8; 4];
+    k.read_app_subdir_chunk(THUMB_DIR, file, 0, &mut header)
+        .ok()?;
+    let width = u16::from_le_bytes([header[0], header[1]]);
+    let height = u16::from_le_bytes([header[2], header[3]]);
+    let stride = (width as usize).div_ceil(8);
+    let data_len = stride * height as usize;
+    if 4 + data_len > size {
+        return None;
+    }
+
+    let image = if data_len > 0 {
+        let mut data = Vec::new();
+        data.try_reserve_exact(data_len).ok()?;
+        data.resize(data_len, 0);
+        k.read_app_subdir_chunk(THUMB_DIR, file, 4, &mut data)
+            .ok()?;
+        Some(DecodedImage {
+            width,
+            height,
+            data,
+            stride,
+        })
+    } else {
+        None
+    };
+
+    let mut author = [0u8; AUTHOR_CAP];
+    let author_len = (size - 4 - data_len).min(AUTHOR_CAP);
+    if author_len > 0 {
+        let off = (4 + data_len) as u32;
+        k.read_app_subdir_chunk(THUMB_DIR, file, off, &mut author[..author_len])
+            .ok()?;
+    }
+
+    Some(BookThumb {
+        image,
+        author,
+        author_len: author_len as u8,
+    })
+}
+
+pub fn mark_no_thumb(k: &mut KernelHandle<'_>, epub_name: &str) {
+    let name = thumb_name(epub_name);
+    let _ = k.ensure_app_subdir(THUMB_DIR);
+    let _ = k.write_app_subdir(THUMB_DIR, img_cache_str(&name), &[0u8; 4]);
+}
+
+pub fn delete_thumb(k: &mut KernelHandle<'_>, epub_name: &str) {
+    let name = thumb_name(epub_name);
+    let _ = k.delete_app_subdir(THUMB_DIR, img_cache_str(&name));
+}
+
+// returns Ok(false) when the book has no cover (author still stored)
+pub fn make_thumb(
+    k: &mut KernelHandle<'_>,
+    epub_name: &str,
+    zip: &ZipIndex,
+    opf: &[u8],
+    opf_dir: &str,
+) -> crate::error::Result<bool> {
+    let mut meta = EpubMeta::new();
+    let mut spine = EpubSpine::new();
+    let author_ok = epub::parse_opf(opf, opf_dir, zip, &mut meta, &mut spine).is_ok();
+    drop(spine);
+    let author = if author_ok {
+        let a = meta.author_str();
+        &a.as_bytes()[..floor_char_boundary(a, AUTHOR_CAP)]
+    } else {
+        &[][..]
+    };
+
+    let img = match find_cover(opf, opf_dir, zip) {
+        Some(idx) => Some(decode_cover(k, epub_name, zip, idx, THUMB_W, THUMB_H)?),
+        None => None,
+    };
+
+    let name = thumb_name(epub_name);
+    let file = img_cache_str(&name);
+    k.ensure_app_subdir(THUMB_DIR)?;
+    let mut header = [0u8; 4];
+    if let Some(ref img) = img {
+        header[0..2].copy_from_slice(&img.width.to_le_bytes());
+        header[2..4].copy_from_slice(&img.height.to_le_bytes());
+    }
+    k.write_app_subdir(THUMB_DIR, file, &header)?;
+    if let Some(ref img) = img {
+        k.append_app_subdir(THUMB_DIR, file, &img.data)?;
+    }
+    if !author.is_empty() {
+        k.append_app_subdir(THUMB_DIR, file, author)?;
+    }
+    Ok(img.is_some())
+}
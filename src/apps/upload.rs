@@ -1,6 +1,8 @@
 // wifi upload server: HTTP file upload + mDNS (pulp.local)
+// also serves _PULP/COLLECT.TXT for editing collections in the browser
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt::Write as FmtWrite;
 
 use embassy_futures::select::{Either, select};
@@ -13,6 +15,7 @@ use esp_hal::delay::Delay;
 use esp_radio::wifi::{ClientConfig, Config, ModeConfig};
 use log::info;
 
+use crate::apps::collections::{self, Collections};
 use crate::board::action::{Action, ActionEvent, ButtonMapper};
 use crate::board::{Epd, SCREEN_H, SCREEN_W};
 use crate::drivers::sdcard::SdStorage;
@@ -43,6 +46,7 @@ const HTTP_200_TEXT: &[u8] =
 const HTTP_500_TEXT: &[u8] =
     b"HTTP/1.0 500 Internal Server Error\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n";
 const HTTP_404: &[u8] = b"HTTP/1.0 404 Not Found\r\nConnection: close\r\n\r\nNot Found";
+const HTTP_411: &[u8] = b"HTTP/1.0 411 Length Required\r\nConnection: close\r\n\r\nLength Required";
 
 const UPLOAD_PAGE: &[u8] = include_bytes!("../../assets/upload.html");
 
@@ -331,6 +335,7 @@ pub async fn run_upload_mode(
             ServerEvent::Deleted { name, name_len } => {
                 let fname = core::str::from_utf8(&name[..name_len as usize]).unwrap_or("???");
                 info!("upload: deleted '{}'", fname);
+                drop_from_collections(sd, &name[..name_len as usize]);
             }
             ServerEvent::DeleteFailed => {
                 info!("upload: file delete failed");
@@ -554,12 +559,107 @@ where
         }
     }
 
+    if is_get && path == b"/collections" {
+        let text = collections_txt(sd);
+        let _ = socket.write_all(HTTP_200_TEXT).await;
+        let _ = socket.write_all(&text).await;
+        let _ = socket.flush().await;
+        close_socket(&mut socket).await;
+        return ServerEvent::Nothing;
+    }
+
+    if is_post && path == b"/collections" {
+        // the body replaces the whole file, so a request that does not
+        // say how long it is must not be read as an empty list
+        let Some(content_len) = extract_content_length(headers) else {
+            let _ = socket.write_all(HTTP_411).await;
+            let _ = socket.flush().await;
+            close_socket(&mut socket).await;
+            return ServerEvent::Nothing;
+        };
+        if content_len > collections::FILE_CAP {
+            send_error_response(&mut socket, "Collections too large").await;
+            close_socket(&mut socket).await;
+            return ServerEvent::Nothing;
+        }
+
+        let mut text = Vec::new();
+        if text.try_reserve_exact(content_len).is_err() {
+            send_error_response(&mut socket, "Out of memory").await;
+            close_socket(&mut socket).await;
+            return ServerEvent::Nothing;
+        }
+        let have = initial_body.len().min(content_len);
+        text.extend_from_slice(&initial_body[..have]);
+
+        let mut chunk = [0u8; 256];
+        while text.len() < content_len {
+            let want = (content_len - text.len()).min(chunk.len());
+            match socket.read(&mut chunk[..want]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => text.extend_from_slice(&chunk[..n]),
+            }
+        }
+        if text.len() < content_len {
+            send_error_response(&mut socket, "Collections truncated").await;
+            close_socket(&mut socket).await;
+            return ServerEvent::Nothing;
+        }
+
+        match save_collections_txt(sd, &text) {
+            Ok(()) => {
+                let _ = socket.write_all(HTTP_200_TEXT).await;
+                let _ = socket.write_all(b"OK").await;
+                let _ = socket.flush().await;
+            }
+            Err(e) => {
+                info!("upload: collections save failed: {}", e);
+                send_error_response(&mut socket, "save failed").await;
+            }
+        }
+        close_socket(&mut socket).await;
+        return ServerEvent::Nothing;
+    }
+
     let _ = socket.write_all(HTTP_404).await;
     let _ = socket.flush().await;
     close_socket(&mut socket).await;
     ServerEvent::Nothing
 }
 
+// the file as the device would write it, so the page always shows
+// what the device actually understood
+fn collections_txt(sd: &SdStorage) -> Vec<u8> {
+    let mut colls = Collections::new();
+    colls.load_sd(sd);
+    let mut buf = Vec::new();
+    if buf.try_reserve_exact(collections::FILE_CAP).is_err() {
+        return buf;
+    }
+    buf.resize(collections::FILE_CAP, 0);
+    let n = colls.write(&mut buf);
+    buf.truncate(n);
+    buf
+}
+
+// parse before writing: whatever the browser sent, the file on the
+// card stays in the format the device reads
+fn save_collections_txt(sd: &SdStorage, text: &[u8]) -> crate::error::Result<()> {
+    let mut colls = Collections::new();
+    colls.parse(text);
+    colls.save_sd(sd)
+}
+
+fn drop_from_collections(sd: &SdStorage, name: &[u8]) {
+    let mut colls = Collections::new();
+    colls.load_sd(sd);
+    if colls.remove_everywhere(name) {
+        if let Err(e) = colls.save_sd(sd) {
+            info!("upload: collections save failed: {}", e);
+        }
+    }
+}
+
 async fn handle_upload(
     socket: &mut TcpSocket<'_>,
     sd: &SdStorage,
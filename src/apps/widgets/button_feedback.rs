@@ -3,14 +3,15 @@
 // renders action labels ("Back", "OK", "<<", ">>") near the
 // physical button positions so users know what each button does.
 // uses the shared ButtonMapper so labels update when buttons are
-// swapped via settings.
+// swapped via settings. in landscape the bottom row sits on the
+// right edge and the side buttons on the top edge, so tabs follow.
 
 use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::PrimitiveStyle};
 
 use crate::board::action::{Action, ButtonMapper};
 use crate::board::button::Button;
 use crate::board::layout::{CX_BACK, CX_CONFIRM, CX_LEFT, CX_RIGHT, CY_VOL_DOWN, CY_VOL_UP};
-use crate::board::{SCREEN_H, SCREEN_W};
+use crate::board::{HEIGHT, SCREEN_H, SCREEN_W, WIDTH};
 use crate::drivers::strip::StripBuffer;
 use crate::fonts::bitmap::BitmapFont;
 use crate::fonts::font_data;
@@ -21,6 +22,9 @@ const TAB_H: u16 = 22;
 
 pub const BUTTON_BAR_H: u16 = TAB_H + BOTTOM_INSET;
 
+// landscape: the bar becomes a column along the right edge
+pub const BUTTON_BAR_W: u16 = TAB_W + BOTTOM_INSET;
+
 const RIDGE_W: u16 = 22;
 const RIDGE_H: u16 = 36;
 
@@ -74,20 +78,32 @@ const BUMPS: [BumpDef; NUM_BUMPS] = [
 
 const BOTTOM_INSET: u16 = 4;
 
-fn bump_region(def: &BumpDef) -> Region {
-    match def.edge {
-        Edge::Bottom => Region::new(
+fn bump_region(def: &BumpDef, landscape: bool) -> Region {
+    // landscape is the unrotated panel: a portrait x along the bottom
+    // edge becomes HEIGHT - x down the right edge, and a portrait y
+    // along the right edge becomes the same x along the top edge
+    match (def.edge, landscape) {
+        (Edge::Bottom, false) => Region::new(
             def.center.saturating_sub(TAB_W / 2),
             SCREEN_H - TAB_H - BOTTOM_INSET,
             TAB_W,
             TAB_H,
         ),
-        Edge::Right => Region::new(
+        (Edge::Right, false) => Region::new(
             SCREEN_W - RIDGE_W,
             def.center.saturating_sub(RIDGE_H / 2),
             RIDGE_W,
             RIDGE_H,
         ),
+        (Edge::Bottom, true) => Region::new(
+            WIDTH - TAB_W - BOTTOM_INSET,
+            (HEIGHT - def.center).saturating_sub(TAB_H / 2),
+            TAB_W,
+            TAB_H,
+        ),
+        (Edge::Right, true) => {
+            Region::new(def.center.saturating_sub(RIDGE_H / 2), 0, RIDGE_H, RIDGE_W)
+        }
     }
 }
 
@@ -105,6 +121,7 @@ fn action_label(action: Action) -> &'static str {
 
 pub struct ButtonFeedback {
     swap: bool,
+    landscape: bool,
     font: Option<&'static BitmapFont>,
 }
 
@@ -118,6 +135,7 @@ impl ButtonFeedback {
     pub const fn new() -> Self {
         Self {
             swap: false,
+            landscape: false,
             font: None,
         }
     }
@@ -135,22 +153,36 @@ impl ButtonFeedback {
         }
     }
 
+    pub fn set_landscape(&mut self, landscape: bool) -> bool {
+        if self.landscape != landscape {
+            self.landscape = landscape;
+            true
+        } else {
+            false
+        }
+    }
+
+    // area covered by the bottom-row tabs in the current orientation
+    pub fn bar_region(&self) -> Region {
+        if self.landscape {
+            Region::new(WIDTH - BUTTON_BAR_W, 0, BUTTON_BAR_W, HEIGHT)
+        } else {
+            Region::new(0, SCREEN_H - BUTTON_BAR_H, SCREEN_W, BUTTON_BAR_H)
+        }
+    }
+
     pub fn draw(&self, strip: &mut StripBuffer) {
         let font = self.font.unwrap_or(&font_data::REGULAR_BODY_SMALL);
-        let mapper = if self.swap {
-            let mut m = ButtonMapper::new();
-            m.set_swap(true);
-            m
-        } else {
-            ButtonMapper::new()
-        };
+        let mut mapper = ButtonMapper::new();
+        mapper.set_swap(self.swap);
+        mapper.set_landscape(self.landscape);
 
         for def in BUMPS.iter() {
             if def.edge != Edge::Bottom {
                 continue;
             }
 
-            let r = bump_region(def);
+            let r = bump_region(def, self.landscape);
 
             if !r.intersects(strip.logical_window()) {
                 continue;
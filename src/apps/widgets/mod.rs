@@ -12,7 +12,7 @@ pub mod quick_menu;
 pub mod selectable_row;
 
 pub use bitmap_label::{BitmapDynLabel, BitmapLabel};
-pub use button_feedback::{BUTTON_BAR_H, ButtonFeedback};
+pub use button_feedback::{BUTTON_BAR_H, BUTTON_BAR_W, ButtonFeedback};
 pub use format::{draw_position_indicator, fmt_percent, fmt_position};
 pub use list::ListSelection;
 pub use quick_menu::QuickMenu;
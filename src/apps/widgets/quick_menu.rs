@@ -1,6 +1,6 @@
 use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::PrimitiveStyle};
 
-use crate::board::SCREEN_W;
+use crate::board::Orientation;
 use crate::board::action::Action;
 use crate::drivers::strip::StripBuffer;
 use crate::fonts::bitmap::BitmapFont;
@@ -12,16 +12,17 @@ use crate::ui::{Alignment, Region, wrap_next, wrap_prev};
 use super::selectable_row::draw_selection_if_visible;
 
 const OVERLAY_W: u16 = 400;
-const OVERLAY_X: u16 = (SCREEN_W - OVERLAY_W) / 2;
-const OVERLAY_BOTTOM: u16 = 760;
+// gap between the overlay and the bottom of the screen
+const OVERLAY_BOTTOM_GAP: u16 = 40;
 const ITEM_H: u16 = 40;
 const ITEM_GAP: u16 = 4;
 const ITEM_STRIDE: u16 = ITEM_H + ITEM_GAP;
 const PAD_TOP: u16 = 10;
 const PAD_BOTTOM: u16 = 8;
-const LABEL_X: u16 = OVERLAY_X + 16;
+// column offsets from the overlay's left edge
+const LABEL_DX: u16 = 16;
 const LABEL_W: u16 = 150;
-const VALUE_X: u16 = LABEL_X + LABEL_W + 8;
+const VALUE_DX: u16 = LABEL_DX + LABEL_W + 8;
 const VALUE_W: u16 = OVERLAY_W - 16 - LABEL_W - 8 - 16;
 const HELP_H: u16 = 20;
 
@@ -73,6 +74,7 @@ pub struct QuickMenu {
     selected: usize,
     pub dirty: bool,
     overlay_region: Region,
+    orientation: Orientation,
     font: Option<&'static BitmapFont>,
 }
 
@@ -92,6 +94,7 @@ impl QuickMenu {
             selected: 0,
             dirty: false,
             overlay_region: Region::new(0, 0, 0, 0),
+            orientation: Orientation::Portrait,
             font: None,
         }
     }
@@ -100,6 +103,11 @@ impl QuickMenu {
         self.font = Some(font);
     }
 
+    // takes effect on the next show()
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
     pub fn show(&mut self, app_actions: &[QuickAction]) {
         let n_app = app_actions.len().min(MAX_APP_ACTIONS);
         self.app_count = n_app;
@@ -133,7 +141,7 @@ impl QuickMenu {
         self.selected = 0;
         self.open = true;
         self.dirty = true;
-        self.overlay_region = Self::compute_region(self.count);
+        self.overlay_region = self.compute_region(self.count);
     }
 
     pub fn hide(&mut self) {
@@ -242,10 +250,11 @@ impl QuickMenu {
         }
     }
 
-    fn compute_region(total_items: usize) -> Region {
+    fn compute_region(&self, total_items: usize) -> Region {
         let content_h = PAD_TOP + (ITEM_STRIDE * total_items as u16) + HELP_H + PAD_BOTTOM;
-        let y = OVERLAY_BOTTOM - content_h;
-        Region::new(OVERLAY_X, y, OVERLAY_W, content_h)
+        let x = (self.orientation.screen_w() - OVERLAY_W) / 2;
+        let y = (self.orientation.screen_h() - OVERLAY_BOTTOM_GAP).saturating_sub(content_h);
+        Region::new(x, y, OVERLAY_W, content_h)
     }
 
     fn item_y(&self, i: usize) -> u16 {
@@ -253,17 +262,32 @@ impl QuickMenu {
     }
 
     fn item_label_region(&self, i: usize) -> Region {
-        Region::new(LABEL_X, self.item_y(i), LABEL_W, ITEM_H)
+        Region::new(
+            self.overlay_region.x + LABEL_DX,
+            self.item_y(i),
+            LABEL_W,
+            ITEM_H,
+        )
     }
 
     fn item_value_region(&self, i: usize) -> Region {
-        Region::new(VALUE_X, self.item_y(i), VALUE_W, ITEM_H)
+        Region::new(
+            self.overlay_region.x + VALUE_DX,
+            self.item_y(i),
+            VALUE_W,
+            ITEM_H,
+        )
     }
 
     fn help_region(&self) -> Region {
         let last = self.count.saturating_sub(1);
         let below_last = self.item_y(last) + ITEM_STRIDE + 2;
-        Region::new(OVERLAY_X + 12, below_last, OVERLAY_W - 24, HELP_H)
+        Region::new(
+            self.overlay_region.x + 12,
+            below_last,
+            OVERLAY_W - 24,
+            HELP_H,
+        )
     }
 
     fn format_value(&self, i: usize, buf: &mut StackFmt<20>) {
@@ -310,7 +334,7 @@ impl QuickMenu {
 
         for i in 0..self.count {
             let selected = i == self.selected;
-            let row_region = Region::new(OVERLAY_X, self.item_y(i), OVERLAY_W, ITEM_H);
+            let row_region = Region::new(self.overlay_region.x, self.item_y(i), OVERLAY_W, ITEM_H);
             let fg = draw_selection_if_visible(strip, row_region, selected);
 
             let label_region = self.item_label_region(i);
@@ -3,14 +3,22 @@
 // zero heap, zero parsing at runtime
 //
 // five size tiers: 0=XSmall  1=Small  2=Medium  3=Large  4=XLarge
+//
+// large scripts (CJK) don't fit in flash; see paged.rs for the
+// on-SD glyph tables the reader falls back to
 
 pub mod bitmap;
+pub mod paged;
 
 #[allow(clippy::all)]
 pub mod font_data {
     include!(concat!(env!("OUT_DIR"), "/font_data.rs"));
 }
 
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
 use crate::drivers::strip::StripBuffer;
 use bitmap::BitmapFont;
 
@@ -76,6 +84,24 @@ pub fn heading_font(idx: u8) -> &'static BitmapFont {
     }
 }
 
+// fixed-width spaces the fonts don't carry draw as a plain space
+#[inline]
+fn substitute(ch: char) -> char {
+    match ch {
+        '\u{00A0}' | '\u{2007}' | '\u{202F}' => ' ',
+        _ => ch,
+    }
+}
+
+// zero-width format characters: no ink, no advance, never boxed
+#[inline]
+fn is_invisible(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{00AD}' | '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Style {
     Regular,
@@ -86,12 +112,18 @@ pub enum Style {
 
 // complete set of four style variants at a single size tier
 // missing weights fall back to regular automatically
+//
+// glyph lookup walks a chain: style font, regular, then the fallback
+// fonts build.rs found in assets/fonts/fallback/ (body or heading
+// size). codepoints nothing covers draw as a hollow replacement box
 #[derive(Clone, Copy)]
 pub struct FontSet {
     regular: &'static BitmapFont,
     bold: &'static BitmapFont,
     italic: &'static BitmapFont,
     heading: &'static BitmapFont,
+    fallback: &'static [&'static BitmapFont],
+    heading_fallback: &'static [&'static BitmapFont],
 }
 
 impl FontSet {
@@ -100,6 +132,7 @@ impl FontSet {
         bold_candidate: &'static BitmapFont,
         italic_candidate: &'static BitmapFont,
         heading: &'static BitmapFont,
+        tier: usize,
     ) -> Self {
         let bold = if bold_candidate.glyph('A').advance > 0 {
             bold_candidate
@@ -116,6 +149,8 @@ impl FontSet {
             bold,
             italic,
             heading,
+            fallback: font_data::FALLBACK_BODY[tier],
+            heading_fallback: font_data::FALLBACK_HEADING[tier],
         }
     }
 
@@ -126,36 +161,42 @@ impl FontSet {
                 &font_data::BOLD_BODY_XSMALL,
                 &font_data::ITALIC_BODY_XSMALL,
                 &font_data::REGULAR_HEADING_XSMALL,
+                0,
             ),
             1 => Self::from_fonts(
                 &font_data::REGULAR_BODY_SMALL,
                 &font_data::BOLD_BODY_SMALL,
                 &font_data::ITALIC_BODY_SMALL,
                 &font_data::REGULAR_HEADING_SMALL,
+                1,
             ),
             2 => Self::from_fonts(
                 &font_data::REGULAR_BODY_MEDIUM,
                 &font_data::BOLD_BODY_MEDIUM,
                 &font_data::ITALIC_BODY_MEDIUM,
                 &font_data::REGULAR_HEADING_MEDIUM,
+                2,
             ),
             3 => Self::from_fonts(
                 &font_data::REGULAR_BODY_LARGE,
                 &font_data::BOLD_BODY_LARGE,
                 &font_data::ITALIC_BODY_LARGE,
                 &font_data::REGULAR_HEADING_LARGE,
+                3,
             ),
             4 => Self::from_fonts(
                 &font_data::REGULAR_BODY_XLARGE,
                 &font_data::BOLD_BODY_XLARGE,
                 &font_data::ITALIC_BODY_XLARGE,
                 &font_data::REGULAR_HEADING_XLARGE,
+                4,
             ),
             _ => Self::from_fonts(
                 &font_data::REGULAR_BODY_SMALL,
                 &font_data::BOLD_BODY_SMALL,
                 &font_data::ITALIC_BODY_SMALL,
                 &font_data::REGULAR_HEADING_SMALL,
+                1,
             ),
         }
     }
@@ -180,9 +221,38 @@ impl FontSet {
         self.font(style).ascent
     }
 
+    // first font in the chain with a real glyph for ch
+    pub fn lookup(&self, ch: char, style: Style) -> Option<&'static BitmapFont> {
+        let primary = self.font(style);
+        if primary.has_glyph(ch) {
+            return Some(primary);
+        }
+        let chain = if style == Style::Heading {
+            self.heading_fallback
+        } else {
+            if self.regular.has_glyph(ch) {
+                return Some(self.regular);
+            }
+            self.fallback
+        };
+        chain.iter().copied().find(|f| f.has_glyph(ch))
+    }
+
+    #[inline]
+    pub fn has_glyph(&self, ch: char, style: Style) -> bool {
+        is_invisible(ch) || self.lookup(substitute(ch), style).is_some()
+    }
+
     #[inline]
     pub fn advance(&self, ch: char, style: Style) -> u8 {
-        self.font(style).advance(ch)
+        if is_invisible(ch) {
+            return 0;
+        }
+        let ch = substitute(ch);
+        match self.lookup(ch, style) {
+            Some(font) => font.advance(ch),
+            None => self.font(style).advance('0'),
+        }
     }
 
     #[inline]
@@ -199,7 +269,31 @@ impl FontSet {
         cx: i32,
         baseline: i32,
     ) -> u8 {
-        self.font(style).draw_char(strip, ch, cx, baseline)
+        if is_invisible(ch) {
+            return 0;
+        }
+        let ch = substitute(ch);
+        match self.lookup(ch, style) {
+            Some(font) => font.draw_char(strip, ch, cx, baseline),
+            None => self.draw_missing(strip, style, cx, baseline),
+        }
+    }
+
+    // replacement box: digit-sized hollow rectangle, so gaps in
+    // coverage are visible instead of silently dropped
+    fn draw_missing(&self, strip: &mut StripBuffer, style: Style, cx: i32, baseline: i32) -> u8 {
+        let font = self.font(style);
+        let digit = font.glyph('0');
+        let w = (digit.advance as u32).saturating_sub(2).max(3);
+        let h = (digit.height as u32).max(3);
+        Rectangle::new(
+            Point::new(cx + 1, baseline + digit.offset_y as i32),
+            Size::new(w, h),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(strip)
+        .unwrap();
+        digit.advance
     }
 
     pub fn draw_bytes(
@@ -210,7 +304,11 @@ impl FontSet {
         cx: i32,
         baseline: i32,
     ) -> i32 {
-        self.font(style).draw_bytes(strip, text, cx, baseline)
+        let mut x = cx;
+        for ch in bitmap::Utf8Iter::new(text) {
+            x += self.draw_char(strip, ch, style, x, baseline) as i32;
+        }
+        x
     }
 
     pub fn draw_str(
@@ -221,6 +319,74 @@ impl FontSet {
         cx: i32,
         baseline: i32,
     ) -> i32 {
-        self.font(style).draw_str(strip, text, cx, baseline)
+        let mut x = cx;
+        for ch in text.chars() {
+            x += self.draw_char(strip, ch, style, x, baseline) as i32;
+        }
+        x
+    }
+}
+
+// distinct codepoints a book needed that no font covered; bounded,
+// the reader logs the summary when the book is closed
+pub const MISSING_TRACK: usize = 16;
+
+pub struct MissingGlyphs {
+    cps: [u32; MISSING_TRACK],
+    counts: [u16; MISSING_TRACK],
+    len: u8,
+    total: u32,
+}
+
+impl Default for MissingGlyphs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MissingGlyphs {
+    pub const fn new() -> Self {
+        Self {
+            cps: [0; MISSING_TRACK],
+            counts: [0; MISSING_TRACK],
+            len: 0,
+            total: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.total = 0;
+    }
+
+    // record one occurrence; true the first time ch is seen
+    pub fn note(&mut self, ch: char) -> bool {
+        self.total = self.total.saturating_add(1);
+        let cp = ch as u32;
+        let n = self.len as usize;
+        if let Some(i) = self.cps[..n].iter().position(|&c| c == cp) {
+            self.counts[i] = self.counts[i].saturating_add(1);
+            return false;
+        }
+        if n == MISSING_TRACK {
+            return false;
+        }
+        self.cps[n] = cp;
+        self.counts[n] = 1;
+        self.len += 1;
+        true
+    }
+
+    #[inline]
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u16)> + '_ {
+        let n = self.len as usize;
+        self.cps[..n]
+            .iter()
+            .copied()
+            .zip(self.counts[..n].iter().copied())
     }
 }
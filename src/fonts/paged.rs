@@ -0,0 +1,385 @@
+// paged glyph tables for large scripts (CJK, kana, hangul)
+// too many codepoints for flash; cells live on SD and are pulled
+// into a small resident arena for the glyphs the current page uses
+//
+// one file per size tier: _PULP/FONTS/CJK<n>.PGF, generated by build.rs
+// when PULP_CJK_FONT names a TTF/OTF (see README)
+//
+// layout, little-endian:
+//   header 32 B   "PGF1", line_height u16, ascent u16, advance u8, pad u8,
+//                 cell_size u16, block_count u16, pad u16, glyph_count u32,
+//                 cells_start u32, reserved
+//   directory     block_count x u32; first cell of each 64-codepoint
+//                 block, NO_BLOCK when the block is empty
+//   cells         cell_size bytes each: advance, offset_x, offset_y,
+//                 width, height, 3 pad, then 1-bit bitmap (MSB-first)
+//
+// paged fonts are fixed-pitch: every covered codepoint advances by the
+// header advance, so wrapping never depends on which cells happen to
+// be resident and page offsets stay stable while the arena churns
+
+use alloc::vec::Vec;
+
+use embedded_graphics_core::pixelcolor::BinaryColor;
+
+use crate::drivers::strip::StripBuffer;
+use crate::fonts::bitmap::BitmapGlyph;
+use crate::fonts::{FontSet, Style};
+use crate::kernel::KernelHandle;
+
+pub const PAGED_FONT_DIR: &str = "FONTS";
+
+const MAGIC: &[u8; 4] = b"PGF1";
+const HEADER_LEN: usize = 32;
+const CELL_HDR: usize = 8;
+const BLOCK_SHIFT: u32 = 6;
+const BLOCK_MASK: u32 = (1 << BLOCK_SHIFT) - 1;
+const MAX_BLOCKS: usize = 1024; // whole BMP
+const NO_BLOCK: u32 = u32::MAX;
+const NO_CP: u32 = u32::MAX;
+const NO_SIZE: u8 = u8::MAX;
+
+// resident arena budget; slot count = budget / largest bitmap
+const ARENA_BUDGET: usize = 32 * 1024;
+const MAX_SLOTS: usize = 512;
+// cells read per open/seek batch
+const LOAD_BATCH: usize = 16;
+
+const FILE_NAMES: [&str; 5] = ["CJK0.PGF", "CJK1.PGF", "CJK2.PGF", "CJK3.PGF", "CJK4.PGF"];
+
+#[derive(Clone, Copy)]
+struct Slot {
+    cp: u32, // NO_CP when free
+    stamp: u32,
+    glyph: BitmapGlyph, // bitmap_offset is this slot's fixed arena offset
+}
+
+pub struct PagedFont {
+    size_idx: u8, // tier currently open, NO_SIZE if none
+    wanted: u8,
+    probed: bool, // open attempted for `wanted`
+
+    advance: u8,
+    cell_size: u16,
+    cells_start: u32,
+    dir: Vec<u32>,
+
+    slots: Vec<Slot>, // sorted by cp, free slots last
+    arena: Vec<u8>,
+    stamp: u32,
+}
+
+impl Default for PagedFont {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PagedFont {
+    pub const fn new() -> Self {
+        Self {
+            size_idx: NO_SIZE,
+            wanted: NO_SIZE,
+            probed: false,
+            advance: 0,
+            cell_size: 0,
+            cells_start: 0,
+            dir: Vec::new(),
+            slots: Vec::new(),
+            arena: Vec::new(),
+            stamp: 0,
+        }
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.size_idx != NO_SIZE
+    }
+
+    // request a size tier; the file is (re)opened on the next sync()
+    pub fn set_size(&mut self, idx: u8) {
+        if idx != self.wanted {
+            self.wanted = idx;
+            self.probed = false;
+        }
+    }
+
+    // open the wanted tier if it changed; cheap no-op otherwise.
+    // a missing file is remembered so books without CJK pay one probe
+    pub fn sync(&mut self, k: &mut KernelHandle<'_>) {
+        if self.probed || self.wanted == self.size_idx {
+            return;
+        }
+        self.probed = true;
+        self.close();
+        let Some(&name) = FILE_NAMES.get(self.wanted as usize) else {
+            return;
+        };
+        match self.open(k, name) {
+            Ok(()) => {
+                self.size_idx = self.wanted;
+                log::info!(
+                    "paged font: {} open, {} slots, advance {}",
+                    name,
+                    self.slots.len(),
+                    self.advance
+                );
+            }
+            Err(e) => {
+                self.close();
+                log::info!("paged font: {} unavailable ({})", name, e);
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.size_idx = NO_SIZE;
+        self.dir = Vec::new();
+        self.slots = Vec::new();
+        self.arena = Vec::new();
+    }
+
+    fn open(&mut self, k: &mut KernelHandle<'_>, name: &str) -> Result<(), &'static str> {
+        let mut hdr = [0u8; HEADER_LEN];
+        let n = k
+            .read_app_subdir_chunk(PAGED_FONT_DIR, name, 0, &mut hdr)
+            .map_err(|_| "not found")?;
+        if n < HEADER_LEN || &hdr[0..4] != MAGIC {
+            return Err("bad header");
+        }
+        let u16_at = |o: usize| u16::from_le_bytes([hdr[o], hdr[o + 1]]);
+        let u32_at = |o: usize| u32::from_le_bytes([hdr[o], hdr[o + 1], hdr[o + 2], hdr[o + 3]]);
+
+        let advance = hdr[8];
+        let cell_size = u16_at(10);
+        let block_count = (u16_at(12) as usize).min(MAX_BLOCKS);
+        let cells_start = u32_at(20);
+        let bitmap_max = (cell_size as usize).saturating_sub(CELL_HDR);
+        if advance == 0 || bitmap_max == 0 || block_count == 0 {
+            return Err("bad metrics");
+        }
+
+        let mut dir_bytes = Vec::new();
+        dir_bytes
+            .try_reserve_exact(block_count * 4)
+            .map_err(|_| "OOM")?;
+        dir_bytes.resize(block_count * 4, 0);
+        let n = k
+            .read_app_subdir_chunk(PAGED_FONT_DIR, name, HEADER_LEN as u32, &mut dir_bytes)
+            .map_err(|_| "read dir")?;
+        if n < dir_bytes.len() {
+            return Err("short dir");
+        }
+        let mut dir = Vec::new();
+        dir.try_reserve_exact(block_count).map_err(|_| "OOM")?;
+        for c in dir_bytes.chunks_exact(4) {
+            dir.push(u32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+        }
+        drop(dir_bytes);
+
+        let slot_count = (ARENA_BUDGET / bitmap_max).clamp(1, MAX_SLOTS);
+        let mut arena = Vec::new();
+        arena
+            .try_reserve_exact(slot_count * bitmap_max)
+            .map_err(|_| "OOM")?;
+        arena.resize(slot_count * bitmap_max, 0);
+        let mut slots = Vec::new();
+        slots.try_reserve_exact(slot_count).map_err(|_| "OOM")?;
+        for i in 0..slot_count {
+            slots.push(Slot {
+                cp: NO_CP,
+                stamp: 0,
+                glyph: BitmapGlyph {
+                    advance,
+                    offset_x: 0,
+                    offset_y: 0,
+                    width: 0,
+                    height: 0,
+                    bitmap_offset: (i * bitmap_max) as u16,
+                },
+            });
+        }
+
+        self.advance = advance;
+        self.cell_size = cell_size;
+        self.cells_start = cells_start;
+        self.dir = dir;
+        self.slots = slots;
+        self.arena = arena;
+        Ok(())
+    }
+
+    // first cell index for cp, if its block is present
+    #[inline]
+    fn cell_index(&self, cp: u32) -> Option<u32> {
+        let first = *self.dir.get((cp >> BLOCK_SHIFT) as usize)?;
+        if first == NO_BLOCK {
+            None
+        } else {
+            Some(first + (cp & BLOCK_MASK))
+        }
+    }
+
+    #[inline]
+    pub fn covers(&self, ch: char) -> bool {
+        self.cell_index(ch as u32).is_some()
+    }
+
+    // fixed-pitch advance for covered codepoints
+    #[inline]
+    pub fn advance(&self, ch: char) -> Option<u8> {
+        if self.covers(ch) {
+            Some(self.advance)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn resident(&self, cp: u32) -> Option<usize> {
+        self.slots.binary_search_by_key(&cp, |s| s.cp).ok()
+    }
+
+    // make every covered codepoint of a page resident, given each
+    // character with the style it is drawn in; glyphs that style's
+    // flash font chain already has are skipped. call before draw, with
+    // SD access
+    pub fn ensure(
+        &mut self,
+        k: &mut KernelHandle<'_>,
+        chars: impl Iterator<Item = (char, Style)>,
+        fonts: &FontSet,
+    ) {
+        if !self.is_open() {
+            return;
+        }
+
+        let mut need: Vec<u32> = Vec::new();
+        for (ch, sty) in chars {
+            if !fonts.has_glyph(ch, sty) && self.covers(ch) {
+                need.push(ch as u32);
+            }
+        }
+        if need.is_empty() {
+            return;
+        }
+        need.sort_unstable();
+        need.dedup();
+
+        self.stamp = self.stamp.wrapping_add(1);
+        let stamp = self.stamp;
+        let mut load: Vec<u32> = Vec::new();
+        for &cp in &need {
+            match self.resident(cp) {
+                Some(idx) => self.slots[idx].stamp = stamp,
+                None => load.push(cp),
+            }
+        }
+        let mut need = load;
+        if need.is_empty() {
+            return;
+        }
+
+        // victims: free slots first, then least recently stamped
+        let mut victims: Vec<usize> = (0..self.slots.len())
+            .filter(|&s| self.slots[s].stamp != stamp)
+            .collect();
+        victims.sort_unstable_by_key(|&s| (self.slots[s].cp != NO_CP, self.slots[s].stamp));
+        if need.len() > victims.len() {
+            log::warn!(
+                "paged font: page needs {} more glyphs than the {} slots",
+                need.len() - victims.len(),
+                self.slots.len()
+            );
+            need.truncate(victims.len());
+        }
+
+        let cell = self.cell_size as usize;
+        let bitmap_max = cell - CELL_HDR;
+        let mut scratch = Vec::new();
+        if scratch.try_reserve_exact(LOAD_BATCH * cell).is_err() {
+            return;
+        }
+        scratch.resize(LOAD_BATCH * cell, 0);
+        let mut offsets = [0u32; LOAD_BATCH];
+        let name = FILE_NAMES[self.size_idx as usize];
+
+        for (batch, slots) in need.chunks(LOAD_BATCH).zip(victims.chunks(LOAD_BATCH)) {
+            for (o, &cp) in offsets.iter_mut().zip(batch) {
+                let idx = self.cell_index(cp).unwrap_or(0);
+                *o = self.cells_start + idx * cell as u32;
+            }
+            let got = match k.read_app_subdir_spans(
+                PAGED_FONT_DIR,
+                name,
+                &offsets[..batch.len()],
+                cell,
+                &mut scratch,
+            ) {
+                Ok(n) => n,
+                Err(e) => {
+                    log::warn!("paged font: read failed: {}", e);
+                    break;
+                }
+            };
+
+            let cells = scratch.chunks_exact(cell).zip(batch).zip(slots).take(got);
+            for ((c, &cp), &victim) in cells {
+                let slot = &mut self.slots[victim];
+                let off = slot.glyph.bitmap_offset as usize;
+                // a bitmap larger than its cell is corrupt: the glyph
+                // is kept blank rather than drawn past what was read
+                let len = (c[3] as usize).div_ceil(8) * c[4] as usize;
+                let len = if len > bitmap_max { 0 } else { len };
+                self.arena[off..off + len].copy_from_slice(&c[CELL_HDR..CELL_HDR + len]);
+                slot.cp = cp;
+                slot.stamp = stamp;
+                slot.glyph = BitmapGlyph {
+                    advance: self.advance,
+                    offset_x: c[1] as i8,
+                    offset_y: c[2] as i8,
+                    width: if len > 0 { c[3] } else { 0 },
+                    height: c[4],
+                    bitmap_offset: off as u16,
+                };
+            }
+        }
+
+        self.slots.sort_unstable_by_key(|s| s.cp);
+    }
+
+    // draw a resident glyph; non-resident covered codepoints leave a
+    // blank cell of the right width. returns the advance
+    pub fn draw_char_fg(
+        &self,
+        strip: &mut StripBuffer,
+        ch: char,
+        fg: BinaryColor,
+        cx: i32,
+        baseline: i32,
+    ) -> u8 {
+        let Some(idx) = self.resident(ch as u32) else {
+            return self.advance;
+        };
+        let g = &self.slots[idx].glyph;
+        if g.width > 0 && g.height > 0 {
+            let w = g.width as usize;
+            strip.blit_1bpp(
+                &self.arena,
+                g.bitmap_offset as usize,
+                w,
+                g.height as usize,
+                w.div_ceil(8),
+                cx + g.offset_x as i32,
+                baseline + g.offset_y as i32,
+                fg == BinaryColor::On,
+            );
+        }
+        self.advance
+    }
+
+    #[inline]
+    pub fn draw_char(&self, strip: &mut StripBuffer, ch: char, cx: i32, baseline: i32) -> u8 {
+        self.draw_char_fg(strip, ch, BinaryColor::On, cx, baseline)
+    }
+}
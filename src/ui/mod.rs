@@ -11,7 +11,7 @@ pub use pulp_kernel::ui::*;
 // app-side font-dependent widgets
 pub use crate::apps::widgets::QuickMenu;
 pub use crate::apps::widgets::bitmap_label::{BitmapDynLabel, BitmapLabel};
-pub use crate::apps::widgets::button_feedback::{BUTTON_BAR_H, ButtonFeedback};
+pub use crate::apps::widgets::button_feedback::{BUTTON_BAR_H, BUTTON_BAR_W, ButtonFeedback};
 pub use crate::apps::widgets::list::ListSelection;
 pub use crate::apps::widgets::quick_menu;
 pub use crate::apps::widgets::selectable_row::{